@@ -19,6 +19,9 @@ mts_data_origin_t = UInt64
 
 mts_create_array_callback_t = Ptr{Cvoid}  # TODO: actual type
 mts_realloc_buffer_t = Ptr{Cvoid}         # TODO: actual type
+mts_progress_callback_t = Ptr{Cvoid}      # TODO: actual type
+mts_map_keys_callback_t = Ptr{Cvoid}      # TODO: actual type
+mts_filter_properties_callback_t = Ptr{Cvoid}  # TODO: actual type
 
 # ====== Enf of manual definitions ====== #
 
@@ -30,6 +33,7 @@ MTS_IO_ERROR = 2
 MTS_SERIALIZATION_ERROR = 3
 MTS_BUFFER_SIZE_ERROR = 254
 MTS_INTERNAL_ERROR = 255
+MTS_LABELS_ANY = -2147483648
 
 
 # ===== Enum definitions
@@ -66,6 +70,7 @@ struct mts_array_t
     copy :: Ptr{Cvoid} #= (Ptr{Cvoid}, Ptr{mts_array_t}) -> mts_status_t =#
     destroy :: Ptr{Cvoid} #= (Ptr{Cvoid}) -> Cvoid =#
     move_samples_from :: Ptr{Cvoid} #= (Ptr{Cvoid}, Ptr{Cvoid}, Ptr{mts_sample_mapping_t}, UIntptr, UIntptr, UIntptr) -> mts_status_t =#
+    element_size :: Ptr{Cvoid} #= (Ptr{Cvoid}, Ptr{UIntptr}) -> mts_status_t =#
 end
 
 
@@ -152,6 +157,14 @@ function mts_labels_intersection(first::mts_labels_t, second::mts_labels_t, resu
     )
 end
 
+function mts_labels_cartesian_product(first::mts_labels_t, second::mts_labels_t, result::Ptr{mts_labels_t})
+    ccall((:mts_labels_cartesian_product, libmetatensor), 
+        mts_status_t,
+        (mts_labels_t, mts_labels_t, Ptr{mts_labels_t},),
+        first, second, result
+    )
+end
+
 function mts_labels_free(labels::Ptr{mts_labels_t})
     ccall((:mts_labels_free, libmetatensor), 
         mts_status_t,
@@ -200,6 +213,14 @@ function mts_block_copy(block::Ptr{mts_block_t})
     )
 end
 
+function mts_block_zeros_like(block::Ptr{mts_block_t})
+    ccall((:mts_block_zeros_like, libmetatensor), 
+        Ptr{mts_block_t},
+        (Ptr{mts_block_t},),
+        block
+    )
+end
+
 function mts_block_labels(block::Ptr{mts_block_t}, axis::UIntptr, labels::Ptr{mts_labels_t})
     ccall((:mts_block_labels, libmetatensor), 
         mts_status_t,
@@ -208,6 +229,38 @@ function mts_block_labels(block::Ptr{mts_block_t}, axis::UIntptr, labels::Ptr{mt
     )
 end
 
+function mts_block_set_labels(block::Ptr{mts_block_t}, axis::UIntptr, labels::mts_labels_t)
+    ccall((:mts_block_set_labels, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_block_t}, UIntptr, mts_labels_t,),
+        block, axis, labels
+    )
+end
+
+function mts_block_set_gradient_samples(block::Ptr{mts_block_t}, parameter::Ptr{Cchar}, labels::mts_labels_t)
+    ccall((:mts_block_set_gradient_samples, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_block_t}, Ptr{Cchar}, mts_labels_t,),
+        block, parameter, labels
+    )
+end
+
+function mts_block_set_units(block::Ptr{mts_block_t}, units::Ptr{Cchar})
+    ccall((:mts_block_set_units, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_block_t}, Ptr{Cchar},),
+        block, units
+    )
+end
+
+function mts_block_units(block::Ptr{mts_block_t}, units::Ptr{Ptr{Cchar}})
+    ccall((:mts_block_units, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_block_t}, Ptr{Ptr{Cchar}},),
+        block, units
+    )
+end
+
 function mts_block_gradient(block::Ptr{mts_block_t}, parameter::Ptr{Cchar}, gradient::Ptr{Ptr{mts_block_t}})
     ccall((:mts_block_gradient, libmetatensor), 
         mts_status_t,
@@ -240,6 +293,46 @@ function mts_block_gradients_list(block::Ptr{mts_block_t}, parameters::Ptr{Ptr{P
     )
 end
 
+function mts_block_compact_gradients(block::Ptr{mts_block_t})
+    ccall((:mts_block_compact_gradients, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_block_t},),
+        block
+    )
+end
+
+function mts_block_nbytes(block::Ptr{mts_block_t}, nbytes::Ptr{UIntptr})
+    ccall((:mts_block_nbytes, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_block_t}, Ptr{UIntptr},),
+        block, nbytes
+    )
+end
+
+function mts_block_append_samples(block::Ptr{mts_block_t}, samples::mts_labels_t, data::mts_array_t)
+    ccall((:mts_block_append_samples, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_block_t}, mts_labels_t, mts_array_t,),
+        block, samples, data
+    )
+end
+
+function mts_block_reorder_properties(block::Ptr{mts_block_t}, order::mts_labels_t)
+    ccall((:mts_block_reorder_properties, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_block_t}, mts_labels_t,),
+        block, order
+    )
+end
+
+function mts_block_concatenate_samples(blocks::Ptr{Ptr{mts_block_t}}, blocks_count::UIntptr)
+    ccall((:mts_block_concatenate_samples, libmetatensor), 
+        Ptr{mts_block_t},
+        (Ptr{Ptr{mts_block_t}}, UIntptr,),
+        blocks, blocks_count
+    )
+end
+
 function mts_tensormap(keys::mts_labels_t, blocks::Ptr{Ptr{mts_block_t}}, blocks_count::UIntptr)
     ccall((:mts_tensormap, libmetatensor), 
         Ptr{mts_tensormap_t},
@@ -264,6 +357,22 @@ function mts_tensormap_copy(tensor::Ptr{mts_tensormap_t})
     )
 end
 
+function mts_tensormap_zeros_like(tensor::Ptr{mts_tensormap_t})
+    ccall((:mts_tensormap_zeros_like, libmetatensor), 
+        Ptr{mts_tensormap_t},
+        (Ptr{mts_tensormap_t},),
+        tensor
+    )
+end
+
+function mts_tensormap_check_invariants(tensor::Ptr{mts_tensormap_t})
+    ccall((:mts_tensormap_check_invariants, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_tensormap_t},),
+        tensor
+    )
+end
+
 function mts_tensormap_keys(tensor::Ptr{mts_tensormap_t}, keys::Ptr{mts_labels_t})
     ccall((:mts_tensormap_keys, libmetatensor), 
         mts_status_t,
@@ -272,6 +381,30 @@ function mts_tensormap_keys(tensor::Ptr{mts_tensormap_t}, keys::Ptr{mts_labels_t
     )
 end
 
+function mts_tensormap_set_metadata(tensor::Ptr{mts_tensormap_t}, key::Ptr{Cchar}, value::Ptr{Cchar})
+    ccall((:mts_tensormap_set_metadata, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_tensormap_t}, Ptr{Cchar}, Ptr{Cchar},),
+        tensor, key, value
+    )
+end
+
+function mts_tensormap_get_metadata(tensor::Ptr{mts_tensormap_t}, key::Ptr{Cchar}, value::Ptr{Ptr{Cchar}})
+    ccall((:mts_tensormap_get_metadata, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_tensormap_t}, Ptr{Cchar}, Ptr{Ptr{Cchar}},),
+        tensor, key, value
+    )
+end
+
+function mts_tensormap_metadata_keys(tensor::Ptr{mts_tensormap_t}, keys::Ptr{Ptr{Ptr{Cchar}}}, keys_count::Ptr{UIntptr})
+    ccall((:mts_tensormap_metadata_keys, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_tensormap_t}, Ptr{Ptr{Ptr{Cchar}}}, Ptr{UIntptr},),
+        tensor, keys, keys_count
+    )
+end
+
 function mts_tensormap_block_by_id(tensor::Ptr{mts_tensormap_t}, block::Ptr{Ptr{mts_block_t}}, index::UIntptr)
     ccall((:mts_tensormap_block_by_id, libmetatensor), 
         mts_status_t,
@@ -288,6 +421,14 @@ function mts_tensormap_blocks_matching(tensor::Ptr{mts_tensormap_t}, block_index
     )
 end
 
+function mts_tensormap_blocks_matching_wildcard(tensor::Ptr{mts_tensormap_t}, block_indexes::Ptr{UIntptr}, count::Ptr{UIntptr}, selection::mts_labels_t)
+    ccall((:mts_tensormap_blocks_matching_wildcard, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_tensormap_t}, Ptr{UIntptr}, Ptr{UIntptr}, mts_labels_t,),
+        tensor, block_indexes, count, selection
+    )
+end
+
 function mts_tensormap_keys_to_properties(tensor::Ptr{mts_tensormap_t}, keys_to_move::mts_labels_t, sort_samples::Cbool)
     ccall((:mts_tensormap_keys_to_properties, libmetatensor), 
         Ptr{mts_tensormap_t},
@@ -312,6 +453,94 @@ function mts_tensormap_keys_to_samples(tensor::Ptr{mts_tensormap_t}, keys_to_mov
     )
 end
 
+function mts_tensormap_keys_to_samples_with_progress(tensor::Ptr{mts_tensormap_t}, keys_to_move::mts_labels_t, sort_samples::Cbool, callback::mts_progress_callback_t, user_data::Ptr{Cvoid})
+    ccall((:mts_tensormap_keys_to_samples_with_progress, libmetatensor), 
+        Ptr{mts_tensormap_t},
+        (Ptr{mts_tensormap_t}, mts_labels_t, Cbool, mts_progress_callback_t, Ptr{Cvoid},),
+        tensor, keys_to_move, sort_samples, callback, user_data
+    )
+end
+
+function mts_tensormap_keys_to_properties_with_progress(tensor::Ptr{mts_tensormap_t}, keys_to_move::mts_labels_t, sort_samples::Cbool, callback::mts_progress_callback_t, user_data::Ptr{Cvoid})
+    ccall((:mts_tensormap_keys_to_properties_with_progress, libmetatensor), 
+        Ptr{mts_tensormap_t},
+        (Ptr{mts_tensormap_t}, mts_labels_t, Cbool, mts_progress_callback_t, Ptr{Cvoid},),
+        tensor, keys_to_move, sort_samples, callback, user_data
+    )
+end
+
+function mts_tensormap_sort_blocks(tensor::Ptr{mts_tensormap_t})
+    ccall((:mts_tensormap_sort_blocks, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_tensormap_t},),
+        tensor
+    )
+end
+
+function mts_tensormap_retain_blocks(tensor::Ptr{mts_tensormap_t}, keep::Ptr{Bool}, keep_count::UIntptr, removed::Ptr{UIntptr})
+    ccall((:mts_tensormap_retain_blocks, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_tensormap_t}, Ptr{Bool}, UIntptr, Ptr{UIntptr},),
+        tensor, keep, keep_count, removed
+    )
+end
+
+function mts_tensormap_make_labels_unique(tensor::Ptr{mts_tensormap_t})
+    ccall((:mts_tensormap_make_labels_unique, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_tensormap_t},),
+        tensor
+    )
+end
+
+function mts_tensormap_properties_to_components(tensor::Ptr{mts_tensormap_t}, dimensions::Ptr{Ptr{Cchar}}, dimensions_count::UIntptr)
+    ccall((:mts_tensormap_properties_to_components, libmetatensor), 
+        Ptr{mts_tensormap_t},
+        (Ptr{mts_tensormap_t}, Ptr{Ptr{Cchar}}, UIntptr,),
+        tensor, dimensions, dimensions_count
+    )
+end
+
+function mts_tensormap_concatenate_properties(tensors::Ptr{Ptr{mts_tensormap_t}}, tensors_count::UIntptr, fill_missing_gradients::Cbool)
+    ccall((:mts_tensormap_concatenate_properties, libmetatensor), 
+        Ptr{mts_tensormap_t},
+        (Ptr{Ptr{mts_tensormap_t}}, UIntptr, Cbool,),
+        tensors, tensors_count, fill_missing_gradients
+    )
+end
+
+function mts_tensormap_concatenate_samples(tensors::Ptr{Ptr{mts_tensormap_t}}, tensors_count::UIntptr, fill_missing_gradients::Cbool)
+    ccall((:mts_tensormap_concatenate_samples, libmetatensor), 
+        Ptr{mts_tensormap_t},
+        (Ptr{Ptr{mts_tensormap_t}}, UIntptr, Cbool,),
+        tensors, tensors_count, fill_missing_gradients
+    )
+end
+
+function mts_tensormap_nbytes(tensor::Ptr{mts_tensormap_t}, nbytes::Ptr{UIntptr})
+    ccall((:mts_tensormap_nbytes, libmetatensor), 
+        mts_status_t,
+        (Ptr{mts_tensormap_t}, Ptr{UIntptr},),
+        tensor, nbytes
+    )
+end
+
+function mts_tensormap_map_keys(tensor::Ptr{mts_tensormap_t}, callback::mts_map_keys_callback_t, user_data::Ptr{Cvoid})
+    ccall((:mts_tensormap_map_keys, libmetatensor), 
+        Ptr{mts_tensormap_t},
+        (Ptr{mts_tensormap_t}, mts_map_keys_callback_t, Ptr{Cvoid},),
+        tensor, callback, user_data
+    )
+end
+
+function mts_tensormap_filter_properties(tensor::Ptr{mts_tensormap_t}, callback::mts_filter_properties_callback_t, user_data::Ptr{Cvoid})
+    ccall((:mts_tensormap_filter_properties, libmetatensor), 
+        Ptr{mts_tensormap_t},
+        (Ptr{mts_tensormap_t}, mts_filter_properties_callback_t, Ptr{Cvoid},),
+        tensor, callback, user_data
+    )
+end
+
 function mts_labels_load(path::Ptr{Cchar}, labels::Ptr{mts_labels_t})
     ccall((:mts_labels_load, libmetatensor), 
         mts_status_t,
@@ -45,6 +45,13 @@
  */
 #define MTS_INTERNAL_ERROR 255
 
+/**
+ * Value used in the selection passed to
+ * `mts_tensormap_blocks_matching_wildcard` to match any value of the
+ * corresponding dimension of the keys.
+ */
+#define MTS_LABELS_ANY INT32_MIN
+
 /**
  * Basic building block for tensor map. A single block contains a n-dimensional
  * `mts_array_t`, and n sets of `mts_labels_t` (one for each dimension).
@@ -224,8 +231,58 @@ typedef struct mts_array_t {
                                     uintptr_t samples_count,
                                     uintptr_t property_start,
                                     uintptr_t property_end);
+  /**
+   * Get the size in bytes of a single element of this array in
+   * `element_size`. This function can be set to `NULL`, in which case the
+   * elements are assumed to be 64-bit floating point values.
+   */
+  mts_status_t (*element_size)(const void *array, uintptr_t *element_size);
 } mts_array_t;
 
+/**
+ * Function pointer used to report progress in
+ * `mts_tensormap_keys_to_samples_with_progress` and
+ * `mts_tensormap_keys_to_properties_with_progress`.
+ *
+ * This function gets the number of blocks created so far in `done`, and the
+ * total number of blocks in the output in `total`. It can return a non-zero
+ * `mts_status_t` to stop the operation with an error. The `user_data`
+ * parameter is passed as-is from the calling function, and can be used to
+ * hold custom data.
+ */
+typedef mts_status_t (*mts_progress_callback_t)(void *user_data, uintptr_t done, uintptr_t total);
+
+/**
+ * Function pointer used to compute new keys in `mts_tensormap_map_keys`.
+ *
+ * This function gets the values of an existing key in `key` (containing
+ * `key_size` elements), and should write the corresponding new values in
+ * `new_key` (which has space for `key_size` elements). It can return a
+ * non-zero `mts_status_t` to indicate an error. The `user_data` parameter is
+ * passed as-is from `mts_tensormap_map_keys`, and can be used to hold custom
+ * data.
+ */
+typedef mts_status_t (*mts_map_keys_callback_t)(void *user_data,
+                                                const int32_t *key,
+                                                int32_t *new_key,
+                                                uintptr_t key_size);
+
+/**
+ * Function pointer used to select properties in
+ * `mts_tensormap_filter_properties`.
+ *
+ * This function gets the values of a single property in `property`
+ * (containing `property_size` elements), and should set `keep` to `true` if
+ * this property should be kept, and `false` otherwise. It can return a
+ * non-zero `mts_status_t` to indicate an error. The `user_data` parameter is
+ * passed as-is from `mts_tensormap_filter_properties`, and can be used to
+ * hold custom data.
+ */
+typedef mts_status_t (*mts_filter_properties_callback_t)(void *user_data,
+                                                         const int32_t *property,
+                                                         uintptr_t property_size,
+                                                         bool *keep);
+
 /**
  * Function pointer to grow in-memory buffers for `mts_tensormap_save_buffer`
  * and `mts_labels_save_buffer`.
@@ -447,6 +504,29 @@ mts_status_t mts_labels_intersection(struct mts_labels_t first,
                                      int64_t *second_mapping,
                                      uintptr_t second_mapping_count);
 
+/**
+ * Take the cartesian product of two `mts_labels_t`.
+ *
+ * The names of `result` are the names of `first` followed by the names of
+ * `second`, and `result` contains every combination of one entry of `first`
+ * followed by one entry of `second`. The two labels must not have any name in
+ * common.
+ *
+ * This function allocates memory for `result` which must be released
+ * `mts_labels_free` when you don't need it anymore.
+ *
+ * @param first first set of labels
+ * @param second second set of labels
+ * @param result empty labels, on output will contain the cartesian product of
+ *        `first` and `second`
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_labels_cartesian_product(struct mts_labels_t first,
+                                          struct mts_labels_t second,
+                                          struct mts_labels_t *result);
+
 /**
  * Decrease the reference count of `labels`, and release the corresponding
  * memory once the reference count reaches 0.
@@ -539,6 +619,25 @@ mts_status_t mts_block_free(struct mts_block_t *block);
  */
 struct mts_block_t *mts_block_copy(const struct mts_block_t *block);
 
+/**
+ * Create a new `mts_block_t` with the same metadata as the given `block`, and
+ * data filled with zeros.
+ *
+ * The values and gradients arrays of the new block are created with the
+ * `mts_array_t.create` function of the arrays in `block`, and the gradients
+ * of the new block are also filled with zeros.
+ *
+ * The memory allocated by this function and the blocks should be released
+ * using `mts_block_free`, or moved into a tensor map using `mts_tensormap`.
+ *
+ * @param block existing block to use as a template
+ *
+ * @returns A pointer to the newly allocated block, or a `NULL` pointer in
+ *          case of error. In case of error, you can use `mts_last_error()`
+ *          to get the error message.
+ */
+struct mts_block_t *mts_block_zeros_like(const struct mts_block_t *block);
+
 /**
  * Get the set of labels from this `block`.
  *
@@ -558,6 +657,87 @@ mts_status_t mts_block_labels(const struct mts_block_t *block,
                               uintptr_t axis,
                               struct mts_labels_t *labels);
 
+/**
+ * Replace the samples or properties labels of this `block` with a copy of
+ * `labels`, without modifying the data. This can be used to change the values
+ * of the labels in place.
+ *
+ * The new labels must have the same names and the same number of entries as
+ * the current labels. Setting the properties also sets the properties of all
+ * gradients. Components labels can not be modified with this function.
+ *
+ * This function can not be used with gradient blocks: the properties of a
+ * gradient are always the same as the properties of the parent block, and the
+ * samples of a gradient should be set with `mts_block_set_gradient_samples`
+ * on the parent block.
+ *
+ * @param block pointer to an existing block
+ * @param axis axis/dimension of the data array for which to set the labels,
+ *        this must be either 0 (samples) or the last axis (properties)
+ * @param labels new labels for this axis
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_block_set_labels(struct mts_block_t *block,
+                                  uintptr_t axis,
+                                  struct mts_labels_t labels);
+
+/**
+ * Replace the samples of the gradient with respect to `parameter` in this
+ * `block` with a copy of `labels`, without modifying the data.
+ *
+ * The new labels must have the same names and the same number of entries as
+ * the current gradient samples, and the values of the first dimension
+ * (`sample`) must refer to existing samples of `block`.
+ *
+ * @param block pointer to an existing block
+ * @param parameter name of the gradient parameter
+ * @param labels new samples for the gradient
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_block_set_gradient_samples(struct mts_block_t *block,
+                                            const char *parameter,
+                                            struct mts_labels_t labels);
+
+/**
+ * Set the physical units of the values in this `block`, or remove them if
+ * `units` is NULL.
+ *
+ * The units are free-form strings (e.g. "eV", or "eV/Angstrom" for a
+ * gradient), and are only used as metadata. They are kept when copying a
+ * block and when saving and loading tensor maps, but operations creating new
+ * blocks do not propagate them.
+ *
+ * @param block pointer to an existing block
+ * @param units NULL-terminated UTF-8 string containing the units, or NULL
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_block_set_units(struct mts_block_t *block, const char *units);
+
+/**
+ * Get the physical units of the values in this `block`.
+ *
+ * The units memory is managed by the block, and is invalidated when the
+ * block is freed or when the units are modified.
+ *
+ * @param block pointer to an existing block
+ * @param units will be set to a NULL-terminated UTF-8 string containing the
+ *              units, or to NULL if the units of this block are not set
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_block_units(const struct mts_block_t *block, const char **units);
+
 /**
  * Get one of the gradients in this `block`.
  *
@@ -637,6 +817,103 @@ mts_status_t mts_block_gradients_list(const struct mts_block_t *block,
                                       const char *const **parameters,
                                       uintptr_t *parameters_count);
 
+/**
+ * Restore the consistency between the samples of this `block` and the samples
+ * of its gradients.
+ *
+ * Gradient samples referring to values samples which no longer exist are
+ * removed, and the remaining gradient samples are re-ordered to follow the
+ * order of the values samples. This is applied recursively to gradients of
+ * gradients.
+ *
+ * @param block pointer to an existing block
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_block_compact_gradients(struct mts_block_t *block);
+
+/**
+ * Get the estimated size in bytes of the data stored in this `block`,
+ * including the data of all its gradients.
+ *
+ * The size is computed from the shape of the arrays, assuming they contain
+ * 64-bit floating point values.
+ *
+ * @param block pointer to an existing block
+ * @param nbytes pointer to an integer, will be set to the size in bytes
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_block_nbytes(const struct mts_block_t *block, uintptr_t *nbytes);
+
+/**
+ * Add new samples at the end of this `block`, together with the
+ * corresponding `data`.
+ *
+ * The new `samples` must have the same names as the existing samples of the
+ * block, and must not contain any of the existing samples. The `data` must
+ * have the same shape as the values of the block, except for the first
+ * dimension. This function returns an error if the block contains gradients.
+ *
+ * The block takes ownership of the `data`, which should not be released
+ * separately.
+ *
+ * @param block pointer to an existing block
+ * @param samples labels containing the new samples
+ * @param data array containing the values for the new samples
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_block_append_samples(struct mts_block_t *block,
+                                      struct mts_labels_t samples,
+                                      struct mts_array_t data);
+
+/**
+ * Re-order the properties of this `block` to follow the entries in `order`,
+ * permuting the values and all gradients accordingly.
+ *
+ * `order` must have the same names as the properties of the block, and
+ * contain each of the existing properties exactly once. The properties of a
+ * gradient block can not be re-ordered on their own, since they must stay the
+ * same as the properties of the parent block.
+ *
+ * @param block pointer to an existing block
+ * @param order labels containing the properties in their new order
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_block_reorder_properties(struct mts_block_t *block, struct mts_labels_t order);
+
+/**
+ * Concatenate multiple blocks along the sample axis.
+ *
+ * All the blocks must have the same components and properties, and gradients
+ * with respect to the same parameters. The samples of the blocks are
+ * concatenated in the order of `blocks`, and must not contain the same entry
+ * more than once. The gradient samples are updated to refer to the samples in
+ * the concatenated block.
+ *
+ * The memory allocated by this function should be released using
+ * `mts_block_free`, or moved into a tensor map using `mts_tensormap`.
+ *
+ * @param blocks pointer to the first element of an array of blocks
+ * @param blocks_count number of elements in the `blocks` array
+ *
+ * @returns A pointer to the newly allocated block, or a `NULL` pointer in
+ *          case of error. In case of error, you can use `mts_last_error()`
+ *          to get the error message.
+ */
+struct mts_block_t *mts_block_concatenate_samples(const struct mts_block_t *const *blocks,
+                                                  uintptr_t blocks_count);
+
 /**
  * Create a new `mts_tensormap_t` with the given `keys` and `blocks`.
  * `blocks_count` must be set to the number of entries in the blocks array.
@@ -687,6 +964,42 @@ mts_status_t mts_tensormap_free(struct mts_tensormap_t *tensor);
  */
 struct mts_tensormap_t *mts_tensormap_copy(const struct mts_tensormap_t *tensor);
 
+/**
+ * Create a new `mts_tensormap_t` with the same keys and blocks metadata as
+ * the given `tensor`, and all data filled with zeros.
+ *
+ * The arrays of the new tensor map are created with the `mts_array_t.create`
+ * function of the arrays in `tensor`. See `mts_block_zeros_like` for more
+ * information.
+ *
+ * The memory allocated by this function and the blocks should be released
+ * using `mts_tensormap_free`.
+ *
+ * @param tensor existing tensor to use as a template
+ *
+ * @returns A pointer to the newly allocated tensor, or a `NULL` pointer in
+ *          case of error. In case of error, you can use `mts_last_error()`
+ *          to get the error message.
+ */
+struct mts_tensormap_t *mts_tensormap_zeros_like(const struct mts_tensormap_t *tensor);
+
+/**
+ * Check that the given `tensor` is still valid, re-running all the checks
+ * performed when creating it with `mts_tensormap`, `mts_block` and
+ * `mts_block_add_gradient`.
+ *
+ * Modifying the data of the blocks in a tensor map (for example through
+ * `mts_block_data`) could break these invariants, and this function can be
+ * used to check that the tensor map is still consistent.
+ *
+ * @param tensor pointer to an existing tensor map
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message describing which invariant is violated.
+ */
+mts_status_t mts_tensormap_check_invariants(const struct mts_tensormap_t *tensor);
+
 /**
  * Get the keys for the given `tensor` map.
  *
@@ -702,6 +1015,64 @@ struct mts_tensormap_t *mts_tensormap_copy(const struct mts_tensormap_t *tensor)
  */
 mts_status_t mts_tensormap_keys(const struct mts_tensormap_t *tensor, struct mts_labels_t *keys);
 
+/**
+ * Set the metadata entry `key` of this `tensor` map to `value`, overwriting
+ * any existing value for this `key`.
+ *
+ * Metadata is a set of arbitrary key/value pairs of strings attached to a
+ * tensor map, which can be used to store provenance information. It is kept
+ * by `mts_tensormap_copy()` and when saving/loading tensor maps, but other
+ * operations creating new tensor maps do not propagate it.
+ *
+ * @param tensor pointer to an existing tensor map
+ * @param key NULL-terminated UTF-8 string containing the metadata key
+ * @param value NULL-terminated UTF-8 string containing the metadata value
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_tensormap_set_metadata(struct mts_tensormap_t *tensor,
+                                        const char *key,
+                                        const char *value);
+
+/**
+ * Get the value of the metadata entry `key` in this `tensor` map.
+ *
+ * The value memory is managed by the tensor map, and is invalidated when the
+ * tensor map is freed or when this metadata entry is modified.
+ *
+ * @param tensor pointer to an existing tensor map
+ * @param key NULL-terminated UTF-8 string containing the metadata key
+ * @param value will be set to a NULL-terminated UTF-8 string containing the
+ *              value associated with `key`, or to NULL if there is no
+ *              metadata entry for this `key`
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_tensormap_get_metadata(const struct mts_tensormap_t *tensor,
+                                        const char *key,
+                                        const char **value);
+
+/**
+ * Get the list of all metadata keys defined in this `tensor` map, in the
+ * order in which they were first set.
+ *
+ * @param tensor pointer to an existing tensor map
+ * @param keys will be set to the first element of an array of
+ *             NULL-terminated UTF-8 strings containing all the metadata keys
+ * @param keys_count will be set to the number of elements in `keys`
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_tensormap_metadata_keys(const struct mts_tensormap_t *tensor,
+                                         const char *const **keys,
+                                         uintptr_t *keys_count);
+
 /**
  * Get a pointer to the `index`-th block in this tensor map.
  *
@@ -728,6 +1099,11 @@ mts_status_t mts_tensormap_block_by_id(struct mts_tensormap_t *tensor,
  * the keys for this tensor map, and only one entry, describing the requested
  * blocks.
  *
+ * All the values of the keys dimensions which are not part of the
+ * `selection` are matched. The values in the `selection` are compared exactly
+ * with the keys, see `mts_tensormap_blocks_matching_wildcard` to match any
+ * value of some dimensions.
+ *
  * When calling this function, `*count` should contain the number of entries in
  * `block_indexes`. When the function returns successfully, `*count` will
  * contain the number of blocks matching the selection, i.e. how many values
@@ -748,6 +1124,33 @@ mts_status_t mts_tensormap_blocks_matching(const struct mts_tensormap_t *tensor,
                                            uintptr_t *count,
                                            struct mts_labels_t selection);
 
+/**
+ * Get indices of the blocks in this `tensor` corresponding to the given
+ * `selection`, where `MTS_LABELS_ANY` can be used as a wildcard.
+ *
+ * This function is the same as `mts_tensormap_blocks_matching`, except that
+ * the `selection` can use `MTS_LABELS_ANY` as the value for some dimensions,
+ * to match all the values of these dimensions while still selecting specific
+ * values for the other dimensions. Keys containing the same value as
+ * `MTS_LABELS_ANY` can still be selected exactly with
+ * `mts_tensormap_blocks_matching`.
+ *
+ * @param tensor pointer to an existing tensor map
+ * @param block_indexes array to be filled with indexes of blocks in the tensor
+ *                      map matching the `selection`
+ * @param count number of entries in `block_indexes`
+ * @param selection labels with a single entry describing which blocks are
+ *                  requested, potentially containing `MTS_LABELS_ANY`
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_tensormap_blocks_matching_wildcard(const struct mts_tensormap_t *tensor,
+                                                    uintptr_t *block_indexes,
+                                                    uintptr_t *count,
+                                                    struct mts_labels_t selection);
+
 /**
  * Merge blocks with the same value for selected keys dimensions along the
  * property axis.
@@ -770,13 +1173,40 @@ mts_status_t mts_tensormap_blocks_matching(const struct mts_tensormap_t *tensor,
  * blocks with properties `p=1, 2` will result in `a, p = (2, 1), (2, 2),
  * (3, 1), (3, 2)`.
  *
+ * The moved dimensions are always added before the existing dimensions of
+ * the properties, in the same order as in `keys_to_move`. For example,
+ * moving `c, a` from keys `a, b, c` to blocks with properties `n, l` gives
+ * properties `c, a, n, l`.
+ *
+ * The new keys contain the dimensions of the current keys which are not
+ * moved, in the same order as in the current keys. If all the dimensions
+ * are moved, the new keys contain a single `_` dimension with a single
+ * entry.
+ *
  * The new sample labels will contains all of the merged blocks sample
  * labels. The order of the samples is controlled by `sort_samples`. If
  * `sort_samples` is true, samples are re-ordered to keep them
  * lexicographically sorted. Otherwise they are kept in the order in which
  * they appear in the blocks.
  *
+ * The samples of the merged blocks do not need to be the same. If a
+ * sample is not present in one of the merged blocks, the values (and
+ * gradients) for this sample in the properties coming from this block
+ * are set to zero.
+ *
+ * The same applies to the gradients: the gradient samples of the merged
+ * blocks do not need to be the same, and the gradient samples of the new
+ * block contain all of them (after updating the `sample` dimension to
+ * refer to the new samples). Gradients which are not present in one of
+ * the merged blocks are set to zero in the properties coming from this
+ * block.
+ *
  * The result is a new tensor map, which should be freed with `mts_tensormap_free`.
+ * The input `tensor` is not modified, and the data is copied in the new tensor
+ * map: keeping both alive will use twice as much memory.
+ *
+ * If `keys_to_move` does not contain any dimension (`keys_to_move.size ==
+ * 0`), the result is a copy of `tensor`, and the samples are not re-ordered.
  *
  * @param tensor pointer to an existing tensor map
  * @param keys_to_move description of the keys to move
@@ -818,10 +1248,22 @@ struct mts_tensormap_t *mts_tensormap_components_to_properties(struct mts_tensor
  * the sample labels, and blocks with the same remaining keys dimensions
  * will be merged together along the sample axis.
  *
+ * Blocks are grouped by the values of the remaining keys dimensions:
+ * blocks which only differ in the moved dimensions are merged together,
+ * while blocks with different values for any of the remaining dimensions
+ * are kept separate. The new keys contain the unique values of the
+ * remaining dimensions, in the order in which they first appear in the
+ * current keys.
+ *
  * `keys_to_move` must be empty (`keys_to_move.count == 0`), and the new
  * sample labels will contain entries corresponding to the merged blocks'
  * keys.
  *
+ * The moved dimensions are always added after the existing dimensions of
+ * the samples, in the same order as in `keys_to_move`. For example, moving
+ * `c, a` from keys `a, b, c` to blocks with samples `system, atom` gives
+ * samples `system, atom, c, a`.
+ *
  * The new sample labels will contains all of the merged blocks sample
  * labels. The order of the samples is controlled by `sort_samples`. If
  * `sort_samples` is true, samples are re-ordered to keep them
@@ -831,6 +1273,9 @@ struct mts_tensormap_t *mts_tensormap_components_to_properties(struct mts_tensor
  * This function is only implemented if all merged block have the same
  * property labels.
  *
+ * If `keys_to_move` does not contain any dimension (`keys_to_move.size ==
+ * 0`), the result is a copy of `tensor`, and the samples are not re-ordered.
+ *
  * @param tensor pointer to an existing tensor map
  * @param keys_to_move description of the keys to move
  * @param sort_samples whether to sort the samples lexicographically after
@@ -844,6 +1289,269 @@ struct mts_tensormap_t *mts_tensormap_keys_to_samples(const struct mts_tensormap
                                                       struct mts_labels_t keys_to_move,
                                                       bool sort_samples);
 
+/**
+ * Same as `mts_tensormap_keys_to_samples`, calling `callback` each time a new
+ * block is created, with the number of blocks created so far and the total
+ * number of blocks in the output. This can be used to report progress when
+ * merging very large tensor maps.
+ *
+ * If `callback` returns a non-zero status, no more blocks are created and
+ * this function returns a `NULL` pointer. If `keys_to_move` does not contain
+ * any dimension, `callback` is not called.
+ *
+ * @param tensor pointer to an existing tensor map
+ * @param keys_to_move description of the keys to move
+ * @param sort_samples whether to sort the samples lexicographically after
+ *                     merging blocks or not
+ * @param callback function called to report progress
+ * @param user_data custom data for the `callback`. This will be passed as the
+ *        first argument to `callback` as-is.
+ *
+ * @returns A pointer to the newly allocated tensor map, or a `NULL` pointer in
+ *          case of error. In case of error, you can use `mts_last_error()`
+ *          to get the error message.
+ */
+struct mts_tensormap_t *mts_tensormap_keys_to_samples_with_progress(const struct mts_tensormap_t *tensor,
+                                                                    struct mts_labels_t keys_to_move,
+                                                                    bool sort_samples,
+                                                                    mts_progress_callback_t callback,
+                                                                    void *user_data);
+
+/**
+ * Same as `mts_tensormap_keys_to_properties`, calling `callback` each time a new
+ * block is created, with the number of blocks created so far and the total
+ * number of blocks in the output. This can be used to report progress when
+ * merging very large tensor maps.
+ *
+ * If `callback` returns a non-zero status, no more blocks are created and
+ * this function returns a `NULL` pointer. If `keys_to_move` does not contain
+ * any dimension, `callback` is not called.
+ *
+ * @param tensor pointer to an existing tensor map
+ * @param keys_to_move description of the keys to move
+ * @param sort_samples whether to sort the samples lexicographically after
+ *                     merging blocks or not
+ * @param callback function called to report progress
+ * @param user_data custom data for the `callback`. This will be passed as the
+ *        first argument to `callback` as-is.
+ *
+ * @returns A pointer to the newly allocated tensor map, or a `NULL` pointer in
+ *          case of error. In case of error, you can use `mts_last_error()`
+ *          to get the error message.
+ */
+struct mts_tensormap_t *mts_tensormap_keys_to_properties_with_progress(const struct mts_tensormap_t *tensor,
+                                                                       struct mts_labels_t keys_to_move,
+                                                                       bool sort_samples,
+                                                                       mts_progress_callback_t callback,
+                                                                       void *user_data);
+
+/**
+ * Sort the keys of this `tensor` in lexicographic order, re-ordering the blocks
+ * to keep them associated with the same keys.
+ *
+ * This invalidates all the block pointers previously obtained from this
+ * tensor map with `mts_tensormap_block_by_id`.
+ *
+ * @param tensor pointer to an existing tensor map
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_tensormap_sort_blocks(struct mts_tensormap_t *tensor);
+
+/**
+ * Only keep the blocks of this `tensor` for which the corresponding entry in
+ * `keep` is `true`, removing all the other blocks and the associated keys. The
+ * order of the remaining blocks is preserved.
+ *
+ * This invalidates all the block pointers previously obtained from this
+ * tensor map with `mts_tensormap_block_by_id`.
+ *
+ * @param tensor pointer to an existing tensor map
+ * @param keep array with one entry for each block in the tensor map,
+ *             indicating whether this block should be kept
+ * @param keep_count number of entries in `keep`, this must be the same as the
+ *                   number of blocks in the tensor map
+ * @param removed pointer to an integer, will be set to the number of blocks
+ *                that were removed
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_tensormap_retain_blocks(struct mts_tensormap_t *tensor,
+                                         const bool *keep,
+                                         uintptr_t keep_count,
+                                         uintptr_t *removed);
+
+/**
+ * Make sure that the labels of each block in this `tensor` are not shared
+ * with any other block, copying the shared labels.
+ *
+ * Labels are usually shared between blocks (and between a block and its
+ * gradients) after operations like `mts_tensormap_keys_to_samples`. Copying
+ * them uses more memory, but ensures that user data attached to the labels of
+ * one block (see `mts_labels_set_user_data`) is not visible from other blocks.
+ *
+ * The user data is not copied together with the labels: the copies start
+ * without any user data, while the original labels keep theirs.
+ *
+ * @param tensor pointer to an existing tensor map
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_tensormap_make_labels_unique(struct mts_tensormap_t *tensor);
+
+/**
+ * Move the given dimensions from the property labels to the component labels
+ * for each block in this tensor map.
+ *
+ * This is the inverse of `mts_tensormap_components_to_properties`. The
+ * `dimensions` must be the first dimensions of the properties, and the
+ * properties of all blocks must form a regular grid over these dimensions.
+ * Each dimension becomes a new component, after the existing components.
+ *
+ * `dimensions` must be an array of `dimensions_count` NULL-terminated strings,
+ * encoded as UTF-8.
+ *
+ * @param tensor pointer to an existing tensor map
+ * @param dimensions names of the property dimensions to move to the components
+ * @param dimensions_count number of entries in the `dimensions` array
+ *
+ * @returns A pointer to the newly allocated tensor map, or a `NULL` pointer in
+ *          case of error. In case of error, you can use `mts_last_error()`
+ *          to get the error message.
+ */
+struct mts_tensormap_t *mts_tensormap_properties_to_components(const struct mts_tensormap_t *tensor,
+                                                               const char *const *dimensions,
+                                                               uintptr_t dimensions_count);
+
+/**
+ * Concatenate multiple tensor maps along the property axis.
+ *
+ * All the tensor maps must have the same keys, and for each key the blocks
+ * must have the same samples and components. The properties of the blocks are
+ * concatenated in the order of `tensors`, and must not contain the same entry
+ * more than once. Gradient samples are merged across the blocks.
+ *
+ * If `fill_missing_gradients` is `false`, all the tensor maps must have
+ * gradients with respect to the same parameters. If it is `true`, gradients
+ * which are only defined in some of the tensor maps are considered to be zero
+ * in the others.
+ *
+ * The memory allocated by this function should be released using
+ * `mts_tensormap_free`.
+ *
+ * @param tensors pointer to the first element of an array of tensor maps
+ * @param tensors_count number of elements in the `tensors` array
+ * @param fill_missing_gradients should gradients which are only defined in
+ *        some of the tensor maps be filled with zeros in the others?
+ *
+ * @returns A pointer to the newly allocated tensor map, or a `NULL` pointer in
+ *          case of error. In case of error, you can use `mts_last_error()`
+ *          to get the error message.
+ */
+struct mts_tensormap_t *mts_tensormap_concatenate_properties(const struct mts_tensormap_t *const *tensors,
+                                                             uintptr_t tensors_count,
+                                                             bool fill_missing_gradients);
+
+/**
+ * Concatenate multiple tensor maps along the sample axis.
+ *
+ * All the tensor maps must have the same keys, and for each key the blocks
+ * must have the same components and properties. The samples of the blocks are
+ * concatenated in the order of `tensors`, and must not contain the same entry
+ * more than once. The gradient samples are updated to refer to the samples in
+ * the concatenated blocks.
+ *
+ * If `fill_missing_gradients` is `false`, all the tensor maps must have
+ * gradients with respect to the same parameters. If it is `true`, gradients
+ * which are only defined in some of the tensor maps are considered to be zero
+ * in the others.
+ *
+ * The memory allocated by this function should be released using
+ * `mts_tensormap_free`.
+ *
+ * @param tensors pointer to the first element of an array of tensor maps
+ * @param tensors_count number of elements in the `tensors` array
+ * @param fill_missing_gradients should gradients which are only defined in
+ *        some of the tensor maps be filled with zeros in the others?
+ *
+ * @returns A pointer to the newly allocated tensor map, or a `NULL` pointer in
+ *          case of error. In case of error, you can use `mts_last_error()`
+ *          to get the error message.
+ */
+struct mts_tensormap_t *mts_tensormap_concatenate_samples(const struct mts_tensormap_t *const *tensors,
+                                                          uintptr_t tensors_count,
+                                                          bool fill_missing_gradients);
+
+/**
+ * Get the estimated size in bytes of the data stored in all the blocks of
+ * this `tensor`, including the data of all gradients.
+ *
+ * The size is computed from the shape of the arrays, assuming they contain
+ * 64-bit floating point values.
+ *
+ * @param tensor pointer to an existing tensor map
+ * @param nbytes pointer to an integer, will be set to the size in bytes
+ *
+ * @returns The status code of this operation. If the status is not
+ *          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+ *          error message.
+ */
+mts_status_t mts_tensormap_nbytes(const struct mts_tensormap_t *tensor, uintptr_t *nbytes);
+
+/**
+ * Create a new tensor map with the same blocks as `tensor`, and keys
+ * transformed by the `callback` function.
+ *
+ * The `callback` is called once for each key in the tensor map, and should
+ * compute the new values for this key. The names of the keys are not
+ * modified, and the new keys must be unique.
+ *
+ * The result is a new tensor map, which should be freed with `mts_tensormap_free`.
+ *
+ * @param tensor pointer to an existing tensor map
+ * @param callback function used to compute the new values of each key
+ * @param user_data custom data for the `callback`. This will be passed as the
+ *        first argument to `callback` as-is.
+ *
+ * @returns A pointer to the newly allocated tensor map, or a `NULL` pointer in
+ *          case of error. In case of error, you can use `mts_last_error()`
+ *          to get the error message.
+ */
+struct mts_tensormap_t *mts_tensormap_map_keys(const struct mts_tensormap_t *tensor,
+                                               mts_map_keys_callback_t callback,
+                                               void *user_data);
+
+/**
+ * Create a new tensor map where each block only contains the properties
+ * selected by the `callback` function, together with the corresponding values
+ * and gradients.
+ *
+ * The `callback` is called once for each property of each block. The samples
+ * and components of the blocks are not modified. If all the properties of a
+ * block are removed, the corresponding block in the output will have zero
+ * properties.
+ *
+ * The result is a new tensor map, which should be freed with `mts_tensormap_free`.
+ *
+ * @param tensor pointer to an existing tensor map
+ * @param callback function used to decide which properties to keep
+ * @param user_data custom data for the `callback`. This will be passed as the
+ *        first argument to `callback` as-is.
+ *
+ * @returns A pointer to the newly allocated tensor map, or a `NULL` pointer in
+ *          case of error. In case of error, you can use `mts_last_error()`
+ *          to get the error message.
+ */
+struct mts_tensormap_t *mts_tensormap_filter_properties(const struct mts_tensormap_t *tensor,
+                                                        mts_filter_properties_callback_t callback,
+                                                        void *user_data);
+
 /**
  * Load labels from the file at the given path.
  *
@@ -2147,12 +2147,34 @@ public:
     /// blocks with properties `p=1, 2` will result in `a, p = (2, 1), (2, 2),
     /// (3, 1), (3, 2)`.
     ///
+    /// The moved dimensions are always added before the existing dimensions of
+    /// the properties, in the same order as in `keys_to_move`. For example,
+    /// moving `c, a` from keys `a, b, c` to blocks with properties `n, l` gives
+    /// properties `c, a, n, l`.
+    ///
+    /// The new keys contain the dimensions of the current keys which are not
+    /// moved, in the same order as in the current keys. If all the dimensions
+    /// are moved, the new keys contain a single `_` dimension with a single
+    /// entry.
+    ///
     /// The new sample labels will contains all of the merged blocks sample
     /// labels. The order of the samples is controlled by `sort_samples`. If
     /// `sort_samples` is true, samples are re-ordered to keep them
     /// lexicographically sorted. Otherwise they are kept in the order in which
     /// they appear in the blocks.
     ///
+    /// The samples of the merged blocks do not need to be the same. If a
+    /// sample is not present in one of the merged blocks, the values (and
+    /// gradients) for this sample in the properties coming from this block
+    /// are set to zero.
+    ///
+    /// The same applies to the gradients: the gradient samples of the merged
+    /// blocks do not need to be the same, and the gradient samples of the new
+    /// block contain all of them (after updating the `sample` dimension to
+    /// refer to the new samples). Gradients which are not present in one of
+    /// the merged blocks are set to zero in the properties coming from this
+    /// block.
+    ///
     /// @param keys_to_move description of the keys to move
     /// @param sort_samples whether to sort the merged samples or keep them in
     ///                     the order in which they appear in the original blocks
@@ -2186,10 +2208,22 @@ public:
     /// the sample labels, and blocks with the same remaining keys dimensions
     /// will be merged together along the sample axis.
     ///
+    /// Blocks are grouped by the values of the remaining keys dimensions:
+    /// blocks which only differ in the moved dimensions are merged together,
+    /// while blocks with different values for any of the remaining dimensions
+    /// are kept separate. The new keys contain the unique values of the
+    /// remaining dimensions, in the order in which they first appear in the
+    /// current keys.
+    ///
     /// If `keys_to_move` must be an empty set of `Labels`
     /// (`keys_to_move.count() == 0`). The new sample labels will contain
     /// entries corresponding to the merged blocks' keys.
     ///
+    /// The moved dimensions are always added after the existing dimensions of
+    /// the samples, in the same order as in `keys_to_move`. For example, moving
+    /// `c, a` from keys `a, b, c` to blocks with samples `system, atom` gives
+    /// samples `system, atom, c, a`.
+    ///
     /// The order of the samples is controlled by `sort_samples`. If
     /// `sort_samples` is true, samples are re-ordered to keep them
     /// lexicographically sorted. Otherwise they are kept in the order in which
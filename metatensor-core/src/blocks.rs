@@ -2,9 +2,11 @@ use std::sync::Arc;
 use std::ffi::CString;
 use std::collections::{HashMap, BTreeSet};
 
+use indexmap::IndexSet;
+
 use crate::utils::ConstCString;
-use crate::{Labels, LabelsBuilder};
-use crate::{mts_array_t, get_data_origin};
+use crate::{Labels, LabelsBuilder, LabelValue};
+use crate::{mts_array_t, mts_sample_mapping_t, get_data_origin};
 use crate::Error;
 
 /// A `Vec` which can not be modified
@@ -117,18 +119,83 @@ pub struct TensorBlock {
     gradients: HashMap<String, TensorBlock>,
     // all the keys from `self.gradients`, as C-compatible strings
     gradient_parameters: Vec<ConstCString>,
+    // optional physical units of the values in this block
+    units: Option<ConstCString>,
+    // is this block stored as a gradient inside another block?
+    is_gradient: bool,
+}
+
+/// Check that the `new` labels can replace the `current` ones without
+/// modifying the data, i.e. that they have the same names and size.
+fn check_same_names_and_count(axis: &str, current: &Labels, new: &Labels) -> Result<(), Error> {
+    if !new.same_names(current) {
+        return Err(Error::InvalidParameter(format!(
+            "can not set {}: expected labels with names [{}], got [{}]",
+            axis, current.names().join(", "), new.names().join(", ")
+        )));
+    }
+
+    if new.count() != current.count() {
+        return Err(Error::InvalidParameter(format!(
+            "can not set {}: expected {} entries, got {}",
+            axis, current.count(), new.count()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Create a new array containing the entries of `array` along the first axis
+/// given in `mapping`, using `mts_array_t::move_samples_from`. The new array
+/// contains `count` entries along the first axis.
+fn gather_first_axis(array: &mts_array_t, mapping: &[mts_sample_mapping_t], count: usize) -> Result<mts_array_t, Error> {
+    let mut shape = array.shape()?.to_vec();
+    shape[0] = count;
+    let last_axis = shape.len() - 1;
+
+    let mut output = array.create(&shape)?;
+    output.move_samples_from(array, mapping, 0..shape[last_axis])?;
+
+    return Ok(output);
+}
+
+/// Replace `labels` with a copy if they are shared with another `Arc`.
+///
+/// The user data attached to the labels (see `Labels::set_user_data`) is not
+/// copied, since we have no way to duplicate it and sharing the same pointer
+/// would call its `delete` function twice. The copy starts without user data,
+/// and the original labels keep theirs.
+fn make_unique(labels: &mut Arc<Labels>) {
+    if Arc::get_mut(labels).is_some() {
+        return;
+    }
+
+    let mut builder = LabelsBuilder::new(labels.names()).expect("invalid labels names");
+    builder.reserve(labels.count());
+    for entry in &**labels {
+        builder.add(entry).expect("duplicated entry in labels");
+    }
+    *labels = Arc::new(builder.finish());
 }
 
 impl TensorBlock {
     /// Create a new `TensorBlock` containing the given values, described by the
     /// `samples`, `components`, and `properties` labels. The block is
     /// initialized without any gradients.
+    ///
+    /// `samples` and `properties` can be given either as `Labels` or as
+    /// `Arc<Labels>`, allowing to share them with other blocks. `components`
+    /// is always a `Vec<Arc<Labels>>`: with a generic element type, the type
+    /// of an empty `vec![]` (used for blocks without components) could not be
+    /// inferred.
     pub fn new(
         values: mts_array_t,
-        samples: Arc<Labels>,
+        samples: impl Into<Arc<Labels>>,
         components: Vec<Arc<Labels>>,
-        properties: Arc<Labels>,
+        properties: impl Into<Arc<Labels>>,
     ) -> Result<TensorBlock, Error> {
+        let samples = samples.into();
+        let properties = properties.into();
         check_data_and_labels(
             "data and labels don't match", &values, &samples, &components, &properties
         )?;
@@ -141,6 +208,8 @@ impl TensorBlock {
             properties,
             gradients: HashMap::new(),
             gradient_parameters: Vec::new(),
+            units: None,
+            is_gradient: false,
         })
     }
 
@@ -153,7 +222,9 @@ impl TensorBlock {
         // Try to clone all gradient blocks
         let mut gradients = HashMap::new();
         for (gradient_parameter, gradient_block) in &self.gradients {
-            gradients.insert(gradient_parameter.clone(), gradient_block.try_clone()?);
+            let mut gradient = gradient_block.try_clone()?;
+            gradient.is_gradient = true;
+            gradients.insert(gradient_parameter.clone(), gradient);
         }
         let gradient_parameters = self.gradient_parameters.clone();
 
@@ -163,10 +234,65 @@ impl TensorBlock {
             components: self.components.clone(),
             properties: Arc::clone(&self.properties),
             gradients,
-            gradient_parameters
+            gradient_parameters,
+            units: self.units.clone(),
+            is_gradient: false,
         })
     }
 
+    /// Create a new `TensorBlock` with the same metadata as this one (sharing
+    /// the labels), and values filled with zeros. The new arrays are allocated
+    /// with `mts_array_t::create`, and gradients (including gradients of
+    /// gradients) are created in the same way.
+    pub fn zeros_like(&self) -> Result<TensorBlock, Error> {
+        let values = self.values.create(self.values.shape()?)?;
+
+        let mut gradients = HashMap::new();
+        for (gradient_parameter, gradient_block) in &self.gradients {
+            let mut gradient = gradient_block.zeros_like()?;
+            gradient.is_gradient = true;
+            gradients.insert(gradient_parameter.clone(), gradient);
+        }
+
+        Ok(TensorBlock {
+            values,
+            samples: Arc::clone(&self.samples),
+            components: self.components.clone(),
+            properties: Arc::clone(&self.properties),
+            gradients,
+            gradient_parameters: self.gradient_parameters.clone(),
+            units: self.units.clone(),
+            is_gradient: false,
+        })
+    }
+
+    /// Get the physical units of the values in this block, if they have been
+    /// set with `set_units`.
+    pub fn units(&self) -> Option<&ConstCString> {
+        self.units.as_ref()
+    }
+
+    /// Set the physical units of the values in this block (e.g. "eV", or
+    /// "eV/Angstrom" for a gradient), or remove them if `units` is `None`.
+    ///
+    /// The units are free-form strings, and are only used as metadata. They
+    /// are kept by `try_clone` and `zeros_like`, and when saving and loading
+    /// tensor maps, but operations creating new blocks (e.g.
+    /// `keys_to_samples`) do not propagate them.
+    pub fn set_units(&mut self, units: Option<&str>) -> Result<(), Error> {
+        self.units = match units {
+            Some(units) => {
+                let units = CString::new(units).map_err(|_| Error::InvalidParameter(
+                    "units can not contain NULL bytes".into()
+                ))?;
+                Some(ConstCString::new(units))
+            }
+            None => None,
+        };
+
+        Ok(())
+    }
+
     /// Get all gradients defined in this block
     pub fn gradients(&self) -> &HashMap<String, TensorBlock> {
         &self.gradients
@@ -189,6 +315,37 @@ impl TensorBlock {
         &self.gradient_parameters
     }
 
+    /// Get the estimated size in bytes of the data in this block, including
+    /// the data of all gradients (and gradients of gradients).
+    pub fn nbytes(&self) -> Result<usize, Error> {
+        let mut nbytes = self.values.nbytes()?;
+        for gradient in self.gradients.values() {
+            nbytes += gradient.nbytes()?;
+        }
+        return Ok(nbytes);
+    }
+
+    /// Make sure all the labels in this block (and its gradients) are not
+    /// shared with any other block, copying them if needed.
+    ///
+    /// Labels which are already only used by this block are not copied. The
+    /// copied labels do not carry over the user data of the original labels.
+    pub fn make_labels_unique(&mut self) {
+        make_unique(&mut self.samples);
+
+        let mut components = std::mem::take(&mut self.components.0);
+        for component in &mut components {
+            make_unique(component);
+        }
+        self.components = ImmutableVec(components);
+
+        make_unique(&mut self.properties);
+
+        for gradient in self.gradients.values_mut() {
+            gradient.make_labels_unique();
+        }
+    }
+
     /// Add a gradient with respect to `parameter` to this block.
     ///
     /// The gradient `data` is given as an array, and the samples and components
@@ -200,7 +357,7 @@ impl TensorBlock {
     pub fn add_gradient(
         &mut self,
         parameter: &str,
-        gradient: TensorBlock
+        mut gradient: TensorBlock
     ) -> Result<(), Error> {
         if self.gradients.contains_key(parameter) {
             return Err(Error::InvalidParameter(format!(
@@ -208,6 +365,19 @@ impl TensorBlock {
             )));
         }
 
+        self.check_gradient(&gradient)?;
+
+        gradient.is_gradient = true;
+        self.gradients.insert(parameter.into(), gradient);
+
+        let parameter = ConstCString::new(CString::new(parameter.to_owned()).expect("invalid C string"));
+        self.gradient_parameters.push(parameter);
+
+        return Ok(());
+    }
+
+    /// Check that `gradient` can be used as a gradient of this block.
+    fn check_gradient(&self, gradient: &TensorBlock) -> Result<(), Error> {
         if gradient.values.origin()? != self.values.origin()? {
             return Err(Error::InvalidParameter(format!(
                 "the gradient data has a different origin ('{}') than the value data ('{}')",
@@ -269,12 +439,452 @@ impl TensorBlock {
                 }
             }
 
-        self.gradients.insert(parameter.into(), gradient);
+        Ok(())
+    }
 
-        let parameter = ConstCString::new(CString::new(parameter.to_owned()).expect("invalid C string"));
-        self.gradient_parameters.push(parameter);
+    /// Check that this block is still valid, i.e. that the values array
+    /// matches the labels, and that all gradients (and gradients of gradients)
+    /// are consistent with the values. This runs the same checks as
+    /// `TensorBlock::new` and `TensorBlock::add_gradient`.
+    pub fn check_invariants(&self) -> Result<(), Error> {
+        check_data_and_labels(
+            "data and labels don't match", &self.values, &self.samples, &self.components, &self.properties
+        )?;
+        check_component_labels(&self.components)?;
 
-        return Ok(());
+        for parameter in &self.gradient_parameters {
+            let gradient = &self.gradients[parameter.as_str()];
+            gradient.check_invariants()?;
+            self.check_gradient(gradient)?;
+        }
+
+        Ok(())
+    }
+
+    /// Restore the consistency between the samples of this block and the
+    /// samples of all its gradients, after a sequence of mutations which did
+    /// not maintain it.
+    ///
+    /// Gradient samples referring to value samples which no longer exist are
+    /// removed, and the remaining gradient samples are re-ordered to follow the
+    /// order of the value samples. This is applied recursively to gradients of
+    /// gradients. An error is returned if the gradients can not be made
+    /// consistent with the values (e.g. if the properties no longer match).
+    pub fn validate_and_compact_gradients(&mut self) -> Result<(), Error> {
+        let n_samples = self.samples.count();
+        for (parameter, gradient) in &mut self.gradients {
+            if gradient.samples.size() == 0 || gradient.samples.names()[0] != "sample" {
+                return Err(Error::InvalidParameter(format!(
+                    "the first dimension in the samples of the gradient with \
+                    respect to '{}' should be 'sample'", parameter
+                )));
+            }
+
+            if gradient.properties != self.properties {
+                return Err(Error::InvalidParameter(format!(
+                    "the properties of the gradient with respect to '{}' are \
+                    not the same as the values properties", parameter
+                )));
+            }
+
+            if !gradient.components.ends_with(&self.components) {
+                return Err(Error::InvalidParameter(format!(
+                    "the components of the gradient with respect to '{}' do \
+                    not end with the values components", parameter
+                )));
+            }
+
+            // keep the gradient samples referring to existing values samples,
+            // sorted by the values sample they refer to
+            let mut kept = gradient.samples.iter()
+                .enumerate()
+                .filter(|(_, sample)| sample[0].i32() >= 0 && sample[0].usize() < n_samples)
+                .map(|(i, sample)| (sample[0], i))
+                .collect::<Vec<_>>();
+            kept.sort();
+            let kept = kept.into_iter().map(|(_, i)| i).collect::<Vec<_>>();
+
+            let unchanged = kept.len() == gradient.samples.count()
+                && kept.iter().enumerate().all(|(new, &old)| new == old);
+
+            if !unchanged {
+                let old_count = gradient.samples.count();
+                let mut new_samples = LabelsBuilder::new(gradient.samples.names())?;
+                new_samples.reserve(kept.len());
+                for &old in &kept {
+                    new_samples.add(&gradient.samples[old])?;
+                }
+                gradient.gather_samples(Arc::new(new_samples.finish()), &kept)?;
+
+                // update the gradients of gradients to refer to the new
+                // positions of the gradient samples
+                let mut new_positions = vec![None; old_count];
+                for (new, &old) in kept.iter().enumerate() {
+                    new_positions[old] = Some(new);
+                }
+
+                for sub_gradient in gradient.gradients.values_mut() {
+                    sub_gradient.renumber_samples(&new_positions)?;
+                }
+            }
+
+            gradient.validate_and_compact_gradients()?;
+        }
+
+        Ok(())
+    }
+
+    /// Update the first dimension of the samples of this (gradient) block,
+    /// which refers to a sample in the parent block, according to
+    /// `new_positions`. Samples refering to a removed parent sample (`None` in
+    /// `new_positions`) are removed, together with the corresponding data.
+    fn renumber_samples(&mut self, new_positions: &[Option<usize>]) -> Result<(), Error> {
+        let mut kept = Vec::new();
+        let mut new_samples = LabelsBuilder::new(self.samples.names())?;
+        for (i, sample) in self.samples.iter().enumerate() {
+            let new_position = if sample[0].i32() >= 0 {
+                new_positions.get(sample[0].usize()).copied().flatten()
+            } else {
+                None
+            };
+
+            if let Some(new_position) = new_position {
+                let mut sample = sample.to_vec();
+                sample[0] = new_position.into();
+                new_samples.add(&sample)?;
+                kept.push(i);
+            }
+        }
+
+        return self.gather_samples(Arc::new(new_samples.finish()), &kept);
+    }
+
+    /// Replace the samples of this block with `new_samples`, taking the data
+    /// for the new sample `i` from the old sample `rows[i]`. The gradients of
+    /// this block are not modified.
+    fn gather_samples(&mut self, new_samples: Arc<Labels>, rows: &[usize]) -> Result<(), Error> {
+        debug_assert_eq!(new_samples.count(), rows.len());
+
+        let mut new_shape = self.values.shape()?.to_vec();
+        new_shape[0] = rows.len();
+        let mut new_values = self.values.create(&new_shape)?;
+
+        let mapping = rows.iter()
+            .enumerate()
+            .map(|(output, &input)| mts_sample_mapping_t { input, output })
+            .collect::<Vec<_>>();
+
+        let property_axis = new_shape.len() - 1;
+        new_values.move_samples_from(&self.values, &mapping, 0..new_shape[property_axis])?;
+
+        self.values = new_values;
+        self.samples = new_samples;
+
+        Ok(())
+    }
+
+    /// Add new samples at the end of this block, with the corresponding
+    /// `values`.
+    ///
+    /// The `samples` must have the same names as the existing samples, and
+    /// must not contain any of the existing samples. The `values` must have
+    /// the same shape as the values of this block, except for the first
+    /// dimension. This is not supported for blocks with gradients.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn append_samples(&mut self, samples: &Labels, values: mts_array_t) -> Result<(), Error> {
+        if !self.gradients.is_empty() {
+            return Err(Error::InvalidParameter(
+                "can not append samples to a block with gradients".into()
+            ));
+        }
+
+        samples.assert_same_names(&self.samples)?;
+        check_data_and_labels(
+            "new values and samples don't match", &values, samples, &self.components, &self.properties
+        )?;
+
+        let origin = self.values.origin()?;
+        let new_origin = values.origin()?;
+        if origin != new_origin {
+            return Err(Error::InvalidParameter(format!(
+                "can not append values with a different origin ('{}') to a block with origin ('{}')",
+                get_data_origin(new_origin), get_data_origin(origin),
+            )));
+        }
+
+        let old_count = self.samples.count();
+        let mut new_samples = LabelsBuilder::new(self.samples.names())?;
+        new_samples.reserve(old_count + samples.count());
+        for sample in self.samples.iter().chain(samples.iter()) {
+            new_samples.add(sample)?;
+        }
+        let new_samples = new_samples.finish();
+
+        let mut new_shape = self.values.shape()?.to_vec();
+        new_shape[0] = new_samples.count();
+        let mut new_values = self.values.create(&new_shape)?;
+
+        let properties_count = self.properties.count();
+        if properties_count != 0 {
+            let old_mapping = (0..old_count)
+                .map(|i| mts_sample_mapping_t { input: i, output: i })
+                .collect::<Vec<_>>();
+            new_values.move_samples_from(&self.values, &old_mapping, 0..properties_count)?;
+
+            let new_mapping = (0..samples.count())
+                .map(|i| mts_sample_mapping_t { input: i, output: old_count + i })
+                .collect::<Vec<_>>();
+            new_values.move_samples_from(&values, &new_mapping, 0..properties_count)?;
+        }
+
+        self.values = new_values;
+        self.samples = Arc::new(new_samples);
+
+        Ok(())
+    }
+
+    /// Re-order the properties of this block (and of all its gradients) to
+    /// follow the order of the entries in `order`.
+    ///
+    /// `order` must have the same names as the properties of this block, and
+    /// contain exactly the same entries, potentially in a different order.
+    /// This can not be called on gradient blocks, since their properties must
+    /// stay the same as the properties of the parent block.
+    pub fn reorder_properties(&mut self, order: &Labels) -> Result<(), Error> {
+        if self.is_gradient {
+            return Err(Error::InvalidParameter(
+                "can not reorder the properties of a gradient block, reorder \
+                the properties of the parent block instead".into()
+            ));
+        }
+
+        order.assert_same_names(&self.properties)?;
+
+        if order.count() != self.properties.count() {
+            return Err(Error::InvalidParameter(format!(
+                "can not reorder properties: expected {} entries in the new \
+                order, got {}", self.properties.count(), order.count()
+            )));
+        }
+
+        let mut mapping = Vec::with_capacity(order.count());
+        for (new_i, entry) in order.iter().enumerate() {
+            let old_i = self.properties.position(entry).ok_or_else(|| Error::InvalidParameter(format!(
+                "can not reorder properties: [{}] is not one of the properties of this block",
+                entry.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+            )))?;
+
+            mapping.push(mts_sample_mapping_t {
+                input: old_i,
+                output: new_i,
+            });
+        }
+
+        if mapping.iter().all(|m| m.input == m.output) {
+            return Ok(());
+        }
+
+        let mut new_properties = LabelsBuilder::new(order.names())?;
+        new_properties.reserve(order.count());
+        for entry in order {
+            new_properties.add(entry)?;
+        }
+        let new_properties = Arc::new(new_properties.finish());
+
+        return self.permute_properties(&mapping, &new_properties);
+    }
+
+    /// Only keep the properties of this block for which `keep` returns
+    /// `true`, removing the corresponding data from the values and all
+    /// gradients. This can leave the block without any properties. As for
+    /// `reorder_properties`, this can not be called on gradient blocks.
+    pub fn filter_properties<F>(&mut self, mut keep: F) -> Result<(), Error>
+        where F: FnMut(&[LabelValue]) -> Result<bool, Error>
+    {
+        if self.is_gradient {
+            return Err(Error::InvalidParameter(
+                "can not filter the properties of a gradient block, filter \
+                the properties of the parent block instead".into()
+            ));
+        }
+
+        let mut mapping = Vec::new();
+        let mut new_properties = LabelsBuilder::new(self.properties.names())?;
+        for (old_i, entry) in self.properties.iter().enumerate() {
+            if keep(entry)? {
+                mapping.push(mts_sample_mapping_t {
+                    input: old_i,
+                    output: mapping.len(),
+                });
+                new_properties.add(entry)?;
+            }
+        }
+
+        if mapping.len() == self.properties.count() {
+            return Ok(());
+        }
+
+        let new_properties = Arc::new(new_properties.finish());
+        return self.permute_properties(&mapping, &new_properties);
+    }
+
+    /// Replace the samples of this block with `samples`, without modifying
+    /// the data. This can be used to change the values of the samples in
+    /// place (e.g. to renumber systems).
+    ///
+    /// The new samples must have the same names and the same number of
+    /// entries as the current ones. Gradients refer to the samples by
+    /// position, and are not modified. The samples of gradient blocks can not
+    /// be set with this function, use `set_gradient_samples` on the parent
+    /// block instead.
+    pub fn set_samples(&mut self, samples: Arc<Labels>) -> Result<(), Error> {
+        if self.is_gradient {
+            return Err(Error::InvalidParameter(
+                "can not set the samples of a gradient block directly, they \
+                must be set from the parent block".into()
+            ));
+        }
+
+        check_same_names_and_count("samples", &self.samples, &samples)?;
+        self.samples = samples;
+        Ok(())
+    }
+
+    /// Replace the samples of the gradient with respect to `parameter` in
+    /// this block with `samples`, without modifying the data.
+    ///
+    /// The new samples must have the same names and the same number of
+    /// entries as the current gradient samples, and the `sample` dimension
+    /// must refer to existing samples of this block.
+    pub fn set_gradient_samples(&mut self, parameter: &str, samples: Arc<Labels>) -> Result<(), Error> {
+        let n_samples = self.samples.count();
+        let gradient = self.gradients.get_mut(parameter).ok_or_else(|| Error::InvalidParameter(format!(
+            "can not find gradients with respect to '{}' in this block", parameter
+        )))?;
+
+        check_same_names_and_count("gradient samples", &gradient.samples, &samples)?;
+        for sample in &*samples {
+            if sample[0].isize() < 0 || sample[0].usize() >= n_samples {
+                return Err(Error::InvalidParameter(format!(
+                    "invalid value for the 'sample' in gradient samples: we got \
+                    {}, but the values contain {} samples", sample[0], n_samples
+                )));
+            }
+        }
+
+        gradient.samples = samples;
+        Ok(())
+    }
+
+    /// Replace the properties of this block and all of its gradients with
+    /// `properties`, without modifying the data.
+    ///
+    /// The new properties must have the same names and the same number of
+    /// entries as the current ones. This can not be called on gradient
+    /// blocks, since their properties must stay the same as the properties
+    /// of the parent block.
+    pub fn set_properties(&mut self, properties: Arc<Labels>) -> Result<(), Error> {
+        if self.is_gradient {
+            return Err(Error::InvalidParameter(
+                "can not set the properties of a gradient block, set the \
+                properties of the parent block instead".into()
+            ));
+        }
+
+        check_same_names_and_count("properties", &self.properties, &properties)?;
+        self.replace_properties(properties);
+
+        Ok(())
+    }
+
+    /// Set the properties of this block and all of its gradients (including
+    /// gradients of gradients) to `properties`
+    fn replace_properties(&mut self, properties: Arc<Labels>) {
+        for gradient in self.gradients.values_mut() {
+            gradient.replace_properties(Arc::clone(&properties));
+        }
+        self.properties = properties;
+    }
+
+    /// Move the data along the property axis of this block and all of its
+    /// gradients according to `mapping`, and set the properties to
+    /// `new_properties`. Properties which do not appear in `mapping` are
+    /// removed.
+    fn permute_properties(
+        &mut self,
+        mapping: &[mts_sample_mapping_t],
+        new_properties: &Arc<Labels>,
+    ) -> Result<(), Error> {
+        // create the new arrays for the values and all the gradients before
+        // modifying anything, to leave the block unchanged in case of error
+        let mut new_arrays = Vec::new();
+        self.permuted_properties_arrays(mapping, new_properties.count(), &mut new_arrays)?;
+
+        self.set_permuted_properties(&mut new_arrays.into_iter(), new_properties);
+
+        Ok(())
+    }
+
+    /// Create new arrays containing the values of this block and of all its
+    /// gradients (depth-first, in the order of `gradient_parameters`) with the
+    /// data moved along the property axis according to `mapping`, and add them
+    /// to `arrays`.
+    fn permuted_properties_arrays(
+        &mut self,
+        mapping: &[mts_sample_mapping_t],
+        n_properties: usize,
+        arrays: &mut Vec<mts_array_t>,
+    ) -> Result<(), Error> {
+        let shape = self.values.shape()?.to_vec();
+        let properties_axis = shape.len() - 1;
+
+        let mut new_shape = shape.clone();
+        new_shape[properties_axis] = n_properties;
+        let mut new_values = self.values.create(&new_shape)?;
+
+        if shape[0] != 0 && !mapping.is_empty() {
+            // `move_samples_from` can only move data along the first axis, so
+            // we first gather the properties in a temporary array with the
+            // properties on the first axis. The temporary array is then copied
+            // to `new_values`, which was created directly with the final shape
+            // and uses the default memory layout of the array backend.
+            self.values.swap_axes(0, properties_axis)?;
+            let permuted = gather_first_axis(&self.values, mapping, new_shape[properties_axis]);
+            self.values.swap_axes(0, properties_axis)?;
+
+            let mut permuted = permuted?;
+            permuted.swap_axes(0, properties_axis)?;
+
+            let samples = (0..shape[0])
+                .map(|i| mts_sample_mapping_t { input: i, output: i })
+                .collect::<Vec<_>>();
+            new_values.move_samples_from(&permuted, &samples, 0..new_shape[properties_axis])?;
+        }
+        arrays.push(new_values);
+
+        for parameter in &self.gradient_parameters {
+            let gradient = self.gradients.get_mut(parameter.as_str()).expect("missing gradient");
+            gradient.permuted_properties_arrays(mapping, n_properties, arrays)?;
+        }
+
+        Ok(())
+    }
+
+    /// Replace the values of this block and all of its gradients with the
+    /// arrays created by `permuted_properties_arrays`, and set the properties
+    /// to `new_properties`.
+    fn set_permuted_properties(
+        &mut self,
+        arrays: &mut impl Iterator<Item=mts_array_t>,
+        new_properties: &Arc<Labels>,
+    ) {
+        self.values = arrays.next().expect("missing permuted array");
+        self.properties = Arc::clone(new_properties);
+
+        for parameter in &self.gradient_parameters {
+            let gradient = self.gradients.get_mut(parameter.as_str()).expect("missing gradient");
+            gradient.set_permuted_properties(arrays, new_properties);
+        }
     }
 
     /// Move components to properties for this block and all gradients in this
@@ -320,7 +930,13 @@ impl TensorBlock {
         new_shape[properties_axis] = new_properties.count();
         new_shape.remove(component_axis + 1);
 
-        self.values.swap_axes(component_axis + 1, properties_axis - 1)?;
+        // move the component axis right before the properties axis, keeping
+        // the other components in the same order. If this is already the last
+        // component, the data does not need to move and only a reshape is
+        // required.
+        for axis in (component_axis + 1)..(properties_axis - 1) {
+            self.values.swap_axes(axis, axis + 1)?;
+        }
         self.values.reshape(&new_shape)?;
 
         self.properties = Arc::new(new_properties);
@@ -332,6 +948,134 @@ impl TensorBlock {
 
         Ok(())
     }
+
+    /// Move properties to components for this block and all gradients in this
+    /// block. This is the inverse of `components_to_properties`.
+    ///
+    /// The `dimensions` must be the first dimensions of the properties, and
+    /// the properties must form a regular grid over these dimensions, i.e.
+    /// contain all the combinations of values for `dimensions` and the
+    /// remaining dimensions, in lexicographic order. Each dimension becomes a
+    /// new component, added after the existing ones. The data is not moved,
+    /// only reshaped.
+    pub fn properties_to_components(&mut self, dimensions: &[&str]) -> Result<(), Error> {
+        if dimensions.is_empty() {
+            return Ok(());
+        }
+
+        let property_names = self.properties.names();
+        let n_moved = dimensions.len();
+        if property_names.len() < n_moved || property_names[..n_moved] != *dimensions {
+            return Err(Error::InvalidParameter(format!(
+                "[{}] must be the first dimensions of the properties, got properties [{}]",
+                dimensions.join(", "), property_names.join(", ")
+            )));
+        }
+
+        if property_names.len() == n_moved {
+            return Err(Error::InvalidParameter(format!(
+                "can not move all the properties dimensions ([{}]) to the components",
+                property_names.join(", ")
+            )));
+        }
+
+        self.check_no_component_named(dimensions)?;
+
+        // unique values taken by each of the moved dimensions, and by the
+        // remaining dimensions, in the order they appear in the properties
+        let mut moved_values = vec![IndexSet::new(); n_moved];
+        let mut remaining = IndexSet::new();
+        for property in self.properties.iter() {
+            for (values, &value) in moved_values.iter_mut().zip(&property[..n_moved]) {
+                values.insert(value);
+            }
+            remaining.insert(&property[n_moved..]);
+        }
+
+        let grid_size = moved_values.iter().map(IndexSet::len).product::<usize>() * remaining.len();
+        let mut regular_grid = grid_size == self.properties.count();
+        if regular_grid {
+            for (i, property) in self.properties.iter().enumerate() {
+                let mut index = i / remaining.len();
+                regular_grid &= remaining[i % remaining.len()] == &property[n_moved..];
+                for (values, &value) in moved_values.iter().zip(&property[..n_moved]).rev() {
+                    regular_grid &= values[index % values.len()] == value;
+                    index /= values.len();
+                }
+            }
+        }
+
+        if !regular_grid {
+            return Err(Error::InvalidParameter(format!(
+                "the properties do not form a regular grid over [{}], unable \
+                to move these dimensions to the components",
+                dimensions.join(", ")
+            )));
+        }
+
+        let mut new_components = Vec::new();
+        for (&name, values) in dimensions.iter().zip(&moved_values) {
+            let mut component = LabelsBuilder::new(vec![name])?;
+            for &value in values {
+                component.add(&[value])?;
+            }
+            new_components.push(component.finish().into_arc());
+        }
+
+        let mut new_properties = LabelsBuilder::new(property_names[n_moved..].to_vec())?;
+        for &property in &remaining {
+            new_properties.add(property)?;
+        }
+        let new_properties = new_properties.finish();
+
+        // all the checks above also apply to the gradients, since they share
+        // the properties of the values and `check_no_component_named` looks
+        // at their components. The data can now be reshaped.
+        return self.split_properties_axis(&new_components, &new_properties.into_arc());
+    }
+
+    /// Check that neither this block nor any of its gradients (including
+    /// gradients of gradients) has a component with one of the given `names`
+    fn check_no_component_named(&self, names: &[&str]) -> Result<(), Error> {
+        for component in &self.components {
+            let name = component.names()[0];
+            if names.contains(&name) {
+                return Err(Error::InvalidParameter(format!(
+                    "there is already a component named '{}' in this block", name
+                )));
+            }
+        }
+
+        for gradient in self.gradients.values() {
+            gradient.check_no_component_named(names)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reshape the values of this block and all of its gradients to split the
+    /// property axis into `new_components` and `new_properties`, and update
+    /// the metadata accordingly.
+    fn split_properties_axis(
+        &mut self,
+        new_components: &[Arc<Labels>],
+        new_properties: &Arc<Labels>,
+    ) -> Result<(), Error> {
+        let mut new_shape = self.values.shape()?.to_vec();
+        new_shape.pop();
+        new_shape.extend(new_components.iter().map(|component| component.count()));
+        new_shape.push(new_properties.count());
+        self.values.reshape(&new_shape)?;
+
+        self.components.0.extend(new_components.iter().cloned());
+        self.properties = Arc::clone(new_properties);
+
+        for gradient in self.gradients.values_mut() {
+            gradient.split_properties_axis(new_components, new_properties)?;
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -438,6 +1182,405 @@ mod tests {
         );
     }
 
+    #[test]
+    fn append_samples() {
+        let properties = example_labels("properties", 2);
+        let mut block = TensorBlock::new(
+            TestArray::with_data(vec![2, 2], vec![1.0, 2.0, 3.0, 4.0]),
+            example_labels("samples", 2),
+            vec![],
+            properties,
+        ).unwrap();
+
+        let mut samples = LabelsBuilder::new(vec!["samples"]).unwrap();
+        samples.add(&[5]).unwrap();
+        let samples = samples.finish();
+
+        block.append_samples(&samples, TestArray::with_data(vec![1, 2], vec![5.0, 6.0])).unwrap();
+        assert_eq!(block.samples.iter().collect::<Vec<_>>(), [[0], [1], [5]]);
+        assert_eq!(block.values.shape().unwrap(), [3, 2]);
+        assert_eq!(block.values.data().unwrap(), [1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+
+        let error = block.append_samples(&samples, TestArray::new(vec![1, 2])).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not have the same label value multiple time: \
+            [5] is already present at position 2"
+        );
+
+        let error = block.append_samples(&samples, TestArray::new(vec![1, 3])).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: new values and samples don't match: the array \
+            shape along axis 1 is 3 but we have 2 properties labels"
+        );
+
+        let error = block.append_samples(&example_labels("other", 1), TestArray::new(vec![1, 2])).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: expected Labels with the same names, got [other] and [samples]"
+        );
+    }
+
+    #[test]
+    fn reorder_properties() {
+        let mut block = TensorBlock::new(
+            TestArray::with_data(vec![2, 3], vec![0.0, 1.0, 2.0, 10.0, 11.0, 12.0]),
+            example_labels("samples", 2),
+            vec![],
+            example_labels("properties", 3),
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            TestArray::with_data(vec![1, 2, 3], vec![0.0, 1.0, 2.0, 10.0, 11.0, 12.0]),
+            example_labels("sample", 1),
+            vec![example_labels("xy", 2)],
+            Arc::clone(&block.properties),
+        ).unwrap();
+        block.add_gradient("g", gradient).unwrap();
+
+        let mut order = LabelsBuilder::new(vec!["properties"]).unwrap();
+        order.add(&[2]).unwrap();
+        order.add(&[0]).unwrap();
+        order.add(&[1]).unwrap();
+        let order = order.finish();
+
+        block.reorder_properties(&order).unwrap();
+        assert_eq!(block.properties.iter().collect::<Vec<_>>(), [[2], [0], [1]]);
+        assert_eq!(block.values.shape().unwrap(), [2, 3]);
+        assert_eq!(block.values.data().unwrap(), [2.0, 0.0, 1.0, 12.0, 10.0, 11.0]);
+
+        let gradient = block.gradient("g").unwrap();
+        assert_eq!(gradient.properties.iter().collect::<Vec<_>>(), [[2], [0], [1]]);
+        assert_eq!(gradient.values.shape().unwrap(), [1, 2, 3]);
+        assert_eq!(gradient.values.data().unwrap(), [2.0, 0.0, 1.0, 12.0, 10.0, 11.0]);
+        block.check_invariants().unwrap();
+
+        let error = block.gradient_mut("g").unwrap().reorder_properties(&order).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not reorder the properties of a gradient \
+            block, reorder the properties of the parent block instead"
+        );
+
+        let error = block.gradient_mut("g").unwrap().filter_properties(|_| Ok(true)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not filter the properties of a gradient \
+            block, filter the properties of the parent block instead"
+        );
+
+        let error = block.reorder_properties(&example_labels("properties", 2)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not reorder properties: expected 3 entries \
+            in the new order, got 2"
+        );
+
+        let mut order = LabelsBuilder::new(vec!["properties"]).unwrap();
+        order.add(&[2]).unwrap();
+        order.add(&[0]).unwrap();
+        order.add(&[4]).unwrap();
+        let error = block.reorder_properties(&order.finish()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not reorder properties: [4] is not one of \
+            the properties of this block"
+        );
+
+        let error = block.reorder_properties(&example_labels("other", 3)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: expected Labels with the same names, got [other] and [properties]"
+        );
+    }
+
+    #[test]
+    fn units() {
+        let mut block = TensorBlock::new(
+            TestArray::new(vec![2, 3]),
+            example_labels("samples", 2),
+            vec![],
+            example_labels("properties", 3),
+        ).unwrap();
+        assert!(block.units().is_none());
+
+        block.set_units(Some("eV")).unwrap();
+        assert_eq!(block.units().unwrap().as_str(), "eV");
+        assert_eq!(block.try_clone().unwrap().units().unwrap().as_str(), "eV");
+        assert_eq!(block.zeros_like().unwrap().units().unwrap().as_str(), "eV");
+
+        let error = block.set_units(Some("e\0V")).unwrap_err();
+        assert_eq!(error.to_string(), "invalid parameter: units can not contain NULL bytes");
+        assert_eq!(block.units().unwrap().as_str(), "eV");
+
+        block.set_units(None).unwrap();
+        assert!(block.units().is_none());
+    }
+
+    #[test]
+    fn set_labels() {
+        let mut block = TensorBlock::new(
+            TestArray::new(vec![2, 3]),
+            example_labels("samples", 2),
+            vec![],
+            example_labels("properties", 3),
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            TestArray::new(vec![1, 3]),
+            example_labels("sample", 1),
+            vec![],
+            Arc::clone(&block.properties),
+        ).unwrap();
+        block.add_gradient("g", gradient).unwrap();
+
+        let mut samples = LabelsBuilder::new(vec!["samples"]).unwrap();
+        samples.add(&[10]).unwrap();
+        samples.add(&[20]).unwrap();
+        block.set_samples(Arc::new(samples.finish())).unwrap();
+        assert_eq!(block.samples.iter().collect::<Vec<_>>(), [[10], [20]]);
+
+        let mut properties = LabelsBuilder::new(vec!["properties"]).unwrap();
+        properties.add(&[5]).unwrap();
+        properties.add(&[6]).unwrap();
+        properties.add(&[7]).unwrap();
+        block.set_properties(Arc::new(properties.finish())).unwrap();
+        assert_eq!(block.properties.iter().collect::<Vec<_>>(), [[5], [6], [7]]);
+        let gradient = block.gradient("g").unwrap();
+        assert_eq!(gradient.properties.iter().collect::<Vec<_>>(), [[5], [6], [7]]);
+        block.check_invariants().unwrap();
+
+        let error = block.set_samples(example_labels("samples", 3)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not set samples: expected 2 entries, got 3"
+        );
+
+        let error = block.set_properties(example_labels("other", 3)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not set properties: expected labels with \
+            names [properties], got [other]"
+        );
+
+        let mut gradient_samples = LabelsBuilder::new(vec!["sample"]).unwrap();
+        gradient_samples.add(&[1]).unwrap();
+        block.set_gradient_samples("g", Arc::new(gradient_samples.finish())).unwrap();
+        assert_eq!(block.gradient("g").unwrap().samples.iter().collect::<Vec<_>>(), [[1]]);
+
+        let mut gradient_samples = LabelsBuilder::new(vec!["sample"]).unwrap();
+        gradient_samples.add(&[99]).unwrap();
+        let gradient_samples = Arc::new(gradient_samples.finish());
+        let error = block.set_gradient_samples("g", Arc::clone(&gradient_samples)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: invalid value for the 'sample' in gradient \
+            samples: we got 99, but the values contain 2 samples"
+        );
+
+        let error = block.set_gradient_samples("other", Arc::clone(&gradient_samples)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not find gradients with respect to 'other' in this block"
+        );
+
+        let gradient = block.gradient_mut("g").unwrap();
+        let error = gradient.set_samples(gradient_samples).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not set the samples of a gradient block \
+            directly, they must be set from the parent block"
+        );
+
+        let error = gradient.set_properties(example_labels("properties", 3)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not set the properties of a gradient \
+            block, set the properties of the parent block instead"
+        );
+        block.check_invariants().unwrap();
+    }
+
+    #[test]
+    fn components_to_properties() {
+        let samples = example_labels("samples", 2);
+        let properties = example_labels("properties", 2);
+        let component_1 = example_labels("component_1", 2);
+        let component_2 = example_labels("component_2", 3);
+        let component_3 = example_labels("component_3", 4);
+
+        let data = (0..96).map(f64::from).collect::<Vec<_>>();
+        let mut block = TensorBlock::new(
+            TestArray::with_data(vec![2, 2, 3, 4, 2], data),
+            samples.clone(),
+            vec![component_1.clone(), component_2.clone(), component_3.clone()],
+            properties.clone(),
+        ).unwrap();
+
+        // moving the last component only changes the shape of the data
+        let data_ptr = block.values.data().unwrap().as_ptr();
+        block.components_to_properties(&["component_3"]).unwrap();
+        assert_eq!(block.values.data().unwrap().as_ptr(), data_ptr);
+        assert_eq!(block.values.shape().unwrap(), [2, 2, 3, 8]);
+        assert_eq!(block.values.data().unwrap(), (0..96).map(f64::from).collect::<Vec<_>>());
+
+        // moving another component moves the data, and keeps the remaining
+        // components in order
+        let mut transposed = TestArray::with_data(vec![2, 2, 3, 4, 2], (0..96).map(f64::from).collect());
+        transposed.swap_axes(3, 2).unwrap();
+        transposed.swap_axes(2, 1).unwrap();
+        let mut reference = TensorBlock::new(
+            transposed,
+            samples.clone(),
+            vec![component_3.clone(), component_1.clone(), component_2.clone()],
+            properties.clone(),
+        ).unwrap();
+        reference.components_to_properties(&["component_2"]).unwrap();
+
+        let mut block = TensorBlock::new(
+            TestArray::with_data(vec![2, 2, 3, 4, 2], (0..96).map(f64::from).collect()),
+            samples,
+            vec![component_1, component_2, component_3],
+            properties,
+        ).unwrap();
+        block.components_to_properties(&["component_2"]).unwrap();
+        block.components_to_properties(&["component_1"]).unwrap();
+        reference.components_to_properties(&["component_1"]).unwrap();
+
+        assert_eq!(block.components.len(), 1);
+        assert_eq!(block.components[0].names(), ["component_3"]);
+        assert_eq!(block.properties, reference.properties);
+        assert_eq!(block.values.shape().unwrap(), reference.values.shape().unwrap());
+        assert_eq!(block.values.data().unwrap(), reference.values.data().unwrap());
+    }
+
+    #[test]
+    fn components_to_properties_first_of_three_components() {
+        // regression test: with three or more components, moving a component
+        // which is not the last one used to swap it with the last component,
+        // changing the order of the remaining components in the data without
+        // changing the corresponding labels.
+        let data = (0..96).map(f64::from).collect::<Vec<_>>();
+        let mut block = TensorBlock::new(
+            TestArray::with_data(vec![2, 2, 3, 4, 2], data),
+            example_labels("samples", 2),
+            vec![
+                example_labels("component_1", 2),
+                example_labels("component_2", 3),
+                example_labels("component_3", 4),
+            ],
+            example_labels("properties", 2),
+        ).unwrap();
+
+        block.components_to_properties(&["component_1"]).unwrap();
+        assert_eq!(block.components[0].names(), ["component_2"]);
+        assert_eq!(block.components[1].names(), ["component_3"]);
+        assert_eq!(block.properties.names(), ["component_1", "properties"]);
+        assert_eq!(block.values.shape().unwrap(), [2, 3, 4, 4]);
+
+        // the new properties are (component_1, properties), and each entry
+        // must contain the value at the corresponding position in the input
+        let mut expected = Vec::new();
+        for sample in 0..2 {
+            for c2 in 0..3 {
+                for c3 in 0..4 {
+                    for c1 in 0..2 {
+                        for property in 0..2 {
+                            let index = (((sample * 2 + c1) * 3 + c2) * 4 + c3) * 2 + property;
+                            expected.push(f64::from(index));
+                        }
+                    }
+                }
+            }
+        }
+        assert_eq!(block.values.data().unwrap(), expected);
+    }
+
+    #[test]
+    fn properties_to_components() {
+        let mut properties = LabelsBuilder::new(vec!["a", "p"]).expect("invalid names");
+        for a in 0..2 {
+            for p in 4..7 {
+                properties.add(&[LabelValue::from(a), LabelValue::from(p)]).unwrap();
+            }
+        }
+        let properties = properties.finish().into_arc();
+
+        let mut block = TensorBlock::new(
+            TestArray::with_data(vec![2, 6], (0..12).map(f64::from).collect()),
+            example_labels("samples", 2),
+            vec![],
+            properties.clone(),
+        ).unwrap();
+
+        let error = block.properties_to_components(&["p"]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: [p] must be the first dimensions of the \
+            properties, got properties [a, p]"
+        );
+
+        let error = block.properties_to_components(&["a", "p"]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not move all the properties dimensions \
+            ([a, p]) to the components"
+        );
+
+        // the gradients are checked before modifying the values
+        let gradient = TensorBlock::new(
+            TestArray::new(vec![2, 2, 6]),
+            example_labels("sample", 2),
+            vec![example_labels("a", 2)],
+            properties.clone(),
+        ).unwrap();
+        let mut invalid = TensorBlock::new(
+            TestArray::new(vec![2, 6]),
+            example_labels("samples", 2),
+            vec![],
+            properties.clone(),
+        ).unwrap();
+        invalid.add_gradient("g", gradient).unwrap();
+
+        let error = invalid.properties_to_components(&["a"]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: there is already a component named 'a' in this block"
+        );
+        assert!(invalid.components.is_empty());
+        assert_eq!(invalid.properties, properties);
+        assert_eq!(invalid.values.shape().unwrap(), [2, 6]);
+
+        block.properties_to_components(&["a"]).unwrap();
+        assert_eq!(block.components.len(), 1);
+        assert_eq!(block.components[0].names(), ["a"]);
+        assert_eq!(block.components[0].count(), 2);
+        assert_eq!(block.properties.names(), ["p"]);
+        assert_eq!(block.properties.count(), 3);
+        assert_eq!(block.values.shape().unwrap(), [2, 2, 3]);
+
+        // properties which are not a full grid over the moved dimension
+        let mut properties = LabelsBuilder::new(vec!["a", "p"]).expect("invalid names");
+        properties.add(&[LabelValue::from(0), LabelValue::from(4)]).unwrap();
+        properties.add(&[LabelValue::from(0), LabelValue::from(5)]).unwrap();
+        properties.add(&[LabelValue::from(1), LabelValue::from(4)]).unwrap();
+
+        let mut block = TensorBlock::new(
+            TestArray::new(vec![2, 3]),
+            example_labels("samples", 2),
+            vec![],
+            properties.finish().into_arc(),
+        ).unwrap();
+
+        let error = block.properties_to_components(&["a"]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: the properties do not form a regular grid over \
+            [a], unable to move these dimensions to the components"
+        );
+    }
+
     mod gradients {
         use super::*;
 
@@ -520,5 +1663,85 @@ mod tests {
             let result = block.add_gradient("components", gradient);
             assert!(result.is_ok());
         }
+
+        #[test]
+        fn compact_gradients() {
+            let properties = example_labels("properties", 2);
+            let mut block = TensorBlock::new(
+                TestArray::new(vec![3, 2]),
+                example_labels("samples", 3),
+                vec![],
+                properties.clone(),
+            ).unwrap();
+
+            let mut gradient_samples = LabelsBuilder::new(vec!["sample"]).unwrap();
+            gradient_samples.add(&[2]).unwrap();
+            gradient_samples.add(&[0]).unwrap();
+            gradient_samples.add(&[1]).unwrap();
+            let gradient = TensorBlock::new(
+                TestArray::with_data(vec![3, 2], vec![20.0, 21.0, 0.0, 1.0, 10.0, 11.0]),
+                gradient_samples.finish(),
+                vec![],
+                properties,
+            ).unwrap();
+            block.add_gradient("g", gradient).unwrap();
+
+            // remove the last sample from the values, without updating the
+            // gradients
+            block.gather_samples(example_labels("samples", 2), &[0, 1]).unwrap();
+
+            block.validate_and_compact_gradients().unwrap();
+            let gradient = block.gradient("g").unwrap();
+            assert_eq!(gradient.samples.iter().collect::<Vec<_>>(), [[0], [1]]);
+            assert_eq!(gradient.values.shape().unwrap(), [2, 2]);
+            assert_eq!(gradient.values.data().unwrap(), [0.0, 1.0, 10.0, 11.0]);
+
+            // this is a no-op if the gradients are already consistent
+            block.validate_and_compact_gradients().unwrap();
+            let gradient = block.gradient("g").unwrap();
+            assert_eq!(gradient.values.data().unwrap(), [0.0, 1.0, 10.0, 11.0]);
+
+            // properties can not be fixed
+            block.properties = example_labels("properties", 3);
+            let error = block.validate_and_compact_gradients().unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                "invalid parameter: the properties of the gradient with respect \
+                to 'g' are not the same as the values properties"
+            );
+        }
+
+        #[test]
+        fn zeros_like() {
+            let properties = example_labels("properties", 2);
+            let mut block = TensorBlock::new(
+                TestArray::with_data(vec![2, 2], vec![1.0, 2.0, 3.0, 4.0]),
+                example_labels("samples", 2),
+                vec![],
+                properties.clone(),
+            ).unwrap();
+
+            let gradient = TensorBlock::new(
+                TestArray::with_data(vec![1, 2], vec![5.0, 6.0]),
+                example_labels("sample", 1),
+                vec![],
+                properties,
+            ).unwrap();
+            block.add_gradient("g", gradient).unwrap();
+
+            let zeros = block.zeros_like().unwrap();
+            assert!(Arc::ptr_eq(&zeros.samples, &block.samples));
+            assert!(Arc::ptr_eq(&zeros.properties, &block.properties));
+            assert_eq!(zeros.values.shape().unwrap(), [2, 2]);
+            assert_eq!(zeros.values.data().unwrap(), [0.0; 4]);
+
+            assert_eq!(zeros.gradients().len(), 1);
+            let gradient = zeros.gradient("g").unwrap();
+            assert!(Arc::ptr_eq(&gradient.samples, &block.gradient("g").unwrap().samples));
+            assert_eq!(gradient.values.data().unwrap(), [0.0; 2]);
+
+            // the original block is unchanged
+            assert_eq!(block.values.data().unwrap(), [1.0, 2.0, 3.0, 4.0]);
+        }
     }
 }
@@ -154,6 +154,46 @@ pub unsafe extern fn mts_block_copy(
     return result;
 }
 
+/// Create a new `mts_block_t` with the same metadata as the given `block`, and
+/// data filled with zeros.
+///
+/// The values and gradients arrays of the new block are created with the
+/// `mts_array_t.create` function of the arrays in `block`, and the gradients
+/// of the new block are also filled with zeros.
+///
+/// The memory allocated by this function and the blocks should be released
+/// using `mts_block_free`, or moved into a tensor map using `mts_tensormap`.
+///
+/// @param block existing block to use as a template
+///
+/// @returns A pointer to the newly allocated block, or a `NULL` pointer in
+///          case of error. In case of error, you can use `mts_last_error()`
+///          to get the error message.
+#[no_mangle]
+pub unsafe extern fn mts_block_zeros_like(
+    block: *const mts_block_t,
+) -> *mut mts_block_t {
+    let mut result = std::ptr::null_mut();
+    let unwind_wrapper = std::panic::AssertUnwindSafe(&mut result);
+    let status = catch_unwind(move || {
+        check_pointers_non_null!(block);
+        let new_block = (*block).zeros_like()?;
+        let boxed = Box::new(mts_block_t(new_block));
+
+        // force the closure to capture the full unwind_wrapper, not just
+        // unwind_wrapper.0
+        let _ = &unwind_wrapper;
+        *(unwind_wrapper.0) = Box::into_raw(boxed);
+        Ok(())
+    });
+
+    if !status.is_success() {
+        return std::ptr::null_mut();
+    }
+
+    return result;
+}
+
 
 /// Get the set of labels from this `block`.
 ///
@@ -207,6 +247,154 @@ pub unsafe extern fn mts_block_labels(
     })
 }
 
+/// Replace the samples or properties labels of this `block` with a copy of
+/// `labels`, without modifying the data. This can be used to change the values
+/// of the labels in place.
+///
+/// The new labels must have the same names and the same number of entries as
+/// the current labels. Setting the properties also sets the properties of all
+/// gradients. Components labels can not be modified with this function.
+///
+/// This function can not be used with gradient blocks: the properties of a
+/// gradient are always the same as the properties of the parent block, and the
+/// samples of a gradient should be set with `mts_block_set_gradient_samples`
+/// on the parent block.
+///
+/// @param block pointer to an existing block
+/// @param axis axis/dimension of the data array for which to set the labels,
+///        this must be either 0 (samples) or the last axis (properties)
+/// @param labels new labels for this axis
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_block_set_labels(
+    block: *mut mts_block_t,
+    axis: usize,
+    labels: mts_labels_t,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(block);
+
+        let block = &mut (*block);
+        let n_components = block.components.len();
+        let labels = mts_labels_to_rust(&labels)?;
+
+        if axis == 0 {
+            block.set_samples(labels)?;
+        } else if axis == n_components + 1 {
+            block.set_properties(labels)?;
+        } else if axis <= n_components {
+            return Err(Error::InvalidParameter(
+                "can not set the labels of components, only samples and properties can be set".into()
+            ));
+        } else {
+            return Err(Error::InvalidParameter(format!(
+                "tried to set the labels for axis {}, but we only have {} axes for this block",
+                axis, n_components + 2
+            )));
+        }
+
+        Ok(())
+    })
+}
+
+/// Replace the samples of the gradient with respect to `parameter` in this
+/// `block` with a copy of `labels`, without modifying the data.
+///
+/// The new labels must have the same names and the same number of entries as
+/// the current gradient samples, and the values of the first dimension
+/// (`sample`) must refer to existing samples of `block`.
+///
+/// @param block pointer to an existing block
+/// @param parameter name of the gradient parameter
+/// @param labels new samples for the gradient
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_block_set_gradient_samples(
+    block: *mut mts_block_t,
+    parameter: *const c_char,
+    labels: mts_labels_t,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(block, parameter);
+        let parameter = CStr::from_ptr(parameter).to_str().unwrap();
+        let labels = mts_labels_to_rust(&labels)?;
+
+        (*block).set_gradient_samples(parameter, labels)?;
+
+        Ok(())
+    })
+}
+
+/// Set the physical units of the values in this `block`, or remove them if
+/// `units` is NULL.
+///
+/// The units are free-form strings (e.g. "eV", or "eV/Angstrom" for a
+/// gradient), and are only used as metadata. They are kept when copying a
+/// block and when saving and loading tensor maps, but operations creating new
+/// blocks do not propagate them.
+///
+/// @param block pointer to an existing block
+/// @param units NULL-terminated UTF-8 string containing the units, or NULL
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_block_set_units(
+    block: *mut mts_block_t,
+    units: *const c_char,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(block);
+
+        let units = if units.is_null() {
+            None
+        } else {
+            Some(CStr::from_ptr(units).to_str().map_err(|_| Error::InvalidParameter(
+                "units must be valid UTF-8".into()
+            ))?)
+        };
+
+        (*block).set_units(units)?;
+        Ok(())
+    })
+}
+
+/// Get the physical units of the values in this `block`.
+///
+/// The units memory is managed by the block, and is invalidated when the
+/// block is freed or when the units are modified.
+///
+/// @param block pointer to an existing block
+/// @param units will be set to a NULL-terminated UTF-8 string containing the
+///              units, or to NULL if the units of this block are not set
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_block_units(
+    block: *const mts_block_t,
+    units: *mut *const c_char,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(block, units);
+
+        *units = match (*block).units() {
+            Some(block_units) => block_units.as_c_str().as_ptr(),
+            None => std::ptr::null(),
+        };
+
+        Ok(())
+    })
+}
+
 fn _get_gradient<'a>(
     block: &'a mut TensorBlock,
     parameter: &str
@@ -360,3 +548,162 @@ pub unsafe extern fn mts_block_gradients_list(
         Ok(())
     })
 }
+
+
+/// Restore the consistency between the samples of this `block` and the samples
+/// of its gradients.
+///
+/// Gradient samples referring to values samples which no longer exist are
+/// removed, and the remaining gradient samples are re-ordered to follow the
+/// order of the values samples. This is applied recursively to gradients of
+/// gradients.
+///
+/// @param block pointer to an existing block
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_block_compact_gradients(
+    block: *mut mts_block_t,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(block);
+        (*block).validate_and_compact_gradients()?;
+        Ok(())
+    })
+}
+
+/// Get the estimated size in bytes of the data stored in this `block`,
+/// including the data of all its gradients.
+///
+/// The size is computed from the shape of the arrays, assuming they contain
+/// 64-bit floating point values.
+///
+/// @param block pointer to an existing block
+/// @param nbytes pointer to an integer, will be set to the size in bytes
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_block_nbytes(
+    block: *const mts_block_t,
+    nbytes: *mut usize,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(block, nbytes);
+        *nbytes = (*block).nbytes()?;
+        Ok(())
+    })
+}
+
+/// Add new samples at the end of this `block`, together with the
+/// corresponding `data`.
+///
+/// The new `samples` must have the same names as the existing samples of the
+/// block, and must not contain any of the existing samples. The `data` must
+/// have the same shape as the values of the block, except for the first
+/// dimension. This function returns an error if the block contains gradients.
+///
+/// The block takes ownership of the `data`, which should not be released
+/// separately.
+///
+/// @param block pointer to an existing block
+/// @param samples labels containing the new samples
+/// @param data array containing the values for the new samples
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_block_append_samples(
+    block: *mut mts_block_t,
+    samples: mts_labels_t,
+    data: mts_array_t,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(block);
+        let samples = mts_labels_to_rust(&samples)?;
+        (*block).append_samples(&samples, data)?;
+        Ok(())
+    })
+}
+
+/// Re-order the properties of this `block` to follow the entries in `order`,
+/// permuting the values and all gradients accordingly.
+///
+/// `order` must have the same names as the properties of the block, and
+/// contain each of the existing properties exactly once. The properties of a
+/// gradient block can not be re-ordered on their own, since they must stay the
+/// same as the properties of the parent block.
+///
+/// @param block pointer to an existing block
+/// @param order labels containing the properties in their new order
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_block_reorder_properties(
+    block: *mut mts_block_t,
+    order: mts_labels_t,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(block);
+        let order = mts_labels_to_rust(&order)?;
+        (*block).reorder_properties(&order)?;
+        Ok(())
+    })
+}
+
+/// Concatenate multiple blocks along the sample axis.
+///
+/// All the blocks must have the same components and properties, and gradients
+/// with respect to the same parameters. The samples of the blocks are
+/// concatenated in the order of `blocks`, and must not contain the same entry
+/// more than once. The gradient samples are updated to refer to the samples in
+/// the concatenated block.
+///
+/// The memory allocated by this function should be released using
+/// `mts_block_free`, or moved into a tensor map using `mts_tensormap`.
+///
+/// @param blocks pointer to the first element of an array of blocks
+/// @param blocks_count number of elements in the `blocks` array
+///
+/// @returns A pointer to the newly allocated block, or a `NULL` pointer in
+///          case of error. In case of error, you can use `mts_last_error()`
+///          to get the error message.
+#[no_mangle]
+pub unsafe extern fn mts_block_concatenate_samples(
+    blocks: *const *const mts_block_t,
+    blocks_count: usize,
+) -> *mut mts_block_t {
+    let mut result = std::ptr::null_mut();
+    let unwind_wrapper = std::panic::AssertUnwindSafe(&mut result);
+    let status = catch_unwind(move || {
+        let mut rust_blocks = Vec::new();
+        if blocks_count != 0 {
+            check_pointers_non_null!(blocks);
+            for &block in std::slice::from_raw_parts(blocks, blocks_count) {
+                check_pointers_non_null!(block);
+                rust_blocks.push(&**block);
+            }
+        }
+
+        let concatenated = TensorBlock::concatenate_samples(&rust_blocks)?;
+        let boxed = Box::new(mts_block_t(concatenated));
+
+        // force the closure to capture the full unwind_wrapper, not just
+        // unwind_wrapper.0
+        let _ = &unwind_wrapper;
+        *(unwind_wrapper.0) = Box::into_raw(boxed);
+        Ok(())
+    });
+
+    if !status.is_success() {
+        return std::ptr::null_mut();
+    }
+
+    return result;
+}
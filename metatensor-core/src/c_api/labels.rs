@@ -5,6 +5,11 @@ use std::sync::Arc;
 use crate::{LabelValue, Labels, LabelsBuilder, Error};
 use super::status::{mts_status_t, catch_unwind};
 
+/// Value used in the selection passed to
+/// `mts_tensormap_blocks_matching_wildcard` to match any value of the
+/// corresponding dimension of the keys.
+pub const MTS_LABELS_ANY: i32 = i32::MIN;
+
 /// A set of labels used to carry metadata associated with a tensor map.
 ///
 /// This is similar to a list of `count` named tuples, but stored as a 2D array
@@ -528,6 +533,55 @@ pub unsafe extern fn mts_labels_intersection(
     })
 }
 
+/// Take the cartesian product of two `mts_labels_t`.
+///
+/// The names of `result` are the names of `first` followed by the names of
+/// `second`, and `result` contains every combination of one entry of `first`
+/// followed by one entry of `second`. The two labels must not have any name in
+/// common.
+///
+/// This function allocates memory for `result` which must be released
+/// `mts_labels_free` when you don't need it anymore.
+///
+/// @param first first set of labels
+/// @param second second set of labels
+/// @param result empty labels, on output will contain the cartesian product of
+///        `first` and `second`
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_labels_cartesian_product(
+    first: mts_labels_t,
+    second: mts_labels_t,
+    result: *mut mts_labels_t,
+) -> mts_status_t {
+    let unwind_wrapper = std::panic::AssertUnwindSafe(result);
+    catch_unwind(|| {
+        labels_set_common(
+            "cartesian product",
+            &first,
+            &second,
+            std::ptr::null_mut(),
+            0,
+            std::ptr::null_mut(),
+            0,
+        )?;
+
+        let first = &*first.internal_ptr_.cast::<Labels>();
+        let second = &*second.internal_ptr_.cast::<Labels>();
+
+        let result_rust = first.cartesian_product(second)?;
+
+        // force the closure to capture the full unwind_wrapper, not just
+        // unwind_wrapper.0
+        let _ = &unwind_wrapper;
+        *unwind_wrapper.0 = rust_to_mts_labels(Arc::new(result_rust));
+
+        Ok(())
+    })
+}
+
 /// Decrease the reference count of `labels`, and release the corresponding
 /// memory once the reference count reaches 0.
 ///
@@ -12,6 +12,8 @@ pub use self::status::{catch_unwind, mts_status_t};
 pub use self::status::MTS_SUCCESS;
 
 mod labels;
+pub use self::labels::MTS_LABELS_ANY;
+
 mod data;
 mod blocks;
 mod tensor;
@@ -1,9 +1,9 @@
-use std::os::raw::c_char;
+use std::os::raw::{c_char, c_void};
 use std::sync::Arc;
 use std::ffi::CStr;
 use std::collections::BTreeSet;
 
-use crate::{TensorMap, TensorBlock, Error};
+use crate::{TensorMap, TensorBlock, LabelValue, Error};
 
 use super::labels::{mts_labels_t, rust_to_mts_labels, mts_labels_to_rust};
 use super::blocks::mts_block_t;
@@ -163,6 +163,70 @@ pub unsafe extern fn mts_tensormap_copy(
     return result;
 }
 
+/// Create a new `mts_tensormap_t` with the same keys and blocks metadata as
+/// the given `tensor`, and all data filled with zeros.
+///
+/// The arrays of the new tensor map are created with the `mts_array_t.create`
+/// function of the arrays in `tensor`. See `mts_block_zeros_like` for more
+/// information.
+///
+/// The memory allocated by this function and the blocks should be released
+/// using `mts_tensormap_free`.
+///
+/// @param tensor existing tensor to use as a template
+///
+/// @returns A pointer to the newly allocated tensor, or a `NULL` pointer in
+///          case of error. In case of error, you can use `mts_last_error()`
+///          to get the error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_zeros_like(
+    tensor: *const mts_tensormap_t,
+) -> *mut mts_tensormap_t {
+    let mut result = std::ptr::null_mut();
+    let unwind_wrapper = std::panic::AssertUnwindSafe(&mut result);
+    let status = catch_unwind(move || {
+        check_pointers_non_null!(tensor);
+        let new_tensor = (*tensor).zeros_like()?;
+        let boxed = Box::new(mts_tensormap_t(new_tensor));
+
+        // force the closure to capture the full unwind_wrapper, not just
+        // unwind_wrapper.0
+        let _ = &unwind_wrapper;
+        *(unwind_wrapper.0) = Box::into_raw(boxed);
+        Ok(())
+    });
+
+    if !status.is_success() {
+        return std::ptr::null_mut();
+    }
+
+    return result;
+}
+
+/// Check that the given `tensor` is still valid, re-running all the checks
+/// performed when creating it with `mts_tensormap`, `mts_block` and
+/// `mts_block_add_gradient`.
+///
+/// Modifying the data of the blocks in a tensor map (for example through
+/// `mts_block_data`) could break these invariants, and this function can be
+/// used to check that the tensor map is still consistent.
+///
+/// @param tensor pointer to an existing tensor map
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message describing which invariant is violated.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_check_invariants(
+    tensor: *const mts_tensormap_t,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(tensor);
+        (*tensor).check_invariants()?;
+        Ok(())
+    })
+}
+
 
 /// Get the keys for the given `tensor` map.
 ///
@@ -195,6 +259,113 @@ pub unsafe extern fn mts_tensormap_keys(
 }
 
 
+/// Set the metadata entry `key` of this `tensor` map to `value`, overwriting
+/// any existing value for this `key`.
+///
+/// Metadata is a set of arbitrary key/value pairs of strings attached to a
+/// tensor map, which can be used to store provenance information. It is kept
+/// by `mts_tensormap_copy()` and when saving/loading tensor maps, but other
+/// operations creating new tensor maps do not propagate it.
+///
+/// @param tensor pointer to an existing tensor map
+/// @param key NULL-terminated UTF-8 string containing the metadata key
+/// @param value NULL-terminated UTF-8 string containing the metadata value
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_set_metadata(
+    tensor: *mut mts_tensormap_t,
+    key: *const c_char,
+    value: *const c_char,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(tensor, key, value);
+
+        let key = CStr::from_ptr(key).to_str().map_err(|_| Error::InvalidParameter(
+            "metadata key must be valid UTF-8".into()
+        ))?;
+        let value = CStr::from_ptr(value).to_str().map_err(|_| Error::InvalidParameter(
+            "metadata value must be valid UTF-8".into()
+        ))?;
+
+        (*tensor).set_metadata(key, value)?;
+        Ok(())
+    })
+}
+
+
+/// Get the value of the metadata entry `key` in this `tensor` map.
+///
+/// The value memory is managed by the tensor map, and is invalidated when the
+/// tensor map is freed or when this metadata entry is modified.
+///
+/// @param tensor pointer to an existing tensor map
+/// @param key NULL-terminated UTF-8 string containing the metadata key
+/// @param value will be set to a NULL-terminated UTF-8 string containing the
+///              value associated with `key`, or to NULL if there is no
+///              metadata entry for this `key`
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_get_metadata(
+    tensor: *const mts_tensormap_t,
+    key: *const c_char,
+    value: *mut *const c_char,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(tensor, key, value);
+
+        let key = CStr::from_ptr(key).to_str().map_err(|_| Error::InvalidParameter(
+            "metadata key must be valid UTF-8".into()
+        ))?;
+
+        *value = match (*tensor).metadata_c(key) {
+            Some(metadata) => metadata.as_c_str().as_ptr(),
+            None => std::ptr::null(),
+        };
+
+        Ok(())
+    })
+}
+
+
+/// Get the list of all metadata keys defined in this `tensor` map, in the
+/// order in which they were first set.
+///
+/// @param tensor pointer to an existing tensor map
+/// @param keys will be set to the first element of an array of
+///             NULL-terminated UTF-8 strings containing all the metadata keys
+/// @param keys_count will be set to the number of elements in `keys`
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_metadata_keys(
+    tensor: *const mts_tensormap_t,
+    keys: *mut *const *const c_char,
+    keys_count: *mut usize,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(tensor, keys, keys_count);
+
+        let list = (*tensor).metadata_keys_c();
+        (*keys_count) = list.len();
+
+        (*keys) = if list.is_empty() {
+            std::ptr::null()
+        } else {
+            list.as_ptr().cast()
+        };
+        Ok(())
+    })
+}
+
+
 /// Get a pointer to the `index`-th block in this tensor map.
 ///
 /// The block memory is still managed by the tensor map, this block should not
@@ -241,6 +412,11 @@ pub unsafe extern fn mts_tensormap_block_by_id(
 /// the keys for this tensor map, and only one entry, describing the requested
 /// blocks.
 ///
+/// All the values of the keys dimensions which are not part of the
+/// `selection` are matched. The values in the `selection` are compared exactly
+/// with the keys, see `mts_tensormap_blocks_matching_wildcard` to match any
+/// value of some dimensions.
+///
 /// When calling this function, `*count` should contain the number of entries in
 /// `block_indexes`. When the function returns successfully, `*count` will
 /// contain the number of blocks matching the selection, i.e. how many values
@@ -263,31 +439,76 @@ pub unsafe extern fn mts_tensormap_blocks_matching(
     selection: mts_labels_t,
 ) -> mts_status_t {
     catch_unwind(|| {
-        check_pointers_non_null!(tensor, count);
+        blocks_matching(tensor, block_indexes, count, &selection, false, "mts_tensormap_blocks_matching")
+    })
+}
 
-        if *count != (*tensor).keys().count() {
-            return Err(Error::InvalidParameter(format!(
-                "expected space for {} indices as input to mts_tensormap_blocks_matching, got space for {}",
-                (*tensor).keys().count(), *count
-            )));
-        }
+/// Get indices of the blocks in this `tensor` corresponding to the given
+/// `selection`, where `MTS_LABELS_ANY` can be used as a wildcard.
+///
+/// This function is the same as `mts_tensormap_blocks_matching`, except that
+/// the `selection` can use `MTS_LABELS_ANY` as the value for some dimensions,
+/// to match all the values of these dimensions while still selecting specific
+/// values for the other dimensions. Keys containing the same value as
+/// `MTS_LABELS_ANY` can still be selected exactly with
+/// `mts_tensormap_blocks_matching`.
+///
+/// @param tensor pointer to an existing tensor map
+/// @param block_indexes array to be filled with indexes of blocks in the tensor
+///                      map matching the `selection`
+/// @param count number of entries in `block_indexes`
+/// @param selection labels with a single entry describing which blocks are
+///                  requested, potentially containing `MTS_LABELS_ANY`
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_blocks_matching_wildcard(
+    tensor: *const mts_tensormap_t,
+    block_indexes: *mut usize,
+    count: *mut usize,
+    selection: mts_labels_t,
+) -> mts_status_t {
+    catch_unwind(|| {
+        blocks_matching(tensor, block_indexes, count, &selection, true, "mts_tensormap_blocks_matching_wildcard")
+    })
+}
+
+/// Implementation of `mts_tensormap_blocks_matching` and
+/// `mts_tensormap_blocks_matching_wildcard`
+unsafe fn blocks_matching(
+    tensor: *const mts_tensormap_t,
+    block_indexes: *mut usize,
+    count: *mut usize,
+    selection: &mts_labels_t,
+    wildcard: bool,
+    function: &str,
+) -> Result<(), Error> {
+    check_pointers_non_null!(tensor, count);
+
+    if *count != (*tensor).keys().count() {
+        return Err(Error::InvalidParameter(format!(
+            "expected space for {} indices as input to {}, got space for {}",
+            (*tensor).keys().count(), function, *count
+        )));
+    }
 
-        let selection = mts_labels_to_rust(&selection)?;
-        let rust_blocks = (*tensor).blocks_matching(&selection)?;
-		*count = rust_blocks.len();
+    let selection = mts_labels_to_rust(selection)?;
+    let rust_blocks = (*tensor).blocks_matching(&selection, wildcard)?;
+    *count = rust_blocks.len();
 
-        if (*tensor).keys().is_empty() {
-            return Ok(());
-        }
+    if (*tensor).keys().is_empty() {
+        return Ok(());
+    }
 
-        check_pointers_non_null!(block_indexes);
-        let block_indexes = std::slice::from_raw_parts_mut(block_indexes, *count);
-		for (idx,block) in rust_blocks.into_iter().enumerate() {
-            block_indexes[idx] = block;
-		}
+    check_pointers_non_null!(block_indexes);
+    let block_indexes = std::slice::from_raw_parts_mut(block_indexes, *count);
+    for (idx, block) in rust_blocks.into_iter().enumerate() {
+        block_indexes[idx] = block;
+    }
 
-        Ok(())
-    })
+    Ok(())
 }
 
 
@@ -312,13 +533,40 @@ pub unsafe extern fn mts_tensormap_blocks_matching(
 /// blocks with properties `p=1, 2` will result in `a, p = (2, 1), (2, 2),
 /// (3, 1), (3, 2)`.
 ///
+/// The moved dimensions are always added before the existing dimensions of
+/// the properties, in the same order as in `keys_to_move`. For example,
+/// moving `c, a` from keys `a, b, c` to blocks with properties `n, l` gives
+/// properties `c, a, n, l`.
+///
+/// The new keys contain the dimensions of the current keys which are not
+/// moved, in the same order as in the current keys. If all the dimensions
+/// are moved, the new keys contain a single `_` dimension with a single
+/// entry.
+///
 /// The new sample labels will contains all of the merged blocks sample
 /// labels. The order of the samples is controlled by `sort_samples`. If
 /// `sort_samples` is true, samples are re-ordered to keep them
 /// lexicographically sorted. Otherwise they are kept in the order in which
 /// they appear in the blocks.
 ///
+/// The samples of the merged blocks do not need to be the same. If a
+/// sample is not present in one of the merged blocks, the values (and
+/// gradients) for this sample in the properties coming from this block
+/// are set to zero.
+///
+/// The same applies to the gradients: the gradient samples of the merged
+/// blocks do not need to be the same, and the gradient samples of the new
+/// block contain all of them (after updating the `sample` dimension to
+/// refer to the new samples). Gradients which are not present in one of
+/// the merged blocks are set to zero in the properties coming from this
+/// block.
+///
 /// The result is a new tensor map, which should be freed with `mts_tensormap_free`.
+/// The input `tensor` is not modified, and the data is copied in the new tensor
+/// map: keeping both alive will use twice as much memory.
+///
+/// If `keys_to_move` does not contain any dimension (`keys_to_move.size ==
+/// 0`), the result is a copy of `tensor`, and the samples are not re-ordered.
 ///
 /// @param tensor pointer to an existing tensor map
 /// @param keys_to_move description of the keys to move
@@ -415,10 +663,22 @@ pub unsafe extern fn mts_tensormap_components_to_properties(
 /// the sample labels, and blocks with the same remaining keys dimensions
 /// will be merged together along the sample axis.
 ///
+/// Blocks are grouped by the values of the remaining keys dimensions:
+/// blocks which only differ in the moved dimensions are merged together,
+/// while blocks with different values for any of the remaining dimensions
+/// are kept separate. The new keys contain the unique values of the
+/// remaining dimensions, in the order in which they first appear in the
+/// current keys.
+///
 /// `keys_to_move` must be empty (`keys_to_move.count == 0`), and the new
 /// sample labels will contain entries corresponding to the merged blocks'
 /// keys.
 ///
+/// The moved dimensions are always added after the existing dimensions of
+/// the samples, in the same order as in `keys_to_move`. For example, moving
+/// `c, a` from keys `a, b, c` to blocks with samples `system, atom` gives
+/// samples `system, atom, c, a`.
+///
 /// The new sample labels will contains all of the merged blocks sample
 /// labels. The order of the samples is controlled by `sort_samples`. If
 /// `sort_samples` is true, samples are re-ordered to keep them
@@ -428,6 +688,9 @@ pub unsafe extern fn mts_tensormap_components_to_properties(
 /// This function is only implemented if all merged block have the same
 /// property labels.
 ///
+/// If `keys_to_move` does not contain any dimension (`keys_to_move.size ==
+/// 0`), the result is a copy of `tensor`, and the samples are not re-ordered.
+///
 /// @param tensor pointer to an existing tensor map
 /// @param keys_to_move description of the keys to move
 /// @param sort_samples whether to sort the samples lexicographically after
@@ -464,3 +727,594 @@ pub unsafe extern fn mts_tensormap_keys_to_samples(
 
     return result;
 }
+
+/// Function pointer used to report progress in
+/// `mts_tensormap_keys_to_samples_with_progress` and
+/// `mts_tensormap_keys_to_properties_with_progress`.
+///
+/// This function gets the number of blocks created so far in `done`, and the
+/// total number of blocks in the output in `total`. It can return a non-zero
+/// `mts_status_t` to stop the operation with an error. The `user_data`
+/// parameter is passed as-is from the calling function, and can be used to
+/// hold custom data.
+#[allow(non_camel_case_types)]
+type mts_progress_callback_t = unsafe extern fn(
+    user_data: *mut c_void,
+    done: usize,
+    total: usize,
+) -> mts_status_t;
+
+/// Same as `mts_tensormap_keys_to_samples`, calling `callback` each time a new
+/// block is created, with the number of blocks created so far and the total
+/// number of blocks in the output. This can be used to report progress when
+/// merging very large tensor maps.
+///
+/// If `callback` returns a non-zero status, no more blocks are created and
+/// this function returns a `NULL` pointer. If `keys_to_move` does not contain
+/// any dimension, `callback` is not called.
+///
+/// @param tensor pointer to an existing tensor map
+/// @param keys_to_move description of the keys to move
+/// @param sort_samples whether to sort the samples lexicographically after
+///                     merging blocks or not
+/// @param callback function called to report progress
+/// @param user_data custom data for the `callback`. This will be passed as the
+///        first argument to `callback` as-is.
+///
+/// @returns A pointer to the newly allocated tensor map, or a `NULL` pointer in
+///          case of error. In case of error, you can use `mts_last_error()`
+///          to get the error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_keys_to_samples_with_progress(
+    tensor: *const mts_tensormap_t,
+    keys_to_move: mts_labels_t,
+    sort_samples: bool,
+    callback: mts_progress_callback_t,
+    user_data: *mut c_void,
+) -> *mut mts_tensormap_t {
+    let mut result = std::ptr::null_mut();
+    let unwind_wrapper = std::panic::AssertUnwindSafe(&mut result);
+
+    let status = catch_unwind(move || {
+        check_pointers_non_null!(tensor);
+
+        let keys_to_move = mts_labels_to_rust(&keys_to_move)?;
+        let moved = (*tensor).keys_to_samples_with_progress(&keys_to_move, sort_samples, |done, total| {
+            let status = callback(user_data, done, total);
+            if !status.is_success() {
+                return Err(Error::External {
+                    status, context: "calling mts_progress_callback_t failed".into()
+                });
+            }
+            Ok(())
+        })?;
+
+        // force the closure to capture the full unwind_wrapper, not just
+        // unwind_wrapper.0
+        let _ = &unwind_wrapper;
+        *unwind_wrapper.0 = mts_tensormap_t::into_boxed_raw(moved);
+        Ok(())
+    });
+
+    if !status.is_success() {
+        return std::ptr::null_mut();
+    }
+
+    return result;
+}
+
+/// Same as `mts_tensormap_keys_to_properties`, calling `callback` each time a new
+/// block is created, with the number of blocks created so far and the total
+/// number of blocks in the output. This can be used to report progress when
+/// merging very large tensor maps.
+///
+/// If `callback` returns a non-zero status, no more blocks are created and
+/// this function returns a `NULL` pointer. If `keys_to_move` does not contain
+/// any dimension, `callback` is not called.
+///
+/// @param tensor pointer to an existing tensor map
+/// @param keys_to_move description of the keys to move
+/// @param sort_samples whether to sort the samples lexicographically after
+///                     merging blocks or not
+/// @param callback function called to report progress
+/// @param user_data custom data for the `callback`. This will be passed as the
+///        first argument to `callback` as-is.
+///
+/// @returns A pointer to the newly allocated tensor map, or a `NULL` pointer in
+///          case of error. In case of error, you can use `mts_last_error()`
+///          to get the error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_keys_to_properties_with_progress(
+    tensor: *const mts_tensormap_t,
+    keys_to_move: mts_labels_t,
+    sort_samples: bool,
+    callback: mts_progress_callback_t,
+    user_data: *mut c_void,
+) -> *mut mts_tensormap_t {
+    let mut result = std::ptr::null_mut();
+    let unwind_wrapper = std::panic::AssertUnwindSafe(&mut result);
+
+    let status = catch_unwind(move || {
+        check_pointers_non_null!(tensor);
+
+        let keys_to_move = mts_labels_to_rust(&keys_to_move)?;
+        let moved = (*tensor).keys_to_properties_with_progress(&keys_to_move, sort_samples, |done, total| {
+            let status = callback(user_data, done, total);
+            if !status.is_success() {
+                return Err(Error::External {
+                    status, context: "calling mts_progress_callback_t failed".into()
+                });
+            }
+            Ok(())
+        })?;
+
+        // force the closure to capture the full unwind_wrapper, not just
+        // unwind_wrapper.0
+        let _ = &unwind_wrapper;
+        *unwind_wrapper.0 = mts_tensormap_t::into_boxed_raw(moved);
+        Ok(())
+    });
+
+    if !status.is_success() {
+        return std::ptr::null_mut();
+    }
+
+    return result;
+}
+
+
+/// Sort the keys of this `tensor` in lexicographic order, re-ordering the blocks
+/// to keep them associated with the same keys.
+///
+/// This invalidates all the block pointers previously obtained from this
+/// tensor map with `mts_tensormap_block_by_id`.
+///
+/// @param tensor pointer to an existing tensor map
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_sort_blocks(
+    tensor: *mut mts_tensormap_t,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(tensor);
+        (*tensor).sort_blocks_by_key();
+        Ok(())
+    })
+}
+
+/// Only keep the blocks of this `tensor` for which the corresponding entry in
+/// `keep` is `true`, removing all the other blocks and the associated keys. The
+/// order of the remaining blocks is preserved.
+///
+/// This invalidates all the block pointers previously obtained from this
+/// tensor map with `mts_tensormap_block_by_id`.
+///
+/// @param tensor pointer to an existing tensor map
+/// @param keep array with one entry for each block in the tensor map,
+///             indicating whether this block should be kept
+/// @param keep_count number of entries in `keep`, this must be the same as the
+///                   number of blocks in the tensor map
+/// @param removed pointer to an integer, will be set to the number of blocks
+///                that were removed
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_retain_blocks(
+    tensor: *mut mts_tensormap_t,
+    keep: *const bool,
+    keep_count: usize,
+    removed: *mut usize,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(tensor, removed);
+
+        let n_blocks = (*tensor).blocks().len();
+        if keep_count != n_blocks {
+            return Err(Error::InvalidParameter(format!(
+                "expected {} entries in `keep`, one for each block, got {}",
+                n_blocks, keep_count
+            )));
+        }
+
+        let keep = if keep_count == 0 {
+            &[]
+        } else {
+            check_pointers_non_null!(keep);
+            std::slice::from_raw_parts(keep, keep_count)
+        };
+
+        let mut keep = keep.iter();
+        *removed = (*tensor).retain_blocks(|_, _| *keep.next().expect("missing entry in keep"));
+
+        Ok(())
+    })
+}
+
+/// Make sure that the labels of each block in this `tensor` are not shared
+/// with any other block, copying the shared labels.
+///
+/// Labels are usually shared between blocks (and between a block and its
+/// gradients) after operations like `mts_tensormap_keys_to_samples`. Copying
+/// them uses more memory, but ensures that user data attached to the labels of
+/// one block (see `mts_labels_set_user_data`) is not visible from other blocks.
+///
+/// The user data is not copied together with the labels: the copies start
+/// without any user data, while the original labels keep theirs.
+///
+/// @param tensor pointer to an existing tensor map
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_make_labels_unique(
+    tensor: *mut mts_tensormap_t,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(tensor);
+        (*tensor).make_labels_unique();
+        Ok(())
+    })
+}
+
+/// Move the given dimensions from the property labels to the component labels
+/// for each block in this tensor map.
+///
+/// This is the inverse of `mts_tensormap_components_to_properties`. The
+/// `dimensions` must be the first dimensions of the properties, and the
+/// properties of all blocks must form a regular grid over these dimensions.
+/// Each dimension becomes a new component, after the existing components.
+///
+/// `dimensions` must be an array of `dimensions_count` NULL-terminated strings,
+/// encoded as UTF-8.
+///
+/// @param tensor pointer to an existing tensor map
+/// @param dimensions names of the property dimensions to move to the components
+/// @param dimensions_count number of entries in the `dimensions` array
+///
+/// @returns A pointer to the newly allocated tensor map, or a `NULL` pointer in
+///          case of error. In case of error, you can use `mts_last_error()`
+///          to get the error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_properties_to_components(
+    tensor: *const mts_tensormap_t,
+    dimensions: *const *const c_char,
+    dimensions_count: usize,
+) -> *mut mts_tensormap_t {
+    let mut result = std::ptr::null_mut();
+    let unwind_wrapper = std::panic::AssertUnwindSafe(&mut result);
+
+    let status = catch_unwind(move || {
+        check_pointers_non_null!(tensor, dimensions);
+
+        let mut rust_dimensions = Vec::new();
+        for &dimension in std::slice::from_raw_parts(dimensions, dimensions_count) {
+            check_pointers_non_null!(dimension);
+            let dimension = CStr::from_ptr(dimension).to_str().expect("invalid utf8");
+            rust_dimensions.push(dimension);
+        }
+
+        let moved = (*tensor).properties_to_components(&rust_dimensions)?;
+
+        // force the closure to capture the full unwind_wrapper, not just
+        // unwind_wrapper.0
+        let _ = &unwind_wrapper;
+        *unwind_wrapper.0 = mts_tensormap_t::into_boxed_raw(moved);
+
+        Ok(())
+    });
+
+    if !status.is_success() {
+        return std::ptr::null_mut();
+    }
+
+    return result;
+}
+
+/// Concatenate multiple tensor maps along the property axis.
+///
+/// All the tensor maps must have the same keys, and for each key the blocks
+/// must have the same samples and components. The properties of the blocks are
+/// concatenated in the order of `tensors`, and must not contain the same entry
+/// more than once. Gradient samples are merged across the blocks.
+///
+/// If `fill_missing_gradients` is `false`, all the tensor maps must have
+/// gradients with respect to the same parameters. If it is `true`, gradients
+/// which are only defined in some of the tensor maps are considered to be zero
+/// in the others.
+///
+/// The memory allocated by this function should be released using
+/// `mts_tensormap_free`.
+///
+/// @param tensors pointer to the first element of an array of tensor maps
+/// @param tensors_count number of elements in the `tensors` array
+/// @param fill_missing_gradients should gradients which are only defined in
+///        some of the tensor maps be filled with zeros in the others?
+///
+/// @returns A pointer to the newly allocated tensor map, or a `NULL` pointer in
+///          case of error. In case of error, you can use `mts_last_error()`
+///          to get the error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_concatenate_properties(
+    tensors: *const *const mts_tensormap_t,
+    tensors_count: usize,
+    fill_missing_gradients: bool,
+) -> *mut mts_tensormap_t {
+    let mut result = std::ptr::null_mut();
+    let unwind_wrapper = std::panic::AssertUnwindSafe(&mut result);
+
+    let status = catch_unwind(move || {
+        let mut rust_tensors = Vec::new();
+        if tensors_count != 0 {
+            check_pointers_non_null!(tensors);
+            for &tensor in std::slice::from_raw_parts(tensors, tensors_count) {
+                check_pointers_non_null!(tensor);
+                rust_tensors.push(&**tensor);
+            }
+        }
+
+        let concatenated = TensorMap::concatenate_properties(&rust_tensors, fill_missing_gradients)?;
+
+        // force the closure to capture the full unwind_wrapper, not just
+        // unwind_wrapper.0
+        let _ = &unwind_wrapper;
+        *unwind_wrapper.0 = mts_tensormap_t::into_boxed_raw(concatenated);
+
+        Ok(())
+    });
+
+    if !status.is_success() {
+        return std::ptr::null_mut();
+    }
+
+    return result;
+}
+
+/// Concatenate multiple tensor maps along the sample axis.
+///
+/// All the tensor maps must have the same keys, and for each key the blocks
+/// must have the same components and properties. The samples of the blocks are
+/// concatenated in the order of `tensors`, and must not contain the same entry
+/// more than once. The gradient samples are updated to refer to the samples in
+/// the concatenated blocks.
+///
+/// If `fill_missing_gradients` is `false`, all the tensor maps must have
+/// gradients with respect to the same parameters. If it is `true`, gradients
+/// which are only defined in some of the tensor maps are considered to be zero
+/// in the others.
+///
+/// The memory allocated by this function should be released using
+/// `mts_tensormap_free`.
+///
+/// @param tensors pointer to the first element of an array of tensor maps
+/// @param tensors_count number of elements in the `tensors` array
+/// @param fill_missing_gradients should gradients which are only defined in
+///        some of the tensor maps be filled with zeros in the others?
+///
+/// @returns A pointer to the newly allocated tensor map, or a `NULL` pointer in
+///          case of error. In case of error, you can use `mts_last_error()`
+///          to get the error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_concatenate_samples(
+    tensors: *const *const mts_tensormap_t,
+    tensors_count: usize,
+    fill_missing_gradients: bool,
+) -> *mut mts_tensormap_t {
+    let mut result = std::ptr::null_mut();
+    let unwind_wrapper = std::panic::AssertUnwindSafe(&mut result);
+
+    let status = catch_unwind(move || {
+        let mut rust_tensors = Vec::new();
+        if tensors_count != 0 {
+            check_pointers_non_null!(tensors);
+            for &tensor in std::slice::from_raw_parts(tensors, tensors_count) {
+                check_pointers_non_null!(tensor);
+                rust_tensors.push(&**tensor);
+            }
+        }
+
+        let concatenated = TensorMap::concatenate_samples(&rust_tensors, fill_missing_gradients)?;
+
+        // force the closure to capture the full unwind_wrapper, not just
+        // unwind_wrapper.0
+        let _ = &unwind_wrapper;
+        *unwind_wrapper.0 = mts_tensormap_t::into_boxed_raw(concatenated);
+
+        Ok(())
+    });
+
+    if !status.is_success() {
+        return std::ptr::null_mut();
+    }
+
+    return result;
+}
+
+/// Get the estimated size in bytes of the data stored in all the blocks of
+/// this `tensor`, including the data of all gradients.
+///
+/// The size is computed from the shape of the arrays, assuming they contain
+/// 64-bit floating point values.
+///
+/// @param tensor pointer to an existing tensor map
+/// @param nbytes pointer to an integer, will be set to the size in bytes
+///
+/// @returns The status code of this operation. If the status is not
+///          `MTS_SUCCESS`, you can use `mts_last_error()` to get the full
+///          error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_nbytes(
+    tensor: *const mts_tensormap_t,
+    nbytes: *mut usize,
+) -> mts_status_t {
+    catch_unwind(|| {
+        check_pointers_non_null!(tensor, nbytes);
+        *nbytes = (*tensor).nbytes()?;
+        Ok(())
+    })
+}
+
+
+
+/// Function pointer used to compute new keys in `mts_tensormap_map_keys`.
+///
+/// This function gets the values of an existing key in `key` (containing
+/// `key_size` elements), and should write the corresponding new values in
+/// `new_key` (which has space for `key_size` elements). It can return a
+/// non-zero `mts_status_t` to indicate an error. The `user_data` parameter is
+/// passed as-is from `mts_tensormap_map_keys`, and can be used to hold custom
+/// data.
+#[allow(non_camel_case_types)]
+type mts_map_keys_callback_t = unsafe extern fn(
+    user_data: *mut c_void,
+    key: *const i32,
+    new_key: *mut i32,
+    key_size: usize,
+) -> mts_status_t;
+
+
+/// Create a new tensor map with the same blocks as `tensor`, and keys
+/// transformed by the `callback` function.
+///
+/// The `callback` is called once for each key in the tensor map, and should
+/// compute the new values for this key. The names of the keys are not
+/// modified, and the new keys must be unique.
+///
+/// The result is a new tensor map, which should be freed with `mts_tensormap_free`.
+///
+/// @param tensor pointer to an existing tensor map
+/// @param callback function used to compute the new values of each key
+/// @param user_data custom data for the `callback`. This will be passed as the
+///        first argument to `callback` as-is.
+///
+/// @returns A pointer to the newly allocated tensor map, or a `NULL` pointer in
+///          case of error. In case of error, you can use `mts_last_error()`
+///          to get the error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_map_keys(
+    tensor: *const mts_tensormap_t,
+    callback: mts_map_keys_callback_t,
+    user_data: *mut c_void,
+) -> *mut mts_tensormap_t {
+    let mut result = std::ptr::null_mut();
+    let unwind_wrapper = std::panic::AssertUnwindSafe(&mut result);
+
+    let status = catch_unwind(move || {
+        check_pointers_non_null!(tensor);
+
+        let mapped = (*tensor).try_clone()?.map_keys(|key| {
+            let mut new_key = vec![LabelValue::new(0); key.len()];
+            let status = callback(
+                user_data,
+                key.as_ptr().cast(),
+                new_key.as_mut_ptr().cast(),
+                key.len(),
+            );
+
+            if !status.is_success() {
+                return Err(Error::External {
+                    status, context: "calling mts_map_keys_callback_t failed".into()
+                });
+            }
+
+            Ok(new_key)
+        })?;
+
+        // force the closure to capture the full unwind_wrapper, not just
+        // unwind_wrapper.0
+        let _ = &unwind_wrapper;
+        *unwind_wrapper.0 = mts_tensormap_t::into_boxed_raw(mapped);
+        Ok(())
+    });
+
+    if !status.is_success() {
+        return std::ptr::null_mut();
+    }
+
+    return result;
+}
+
+/// Function pointer used to select properties in
+/// `mts_tensormap_filter_properties`.
+///
+/// This function gets the values of a single property in `property`
+/// (containing `property_size` elements), and should set `keep` to `true` if
+/// this property should be kept, and `false` otherwise. It can return a
+/// non-zero `mts_status_t` to indicate an error. The `user_data` parameter is
+/// passed as-is from `mts_tensormap_filter_properties`, and can be used to
+/// hold custom data.
+#[allow(non_camel_case_types)]
+type mts_filter_properties_callback_t = unsafe extern fn(
+    user_data: *mut c_void,
+    property: *const i32,
+    property_size: usize,
+    keep: *mut bool,
+) -> mts_status_t;
+
+/// Create a new tensor map where each block only contains the properties
+/// selected by the `callback` function, together with the corresponding values
+/// and gradients.
+///
+/// The `callback` is called once for each property of each block. The samples
+/// and components of the blocks are not modified. If all the properties of a
+/// block are removed, the corresponding block in the output will have zero
+/// properties.
+///
+/// The result is a new tensor map, which should be freed with `mts_tensormap_free`.
+///
+/// @param tensor pointer to an existing tensor map
+/// @param callback function used to decide which properties to keep
+/// @param user_data custom data for the `callback`. This will be passed as the
+///        first argument to `callback` as-is.
+///
+/// @returns A pointer to the newly allocated tensor map, or a `NULL` pointer in
+///          case of error. In case of error, you can use `mts_last_error()`
+///          to get the error message.
+#[no_mangle]
+pub unsafe extern fn mts_tensormap_filter_properties(
+    tensor: *const mts_tensormap_t,
+    callback: mts_filter_properties_callback_t,
+    user_data: *mut c_void,
+) -> *mut mts_tensormap_t {
+    let mut result = std::ptr::null_mut();
+    let unwind_wrapper = std::panic::AssertUnwindSafe(&mut result);
+
+    let status = catch_unwind(move || {
+        check_pointers_non_null!(tensor);
+
+        let filtered = (*tensor).filter_properties(|property| {
+            let mut keep = false;
+            let status = callback(
+                user_data,
+                property.as_ptr().cast(),
+                property.len(),
+                &mut keep,
+            );
+
+            if !status.is_success() {
+                return Err(Error::External {
+                    status, context: "calling mts_filter_properties_callback_t failed".into()
+                });
+            }
+
+            Ok(keep)
+        })?;
+
+        // force the closure to capture the full unwind_wrapper, not just
+        // unwind_wrapper.0
+        let _ = &unwind_wrapper;
+        *unwind_wrapper.0 = mts_tensormap_t::into_boxed_raw(filtered);
+        Ok(())
+    });
+
+    if !status.is_success() {
+        return std::ptr::null_mut();
+    }
+
+    return result;
+}
@@ -163,6 +163,14 @@ pub struct mts_array_t {
         property_start: usize,
         property_end: usize,
     ) -> mts_status_t>,
+
+    /// Get the size in bytes of a single element of this array in
+    /// `element_size`. This function can be set to `NULL`, in which case the
+    /// elements are assumed to be 64-bit floating point values.
+    element_size: Option<unsafe extern fn(
+        array: *const c_void,
+        element_size: *mut usize,
+    ) -> mts_status_t>,
 }
 
 /// Representation of a single sample moved from an array to another one
@@ -218,6 +226,7 @@ impl mts_array_t {
             // do not copy destroy, the user should never call it
             destroy: None,
             move_samples_from: self.move_samples_from,
+            element_size: self.element_size,
         }
     }
 
@@ -234,6 +243,7 @@ impl mts_array_t {
             copy: None,
             destroy: None,
             move_samples_from: None,
+            element_size: None,
         }
     }
 
@@ -362,6 +372,36 @@ impl mts_array_t {
         return Ok(shape);
     }
 
+    /// Get the size in bytes of a single element of this array, using
+    /// the size of a 64-bit floating point value if `mts_array_t.element_size`
+    /// is `NULL`.
+    pub fn element_size(&self) -> Result<usize, Error> {
+        let function = match self.element_size {
+            Some(function) => function,
+            None => return Ok(std::mem::size_of::<f64>()),
+        };
+
+        let mut element_size = 0;
+        let status = unsafe {
+            function(self.ptr, &mut element_size)
+        };
+
+        if !status.is_success() {
+            return Err(Error::External {
+                status, context: "calling mts_array_t.element_size failed".into()
+            });
+        }
+
+        return Ok(element_size);
+    }
+
+    /// Get the estimated size in bytes of the data in this array, computed
+    /// from the number of elements and the size of a single element.
+    pub fn nbytes(&self) -> Result<usize, Error> {
+        let n_elements = self.shape()?.iter().product::<usize>();
+        return Ok(n_elements * self.element_size()?);
+    }
+
     /// Set the shape of this array to the given new `shape`
     pub fn reshape(&mut self, shape: &[usize]) -> Result<(), Error> {
         let function = self.reshape.expect("mts_array_t.reshape function is NULL");
@@ -384,7 +424,18 @@ impl mts_array_t {
     }
 
     /// Swap the axes `axis_1` and `axis_2` in the dimensions of this array.
+    ///
+    /// This returns an error if either axis is out of bounds for the array.
+    /// The array implementation decides whether this requires copying the data.
     pub fn swap_axes(&mut self, axis_1: usize, axis_2: usize) -> Result<(), Error> {
+        let n_dims = self.shape()?.len();
+        if axis_1 >= n_dims || axis_2 >= n_dims {
+            return Err(Error::InvalidParameter(format!(
+                "can not swap axes {} and {} in an array with {} dimensions",
+                axis_1, axis_2, n_dims
+            )));
+        }
+
         let function = self.swap_axes.expect("mts_array_t.swap_axes function is NULL");
 
         let status = unsafe {
@@ -497,24 +548,34 @@ mod tests {
 
     pub struct TestArray {
         shape: Vec<usize>,
+        data: Vec<f64>,
     }
 
     impl TestArray {
+        /// Create a new `TestArray` with the given `shape`, filled with zeros
         #[allow(clippy::new_ret_no_self)]
         pub fn new(shape: Vec<usize>) -> mts_array_t {
-            let array = Box::new(TestArray {shape});
+            let len = shape.iter().product();
+            return TestArray::with_data(shape, vec![0.0; len]);
+        }
+
+        /// Create a new `TestArray` with the given `shape` and `data`
+        pub fn with_data(shape: Vec<usize>, data: Vec<f64>) -> mts_array_t {
+            assert_eq!(shape.iter().product::<usize>(), data.len());
+            let array = Box::new(TestArray {shape, data});
 
             return mts_array_t {
                 ptr: Box::into_raw(array).cast(),
                 origin: Some(TestArray::origin),
-                data: None,
+                data: Some(TestArray::data),
                 shape: Some(TestArray::shape),
                 reshape: Some(TestArray::reshape),
                 swap_axes: Some(TestArray::swap_axes),
-                create: None,
-                copy: None,
+                create: Some(TestArray::create),
+                copy: Some(TestArray::copy),
                 destroy: Some(TestArray::destroy),
-                move_samples_from: None,
+                move_samples_from: Some(TestArray::move_samples_from),
+                element_size: None,
             }
         }
 
@@ -524,6 +585,13 @@ mod tests {
             return mts_status_t(MTS_SUCCESS);
         }
 
+        unsafe extern fn data(ptr: *mut c_void, data: *mut *mut f64) -> mts_status_t {
+            let ptr = ptr.cast::<TestArray>();
+            *data = (*ptr).data.as_mut_ptr();
+
+            return mts_status_t(MTS_SUCCESS);
+        }
+
         unsafe extern fn shape(ptr: *const c_void, shape: *mut *const usize, shape_count: *mut usize) -> mts_status_t {
             let ptr = ptr.cast::<TestArray>();
 
@@ -547,10 +615,50 @@ mod tests {
         }
 
         unsafe extern fn swap_axes(ptr: *mut c_void, axis_1: usize, axis_2: usize) -> mts_status_t {
-            let ptr = ptr.cast::<TestArray>();
+            let array = &mut *ptr.cast::<TestArray>();
+
+            let old_shape = array.shape.clone();
+            let mut new_shape = old_shape.clone();
+            new_shape.swap(axis_1, axis_2);
+
+            // transpose the data to match the new shape
+            let mut new_data = vec![0.0; array.data.len()];
+            let mut index = vec![0; old_shape.len()];
+            for value in &array.data {
+                index.swap(axis_1, axis_2);
+                let mut linear = 0;
+                for (i, n) in index.iter().zip(&new_shape) {
+                    linear = linear * n + i;
+                }
+                new_data[linear] = *value;
+                index.swap(axis_1, axis_2);
+
+                // increment the multi-dimensional index in the old shape
+                for axis in (0..old_shape.len()).rev() {
+                    index[axis] += 1;
+                    if index[axis] < old_shape[axis] {
+                        break;
+                    }
+                    index[axis] = 0;
+                }
+            }
 
-            let shape = &mut (*ptr).shape;
-            shape.swap(axis_1, axis_2);
+            array.shape = new_shape;
+            array.data = new_data;
+
+            return mts_status_t(MTS_SUCCESS);
+        }
+
+        unsafe extern fn create(_: *const c_void, shape_ptr: *const usize, shape_count: usize, new_array: *mut mts_array_t) -> mts_status_t {
+            let shape = std::slice::from_raw_parts(shape_ptr, shape_count).to_vec();
+            *new_array = TestArray::new(shape);
+
+            return mts_status_t(MTS_SUCCESS);
+        }
+
+        unsafe extern fn copy(ptr: *const c_void, new_array: *mut mts_array_t) -> mts_status_t {
+            let array = &*ptr.cast::<TestArray>();
+            *new_array = TestArray::with_data(array.shape.clone(), array.data.clone());
 
             return mts_status_t(MTS_SUCCESS);
         }
@@ -560,6 +668,37 @@ mod tests {
             let boxed = Box::from_raw(ptr);
             std::mem::drop(boxed);
         }
+
+        unsafe extern fn move_samples_from(
+            output: *mut c_void,
+            input: *const c_void,
+            samples: *const mts_sample_mapping_t,
+            samples_count: usize,
+            property_start: usize,
+            property_end: usize,
+        ) -> mts_status_t {
+            let output = &mut *output.cast::<TestArray>();
+            let input = &*input.cast::<TestArray>();
+            let samples = std::slice::from_raw_parts(samples, samples_count);
+
+            let n_input_properties = *input.shape.last().unwrap();
+            let n_output_properties = *output.shape.last().unwrap();
+            assert_eq!(property_end - property_start, n_input_properties);
+
+            let n_components = input.shape[1..input.shape.len() - 1].iter().product::<usize>();
+            for sample in samples {
+                for component in 0..n_components {
+                    let input_start = (sample.input * n_components + component) * n_input_properties;
+                    let output_start = (sample.output * n_components + component) * n_output_properties;
+
+                    let input = &input.data[input_start..(input_start + n_input_properties)];
+                    let output = &mut output.data[(output_start + property_start)..(output_start + property_end)];
+                    output.copy_from_slice(input);
+                }
+            }
+
+            return mts_status_t(MTS_SUCCESS);
+        }
     }
 
     #[test]
@@ -581,4 +720,50 @@ mod tests {
             data.ptr
         ));
     }
+
+    #[test]
+    fn nbytes() {
+        unsafe extern fn element_size(_: *const c_void, element_size: *mut usize) -> mts_status_t {
+            *element_size = 4;
+            return mts_status_t(MTS_SUCCESS);
+        }
+
+        let mut data: mts_array_t = TestArray::new(vec![3, 4, 5]);
+        assert_eq!(data.nbytes().unwrap(), 3 * 4 * 5 * 8);
+
+        data.element_size = Some(element_size);
+        assert_eq!(data.nbytes().unwrap(), 3 * 4 * 5 * 4);
+    }
+
+    #[test]
+    fn move_samples_from() {
+        let input = TestArray::with_data(vec![2, 2], vec![1.0, 2.0, 3.0, 4.0]);
+        let mut output = input.create(&[3, 3]).unwrap();
+
+        let samples = [
+            mts_sample_mapping_t { input: 0, output: 2 },
+            mts_sample_mapping_t { input: 1, output: 0 },
+        ];
+        output.move_samples_from(&input, &samples, 1..3).unwrap();
+
+        assert_eq!(output.data().unwrap(), [
+            0.0, 3.0, 4.0,
+            0.0, 0.0, 0.0,
+            0.0, 1.0, 2.0,
+        ]);
+    }
+
+    #[test]
+    fn swap_axes() {
+        let mut array = TestArray::with_data(vec![2, 3], vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        array.swap_axes(0, 1).unwrap();
+        assert_eq!(array.shape().unwrap(), [3, 2]);
+        assert_eq!(array.data().unwrap(), [1.0, 4.0, 2.0, 5.0, 3.0, 6.0]);
+
+        let error = array.swap_axes(0, 2).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not swap axes 0 and 2 in an array with 2 dimensions"
+        );
+    }
 }
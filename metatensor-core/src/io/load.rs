@@ -4,11 +4,13 @@ use std::sync::Arc;
 
 use byteorder::{LittleEndian, BigEndian, ReadBytesExt};
 use zip::ZipArchive;
+use zip::result::ZipError;
 
 use crate::{TensorMap, TensorBlock, Labels, Error, mts_array_t};
 
 use super::{check_for_extra_bytes, PathOrBuffer};
 use super::labels::load_labels;
+use super::metadata::{load_metadata, load_units};
 use super::npy_header::{Header, DataType};
 
 
@@ -76,6 +78,8 @@ pub fn looks_like_tensormap_data(mut data: PathOrBuffer) -> bool {
 ///                                       / <n_components>.npy
 ///                         / properties.npy
 ///                         / values.npy
+///                         # optional physical units of the values
+///                         / units.npy
 ///
 ///                         # optional sections for gradients, one by parameter
 ///                         /   gradients / <parameter> / samples.npy
@@ -83,7 +87,12 @@ pub fn looks_like_tensormap_data(mut data: PathOrBuffer) -> bool {
 ///                                                                     / <...>.npy
 ///                                                                     / <n_components>.npy
 ///                                                     /   values.npy
+///                                                     /   units.npy
 /// ```
+///
+/// Finally, the optional metadata of the `TensorMap` is stored in
+/// `/metadata.npy`, and the optional units of the blocks in `units.npy`, see
+/// the `metadata` module for more information.
 pub fn load<R, F>(reader: R, create_array: F) -> Result<TensorMap, Error>
     where R: std::io::Read + std::io::Seek,
           F: Fn(Vec<usize>) -> Result<mts_array_t, Error>
@@ -112,7 +121,16 @@ pub fn load<R, F>(reader: R, create_array: F) -> Result<TensorMap, Error>
         )?,);
     }
 
-    return TensorMap::new(Arc::new(keys), blocks);
+    let mut tensor = TensorMap::new(keys, blocks)?;
+
+    let path = String::from("metadata.npy");
+    match archive.by_name(&path) {
+        Ok(metadata_file) => load_metadata(metadata_file, &mut tensor)?,
+        Err(ZipError::FileNotFound) => {},
+        Err(e) => return Err((path, e).into()),
+    }
+
+    return Ok(tensor);
 }
 
 #[allow(clippy::needless_pass_by_value)]
@@ -131,7 +149,7 @@ fn read_block<R, F>(
 
     let path = format!("{}/samples.npy", prefix);
     let samples_file = archive.by_name(&path).map_err(|e| (path, e))?;
-    let samples = Arc::new(load_labels(samples_file)?);
+    let samples = load_labels(samples_file)?;
 
     let mut components = Vec::new();
     for i in 0..(shape.len() - 2) {
@@ -150,6 +168,13 @@ fn read_block<R, F>(
 
     let mut block = TensorBlock::new(data, samples, components, properties.clone())?;
 
+    let path = format!("{}/units.npy", prefix);
+    match archive.by_name(&path) {
+        Ok(units_file) => load_units(units_file, &mut block)?,
+        Err(ZipError::FileNotFound) => {},
+        Err(e) => return Err((path, e).into()),
+    }
+
     let mut parameters = HashSet::new();
     let gradient_prefix = format!("{}/gradients/", prefix);
     for name in archive.file_names() {
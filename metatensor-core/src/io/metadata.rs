@@ -0,0 +1,147 @@
+use byteorder::{LittleEndian, ReadBytesExt, BigEndian, WriteBytesExt, NativeEndian};
+
+use super::check_for_extra_bytes;
+use super::npy_header::{Header, DataType};
+use crate::{Error, TensorBlock, TensorMap};
+
+/// Read the metadata of a `TensorMap` stored using numpy's NPY format, and
+/// add it to `tensor`.
+///
+/// The metadata is stored as a 2-dimensional array of unicode strings (see
+/// [`read_strings`]) with shape `(n_entries, 2)`, where the first column
+/// contains the keys and the second column the values.
+pub fn load_metadata<R: std::io::Read>(reader: R, tensor: &mut TensorMap) -> Result<(), Error> {
+    let (shape, strings) = read_strings(reader, "metadata")?;
+    if shape.len() != 2 || shape[1] != 2 {
+        return Err(Error::Serialization("expected a N x 2 array when loading metadata".into()));
+    }
+
+    for entry in strings.chunks_exact(2) {
+        tensor.set_metadata(&entry[0], &entry[1])?;
+    }
+
+    return Ok(());
+}
+
+/// Write the metadata of `tensor` to the writer using numpy's NPY format.
+///
+/// See [`load_metadata`] for more information on how the metadata is stored.
+pub fn save_metadata<W: std::io::Write>(writer: &mut W, tensor: &TensorMap) -> Result<(), Error> {
+    let entries = tensor.metadata_iter().collect::<Vec<_>>();
+    let strings = entries.iter()
+        .flat_map(|(key, value)| [*key, *value])
+        .collect::<Vec<_>>();
+
+    return write_strings(writer, vec![entries.len(), 2], &strings);
+}
+
+/// Read the units of a `TensorBlock` stored using numpy's NPY format, and set
+/// them on `block`.
+///
+/// The units are stored as a 1-dimensional array of unicode strings (see
+/// [`read_strings`]) with shape `(1,)`.
+pub fn load_units<R: std::io::Read>(reader: R, block: &mut TensorBlock) -> Result<(), Error> {
+    let (shape, strings) = read_strings(reader, "units")?;
+    if shape != [1] {
+        return Err(Error::Serialization("expected an array with a single entry when loading units".into()));
+    }
+
+    return block.set_units(Some(&strings[0]));
+}
+
+/// Write the `units` of a `TensorBlock` to the writer using numpy's NPY format.
+///
+/// See [`load_units`] for more information on how the units are stored.
+pub fn save_units<W: std::io::Write>(writer: &mut W, units: &str) -> Result<(), Error> {
+    return write_strings(writer, vec![1], &[units]);
+}
+
+/// Read an array of unicode strings stored using numpy's NPY format, returning
+/// the shape of the array and the strings in row-major order. `context` is
+/// used in error messages.
+///
+/// The strings use numpy dtype "<U{n}" for little endian file or ">U{n}" for
+/// big endian file. Each string is stored as `n` UTF-32 code points, padded
+/// with zeros at the end.
+fn read_strings<R: std::io::Read>(mut reader: R, context: &str) -> Result<(Vec<usize>, Vec<String>), Error> {
+    let header = Header::from_reader(&mut reader)?;
+    if header.fortran_order {
+        return Err(Error::Serialization(format!("{} can not be loaded from fortran-order arrays", context)));
+    }
+
+    let error = || Error::Serialization(format!(
+        "invalid dtype for {}, expected unicode strings, got {}",
+        context, header.type_descriptor
+    ));
+
+    let (little_endian, length) = match &header.type_descriptor {
+        DataType::Scalar(s) if s.starts_with("<U") => (true, &s[2..]),
+        DataType::Scalar(s) if s.starts_with(">U") => (false, &s[2..]),
+        _ => return Err(error()),
+    };
+    let length = length.parse::<usize>().map_err(|_| error())?;
+    let count = header.shape.iter().product::<usize>();
+
+    let mut data = vec![0; count * length];
+    if little_endian {
+        reader.read_u32_into::<LittleEndian>(&mut data)?;
+    } else {
+        reader.read_u32_into::<BigEndian>(&mut data)?;
+    }
+
+    check_for_extra_bytes(&mut reader)?;
+
+    if length == 0 {
+        // all strings are empty
+        return Ok((header.shape, vec![String::new(); count]));
+    }
+
+    let mut strings = Vec::with_capacity(count);
+    for chunk in data.chunks_exact(length) {
+        let string = chunk.iter()
+            .take_while(|&&c| c != 0)
+            .map(|&c| char::from_u32(c).ok_or_else(|| Error::Serialization(
+                format!("invalid unicode code point in {}: {}", context, c)
+            )))
+            .collect::<Result<String, Error>>()?;
+        strings.push(string);
+    }
+
+    return Ok((header.shape, strings));
+}
+
+/// Write `strings` as an array with the given `shape` to the writer, using
+/// numpy's NPY format. See [`read_strings`] for the format of the strings.
+fn write_strings<W: std::io::Write>(writer: &mut W, shape: Vec<usize>, strings: &[&str]) -> Result<(), Error> {
+    let mut length = 1;
+    for string in strings {
+        length = usize::max(length, string.chars().count());
+    }
+
+    let type_descriptor = if cfg!(target_endian = "little") {
+        format!("<U{}", length)
+    } else {
+        format!(">U{}", length)
+    };
+
+    let header = Header {
+        type_descriptor: DataType::Scalar(type_descriptor),
+        fortran_order: false,
+        shape: shape,
+    };
+    header.write(&mut *writer)?;
+
+    for string in strings {
+        let mut count = 0;
+        for c in string.chars() {
+            writer.write_u32::<NativeEndian>(c.into())?;
+            count += 1;
+        }
+
+        for _ in count..length {
+            writer.write_u32::<NativeEndian>(0)?;
+        }
+    }
+
+    return Ok(());
+}
@@ -3,6 +3,8 @@ mod labels;
 pub use self::labels::load_labels;
 pub use self::labels::looks_like_labels_data;
 
+mod metadata;
+
 mod load;
 pub use self::load::load;
 pub use self::load::looks_like_tensormap_data;
@@ -5,6 +5,7 @@ use crate::{TensorMap, TensorBlock, Error, mts_array_t};
 
 use super::npy_header::{Header, DataType};
 use super::labels::save_labels;
+use super::metadata::{save_metadata, save_units};
 
 
 /// Save the given tensor to a file (or any other writer).
@@ -23,6 +24,12 @@ pub fn save<W: std::io::Write + std::io::Seek>(writer: W, tensor: &TensorMap) ->
     archive.start_file(&path, options).map_err(|e| (path, e))?;
     save_labels(&mut archive, tensor.keys())?;
 
+    if tensor.metadata_iter().next().is_some() {
+        let path = String::from("metadata.npy");
+        archive.start_file(&path, options).map_err(|e| (path, e))?;
+        save_metadata(&mut archive, tensor)?;
+    }
+
     for (block_i, block) in tensor.blocks().iter().enumerate() {
         write_block(&mut archive, &format!("blocks/{}", block_i), true, block)?;
     }
@@ -63,6 +70,12 @@ fn write_block<W: std::io::Write + std::io::Seek>(
         save_labels(archive, &block.properties)?;
     }
 
+    if let Some(units) = block.units() {
+        let path = format!("{}/units.npy", prefix);
+        archive.start_file(&path, options).map_err(|e| (path, e))?;
+        save_units(archive, units.as_str())?;
+    }
+
     for (parameter, gradient) in block.gradients() {
         let prefix = format!("{}/gradients/{}", prefix, parameter);
         write_block(archive, &prefix, false, gradient)?;
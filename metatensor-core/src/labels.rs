@@ -1,5 +1,5 @@
 #![allow(clippy::default_trait_access, clippy::module_name_repetitions)]
-use std::sync::RwLock;
+use std::sync::{Arc, RwLock};
 use std::ffi::CString;
 use std::collections::BTreeSet;
 use std::os::raw::c_void;
@@ -94,6 +94,13 @@ impl LabelValue {
     pub fn i32(self) -> i32 {
         self.0
     }
+
+    /// Special value used in block selections to match any value of the
+    /// corresponding key dimension, see `TensorMap::blocks_matching`.
+    ///
+    /// This is stored as `i32::MIN`, and is only used as a wildcard when
+    /// requested, so keys can also contain this value.
+    pub const ANY: LabelValue = LabelValue(crate::c_api::MTS_LABELS_ANY);
 }
 
 type DefaultHasher = std::hash::BuildHasherDefault<ahash::AHasher>;
@@ -333,6 +340,30 @@ impl Labels {
         self.names.iter().map(|s| s.as_str()).collect()
     }
 
+    /// Check if this set of labels and `other` have the same names, in the
+    /// same order
+    pub fn same_names(&self, other: &Labels) -> bool {
+        self.names == other.names
+    }
+
+    /// Check that this set of labels and `other` have the same names, in the
+    /// same order, returning an error otherwise
+    pub fn assert_same_names(&self, other: &Labels) -> Result<(), Error> {
+        if !self.same_names(other) {
+            return Err(Error::InvalidParameter(format!(
+                "expected Labels with the same names, got [{}] and [{}]",
+                self.names().join(", "),
+                other.names().join(", "),
+            )));
+        }
+        Ok(())
+    }
+
+    /// Wrap these `Labels` in an `Arc`, to share them between multiple blocks
+    pub fn into_arc(self) -> Arc<Labels> {
+        Arc::new(self)
+    }
+
     /// Get the names of the entries/columns in this set of labels as
     /// C-compatible (null terminated) strings
     pub fn c_names(&self) -> &[ConstCString] {
@@ -406,7 +437,7 @@ impl Labels {
     /// Mapping will be computed only if slices are not empty.
     #[allow(clippy::needless_range_loop)]
     pub fn union(&self, other: &Labels, first_mapping: &mut [i64], second_mapping: &mut [i64]) -> Result<Labels, Error> {
-        if self.names != other.names {
+        if !self.same_names(other) {
             return Err(Error::InvalidParameter(
                 "can not take the union of these Labels, they have different names".into()
             ));
@@ -450,7 +481,7 @@ impl Labels {
     ///
     /// Mapping will be computed only if slices are not empty.
     pub fn intersection(&self, other: &Labels, first_mapping: &mut [i64], second_mapping: &mut [i64]) -> Result<Labels, Error> {
-        if self.names != other.names {
+        if !self.same_names(other) {
             return Err(Error::InvalidParameter(
                 "can not take the intersection of these Labels, they have different names".into()
             ));
@@ -492,6 +523,37 @@ impl Labels {
 
         return Ok(builder.finish());
     }
+
+    /// Compute the cartesian product of two labels. The names of the result
+    /// are the names of `self` followed by the names of `other`, and the
+    /// entries contain every combination of one entry from `self` followed by
+    /// one entry from `other`.
+    ///
+    /// The two sets of labels must not have any name in common.
+    pub fn cartesian_product(&self, other: &Labels) -> Result<Labels, Error> {
+        let mut names = self.names();
+        for name in other.names() {
+            if names.contains(&name) {
+                return Err(Error::InvalidParameter(format!(
+                    "can not take the cartesian product of these Labels, \
+                    they both contain the '{}' dimension", name
+                )));
+            }
+            names.push(name);
+        }
+
+        let mut builder = LabelsBuilder::new(names)?;
+        builder.reserve(self.count() * other.count());
+        for first in self {
+            for second in other {
+                let mut entry = first.to_vec();
+                entry.extend_from_slice(second);
+                builder.add(&entry)?;
+            }
+        }
+
+        return Ok(builder.finish());
+    }
 }
 
 /// iterator over `Labels` entries
@@ -643,6 +705,56 @@ mod tests {
         assert_eq!(second_mapping, &[]);
     }
 
+    #[test]
+    fn cartesian_product() {
+        let mut builder = LabelsBuilder::new(vec!["aa"]).unwrap();
+        builder.add(&[0]).unwrap();
+        builder.add(&[1]).unwrap();
+        let first = builder.finish();
+
+        let mut builder = LabelsBuilder::new(vec!["bb", "cc"]).unwrap();
+        builder.add(&[2, 3]).unwrap();
+        builder.add(&[4, 5]).unwrap();
+        builder.add(&[6, 7]).unwrap();
+        let second = builder.finish();
+
+        let product = first.cartesian_product(&second).unwrap();
+        assert_eq!(product.names(), ["aa", "bb", "cc"]);
+        assert_eq!(product.values, &[
+            0, 2, 3, 0, 4, 5, 0, 6, 7,
+            1, 2, 3, 1, 4, 5, 1, 6, 7,
+        ]);
+
+        let empty = LabelsBuilder::new(vec!["dd"]).unwrap().finish();
+        let product = first.cartesian_product(&empty).unwrap();
+        assert_eq!(product.names(), ["aa", "dd"]);
+        assert_eq!(product.count(), 0);
+
+        let err = second.cartesian_product(&second).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid parameter: can not take the cartesian product of these \
+            Labels, they both contain the 'bb' dimension"
+        );
+    }
+
+    #[test]
+    fn same_names() {
+        let first = LabelsBuilder::new(vec!["aa", "bb"]).unwrap().finish();
+        let second = LabelsBuilder::new(vec!["aa", "bb"]).unwrap().finish();
+        let third = LabelsBuilder::new(vec!["bb", "aa"]).unwrap().finish();
+
+        assert!(first.same_names(&second));
+        assert!(!first.same_names(&third));
+
+        assert!(first.assert_same_names(&second).is_ok());
+        let err = first.assert_same_names(&third).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid parameter: expected Labels with the same names, got [aa, bb] and [bb, aa]"
+        );
+    }
+
     #[test]
     fn marker_traits() {
         // ensure Arc<Labels> is Send and Sync, assuming the user data is
@@ -0,0 +1,446 @@
+use std::sync::Arc;
+
+use indexmap::IndexSet;
+
+use crate::labels::{Labels, LabelsBuilder, LabelValue};
+use crate::{Error, TensorBlock, get_data_origin};
+
+use crate::data::mts_sample_mapping_t;
+
+use super::TensorMap;
+
+impl TensorMap {
+    /// Concatenate multiple tensor maps along the property axis.
+    ///
+    /// All the `tensors` must have the same keys, and for each key the blocks
+    /// must have the same samples and components. The properties of the blocks
+    /// are concatenated in the order of `tensors`, and must not contain the
+    /// same entry more than once.
+    ///
+    /// Gradient samples are merged across the blocks, and the values of
+    /// gradients which are only defined in some of the `tensors` are set to
+    /// zero for the other properties.
+    ///
+    /// If `fill_missing_gradients` is `false`, all the `tensors` must have
+    /// gradients with respect to the same parameters. If it is `true`, the
+    /// result contains the gradients with respect to all the parameters
+    /// defined in any of the `tensors`, and gradients missing from some of the
+    /// `tensors` are considered to be zero.
+    ///
+    /// This function returns a new `TensorMap` containing a copy of all the
+    /// data.
+    pub fn concatenate_properties(tensors: &[&TensorMap], fill_missing_gradients: bool) -> Result<TensorMap, Error> {
+        let keys = check_same_keys(tensors, "properties")?;
+
+        let mut new_blocks = Vec::new();
+        for key in &*keys {
+            let blocks = blocks_for_key(tensors, key);
+            new_blocks.push(concatenate_blocks_properties(&blocks, fill_missing_gradients)?);
+        }
+
+        return TensorMap::new(keys, new_blocks);
+    }
+
+    /// Concatenate multiple tensor maps along the sample axis.
+    ///
+    /// All the `tensors` must have the same keys, and for each key the blocks
+    /// must have the same components and properties. The samples of the
+    /// blocks are concatenated in the order of `tensors`, and must not contain
+    /// the same entry more than once.
+    ///
+    /// The first dimension of the gradient samples (`"sample"`) is updated to
+    /// refer to the position of the corresponding sample in the concatenated
+    /// block.
+    ///
+    /// If `fill_missing_gradients` is `false`, all the `tensors` must have
+    /// gradients with respect to the same parameters. If it is `true`, the
+    /// result contains the gradients with respect to all the parameters
+    /// defined in any of the `tensors`, and gradients missing from some of the
+    /// `tensors` are considered to be zero (i.e. they do not contribute any
+    /// gradient sample).
+    ///
+    /// This function returns a new `TensorMap` containing a copy of all the
+    /// data.
+    pub fn concatenate_samples(tensors: &[&TensorMap], fill_missing_gradients: bool) -> Result<TensorMap, Error> {
+        let keys = check_same_keys(tensors, "samples")?;
+
+        let mut new_blocks = Vec::new();
+        for key in &*keys {
+            let blocks = blocks_for_key(tensors, key);
+            new_blocks.push(concatenate_blocks_samples(&blocks, "tensor map", fill_missing_gradients)?);
+        }
+
+        return TensorMap::new(keys, new_blocks);
+    }
+}
+
+/// Check that all the `tensors` have the same keys (potentially in a different
+/// order), and return the keys of the first tensor.
+fn check_same_keys(tensors: &[&TensorMap], axis: &str) -> Result<Arc<Labels>, Error> {
+    if tensors.is_empty() {
+        return Err(Error::InvalidParameter(format!(
+            "can not concatenate the {} of an empty list of tensor maps", axis
+        )));
+    }
+
+    let keys = &tensors[0].keys;
+    for tensor in &tensors[1..] {
+        tensor.keys.assert_same_names(keys)?;
+
+        if tensor.keys.count() != keys.count() || !keys.iter().all(|key| tensor.keys.contains(key)) {
+            return Err(Error::InvalidParameter(format!(
+                "all tensor maps must have the same keys to concatenate their {}", axis
+            )));
+        }
+    }
+
+    return Ok(Arc::clone(keys));
+}
+
+/// Get the blocks associated with the given `key` in all the `tensors`
+fn blocks_for_key<'a>(tensors: &[&'a TensorMap], key: &[LabelValue]) -> Vec<&'a TensorBlock> {
+    return tensors.iter()
+        .map(|tensor| {
+            let position = tensor.keys.position(key).expect("missing key");
+            &tensor.blocks[position]
+        })
+        .collect();
+}
+
+/// Concatenate the given `blocks` along the property axis. If
+/// `fill_missing_gradients` is `true`, gradients which are only defined in some
+/// of the blocks are considered to be zero in the other blocks.
+fn concatenate_blocks_properties(blocks: &[&TensorBlock], fill_missing_gradients: bool) -> Result<TensorBlock, Error> {
+    let first_block = blocks[0];
+    check_origin_of_blocks(blocks)?;
+
+    for block in blocks {
+        if block.samples != first_block.samples {
+            return Err(Error::InvalidParameter(
+                "can not concatenate properties of blocks with different samples".into()
+            ));
+        }
+
+        if block.components != first_block.components {
+            return Err(Error::InvalidParameter(
+                "can not concatenate properties of blocks with different components".into()
+            ));
+        }
+    }
+
+    let all_gradients = check_gradients(blocks, "properties", fill_missing_gradients)?;
+
+    let mut new_properties = IndexSet::new();
+    let mut property_ranges = Vec::new();
+    for block in blocks {
+        block.properties.assert_same_names(&first_block.properties)?;
+
+        let start = new_properties.len();
+        for property in &*block.properties {
+            if !new_properties.insert(property) {
+                return Err(Error::InvalidParameter(format!(
+                    "can not concatenate properties: the entry [{}] is present \
+                    in more than one tensor map",
+                    property.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ")
+                )));
+            }
+        }
+        property_ranges.push(start..new_properties.len());
+    }
+
+    let mut new_properties_builder = LabelsBuilder::new(first_block.properties.names())?;
+    new_properties_builder.reserve(new_properties.len());
+    for property in new_properties {
+        new_properties_builder.add(property)?;
+    }
+    let new_properties = Arc::new(new_properties_builder.finish());
+
+    let samples_mapping = (0..first_block.samples.count())
+        .map(|i| mts_sample_mapping_t { input: i, output: i })
+        .collect::<Vec<_>>();
+
+    let mut new_shape = first_block.values.shape()?.to_vec();
+    let property_axis = new_shape.len() - 1;
+    new_shape[property_axis] = new_properties.count();
+    let mut new_data = first_block.values.create(&new_shape)?;
+    for (block, property_range) in blocks.iter().zip(&property_ranges) {
+        if !property_range.is_empty() {
+            new_data.move_samples_from(&block.values, &samples_mapping, property_range.clone())?;
+        }
+    }
+
+    let mut new_block = TensorBlock::new(
+        new_data,
+        Arc::clone(&first_block.samples),
+        first_block.components.to_vec(),
+        new_properties,
+    ).expect("constructed an invalid block");
+
+    for (parameter, first_gradient) in all_gradients {
+        let gradients = blocks.iter()
+            .map(|block| block.gradient(parameter))
+            .collect::<Vec<_>>();
+
+        // merge the gradient samples, keeping them in the order in which they
+        // appear in the blocks
+        let mut new_gradient_samples = IndexSet::new();
+        for gradient in gradients.iter().flatten() {
+            new_gradient_samples.extend(gradient.samples.iter());
+        }
+
+        let mut new_shape = first_gradient.values.shape()?.to_vec();
+        new_shape[0] = new_gradient_samples.len();
+        let property_axis = new_shape.len() - 1;
+        new_shape[property_axis] = new_block.properties.count();
+        let mut new_gradient = first_block.values.create(&new_shape)?;
+
+        for (gradient, property_range) in gradients.iter().zip(&property_ranges) {
+            let gradient = match gradient {
+                Some(gradient) if !property_range.is_empty() => gradient,
+                _ => continue,
+            };
+
+            let samples_to_move = gradient.samples.iter()
+                .enumerate()
+                .map(|(sample_i, sample)| mts_sample_mapping_t {
+                    input: sample_i,
+                    output: new_gradient_samples.get_index_of(sample).expect("missing gradient sample"),
+                })
+                .collect::<Vec<_>>();
+
+            new_gradient.move_samples_from(&gradient.values, &samples_to_move, property_range.clone())?;
+        }
+
+        let mut new_gradient_samples_builder = LabelsBuilder::new(first_gradient.samples.names())?;
+        new_gradient_samples_builder.reserve(new_gradient_samples.len());
+        for sample in new_gradient_samples {
+            new_gradient_samples_builder.add(sample)?;
+        }
+
+        let new_gradient = TensorBlock::new(
+            new_gradient,
+            new_gradient_samples_builder.finish(),
+            first_gradient.components.to_vec(),
+            Arc::clone(&new_block.properties),
+        ).expect("created invalid gradient");
+
+        new_block.add_gradient(parameter, new_gradient).expect("could not add gradient");
+    }
+
+    return Ok(new_block);
+}
+
+impl TensorBlock {
+    /// Concatenate multiple blocks along the sample axis.
+    ///
+    /// All the `blocks` must have the same components and properties, and
+    /// gradients with respect to the same parameters. The samples of the
+    /// blocks are concatenated in the order of `blocks`, and must not contain
+    /// the same entry more than once. The first dimension of the gradient
+    /// samples (`"sample"`) is updated to refer to the position of the
+    /// corresponding sample in the concatenated block.
+    ///
+    /// This function returns a new `TensorBlock` containing a copy of all the
+    /// data.
+    pub fn concatenate_samples(blocks: &[&TensorBlock]) -> Result<TensorBlock, Error> {
+        if blocks.is_empty() {
+            return Err(Error::InvalidParameter(
+                "can not concatenate the samples of an empty list of blocks".into()
+            ));
+        }
+
+        return concatenate_blocks_samples(blocks, "block", false);
+    }
+}
+
+/// Concatenate the given `blocks` along the sample axis. `source` describes
+/// where the blocks come from (`"tensor map"` or `"block"`), and is used in
+/// error messages. If `fill_missing_gradients` is `true`, gradients which are
+/// only defined in some of the blocks are considered to be zero in the other
+/// blocks.
+fn concatenate_blocks_samples(blocks: &[&TensorBlock], source: &str, fill_missing_gradients: bool) -> Result<TensorBlock, Error> {
+    let first_block = blocks[0];
+    check_origin_of_blocks(blocks)?;
+
+    for block in blocks {
+        if block.components != first_block.components {
+            return Err(Error::InvalidParameter(
+                "can not concatenate samples of blocks with different components".into()
+            ));
+        }
+
+        if block.properties != first_block.properties {
+            return Err(Error::InvalidParameter(
+                "can not concatenate samples of blocks with different properties".into()
+            ));
+        }
+    }
+
+    let all_gradients = check_gradients(blocks, "samples", fill_missing_gradients)?;
+
+    let mut new_samples = IndexSet::new();
+    let mut samples_mappings = Vec::new();
+    for block in blocks {
+        block.samples.assert_same_names(&first_block.samples)?;
+
+        let mut samples_mapping = Vec::new();
+        for (sample_i, sample) in block.samples.iter().enumerate() {
+            samples_mapping.push(mts_sample_mapping_t {
+                input: sample_i,
+                output: new_samples.len(),
+            });
+
+            if !new_samples.insert(sample) {
+                return Err(Error::InvalidParameter(format!(
+                    "can not concatenate samples: the entry [{}] is present \
+                    in more than one {}",
+                    sample.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", "),
+                    source,
+                )));
+            }
+        }
+        samples_mappings.push(samples_mapping);
+    }
+
+    let mut new_samples_builder = LabelsBuilder::new(first_block.samples.names())?;
+    new_samples_builder.reserve(new_samples.len());
+    for sample in new_samples {
+        new_samples_builder.add(sample)?;
+    }
+    let new_samples = new_samples_builder.finish();
+
+    let properties_count = first_block.properties.count();
+
+    let mut new_shape = first_block.values.shape()?.to_vec();
+    new_shape[0] = new_samples.count();
+    let mut new_data = first_block.values.create(&new_shape)?;
+    if properties_count != 0 {
+        for (block, samples_mapping) in blocks.iter().zip(&samples_mappings) {
+            new_data.move_samples_from(&block.values, samples_mapping, 0..properties_count)?;
+        }
+    }
+
+    let mut new_block = TensorBlock::new(
+        new_data,
+        new_samples,
+        first_block.components.to_vec(),
+        Arc::clone(&first_block.properties),
+    ).expect("constructed an invalid block");
+
+    for (parameter, first_gradient) in all_gradients {
+        let mut new_gradient_samples = LabelsBuilder::new(first_gradient.samples.names())?;
+        let mut gradient_mappings = Vec::new();
+        for (block, samples_mapping) in blocks.iter().zip(&samples_mappings) {
+            // missing gradients do not contribute any gradient sample
+            let mut gradient_mapping = Vec::new();
+            if let Some(gradient) = block.gradient(parameter) {
+                for (grad_sample_i, grad_sample) in gradient.samples.iter().enumerate() {
+                    // translate from the old sample id in gradients to the new ones
+                    let mut grad_sample = grad_sample.to_vec();
+                    grad_sample[0] = samples_mapping[grad_sample[0].usize()].output.into();
+
+                    gradient_mapping.push(mts_sample_mapping_t {
+                        input: grad_sample_i,
+                        output: new_gradient_samples.count(),
+                    });
+                    new_gradient_samples.add(&grad_sample)?;
+                }
+            }
+            gradient_mappings.push(gradient_mapping);
+        }
+        let new_gradient_samples = new_gradient_samples.finish();
+
+        let mut new_shape = first_gradient.values.shape()?.to_vec();
+        new_shape[0] = new_gradient_samples.count();
+        let mut new_gradient = first_block.values.create(&new_shape)?;
+
+        if properties_count != 0 {
+            for (block, gradient_mapping) in blocks.iter().zip(&gradient_mappings) {
+                if let Some(gradient) = block.gradient(parameter) {
+                    new_gradient.move_samples_from(&gradient.values, gradient_mapping, 0..properties_count)?;
+                }
+            }
+        }
+
+        let new_gradient = TensorBlock::new(
+            new_gradient,
+            new_gradient_samples,
+            first_gradient.components.to_vec(),
+            Arc::clone(&new_block.properties),
+        ).expect("created invalid gradient");
+
+        new_block.add_gradient(parameter, new_gradient).expect("could not add gradient");
+    }
+
+    return Ok(new_block);
+}
+
+/// Check that all `blocks` have the same data origin, since data can only be
+/// moved between arrays with the same origin.
+fn check_origin_of_blocks(blocks: &[&TensorBlock]) -> Result<(), Error> {
+    let first_origin = blocks[0].values.origin()?;
+    for block in &blocks[1..] {
+        let block_origin = block.values.origin()?;
+        if first_origin != block_origin {
+            return Err(Error::InvalidParameter(format!(
+                "can not concatenate blocks with different origins: at least ('{}') and ('{}') were detected",
+                get_data_origin(first_origin),
+                get_data_origin(block_origin),
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Check the gradients of all the `blocks` before concatenating them along
+/// `axis`, and get the list of gradients in the concatenated block, together
+/// with a reference gradient for each parameter.
+///
+/// If `fill_missing_gradients` is `false`, all the blocks must have gradients
+/// with respect to the same parameters. Otherwise, the list contains all the
+/// parameters defined in any of the blocks. In both cases, all the gradients
+/// with respect to the same parameter must have the same sample names and
+/// components, and there must be no gradients of gradients.
+fn check_gradients<'a>(
+    blocks: &[&'a TensorBlock],
+    axis: &str,
+    fill_missing_gradients: bool,
+) -> Result<Vec<(&'a str, &'a TensorBlock)>, Error> {
+    let mut all_gradients: Vec<(&str, &TensorBlock)> = Vec::new();
+    for &block in blocks {
+        for (parameter, gradient) in block.gradients() {
+            if !gradient.gradients().is_empty() {
+                return Err(Error::InvalidParameter(format!(
+                    "gradient of gradients are not supported yet when concatenating {}", axis
+                )));
+            }
+
+            let reference = all_gradients.iter().find(|&&(p, _)| p == parameter);
+            if let Some(&(_, reference)) = reference {
+                gradient.samples.assert_same_names(&reference.samples)?;
+
+                if gradient.components != reference.components {
+                    return Err(Error::InvalidParameter(format!(
+                        "can not concatenate {} of blocks with different components \
+                        in the '{}' gradients", axis, parameter
+                    )));
+                }
+            } else {
+                all_gradients.push((parameter.as_str(), gradient));
+            }
+        }
+    }
+
+    if !fill_missing_gradients {
+        for block in blocks {
+            if block.gradients().len() != all_gradients.len() {
+                return Err(Error::InvalidParameter(format!(
+                    "can not concatenate {} of blocks with different gradients", axis
+                )));
+            }
+        }
+    }
+
+    return Ok(all_gradients);
+}
@@ -7,7 +7,7 @@ use crate::{Error, TensorBlock};
 
 use crate::data::mts_sample_mapping_t;
 
-use super::TensorMap;
+use super::{TensorMap, check_labels_names};
 use super::utils::{KeyAndBlock, remove_dimensions_from_keys, merge_samples, merge_gradient_samples};
 
 
@@ -33,12 +33,64 @@ impl TensorMap {
     /// blocks with properties `p=1, 2` will result in `a, p = (2, 1), (2, 2),
     /// (3, 1), (3, 2)`.
     ///
+    /// The moved dimensions are always added before the existing dimensions of
+    /// the properties, in the same order as in `keys_to_move`. For example,
+    /// moving `c, a` from keys `a, b, c` to blocks with properties `n, l` gives
+    /// properties `c, a, n, l`.
+    ///
+    /// The new keys contain the dimensions of the current keys which are not
+    /// moved, in the same order as in the current keys. If all the dimensions
+    /// are moved, the new keys contain a single `_` dimension with a single
+    /// entry.
+    ///
     /// The new sample labels will contains all of the merged blocks sample
     /// labels. The order of the samples is controlled by `sort_samples`. If
     /// `sort_samples` is true, samples are re-ordered to keep them
     /// lexicographically sorted. Otherwise they are kept in the order in which
     /// they appear in the blocks.
+    ///
+    /// The samples of the merged blocks do not need to be the same. If a
+    /// sample is not present in one of the merged blocks, the values (and
+    /// gradients) for this sample in the properties coming from this block
+    /// are set to zero.
+    ///
+    /// The same applies to the gradients: the gradient samples of the merged
+    /// blocks do not need to be the same, and the gradient samples of the new
+    /// block contain all of them (after updating the `sample` dimension to
+    /// refer to the new samples). Gradients which are not present in one of
+    /// the merged blocks are set to zero in the properties coming from this
+    /// block.
+    ///
+    /// This function does not modify the current `TensorMap`, and returns a
+    /// new `TensorMap` containing a copy of all the data. Keeping both the
+    /// original and the new `TensorMap` alive will use twice as much memory.
+    ///
+    /// If `keys_to_move` does not contain any dimension, this function returns
+    /// a copy of the current `TensorMap`, without re-ordering the samples.
     pub fn keys_to_properties(&self, keys_to_move: &Labels, sort_samples: bool) -> Result<TensorMap, Error> {
+        return self.keys_to_properties_with_progress(keys_to_move, sort_samples, |_, _| Ok(()));
+    }
+
+    /// Same as `keys_to_properties`, calling `progress` each time a new block is
+    /// created with the number of blocks created so far and the total number
+    /// of blocks in the output. This can be used to report progress when
+    /// merging very large tensor maps.
+    ///
+    /// If `progress` returns an error, no more blocks are created and the error
+    /// is returned. If `keys_to_move` does not contain any dimension,
+    /// `progress` is not called.
+    pub fn keys_to_properties_with_progress<F>(
+        &self,
+        keys_to_move: &Labels,
+        sort_samples: bool,
+        mut progress: F,
+    ) -> Result<TensorMap, Error>
+        where F: FnMut(usize, usize) -> Result<(), Error>
+    {
+        if keys_to_move.size() == 0 {
+            return self.try_clone();
+        }
+
         if self.keys.is_empty() {
             return Err(Error::InvalidParameter(
                 "there are no keys to move in an empty TensorMap".into()
@@ -79,12 +131,13 @@ impl TensorMap {
                 sort_samples,
             )?;
             new_blocks.push(block);
+            progress(1, 1)?;
         } else {
             for entry in &splitted_keys.new_keys {
                 let mut selection = LabelsBuilder::new(splitted_keys.new_keys.names())?;
                 selection.add(entry)?;
 
-                let matching = self.blocks_matching(&selection.finish())?;
+                let matching = self.blocks_matching(&selection.finish(), false)?;
                 let blocks_to_merge = matching.iter()
                     .map(|&i| {
                         let block = &self.blocks[i];
@@ -108,10 +161,11 @@ impl TensorMap {
                     sort_samples,
                 )?;
                 new_blocks.push(block);
+                progress(new_blocks.len(), splitted_keys.new_keys.count())?;
             }
         }
 
-        return TensorMap::new(Arc::new(splitted_keys.new_keys), new_blocks);
+        return TensorMap::new(splitted_keys.new_keys, new_blocks);
     }
 }
 
@@ -134,15 +188,14 @@ fn merge_blocks_along_properties(
         }
     }
 
-    let first_components_label = &first_block.components;
     let first_property_labels = &first_block.properties;
     for KeyAndBlock{block, ..} in blocks_to_merge {
-        if &block.components != first_components_label {
-            return Err(Error::InvalidParameter(
-                "can not move keys to properties if the blocks have \
-                different components labels, call components_to_properties first".into()
-            ));
-        }
+        check_labels_names(
+            block,
+            first_block,
+            " to move keys to properties, call components_to_properties first",
+            true,
+        )?;
 
         if keys_to_move.is_some() && &block.properties != first_property_labels {
             // TODO: this might be possible but also pretty slow. It would
@@ -162,7 +215,15 @@ fn merge_blocks_along_properties(
         sort_samples,
     );
 
-    let mut new_properties = IndexSet::new();
+    // pre-compute the number of new properties, to allocate all the memory
+    // we need in one go, even when merging a lot of blocks together
+    let properties_capacity = if let Some(keys_to_move) = keys_to_move {
+        first_property_labels.count() * keys_to_move.count()
+    } else {
+        blocks_to_merge.iter().map(|b| b.block.properties.count()).sum()
+    };
+
+    let mut new_properties = IndexSet::with_capacity(properties_capacity);
     if let Some(keys_to_move) = keys_to_move {
         // use the user-provided new values
         for new_property in keys_to_move {
@@ -174,7 +235,7 @@ fn merge_blocks_along_properties(
                 }
             }
         }
-        assert_eq!(new_properties.len(), first_property_labels.count() * keys_to_move.count());
+        assert_eq!(new_properties.len(), properties_capacity);
     } else {
         // collect properties from the blocks, augmenting them with the new
         // properties
@@ -187,11 +248,15 @@ fn merge_blocks_along_properties(
         }
     }
 
+    // the moved dimensions come first, in the order of `keys_to_move`, followed
+    // by the existing properties dimensions. This layout is documented in
+    // `keys_to_properties` and code using the properties relies on it.
     let new_property_names = extracted_names.iter()
         .chain(first_block.properties.names().iter())
         .copied()
         .collect();
     let mut new_properties_builder = LabelsBuilder::new(new_property_names)?;
+    new_properties_builder.reserve(new_properties.len());
     for property in new_properties {
         new_properties_builder.add(&property)?;
     }
@@ -209,7 +274,7 @@ fn merge_blocks_along_properties(
 
     // compute the property range for each block, i.e. where we want to put
     // the corresponding data
-    let mut property_ranges = Vec::new();
+    let mut property_ranges = Vec::with_capacity(blocks_to_merge.len());
     for KeyAndBlock{key, block} in blocks_to_merge {
         if block.properties.is_empty() {
             // no properties, ignore this block
@@ -278,7 +343,7 @@ fn merge_blocks_along_properties(
             let gradient = block.gradient(parameter).expect("missing gradient");
             debug_assert!(*gradient.components == *new_components);
 
-            let mut samples_to_move = Vec::new();
+            let mut samples_to_move = Vec::with_capacity(gradient.samples.count());
             for (sample_i, grad_sample) in gradient.samples.iter().enumerate() {
                 // translate from the old sample id in gradients to the new ones
                 let mut grad_sample = grad_sample.to_vec();
@@ -16,10 +16,22 @@ impl TensorMap {
     /// the sample labels, and blocks with the same remaining keys dimensions
     /// will be merged together along the sample axis.
     ///
+    /// Blocks are grouped by the values of the remaining keys dimensions:
+    /// blocks which only differ in the moved dimensions are merged together,
+    /// while blocks with different values for any of the remaining dimensions
+    /// are kept separate. The new keys contain the unique values of the
+    /// remaining dimensions, in the order in which they first appear in the
+    /// current keys.
+    ///
     /// `keys_to_move` must be empty (`keys_to_move.count() == 0`), and the new
     /// sample labels will contain entries corresponding to the merged blocks'
     /// keys.
     ///
+    /// The moved dimensions are always added after the existing dimensions of
+    /// the samples, in the same order as in `keys_to_move`. For example, moving
+    /// `c, a` from keys `a, b, c` to blocks with samples `system, atom` gives
+    /// samples `system, atom, c, a`.
+    ///
     /// The new sample labels will contains all of the merged blocks sample
     /// labels. The order of the samples is controlled by `sort_samples`. If
     /// `sort_samples` is true, samples are re-ordered to keep them
@@ -28,7 +40,33 @@ impl TensorMap {
     ///
     /// This function is only implemented if all merged block have the same
     /// property labels.
+    ///
+    /// If `keys_to_move` does not contain any dimension, this function returns
+    /// a copy of the current `TensorMap`, without re-ordering the samples.
     pub fn keys_to_samples(&self, keys_to_move: &Labels, sort_samples: bool) -> Result<TensorMap, Error> {
+        return self.keys_to_samples_with_progress(keys_to_move, sort_samples, |_, _| Ok(()));
+    }
+
+    /// Same as `keys_to_samples`, calling `progress` each time a new block is
+    /// created with the number of blocks created so far and the total number
+    /// of blocks in the output. This can be used to report progress when
+    /// merging very large tensor maps.
+    ///
+    /// If `progress` returns an error, no more blocks are created and the error
+    /// is returned. If `keys_to_move` does not contain any dimension,
+    /// `progress` is not called.
+    pub fn keys_to_samples_with_progress<F>(
+        &self,
+        keys_to_move: &Labels,
+        sort_samples: bool,
+        mut progress: F,
+    ) -> Result<TensorMap, Error>
+        where F: FnMut(usize, usize) -> Result<(), Error>
+    {
+        if keys_to_move.size() == 0 {
+            return self.try_clone();
+        }
+
         if self.keys.is_empty() {
             return Err(Error::InvalidParameter(
                 "there are no keys to move in an empty TensorMap".into()
@@ -43,6 +81,21 @@ impl TensorMap {
         }
 
         let names_to_move = keys_to_move.names();
+
+        // blocks have unique keys, so the merged samples can only contain
+        // duplicated entries if one of the moved dimensions is already part of
+        // the samples
+        let sample_names = self.blocks[0].samples.names();
+        for name in &names_to_move {
+            if sample_names.contains(name) {
+                return Err(Error::InvalidParameter(format!(
+                    "can not move keys to samples: '{}' is already a dimension of \
+                    the samples, the merged block would contain duplicated samples",
+                    name
+                )));
+            }
+        }
+
         let splitted_keys = remove_dimensions_from_keys(&self.keys, &names_to_move)?;
 
         let mut new_blocks = Vec::new();
@@ -69,12 +122,13 @@ impl TensorMap {
                 sort_samples,
             )?;
             new_blocks.push(block);
+            progress(1, 1)?;
         } else {
             for entry in &splitted_keys.new_keys {
                 let mut selection = LabelsBuilder::new(splitted_keys.new_keys.names())?;
                 selection.add(entry)?;
 
-                let matching = self.blocks_matching(&selection.finish())?;
+                let matching = self.blocks_matching(&selection.finish(), false)?;
                 let blocks_to_merge = matching.iter()
                     .map(|&i| {
                         let block = &self.blocks[i];
@@ -96,10 +150,11 @@ impl TensorMap {
                     &names_to_move,
                     sort_samples,
                 )?);
+                progress(new_blocks.len(), splitted_keys.new_keys.count())?;
             }
         }
 
-        return TensorMap::new(Arc::new(splitted_keys.new_keys), new_blocks);
+        return TensorMap::new(splitted_keys.new_keys, new_blocks);
     }
 }
 
@@ -0,0 +1,71 @@
+use std::ffi::CString;
+
+use crate::utils::ConstCString;
+use crate::Error;
+
+use super::TensorMap;
+
+/// Arbitrary metadata attached to a `TensorMap`, as a set of key/value pairs
+/// of strings. The entries are kept in insertion order.
+#[derive(Debug, Clone, Default)]
+pub struct TensorMetadata {
+    keys: Vec<ConstCString>,
+    values: Vec<ConstCString>,
+}
+
+impl TensorMetadata {
+    /// Get the position of `key` in this metadata, if it exists
+    fn position(&self, key: &str) -> Option<usize> {
+        self.keys.iter().position(|k| k.as_str() == key)
+    }
+}
+
+impl TensorMap {
+    /// Set the metadata entry `key` of this `TensorMap` to `value`,
+    /// overwriting any existing value for this `key`.
+    ///
+    /// Metadata is a set of arbitrary key/value pairs of strings, which can
+    /// be used to store provenance information (model version, units, etc.)
+    /// together with the data. Metadata is kept by `try_clone` and
+    /// serialization, but operations creating new `TensorMap` (e.g.
+    /// `keys_to_samples`) do not propagate it.
+    pub fn set_metadata(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        let c_value = CString::new(value).map_err(|_| Error::InvalidParameter(format!(
+            "metadata value for '{}' can not contain NULL bytes", key
+        )))?;
+        let c_value = ConstCString::new(c_value);
+
+        if let Some(position) = self.metadata.position(key) {
+            self.metadata.values[position] = c_value;
+        } else {
+            let c_key = CString::new(key).map_err(|_| Error::InvalidParameter(
+                "metadata key can not contain NULL bytes".into()
+            ))?;
+            self.metadata.keys.push(ConstCString::new(c_key));
+            self.metadata.values.push(c_value);
+        }
+
+        Ok(())
+    }
+
+    /// Get the value associated with `key` in the metadata of this
+    /// `TensorMap` as a C-compatible string, or `None` if there is no such
+    /// entry.
+    pub fn metadata_c(&self, key: &str) -> Option<&ConstCString> {
+        self.metadata.position(key).map(|position| &self.metadata.values[position])
+    }
+
+    /// Get the list of all keys in the metadata of this `TensorMap`, as
+    /// C-compatible strings, in insertion order.
+    pub fn metadata_keys_c(&self) -> &[ConstCString] {
+        &self.metadata.keys
+    }
+
+    /// Iterate over all the key/value pairs in the metadata of this
+    /// `TensorMap`, in insertion order.
+    pub fn metadata_iter(&self) -> impl Iterator<Item=(&str, &str)> + '_ {
+        self.metadata.keys.iter()
+            .zip(&self.metadata.values)
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+}
@@ -2,13 +2,17 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use crate::TensorBlock;
-use crate::{Labels, Error};
+use crate::{Labels, LabelsBuilder, LabelValue, Error};
 use crate::get_data_origin;
 
 mod utils;
 
+mod metadata;
+use self::metadata::TensorMetadata;
+
 mod keys_to_samples;
 mod keys_to_properties;
+mod concatenate;
 
 
 /// A tensor map is the main user-facing struct of this library, and can store
@@ -24,40 +28,53 @@ mod keys_to_properties;
 pub struct TensorMap {
     keys: Arc<Labels>,
     blocks: Vec<TensorBlock>,
-    // TODO: arbitrary tensor-level metadata? e.g. using `HashMap<String, String>`
+    /// arbitrary tensor-level metadata, see `TensorMap::set_metadata`
+    metadata: TensorMetadata,
 }
 
+/// Check that `block` has the same samples and components names as
+/// `reference`. If `compare_component_values` is `true`, the values of the
+/// components must also be the same, which is required to merge the blocks
+/// along the properties. `context` is added at the end of error messages.
 fn check_labels_names(
     block: &TensorBlock,
-    sample_names: &[&str],
-    component_names: &[Vec<&str>],
+    reference: &TensorBlock,
     context: &str,
+    compare_component_values: bool,
 ) -> Result<(), Error> {
-    if block.samples.names() != sample_names {
+    if !block.samples.same_names(&reference.samples) {
         return Err(Error::InvalidParameter(format!(
             "all blocks must have the same sample names, got [{}] and [{}]{}",
             block.samples.names().join(", "),
-            sample_names.join(", "),
+            reference.samples.names().join(", "),
             context,
         )));
     }
 
-    if block.components.len() != component_names.len() {
+    if block.components.len() != reference.components.len() {
         return Err(Error::InvalidParameter(format!(
             "all blocks must contains the same set of components, the current \
             block has {} components while the first block has {}{}",
             block.components.len(),
-            component_names.len(),
+            reference.components.len(),
             context,
         )));
     }
 
-    for (component_i, component) in block.components.iter().enumerate() {
-        if component.names() != component_names[component_i] {
+    for (component, reference_component) in block.components.iter().zip(&reference.components) {
+        if !component.same_names(reference_component) {
             return Err(Error::InvalidParameter(format!(
                 "all blocks must have the same component names, got [{}] and [{}]{}",
                 component.names().join(", "),
-                component_names[component_i].join(", "),
+                reference_component.names().join(", "),
+                context,
+            )));
+        }
+
+        if compare_component_values && component != reference_component {
+            return Err(Error::InvalidParameter(format!(
+                "all blocks must have the same values for the [{}] component{}",
+                component.names().join(", "),
                 context,
             )));
         }
@@ -66,6 +83,96 @@ fn check_labels_names(
     Ok(())
 }
 
+/// Check that the gradients of `block` (associated with `key`) have the same
+/// parameters and metadata names as the gradients of `reference`, recursing
+/// into gradients of gradients. `path` contains the parameters leading to
+/// these gradients, and is empty for the gradients of the values.
+fn check_gradients(
+    block: &TensorBlock,
+    reference: &TensorBlock,
+    key: &[LabelValue],
+    path: &str,
+) -> Result<(), Error> {
+    let mut parameters = block.gradients().keys().map(|p| &**p).collect::<Vec<_>>();
+    parameters.sort_unstable();
+    let mut reference_parameters = reference.gradients().keys().map(|p| &**p).collect::<Vec<_>>();
+    reference_parameters.sort_unstable();
+
+    if parameters != reference_parameters {
+        let location = if path.is_empty() {
+            String::new()
+        } else {
+            format!(" in the gradient with respect to '{}'", path)
+        };
+
+        return Err(Error::InvalidParameter(format!(
+            "all blocks must have the same set of gradients, got [{}] and [{}]{} \
+            for the block with key [{}]",
+            parameters.join(", "),
+            reference_parameters.join(", "),
+            location,
+            format_key(key),
+        )));
+    }
+
+    for parameter in reference_parameters {
+        let gradient = &block.gradients()[parameter];
+        let reference_gradient = &reference.gradients()[parameter];
+
+        let path = if path.is_empty() {
+            parameter.to_owned()
+        } else {
+            format!("{}/{}", path, parameter)
+        };
+
+        let context = format!(
+            " in the gradient with respect to '{}' for the block with key [{}]",
+            path, format_key(key),
+        );
+        check_labels_names(gradient, reference_gradient, &context, false)?;
+        check_gradients(gradient, reference_gradient, key, &path)?;
+    }
+
+    Ok(())
+}
+
+/// Check that the `blocks` can be used together with `keys` in a `TensorMap`
+fn check_blocks(keys: &Labels, blocks: &[TensorBlock]) -> Result<(), Error> {
+    if blocks.len() != keys.count() {
+        return Err(Error::InvalidParameter(format!(
+            "expected the same number of blocks as the number of \
+            entries in the keys ({}) when creating a tensor, got {}",
+            keys.count(), blocks.len()
+        )))
+    }
+
+    check_origin(blocks)?;
+
+    if !blocks.is_empty() {
+        // extract metadata from the first block
+        let first_block = &blocks[0];
+
+        for (key, block) in keys.iter().zip(blocks) {
+            // check samples and components are the same as those of the first block
+            check_labels_names(block, first_block, "", false)?;
+
+            // check properties are the same as those of the first block
+            if !block.properties.same_names(&first_block.properties) {
+                return Err(Error::InvalidParameter(format!(
+                    "all blocks must have the same property names, got [{}] and [{}]",
+                    block.properties.names().join(", "),
+                    first_block.properties.names().join(", "),
+                )));
+            }
+
+            // check gradients are the same as those of the first block
+            check_gradients(block, first_block, key, "")?;
+        }
+    }
+
+    Ok(())
+}
+
 fn check_origin(blocks: &[TensorBlock]) -> Result<(), Error> {
     if blocks.is_empty() {
         return Ok(());
@@ -86,34 +193,9 @@ fn check_origin(blocks: &[TensorBlock]) -> Result<(), Error> {
     Ok(())
 }
 
-#[derive(Debug, Clone, PartialEq)]
-struct GradientMetadata<'a> {
-    sample_names: Vec<&'a str>,
-    component_names: Vec<Vec<&'a str>>,
-}
-
-#[derive(Debug, Clone, PartialEq)]
-struct GradientMap<'a> {
-    // a struct that contains all the gradient information for a single
-    // TensorBlock. The GradientMap is recursive for gradients of gradients
-    gradients: HashMap<String, (GradientMetadata<'a>, GradientMap<'a>)>
-}
-
-impl GradientMap<'_> {
-    fn new(block: &TensorBlock) -> GradientMap {
-        // generate gradient information for a block
-        let mut gradients = HashMap::new();
-        for (gradient_name, sub_gradient) in block.gradients() {
-            let metadata = GradientMetadata {
-                sample_names: sub_gradient.samples.names(),
-                component_names: sub_gradient.components.iter()
-                    .map(|c| c.names())
-                    .collect::<Vec<_>>(),
-            };
-            gradients.insert(gradient_name.clone(), (metadata, GradientMap::new(sub_gradient)));
-        }
-        GradientMap { gradients }
-    }
+/// Format a single key as a comma-separated list of values
+fn format_key(key: &[LabelValue]) -> String {
+    key.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ")
 }
 
 impl TensorMap {
@@ -122,58 +204,55 @@ impl TensorMap {
     /// The number of keys must match the number of blocks, and all the blocks
     /// must contain the same kind of data (same labels names, same gradients
     /// defined on all blocks).
-    #[allow(clippy::similar_names)]
-    pub fn new(keys: Arc<Labels>, blocks: Vec<TensorBlock>) -> Result<TensorMap, Error> {
-        if blocks.len() != keys.count() {
-            return Err(Error::InvalidParameter(format!(
-                "expected the same number of blocks as the number of \
-                entries in the keys ({}) when creating a tensor, got {}",
-                keys.count(), blocks.len()
-            )))
-        }
-
-        check_origin(&blocks)?;
-
-        if !blocks.is_empty() {
-            // extract metadata from the first block
-            let sample_names = blocks[0].samples.names();
-            let component_names = blocks[0].components.iter()
-                .map(|c| c.names())
-                .collect::<Vec<_>>();
-            let property_names = blocks[0].properties.names();
-            let gradient_map = GradientMap::new(&blocks[0]);
-
-            for block in &blocks {
-                // check samples and components are the same as those of the first block
-                check_labels_names(block, &sample_names, &component_names, "")?;
-
-                // check properties are the same as those of the first block
-                if block.properties.names() != property_names {
-                    return Err(Error::InvalidParameter(format!(
-                        "all blocks must have the same property names, got [{}] and [{}]",
-                        block.properties.names().join(", "),
-                        property_names.join(", "),
-                    )));
-                }
-
-                // check gradients are the same as those of the first block
-                if GradientMap::new(block) != gradient_map {
-                    return Err(Error::InvalidParameter(
-                        "all blocks must have the same set of gradients, with \
-                        the same sample, property and component names, \
-                        and the same must be true for gradients of gradients".into(),
-                    ));
-                }
-
-            }
-        }
+    ///
+    /// `keys` can be given either as `Labels` or as `Arc<Labels>`.
+    pub fn new(keys: impl Into<Arc<Labels>>, blocks: Vec<TensorBlock>) -> Result<TensorMap, Error> {
+        let keys = keys.into();
+        check_blocks(&keys, &blocks)?;
 
         Ok(TensorMap {
             keys: keys,
             blocks,
+            metadata: TensorMetadata::default(),
         })
     }
 
+    /// Check that this `TensorMap` is still valid, re-running all the checks
+    /// performed when creating it and its blocks. This can be used after
+    /// modifying the blocks in place, which could break these invariants.
+    pub fn check_invariants(&self) -> Result<(), Error> {
+        for block in &self.blocks {
+            block.check_invariants()?;
+        }
+
+        return check_blocks(&self.keys, &self.blocks);
+    }
+
+    /// Create a new `TensorMap` from an iterator over pairs of keys and blocks.
+    ///
+    /// The keys are accumulated in `Labels` with the given `key_names`, and
+    /// the blocks are checked in the same way as in `TensorMap::new`. This
+    /// function returns an error if the same key is given more than once.
+    pub fn from_iter_checked<I>(key_names: Vec<&str>, iter: I) -> Result<TensorMap, Error>
+        where I: IntoIterator<Item=(Vec<LabelValue>, TensorBlock)>
+    {
+        let mut keys = LabelsBuilder::new(key_names)?;
+        let mut blocks = Vec::new();
+        for (key, block) in iter {
+            if key.len() != keys.size() {
+                return Err(Error::InvalidParameter(format!(
+                    "the key [{}] has {} values, but the keys have {} dimensions",
+                    format_key(&key), key.len(), keys.size()
+                )));
+            }
+
+            keys.add(&key)?;
+            blocks.push(block);
+        }
+
+        return TensorMap::new(keys.finish(), blocks);
+    }
+
     /// Try to copy this `TensorMap`. This can fail if we are unable to copy the
     /// underlying `mts_array_t` data array
     pub fn try_clone(&self) -> Result<TensorMap, Error> {
@@ -184,7 +263,24 @@ impl TensorMap {
 
         return Ok(TensorMap {
             keys: Arc::clone(&self.keys),
-            blocks
+            blocks,
+            metadata: self.metadata.clone(),
+        });
+    }
+
+    /// Create a new `TensorMap` with the same keys and blocks metadata as this
+    /// one (sharing the labels), and all data filled with zeros. See
+    /// `TensorBlock::zeros_like` for more information.
+    pub fn zeros_like(&self) -> Result<TensorMap, Error> {
+        let mut blocks = Vec::new();
+        for block in &self.blocks {
+            blocks.push(block.zeros_like()?);
+        }
+
+        return Ok(TensorMap {
+            keys: Arc::clone(&self.keys),
+            blocks,
+            metadata: TensorMetadata::default(),
         });
     }
 
@@ -203,12 +299,43 @@ impl TensorMap {
         &self.keys
     }
 
+    /// Get the estimated size in bytes of the data in all the blocks of this
+    /// `TensorMap`, including gradients.
+    pub fn nbytes(&self) -> Result<usize, Error> {
+        let mut nbytes = 0;
+        for block in &self.blocks {
+            nbytes += block.nbytes()?;
+        }
+        return Ok(nbytes);
+    }
+
+    /// Make sure that the labels of each block are not shared with any other
+    /// block, copying the shared labels.
+    ///
+    /// Labels are usually shared between blocks (and between a block and its
+    /// gradients) after operations like `keys_to_samples` or `try_clone`.
+    /// Copying them uses more memory, but ensures that modifying the labels
+    /// of a block in place will not affect other blocks. The copied labels
+    /// start without user data, which is not carried over from the originals.
+    pub fn make_labels_unique(&mut self) {
+        for block in &mut self.blocks {
+            block.make_labels_unique();
+        }
+    }
+
     /// Get the index of blocks matching the given selection.
     ///
     /// The selection must contains a single entry, defining the requested key
     /// or keys. If the selection contains only a subset of the dimensions of the
-    /// keys, there can be multiple matching blocks.
-    pub fn blocks_matching(&self, selection: &Labels) -> Result<Vec<usize>, Error> {
+    /// keys, there can be multiple matching blocks: all the values of the
+    /// dimensions which are not part of the selection are matched.
+    ///
+    /// If `wildcard` is `true`, the selection can also use `LabelValue::ANY` as
+    /// the value for some dimensions, to match all the values of these
+    /// dimensions while still selecting specific values for the other
+    /// dimensions. If `wildcard` is `false`, all the values in the selection
+    /// (including `LabelValue::ANY`) are compared exactly with the keys.
+    pub fn blocks_matching(&self, selection: &Labels, wildcard: bool) -> Result<Vec<usize>, Error> {
         if selection.size() == 0 {
             return Ok((0..self.blocks().len()).collect());
         }
@@ -241,6 +368,10 @@ impl TensorMap {
         for (block_i, labels) in self.keys.iter().enumerate() {
             let mut selected = true;
             for (&requested_i, &value) in dimensions.iter().zip(selection) {
+                if wildcard && value == LabelValue::ANY {
+                    continue;
+                }
+
                 if labels[requested_i] != value {
                     selected = false;
                     break;
@@ -255,6 +386,120 @@ impl TensorMap {
         return Ok(matching);
     }
 
+    /// Sort the keys of this `TensorMap` in lexicographic order, re-ordering
+    /// the blocks to keep them associated with the same keys.
+    ///
+    /// This is useful to get a canonical representation of a `TensorMap`
+    /// before comparing or serializing it.
+    pub fn sort_blocks_by_key(&mut self) {
+        let keys = &self.keys;
+        let mut order = (0..keys.count()).collect::<Vec<_>>();
+        order.sort_by(|&i, &j| keys[i].cmp(&keys[j]));
+
+        if order.iter().enumerate().all(|(new, &old)| new == old) {
+            return;
+        }
+
+        let mut new_keys = LabelsBuilder::new(keys.names()).expect("invalid keys names");
+        new_keys.reserve(keys.count());
+        for &i in &order {
+            new_keys.add(&keys[i]).expect("duplicated key");
+        }
+
+        let mut blocks = std::mem::take(&mut self.blocks).into_iter().map(Some).collect::<Vec<_>>();
+        self.blocks = order.iter()
+            .map(|&i| blocks[i].take().expect("block used twice"))
+            .collect();
+        self.keys = Arc::new(new_keys.finish());
+    }
+
+    /// Only keep the blocks (and corresponding keys) for which `predicate`
+    /// returns `true`, removing all the others. This works like
+    /// `Vec::retain`, and the order of the remaining blocks is preserved.
+    ///
+    /// `predicate` is called once for each block, with the values of the
+    /// corresponding key and the block itself. This function returns the
+    /// number of blocks that were removed.
+    pub fn retain_blocks<F>(&mut self, mut predicate: F) -> usize
+        where F: FnMut(&[LabelValue], &TensorBlock) -> bool
+    {
+        let keep = self.keys.iter()
+            .zip(&self.blocks)
+            .map(|(key, block)| predicate(key, block))
+            .collect::<Vec<_>>();
+
+        let removed = keep.iter().filter(|&&keep| !keep).count();
+        if removed == 0 {
+            return 0;
+        }
+
+        let mut new_keys = LabelsBuilder::new(self.keys.names()).expect("invalid keys names");
+        new_keys.reserve(self.keys.count() - removed);
+        for (key, &keep) in self.keys.iter().zip(&keep) {
+            if keep {
+                new_keys.add(key).expect("duplicated key");
+            }
+        }
+
+        let mut keep = keep.into_iter();
+        self.blocks.retain(|_| keep.next().expect("missing block"));
+        self.keys = Arc::new(new_keys.finish());
+
+        return removed;
+    }
+
+    /// Transform the values of the keys of this `TensorMap`, using `function`
+    /// to compute the new values of each key from the old ones.
+    ///
+    /// The blocks are kept associated with the same (transformed) keys, and
+    /// the names of the keys are not modified. `function` must return the same
+    /// number of values as the size of the keys, and the new keys must be
+    /// unique.
+    pub fn map_keys<F>(self, mut function: F) -> Result<TensorMap, Error>
+        where F: FnMut(&[LabelValue]) -> Result<Vec<LabelValue>, Error>
+    {
+        let mut new_keys = Vec::with_capacity(self.keys.count());
+        let mut original_keys = HashMap::new();
+        for key in &*self.keys {
+            let new_key = function(key)?;
+            if new_key.len() != self.keys.size() {
+                return Err(Error::InvalidParameter(format!(
+                    "the new key for [{}] has {} values, but the keys have {} dimensions",
+                    format_key(key), new_key.len(), self.keys.size()
+                )));
+            }
+
+            if let Some(previous) = original_keys.insert(new_key.clone(), key) {
+                return Err(Error::InvalidParameter(format!(
+                    "the new keys are not unique: both [{}] and [{}] are mapped to [{}]",
+                    format_key(previous), format_key(key), format_key(&new_key)
+                )));
+            }
+
+            new_keys.push(new_key);
+        }
+
+        return TensorMap::from_iter_checked(self.keys.names(), new_keys.into_iter().zip(self.blocks));
+    }
+
+    /// Create a new `TensorMap` where each block only contains the properties
+    /// for which `keep` returns `true`, together with the corresponding values
+    /// and gradients.
+    ///
+    /// The samples and components of the blocks are not modified. If `keep`
+    /// rejects all the properties of a block, the corresponding block in the
+    /// output will have zero properties.
+    pub fn filter_properties<F>(&self, mut keep: F) -> Result<TensorMap, Error>
+        where F: FnMut(&[LabelValue]) -> Result<bool, Error>
+    {
+        let mut clone = self.try_clone()?;
+        for block in &mut clone.blocks {
+            block.filter_properties(&mut keep)?;
+        }
+
+        return Ok(clone);
+    }
+
     /// Move the given dimensions from the component labels to the property labels
     /// for each block in this `TensorMap`.
     pub fn components_to_properties(&self, dimensions: &[&str]) -> Result<TensorMap, Error> {
@@ -270,6 +515,31 @@ impl TensorMap {
 
         return Ok(clone);
     }
+
+    /// Move the given dimensions from the property labels to the component
+    /// labels for each block in this `TensorMap`. This is the inverse of
+    /// `components_to_properties`.
+    ///
+    /// The `dimensions` must be the first dimensions of the properties, and
+    /// the properties of all blocks must form a regular grid over these
+    /// dimensions. Each dimension becomes a new component, after the existing
+    /// components.
+    pub fn properties_to_components(&self, dimensions: &[&str]) -> Result<TensorMap, Error> {
+        let mut clone = self.try_clone()?;
+
+        if dimensions.is_empty() {
+            return Ok(clone);
+        }
+
+        for block in &mut clone.blocks {
+            block.properties_to_components(dimensions)?;
+        }
+
+        // check that all blocks still share the same metadata structure
+        check_blocks(&clone.keys, &clone.blocks)?;
+
+        return Ok(clone);
+    }
 }
 
 
@@ -405,64 +675,1150 @@ mod tests {
             got [something_else] and [properties]"
         );
 
-        // TODO: check error messages for gradients
-    }
-
-    #[test]
-    fn blocks_matching() {
-        let mut blocks = Vec::new();
-        for _ in 0..6 {
-            blocks.push(TensorBlock::new(
+        /**********************************************************************/
+        let create_block = |parameter: &str, gradient_samples: Vec<&str>, gradient_components: Vec<&str>| {
+            let mut block = TensorBlock::new(
                 TestArray::new(vec![1, 1]),
                 example_labels(vec!["samples"], vec![[0]]),
                 vec![],
                 example_labels(vec!["properties"], vec![[0]]),
-            ).unwrap());
-        }
+            ).unwrap();
 
-        let keys = example_labels(vec!["key_1", "key_2"], vec![
-            [0, 1], [0, 2], [1, 1],
-            [1, 2], [3, 0], [4, 3],
-        ]);
+            let mut samples = LabelsBuilder::new(gradient_samples.clone()).unwrap();
+            samples.add(&vec![0; gradient_samples.len()]).unwrap();
 
-        let tensor = TensorMap::new(keys, blocks).unwrap();
+            let mut shape = vec![1];
+            let mut components = Vec::new();
+            for name in gradient_components {
+                shape.push(1);
+                components.push(example_labels(vec![name], vec![[0]]));
+            }
+            shape.push(1);
 
-        let mut selection = LabelsBuilder::new(vec!["key_1", "key_2"]).unwrap();
-        selection.add(&[1, 1]).unwrap();
+            let gradient = TensorBlock::new(
+                TestArray::new(shape),
+                Arc::new(samples.finish()),
+                components,
+                example_labels(vec!["properties"], vec![[0]]),
+            ).unwrap();
+            block.add_gradient(parameter, gradient).unwrap();
+
+            return block;
+        };
+
+        let result = TensorMap::new(
+            example_labels(vec!["keys"], vec![[0], [1]]),
+            vec![
+                create_block("positions", vec!["sample"], vec![]),
+                create_block("cell", vec!["sample"], vec![]),
+            ],
+        );
         assert_eq!(
-            tensor.blocks_matching(&selection.finish()).unwrap(),
-            [2]
+            result.unwrap_err().to_string(),
+            "invalid parameter: all blocks must have the same set of gradients, \
+            got [cell] and [positions] for the block with key [1]"
         );
 
-        let mut selection = LabelsBuilder::new(vec!["key_1"]).unwrap();
-        selection.add(&[1]).unwrap();
+        let result = TensorMap::new(
+            example_labels(vec!["keys"], vec![[0], [1]]),
+            vec![
+                create_block("positions", vec!["sample"], vec![]),
+                create_block("positions", vec!["sample", "atom"], vec![]),
+            ],
+        );
         assert_eq!(
-            tensor.blocks_matching(&selection.finish()).unwrap(),
-            [2, 3]
+            result.unwrap_err().to_string(),
+            "invalid parameter: all blocks must have the same sample names, \
+            got [sample, atom] and [sample] in the gradient with respect to \
+            'positions' for the block with key [1]"
         );
 
-        let selection = LabelsBuilder::new(vec!["key_1"]).unwrap();
-        let result = tensor.blocks_matching(&selection.finish());
+        let result = TensorMap::new(
+            example_labels(vec!["keys"], vec![[0], [1]]),
+            vec![
+                create_block("positions", vec!["sample"], vec!["xyz"]),
+                create_block("positions", vec!["sample"], vec![]),
+            ],
+        );
         assert_eq!(
             result.unwrap_err().to_string(),
-            "invalid parameter: block selection must contain exactly one entry, got 0"
+            "invalid parameter: all blocks must contains the same set of \
+            components, the current block has 0 components while the first \
+            block has 1 in the gradient with respect to 'positions' for the \
+            block with key [1]"
         );
 
-        let mut selection = LabelsBuilder::new(vec!["key_1", "key_2"]).unwrap();
-        selection.add(&[3, 4]).unwrap();
-        selection.add(&[1, 2]).unwrap();
-        let result = tensor.blocks_matching(&selection.finish());
+        let result = TensorMap::new(
+            example_labels(vec!["keys"], vec![[0], [1]]),
+            vec![
+                create_block("positions", vec!["sample"], vec!["xyz"]),
+                create_block("positions", vec!["sample"], vec!["direction"]),
+            ],
+        );
         assert_eq!(
             result.unwrap_err().to_string(),
-            "invalid parameter: block selection must contain exactly one entry, got 2"
+            "invalid parameter: all blocks must have the same component names, \
+            got [direction] and [xyz] in the gradient with respect to \
+            'positions' for the block with key [1]"
         );
+    }
 
-        let mut selection = LabelsBuilder::new(vec!["key_3"]).unwrap();
-        selection.add(&[1]).unwrap();
-        let result = tensor.blocks_matching(&selection.finish());
+    #[test]
+    fn nested_gradients_validation() {
+        let create_block = |hessian_samples: Vec<&str>| {
+            let mut block = TensorBlock::new(
+                TestArray::new(vec![1, 1]),
+                example_labels(vec!["samples"], vec![[0]]),
+                vec![],
+                example_labels(vec!["properties"], vec![[0]]),
+            ).unwrap();
+
+            let mut gradient = TensorBlock::new(
+                TestArray::new(vec![1, 3, 1]),
+                example_labels(vec!["sample", "atom"], vec![[0, 0]]),
+                vec![example_labels(vec!["xyz_1"], vec![[0], [1], [2]])],
+                example_labels(vec!["properties"], vec![[0]]),
+            ).unwrap();
+
+            let hessian_samples_values = vec![vec![0; hessian_samples.len()]];
+            let mut samples = LabelsBuilder::new(hessian_samples).unwrap();
+            for sample in hessian_samples_values {
+                samples.add(&sample).unwrap();
+            }
+
+            let hessian = TensorBlock::new(
+                TestArray::new(vec![1, 3, 3, 1]),
+                Arc::new(samples.finish()),
+                vec![
+                    example_labels(vec!["xyz_2"], vec![[0], [1], [2]]),
+                    example_labels(vec!["xyz_1"], vec![[0], [1], [2]]),
+                ],
+                example_labels(vec!["properties"], vec![[0]]),
+            ).unwrap();
+            gradient.add_gradient("positions", hessian).unwrap();
+            block.add_gradient("positions", gradient).unwrap();
+
+            return block;
+        };
+
+        let tensor = TensorMap::new(
+            example_labels(vec!["keys"], vec![[0], [1]]),
+            vec![create_block(vec!["sample", "atom"]), create_block(vec!["sample", "atom"])],
+        ).unwrap();
+
+        let hessian = tensor.blocks()[1].gradient("positions").unwrap().gradient("positions").unwrap();
+        assert_eq!(hessian.samples.names(), ["sample", "atom"]);
+        assert_eq!(hessian.values.shape().unwrap(), [1, 3, 3, 1]);
+
+        // gradients of gradients must have the same metadata in all blocks
+        let result = TensorMap::new(
+            example_labels(vec!["keys"], vec![[0], [1]]),
+            vec![create_block(vec!["sample", "atom"]), create_block(vec!["sample", "other"])],
+        );
         assert_eq!(
             result.unwrap_err().to_string(),
-            "invalid parameter: 'key_3' is not part of the keys for this tensor"
+            "invalid parameter: all blocks must have the same sample names, \
+            got [sample, other] and [sample, atom] in the gradient with respect \
+            to 'positions/positions' for the block with key [1]"
+        );
+    }
+
+    #[test]
+    fn map_keys() {
+        let mut blocks = Vec::new();
+        for properties in [vec![[0]], vec![[0], [1]], vec![[0], [1], [2]]] {
+            blocks.push(TensorBlock::new(
+                TestArray::new(vec![1, properties.len()]),
+                example_labels(vec!["samples"], vec![[0]]),
+                vec![],
+                example_labels(vec!["properties"], properties),
+            ).unwrap());
+        }
+        let keys = example_labels(vec!["key_1", "key_2"], vec![[0, 1], [2, 1], [3, 4]]);
+        let tensor = TensorMap::new(keys, blocks).unwrap();
+
+        // put the keys in canonical order
+        let tensor = tensor.map_keys(|key| {
+            Ok(if key[0] <= key[1] { key.to_vec() } else { vec![key[1], key[0]] })
+        }).unwrap();
+
+        assert_eq!(tensor.keys().names(), ["key_1", "key_2"]);
+        assert_eq!(tensor.keys().iter().collect::<Vec<_>>(), [[0, 1], [1, 2], [3, 4]]);
+        assert_eq!(tensor.blocks()[1].properties.count(), 2);
+
+        let error = tensor.map_keys(|key| Ok(vec![LabelValue::new(key[0].i32() % 2), LabelValue::new(0)])).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: the new keys are not unique: both [1, 2] and \
+            [3, 4] are mapped to [1, 0]"
+        );
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn filter_properties() {
+        let mut blocks = Vec::new();
+        for properties in [vec![[0, 0], [1, 0], [5, 1]], vec![[6, 0], [7, 1]]] {
+            let n_properties = properties.len();
+            let mut block = TensorBlock::new(
+                TestArray::with_data(vec![1, n_properties], (0..n_properties).map(|i| i as f64).collect()),
+                example_labels(vec!["samples"], vec![[0]]),
+                vec![],
+                example_labels(vec!["n", "l"], properties),
+            ).unwrap();
+
+            let gradient = TensorBlock::new(
+                TestArray::with_data(vec![1, n_properties], (0..n_properties).map(|i| 10.0 * i as f64).collect()),
+                example_labels(vec!["sample"], vec![[0]]),
+                vec![],
+                Arc::clone(&block.properties),
+            ).unwrap();
+            block.add_gradient("g", gradient).unwrap();
+            blocks.push(block);
+        }
+        let keys = example_labels(vec!["key"], vec![[0], [1]]);
+        let tensor = TensorMap::new(keys, blocks).unwrap();
+
+        let filtered = tensor.filter_properties(|property| Ok(property[0].i32() < 5)).unwrap();
+        assert_eq!(filtered.keys().iter().collect::<Vec<_>>(), [[0], [1]]);
+
+        let block = &filtered.blocks()[0];
+        assert_eq!(block.properties.iter().collect::<Vec<_>>(), [[0, 0], [1, 0]]);
+        assert_eq!(block.values.shape().unwrap(), [1, 2]);
+        assert_eq!(block.values.data().unwrap(), [0.0, 1.0]);
+
+        let gradient = block.gradient("g").unwrap();
+        assert_eq!(gradient.properties.iter().collect::<Vec<_>>(), [[0, 0], [1, 0]]);
+        assert_eq!(gradient.values.data().unwrap(), [0.0, 10.0]);
+
+        // the second block has no properties left
+        let block = &filtered.blocks()[1];
+        assert_eq!(block.properties.names(), ["n", "l"]);
+        assert_eq!(block.properties.count(), 0);
+        assert_eq!(block.values.shape().unwrap(), [1, 0]);
+        assert_eq!(block.gradient("g").unwrap().values.shape().unwrap(), [1, 0]);
+
+        // the input is not modified
+        assert_eq!(tensor.blocks()[1].properties.count(), 2);
+
+        let error = tensor.filter_properties(|_| Err(Error::InvalidParameter("oops".into()))).unwrap_err();
+        assert_eq!(error.to_string(), "invalid parameter: oops");
+    }
+
+    #[test]
+    fn duplicated_keys() {
+        // keys are stored in `Labels`, which can not contain the same entry
+        // twice, so a `TensorMap` can never have two blocks with the same key
+        let mut keys = LabelsBuilder::new(vec!["key_1", "key_2"]).unwrap();
+        keys.add(&[0, 1]).unwrap();
+        let error = keys.add(&[0, 1]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not have the same label value multiple time: \
+            [0, 1] is already present at position 0"
+        );
+
+        let block = |samples: Vec<[i32; 1]>| TensorBlock::new(
+            TestArray::new(vec![samples.len(), 1]),
+            example_labels(vec!["samples"], samples),
+            vec![],
+            example_labels(vec!["properties"], vec![[0]]),
+        ).unwrap();
+
+        let result = TensorMap::from_iter_checked(vec!["key"], vec![
+            (vec![LabelValue::new(3)], block(vec![[0]])),
+            (vec![LabelValue::new(3)], block(vec![[0], [1]])),
+        ]);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "invalid parameter: can not have the same label value multiple time: \
+            [3] is already present at position 0"
         );
     }
+
+    #[test]
+    fn sort_blocks_by_key() {
+        let mut blocks = Vec::new();
+        for samples in [vec![[0]], vec![[0], [1]], vec![[0], [1], [2]], vec![[0], [1], [2], [3]]] {
+            blocks.push(TensorBlock::new(
+                TestArray::new(vec![samples.len(), 1]),
+                example_labels(vec!["samples"], samples),
+                vec![],
+                example_labels(vec!["properties"], vec![[0]]),
+            ).unwrap());
+        }
+        let keys = example_labels(vec!["key_1", "key_2"], vec![[3, 1], [0, 2], [3, 0], [0, 1]]);
+        let mut tensor = TensorMap::new(keys, blocks).unwrap();
+
+        tensor.sort_blocks_by_key();
+        assert_eq!(tensor.keys().iter().collect::<Vec<_>>(), [[0, 1], [0, 2], [3, 0], [3, 1]]);
+
+        let n_samples = tensor.blocks().iter().map(|b| b.samples.count()).collect::<Vec<_>>();
+        assert_eq!(n_samples, [4, 2, 3, 1]);
+    }
+
+    #[test]
+    fn retain_blocks() {
+        let mut blocks = Vec::new();
+        for samples in [vec![[0]], vec![[0], [1]], vec![[0], [1], [2]], vec![[0], [1], [2], [3]]] {
+            blocks.push(TensorBlock::new(
+                TestArray::new(vec![samples.len(), 1]),
+                example_labels(vec!["samples"], samples),
+                vec![],
+                example_labels(vec!["properties"], vec![[0]]),
+            ).unwrap());
+        }
+        let keys = example_labels(vec!["key_1", "key_2"], vec![[3, 1], [0, 2], [3, 0], [0, 1]]);
+        let mut tensor = TensorMap::new(keys, blocks).unwrap();
+
+        let removed = tensor.retain_blocks(|_, block| block.samples.count() % 2 == 0);
+        assert_eq!(removed, 2);
+        assert_eq!(tensor.keys().iter().collect::<Vec<_>>(), [[0, 2], [0, 1]]);
+
+        let n_samples = tensor.blocks().iter().map(|b| b.samples.count()).collect::<Vec<_>>();
+        assert_eq!(n_samples, [2, 4]);
+
+        let removed = tensor.retain_blocks(|key, _| key[1].isize() == 1);
+        assert_eq!(removed, 1);
+        assert_eq!(tensor.keys().iter().collect::<Vec<_>>(), [[0, 1]]);
+        assert_eq!(tensor.blocks()[0].samples.count(), 4);
+
+        assert_eq!(tensor.retain_blocks(|_, _| true), 0);
+        assert_eq!(tensor.keys().count(), 1);
+
+        assert_eq!(tensor.retain_blocks(|_, _| false), 1);
+        assert_eq!(tensor.keys().count(), 0);
+        assert!(tensor.blocks().is_empty());
+        assert_eq!(tensor.keys().names(), ["key_1", "key_2"]);
+    }
+
+    #[test]
+    fn keys_to_move_progress() {
+        let mut blocks = Vec::new();
+        for samples in [vec![[0]], vec![[1]], vec![[0]], vec![[1]]] {
+            blocks.push(TensorBlock::new(
+                TestArray::new(vec![samples.len(), 1]),
+                example_labels(vec!["samples"], samples),
+                vec![],
+                example_labels(vec!["properties"], vec![[0]]),
+            ).unwrap());
+        }
+        let keys = example_labels(vec!["key_1", "key_2"], vec![[0, 0], [0, 1], [1, 0], [1, 1]]);
+        let tensor = TensorMap::new(keys, blocks).unwrap();
+
+        let keys_to_move = LabelsBuilder::new(vec!["key_2"]).unwrap().finish();
+        let mut calls = Vec::new();
+        let moved = tensor.keys_to_samples_with_progress(&keys_to_move, true, |done, total| {
+            calls.push((done, total));
+            Ok(())
+        }).unwrap();
+        assert_eq!(moved.keys().count(), 2);
+        assert_eq!(calls, [(1, 2), (2, 2)]);
+
+        let keys_to_move = LabelsBuilder::new(vec!["key_1", "key_2"]).unwrap().finish();
+        let mut calls = Vec::new();
+        let moved = tensor.keys_to_properties_with_progress(&keys_to_move, true, |done, total| {
+            calls.push((done, total));
+            Ok(())
+        }).unwrap();
+        assert_eq!(moved.keys().count(), 1);
+        assert_eq!(calls, [(1, 1)]);
+
+        let keys_to_move = LabelsBuilder::new(vec![]).unwrap().finish();
+        let mut calls = Vec::new();
+        tensor.keys_to_samples_with_progress(&keys_to_move, true, |done, total| {
+            calls.push((done, total));
+            Ok(())
+        }).unwrap();
+        assert!(calls.is_empty());
+
+        // errors in the callback stop the merge
+        let keys_to_move = LabelsBuilder::new(vec!["key_2"]).unwrap().finish();
+        let mut calls = 0;
+        let error = tensor.keys_to_properties_with_progress(&keys_to_move, true, |_, _| {
+            calls += 1;
+            Err(Error::InvalidParameter("stop".into()))
+        }).unwrap_err();
+        assert_eq!(error.to_string(), "invalid parameter: stop");
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn keys_to_samples_duplicated_samples() {
+        // `atom` is already part of the samples, moving it from the keys
+        // would create samples with the `atom` dimension twice
+        let mut blocks = Vec::new();
+        for _ in 0..2 {
+            blocks.push(TensorBlock::new(
+                TestArray::new(vec![1, 1]),
+                example_labels(vec!["system", "atom"], vec![[0, 0]]),
+                vec![],
+                example_labels(vec!["properties"], vec![[0]]),
+            ).unwrap());
+        }
+        let keys = example_labels(vec!["center", "atom"], vec![[0, 0], [1, 0]]);
+        let tensor = TensorMap::new(keys, blocks).unwrap();
+
+        let keys_to_move = LabelsBuilder::new(vec!["atom"]).unwrap().finish();
+        let error = tensor.keys_to_samples(&keys_to_move, true).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not move keys to samples: 'atom' is already \
+            a dimension of the samples, the merged block would contain duplicated samples"
+        );
+    }
+
+    #[test]
+    fn metadata() {
+        let block = TensorBlock::new(
+            TestArray::with_data(vec![1, 1], vec![3.0]),
+            example_labels(vec!["samples"], vec![[0]]),
+            vec![],
+            example_labels(vec!["properties"], vec![[0]]),
+        ).unwrap();
+        let keys = example_labels(vec!["key"], vec![[0]]);
+        let mut tensor = TensorMap::new(keys, vec![block]).unwrap();
+
+        assert!(tensor.metadata_c("units").is_none());
+        assert_eq!(tensor.metadata_iter().count(), 0);
+
+        tensor.set_metadata("units", "eV").unwrap();
+        tensor.set_metadata("model", "v1.2 ⚛").unwrap();
+        tensor.set_metadata("units", "meV").unwrap();
+        assert_eq!(tensor.metadata_c("units").unwrap().as_str(), "meV");
+        assert_eq!(tensor.metadata_iter().collect::<Vec<_>>(), [("units", "meV"), ("model", "v1.2 ⚛")]);
+        assert_eq!(tensor.metadata_keys_c().len(), 2);
+
+        let error = tensor.set_metadata("units", "e\0V").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: metadata value for 'units' can not contain NULL bytes"
+        );
+
+        let clone = tensor.try_clone().unwrap();
+        assert_eq!(clone.metadata_iter().collect::<Vec<_>>(), [("units", "meV"), ("model", "v1.2 ⚛")]);
+
+        // metadata is kept when saving and loading
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        crate::io::save(&mut buffer, &tensor).unwrap();
+        buffer.set_position(0);
+        let loaded = crate::io::load(buffer.clone(), |shape| Ok(TestArray::new(shape))).unwrap();
+        assert_eq!(loaded.metadata_iter().collect::<Vec<_>>(), [("units", "meV"), ("model", "v1.2 ⚛")]);
+        assert_eq!(loaded.blocks()[0].values.data().unwrap(), [3.0]);
+
+        // errors when reading the metadata file are not ignored. We mark the
+        // file as compressed with DEFLATE, which is not supported.
+        let mut data = buffer.into_inner();
+        let name = b"metadata.npy";
+        for (signature, offset, compression) in [(b"PK\x03\x04", 30, 8), (b"PK\x01\x02", 46, 10)] {
+            let start = data.windows(offset + name.len())
+                .position(|w| &w[..4] == signature && &w[offset..] == name)
+                .unwrap();
+            data[start + compression] = 8;
+        }
+        let error = crate::io::load(std::io::Cursor::new(data), |shape| Ok(TestArray::new(shape))).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "serialization format error: unsupported Zip archive: Compression method not supported: at 'metadata.npy'"
+        );
+
+        // there is no metadata file if the tensor does not have metadata
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        crate::io::save(&mut buffer, &tensor.zeros_like().unwrap()).unwrap();
+        buffer.set_position(0);
+        let mut archive = zip::ZipArchive::new(buffer).unwrap();
+        assert!(archive.by_name("metadata.npy").is_err());
+    }
+
+    #[test]
+    fn save_load_units() {
+        let create_block = |units: Option<&str>, gradient_units: Option<&str>| {
+            let mut block = TensorBlock::new(
+                TestArray::new(vec![1, 1]),
+                example_labels(vec!["samples"], vec![[0]]),
+                vec![],
+                example_labels(vec!["properties"], vec![[0]]),
+            ).unwrap();
+            block.set_units(units).unwrap();
+
+            let mut gradient = TensorBlock::new(
+                TestArray::new(vec![1, 1]),
+                example_labels(vec!["sample"], vec![[0]]),
+                vec![],
+                example_labels(vec!["properties"], vec![[0]]),
+            ).unwrap();
+            gradient.set_units(gradient_units).unwrap();
+            block.add_gradient("positions", gradient).unwrap();
+
+            return block;
+        };
+
+        let first = create_block(Some("eV"), Some("eV/Å"));
+        let second = create_block(None, None);
+
+        let keys = example_labels(vec!["key"], vec![[0], [1]]);
+        let tensor = TensorMap::new(keys, vec![first, second]).unwrap();
+
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        crate::io::save(&mut buffer, &tensor).unwrap();
+        buffer.set_position(0);
+        let loaded = crate::io::load(buffer.clone(), |shape| Ok(TestArray::new(shape))).unwrap();
+
+        let block = &loaded.blocks()[0];
+        assert_eq!(block.units().unwrap().as_str(), "eV");
+        let gradient = block.gradient("positions").unwrap();
+        assert_eq!(gradient.units().unwrap().as_str(), "eV/Å");
+        assert!(loaded.blocks()[1].units().is_none());
+        assert!(loaded.blocks()[1].gradient("positions").unwrap().units().is_none());
+
+        // there is no units file for blocks without units
+        let mut archive = zip::ZipArchive::new(buffer).unwrap();
+        assert!(archive.by_name("blocks/0/units.npy").is_ok());
+        assert!(archive.by_name("blocks/1/units.npy").is_err());
+    }
+
+    #[test]
+    fn make_labels_unique() {
+        let samples = example_labels(vec!["samples"], vec![[0], [1]]);
+        let properties = example_labels(vec!["properties"], vec![[0]]);
+
+        let mut blocks = Vec::new();
+        for _ in 0..2 {
+            let mut block = TensorBlock::new(
+                TestArray::new(vec![2, 1]),
+                Arc::clone(&samples),
+                vec![],
+                Arc::clone(&properties),
+            ).unwrap();
+
+            let gradient = TensorBlock::new(
+                TestArray::new(vec![1, 1]),
+                example_labels(vec!["sample"], vec![[1]]),
+                vec![],
+                Arc::clone(&properties),
+            ).unwrap();
+            block.add_gradient("g", gradient).unwrap();
+
+            blocks.push(block);
+        }
+        let keys = example_labels(vec!["key"], vec![[0], [1]]);
+        let mut tensor = TensorMap::new(keys, blocks).unwrap();
+
+        let mut user_data = 42_i32;
+        let user_data_ptr = (&mut user_data as *mut i32).cast();
+        samples.set_user_data(user_data_ptr, None);
+
+        std::mem::drop(samples);
+        std::mem::drop(properties);
+        let gradient_samples = Arc::clone(&tensor.blocks()[0].gradient("g").unwrap().samples);
+
+        tensor.make_labels_unique();
+
+        let first = &tensor.blocks()[0];
+        let second = &tensor.blocks()[1];
+        let gradient = first.gradient("g").unwrap();
+        assert!(!Arc::ptr_eq(&first.samples, &second.samples));
+        assert!(!Arc::ptr_eq(&first.properties, &second.properties));
+        assert!(!Arc::ptr_eq(&first.properties, &gradient.properties));
+        assert!(!Arc::ptr_eq(&gradient.samples, &gradient_samples));
+
+        assert_eq!(*first.samples, *second.samples);
+        assert_eq!(*first.properties, *gradient.properties);
+        assert_eq!(*gradient.samples, *gradient_samples);
+
+        // the copied labels do not carry over the user data, while the
+        // original labels (now only used by the second block) keep it
+        assert!(first.samples.user_data().is_null());
+        assert_eq!(second.samples.user_data(), user_data_ptr);
+
+        // labels which are already unique are not copied again
+        let samples_ptr = Arc::as_ptr(&first.samples);
+        tensor.make_labels_unique();
+        assert_eq!(Arc::as_ptr(&tensor.blocks()[0].samples), samples_ptr);
+    }
+
+    #[test]
+    fn nbytes() {
+        let mut blocks = Vec::new();
+        for n_samples in [2, 3] {
+            let samples = (0..n_samples).map(|i| [i]).collect::<Vec<_>>();
+            let mut block = TensorBlock::new(
+                TestArray::new(vec![samples.len(), 4]),
+                example_labels(vec!["samples"], samples.clone()),
+                vec![],
+                example_labels(vec!["properties"], vec![[0], [1], [2], [3]]),
+            ).unwrap();
+
+            let gradient_samples = samples.iter().map(|s| [s[0], 0]).collect();
+            let gradient = TensorBlock::new(
+                TestArray::new(vec![samples.len(), 3, 4]),
+                example_labels(vec!["sample", "atom"], gradient_samples),
+                vec![example_labels(vec!["xyz"], vec![[0], [1], [2]])],
+                example_labels(vec!["properties"], vec![[0], [1], [2], [3]]),
+            ).unwrap();
+            block.add_gradient("positions", gradient).unwrap();
+
+            blocks.push(block);
+        }
+        let keys = example_labels(vec!["key"], vec![[0], [1]]);
+        let tensor = TensorMap::new(keys, blocks).unwrap();
+
+        assert_eq!(tensor.blocks()[0].nbytes().unwrap(), (2 * 4 + 2 * 3 * 4) * 8);
+        assert_eq!(tensor.nbytes().unwrap(), (5 * 4 + 5 * 3 * 4) * 8);
+    }
+
+    #[test]
+    fn zeros_like() {
+        let mut blocks = Vec::new();
+        for samples in [vec![[0]], vec![[0], [1]]] {
+            let n_samples = samples.len();
+            blocks.push(TensorBlock::new(
+                TestArray::with_data(vec![n_samples, 1], vec![3.0; n_samples]),
+                example_labels(vec!["samples"], samples),
+                vec![],
+                example_labels(vec!["properties"], vec![[0]]),
+            ).unwrap());
+        }
+        let keys = example_labels(vec!["key"], vec![[0], [1]]);
+        let tensor = TensorMap::new(keys, blocks).unwrap();
+
+        let zeros = tensor.zeros_like().unwrap();
+        assert!(Arc::ptr_eq(zeros.keys(), tensor.keys()));
+        for (block, reference) in zeros.blocks().iter().zip(tensor.blocks()) {
+            assert!(Arc::ptr_eq(&block.samples, &reference.samples));
+            assert_eq!(block.values.shape().unwrap(), reference.values.shape().unwrap());
+            assert!(block.values.data().unwrap().iter().all(|&v| v == 0.0));
+        }
+    }
+
+    #[test]
+    fn from_iter_checked() {
+        let mut blocks = Vec::new();
+        for l in 0..3 {
+            let block = TensorBlock::new(
+                TestArray::new(vec![1, 1]),
+                example_labels(vec!["samples"], vec![[0]]),
+                vec![],
+                example_labels(vec!["properties"], vec![[0]]),
+            ).unwrap();
+            blocks.push((vec![LabelValue::new(l)], block));
+        }
+
+        let tensor = TensorMap::from_iter_checked(vec!["l"], blocks).unwrap();
+        assert_eq!(tensor.keys().names(), ["l"]);
+        assert_eq!(tensor.keys().iter().collect::<Vec<_>>(), [[0], [1], [2]]);
+        assert_eq!(tensor.blocks().len(), 3);
+
+        let mut blocks = Vec::new();
+        for l in [0, 0] {
+            let block = TensorBlock::new(
+                TestArray::new(vec![1, 1]),
+                example_labels(vec!["samples"], vec![[0]]),
+                vec![],
+                example_labels(vec!["properties"], vec![[0]]),
+            ).unwrap();
+            blocks.push((vec![LabelValue::new(l)], block));
+        }
+        let error = TensorMap::from_iter_checked(vec!["l"], blocks).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not have the same label value multiple time: \
+            [0] is already present at position 0"
+        );
+
+        let block = TensorBlock::new(
+            TestArray::new(vec![1, 1]),
+            example_labels(vec!["samples"], vec![[0]]),
+            vec![],
+            example_labels(vec!["properties"], vec![[0]]),
+        ).unwrap();
+        let blocks = vec![(vec![LabelValue::new(0), LabelValue::new(1)], block)];
+        let error = TensorMap::from_iter_checked(vec!["l"], blocks).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: the key [0, 1] has 2 values, but the keys have 1 dimensions"
+        );
+    }
+
+    #[test]
+    fn check_invariants() {
+        let create_tensor = || {
+            let mut blocks = Vec::new();
+            for _ in 0..2 {
+                let mut block = TensorBlock::new(
+                    TestArray::new(vec![3, 1]),
+                    example_labels(vec!["samples"], vec![[0], [1], [2]]),
+                    vec![],
+                    example_labels(vec!["properties"], vec![[0]]),
+                ).unwrap();
+
+                let gradient = TensorBlock::new(
+                    TestArray::new(vec![1, 1]),
+                    example_labels(vec!["sample"], vec![[2]]),
+                    vec![],
+                    example_labels(vec!["properties"], vec![[0]]),
+                ).unwrap();
+                block.add_gradient("g", gradient).unwrap();
+
+                blocks.push(block);
+            }
+            let keys = example_labels(vec!["key"], vec![[0], [1]]);
+            TensorMap::new(keys, blocks).unwrap()
+        };
+
+        let tensor = create_tensor();
+        tensor.check_invariants().unwrap();
+
+        // labels which do not match the data
+        let mut tensor = create_tensor();
+        tensor.blocks[1].samples = example_labels(vec!["samples"], vec![[0], [1]]);
+        let error = tensor.check_invariants().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: data and labels don't match: the array shape \
+            along axis 0 is 3 but we have 2 sample labels"
+        );
+
+        // labels names which are not the same for all blocks
+        let mut tensor = create_tensor();
+        tensor.blocks[1].samples = example_labels(vec!["other"], vec![[0], [1], [2]]);
+        let error = tensor.check_invariants().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: all blocks must have the same sample names, \
+            got [other] and [samples]"
+        );
+
+        // gradient samples referring to samples which no longer exist
+        let mut tensor = create_tensor();
+        tensor.blocks[0].samples = example_labels(vec!["samples"], vec![[0], [1]]);
+        tensor.blocks[0].values = TestArray::new(vec![2, 1]);
+        let error = tensor.check_invariants().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: invalid value for the 'sample' in gradient \
+            samples: we got 2, but the values contain 2 samples"
+        );
+    }
+
+    #[test]
+    fn properties_to_components() {
+        let mut block = TensorBlock::new(
+            TestArray::with_data(vec![1, 2, 6], (0..12).map(f64::from).collect()),
+            example_labels(vec!["samples"], vec![[0]]),
+            vec![example_labels(vec!["xyz"], vec![[0], [1]])],
+            example_labels(vec!["a", "b", "p"], vec![
+                [0, 0, 4], [0, 0, 5], [0, 2, 4], [1, 0, 4], [1, 0, 5], [1, 2, 4],
+            ]),
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            TestArray::new(vec![1, 2, 6]),
+            example_labels(vec!["sample"], vec![[0]]),
+            vec![example_labels(vec!["xyz"], vec![[0], [1]])],
+            block.properties.clone(),
+        ).unwrap();
+        block.add_gradient("g", gradient).unwrap();
+
+        let keys = example_labels(vec!["key"], vec![[0]]);
+        let tensor = TensorMap::new(keys, vec![block]).unwrap();
+
+        // (a, b) values do not form a full grid
+        let error = tensor.properties_to_components(&["a", "b"]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: the properties do not form a regular grid over \
+            [a, b], unable to move these dimensions to the components"
+        );
+
+        let error = tensor.properties_to_components(&["b"]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: [b] must be the first dimensions of the \
+            properties, got properties [a, b, p]"
+        );
+
+        let error = tensor.properties_to_components(&["xyz"]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: [xyz] must be the first dimensions of the \
+            properties, got properties [a, b, p]"
+        );
+
+        let moved = tensor.properties_to_components(&["a"]).unwrap();
+        let block = &moved.blocks()[0];
+        assert_eq!(block.components.len(), 2);
+        assert_eq!(block.components[1].names(), ["a"]);
+        assert_eq!(block.components[1].iter().collect::<Vec<_>>(), [[0], [1]]);
+        assert_eq!(block.properties.names(), ["b", "p"]);
+        assert_eq!(block.properties.iter().collect::<Vec<_>>(), [[0, 4], [0, 5], [2, 4]]);
+        assert_eq!(block.values.shape().unwrap(), [1, 2, 2, 3]);
+        assert_eq!(block.values.data().unwrap(), tensor.blocks()[0].values.data().unwrap());
+
+        let gradient = block.gradient("g").unwrap();
+        assert_eq!(gradient.components.len(), 2);
+        assert_eq!(gradient.values.shape().unwrap(), [1, 2, 2, 3]);
+
+        // moving the components back gives the initial tensor
+        let back = moved.components_to_properties(&["a"]).unwrap();
+        let block = &back.blocks()[0];
+        assert_eq!(block.properties, tensor.blocks()[0].properties);
+        assert_eq!(block.values.shape().unwrap(), [1, 2, 6]);
+        assert_eq!(block.values.data().unwrap(), tensor.blocks()[0].values.data().unwrap());
+
+        let error = moved.properties_to_components(&["b", "p"]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not move all the properties dimensions \
+            ([b, p]) to the components"
+        );
+    }
+
+    #[test]
+    fn concatenate_properties() {
+        let create_tensor = |properties: Vec<[i32; 1]>, gradient_samples: Vec<[i32; 2]>, value: f64| {
+            let n_properties = properties.len();
+            let mut block = TensorBlock::new(
+                TestArray::with_data(vec![2, n_properties], vec![value; 2 * n_properties]),
+                example_labels(vec!["samples"], vec![[0], [1]]),
+                vec![],
+                example_labels(vec!["p"], properties),
+            ).unwrap();
+
+            let n_gradient_samples = gradient_samples.len();
+            let gradient = TensorBlock::new(
+                TestArray::with_data(
+                    vec![n_gradient_samples, n_properties],
+                    vec![-value; n_gradient_samples * n_properties],
+                ),
+                example_labels(vec!["sample", "atom"], gradient_samples),
+                vec![],
+                block.properties.clone(),
+            ).unwrap();
+            block.add_gradient("g", gradient).unwrap();
+
+            let keys = example_labels(vec!["key"], vec![[0]]);
+            return TensorMap::new(keys, vec![block]).unwrap();
+        };
+
+        let first = create_tensor(vec![[0], [1]], vec![[0, 0], [1, 0]], 1.0);
+        let second = create_tensor(vec![[4]], vec![[1, 0], [1, 1]], 2.0);
+
+        let tensor = TensorMap::concatenate_properties(&[&first, &second], false).unwrap();
+        let block = &tensor.blocks()[0];
+        assert_eq!(block.properties.iter().collect::<Vec<_>>(), [[0], [1], [4]]);
+        assert_eq!(block.values.shape().unwrap(), [2, 3]);
+        assert_eq!(block.values.data().unwrap(), [1.0, 1.0, 2.0, 1.0, 1.0, 2.0]);
+
+        let gradient = block.gradient("g").unwrap();
+        assert_eq!(gradient.samples.iter().collect::<Vec<_>>(), [[0, 0], [1, 0], [1, 1]]);
+        assert_eq!(gradient.values.data().unwrap(), [
+            -1.0, -1.0, 0.0,
+            -1.0, -1.0, -2.0,
+            0.0, 0.0, -2.0,
+        ]);
+
+        let error = TensorMap::concatenate_properties(&[&first, &first], false).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not concatenate properties: the entry [0] \
+            is present in more than one tensor map"
+        );
+
+        let error = TensorMap::concatenate_properties(&[], false).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not concatenate the properties of an empty \
+            list of tensor maps"
+        );
+
+        let block = TensorBlock::new(
+            TestArray::new(vec![1, 1]),
+            example_labels(vec!["samples"], vec![[0]]),
+            vec![],
+            example_labels(vec!["p"], vec![[5]]),
+        ).unwrap();
+        let other = TensorMap::new(example_labels(vec!["key"], vec![[0]]), vec![block]).unwrap();
+        let error = TensorMap::concatenate_properties(&[&first, &other], false).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not concatenate properties of blocks with \
+            different samples"
+        );
+
+        let block = TensorBlock::new(
+            TestArray::new(vec![1, 1]),
+            example_labels(vec!["samples"], vec![[0]]),
+            vec![],
+            example_labels(vec!["p"], vec![[5]]),
+        ).unwrap();
+        let other = TensorMap::new(example_labels(vec!["key"], vec![[1]]), vec![block]).unwrap();
+        let error = TensorMap::concatenate_properties(&[&first, &other], false).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: all tensor maps must have the same keys to \
+            concatenate their properties"
+        );
+    }
+
+    #[test]
+    fn concatenate_samples() {
+        let create_tensor = |samples: Vec<[i32; 1]>, gradient_samples: Vec<[i32; 2]>, value: f64| {
+            let n_samples = samples.len();
+            let mut block = TensorBlock::new(
+                TestArray::with_data(vec![n_samples, 2], vec![value; 2 * n_samples]),
+                example_labels(vec!["system"], samples),
+                vec![],
+                example_labels(vec!["p"], vec![[0], [1]]),
+            ).unwrap();
+
+            let n_gradient_samples = gradient_samples.len();
+            let gradient = TensorBlock::new(
+                TestArray::with_data(vec![n_gradient_samples, 2], vec![-value; 2 * n_gradient_samples]),
+                example_labels(vec!["sample", "atom"], gradient_samples),
+                vec![],
+                block.properties.clone(),
+            ).unwrap();
+            block.add_gradient("g", gradient).unwrap();
+
+            let keys = example_labels(vec!["key"], vec![[0]]);
+            return TensorMap::new(keys, vec![block]).unwrap();
+        };
+
+        let first = create_tensor(vec![[0], [1]], vec![[0, 0], [1, 0]], 1.0);
+        let second = create_tensor(vec![[2], [3]], vec![[1, 1]], 2.0);
+
+        let tensor = TensorMap::concatenate_samples(&[&first, &second], false).unwrap();
+        let block = &tensor.blocks()[0];
+        assert_eq!(block.samples.iter().collect::<Vec<_>>(), [[0], [1], [2], [3]]);
+        assert_eq!(block.values.shape().unwrap(), [4, 2]);
+        assert_eq!(block.values.data().unwrap(), [1.0, 1.0, 1.0, 1.0, 2.0, 2.0, 2.0, 2.0]);
+
+        let gradient = block.gradient("g").unwrap();
+        assert_eq!(gradient.samples.iter().collect::<Vec<_>>(), [[0, 0], [1, 0], [3, 1]]);
+        assert_eq!(gradient.values.data().unwrap(), [-1.0, -1.0, -1.0, -1.0, -2.0, -2.0]);
+
+        let error = TensorMap::concatenate_samples(&[&first, &first], false).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not concatenate samples: the entry [0] \
+            is present in more than one tensor map"
+        );
+
+        let block = TensorBlock::new(
+            TestArray::new(vec![1, 1]),
+            example_labels(vec!["system"], vec![[5]]),
+            vec![],
+            example_labels(vec!["p"], vec![[0]]),
+        ).unwrap();
+        let other = TensorMap::new(example_labels(vec!["key"], vec![[0]]), vec![block]).unwrap();
+        let error = TensorMap::concatenate_samples(&[&first, &other], false).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not concatenate samples of blocks with \
+            different properties"
+        );
+
+        let block = TensorBlock::concatenate_samples(&[&second.blocks()[0], &first.blocks()[0]]).unwrap();
+        assert_eq!(block.samples.iter().collect::<Vec<_>>(), [[2], [3], [0], [1]]);
+        let gradient = block.gradient("g").unwrap();
+        assert_eq!(gradient.samples.iter().collect::<Vec<_>>(), [[1, 1], [2, 0], [3, 0]]);
+
+        let error = TensorBlock::concatenate_samples(&[&first.blocks()[0], &first.blocks()[0]]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not concatenate samples: the entry [0] \
+            is present in more than one block"
+        );
+
+        let error = TensorBlock::concatenate_samples(&[]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not concatenate the samples of an empty list of blocks"
+        );
+    }
+
+    #[test]
+    fn concatenate_fill_missing_gradients() {
+        let create_tensor = |samples: Vec<[i32; 1]>, properties: Vec<[i32; 1]>, gradient: Option<&str>| {
+            let n_samples = samples.len();
+            let n_properties = properties.len();
+            let mut block = TensorBlock::new(
+                TestArray::with_data(vec![n_samples, 3, n_properties], vec![1.0; 3 * n_samples * n_properties]),
+                example_labels(vec!["system"], samples),
+                vec![example_labels(vec!["xyz"], vec![[0], [1], [2]])],
+                example_labels(vec!["p"], properties),
+            ).unwrap();
+
+            if let Some(parameter) = gradient {
+                let gradient = TensorBlock::new(
+                    TestArray::with_data(vec![1, 3, 3, n_properties], vec![-1.0; 9 * n_properties]),
+                    example_labels(vec!["sample", "atom"], vec![[0, 4]]),
+                    vec![
+                        example_labels(vec!["direction"], vec![[0], [1], [2]]),
+                        example_labels(vec!["xyz"], vec![[0], [1], [2]]),
+                    ],
+                    block.properties.clone(),
+                ).unwrap();
+                block.add_gradient(parameter, gradient).unwrap();
+            }
+
+            let keys = example_labels(vec!["key"], vec![[0]]);
+            return TensorMap::new(keys, vec![block]).unwrap();
+        };
+
+        let first = create_tensor(vec![[0], [1]], vec![[0], [1]], Some("positions"));
+        let second = create_tensor(vec![[2]], vec![[0], [1]], Some("cell"));
+
+        let error = TensorMap::concatenate_samples(&[&first, &second], false).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not concatenate samples of blocks with different gradients"
+        );
+
+        let tensor = TensorMap::concatenate_samples(&[&first, &second], true).unwrap();
+        let block = &tensor.blocks()[0];
+        assert_eq!(block.gradients().len(), 2);
+        assert_eq!(block.gradient("positions").unwrap().samples.iter().collect::<Vec<_>>(), [[0, 4]]);
+        assert_eq!(block.gradient("cell").unwrap().samples.iter().collect::<Vec<_>>(), [[2, 4]]);
+        assert_eq!(block.gradient("cell").unwrap().values.shape().unwrap(), [1, 3, 3, 2]);
+
+        let second = create_tensor(vec![[0], [1]], vec![[2]], None);
+        let error = TensorMap::concatenate_properties(&[&first, &second], false).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not concatenate properties of blocks with different gradients"
+        );
+
+        let tensor = TensorMap::concatenate_properties(&[&second, &first], true).unwrap();
+        let block = &tensor.blocks()[0];
+        assert_eq!(block.properties.iter().collect::<Vec<_>>(), [[2], [0], [1]]);
+        let gradient = block.gradient("positions").unwrap();
+        assert_eq!(gradient.samples.iter().collect::<Vec<_>>(), [[0, 4]]);
+        assert_eq!(gradient.values.data().unwrap()[..3], [0.0, -1.0, -1.0]);
+
+        // all the gradients with respect to the same parameter must be
+        // compatible, even when filling the missing ones
+        let mut other = create_tensor(vec![[2]], vec![[0], [1]], None);
+        let gradient = TensorBlock::new(
+            TestArray::new(vec![0, 3, 2]),
+            example_labels::<2>(vec!["sample", "atom"], vec![]),
+            vec![example_labels(vec!["xyz"], vec![[0], [1], [2]])],
+            other.blocks[0].properties.clone(),
+        ).unwrap();
+        other.blocks[0].add_gradient("positions", gradient).unwrap();
+
+        let error = TensorMap::concatenate_samples(&[&first, &other], true).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid parameter: can not concatenate samples of blocks with \
+            different components in the 'positions' gradients"
+        );
+    }
+
+    #[test]
+    fn blocks_matching() {
+        let mut blocks = Vec::new();
+        for _ in 0..6 {
+            blocks.push(TensorBlock::new(
+                TestArray::new(vec![1, 1]),
+                example_labels(vec!["samples"], vec![[0]]),
+                vec![],
+                example_labels(vec!["properties"], vec![[0]]),
+            ).unwrap());
+        }
+
+        let keys = example_labels(vec!["key_1", "key_2"], vec![
+            [0, 1], [0, 2], [1, 1],
+            [1, 2], [3, 0], [4, 3],
+        ]);
+
+        let tensor = TensorMap::new(keys, blocks).unwrap();
+
+        let mut selection = LabelsBuilder::new(vec!["key_1", "key_2"]).unwrap();
+        selection.add(&[1, 1]).unwrap();
+        assert_eq!(
+            tensor.blocks_matching(&selection.finish(), false).unwrap(),
+            [2]
+        );
+
+        let mut selection = LabelsBuilder::new(vec!["key_1"]).unwrap();
+        selection.add(&[1]).unwrap();
+        assert_eq!(
+            tensor.blocks_matching(&selection.finish(), false).unwrap(),
+            [2, 3]
+        );
+
+        let selection = LabelsBuilder::new(vec!["key_1"]).unwrap();
+        let result = tensor.blocks_matching(&selection.finish(), false);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "invalid parameter: block selection must contain exactly one entry, got 0"
+        );
+
+        let mut selection = LabelsBuilder::new(vec!["key_1", "key_2"]).unwrap();
+        selection.add(&[3, 4]).unwrap();
+        selection.add(&[1, 2]).unwrap();
+        let result = tensor.blocks_matching(&selection.finish(), false);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "invalid parameter: block selection must contain exactly one entry, got 2"
+        );
+
+        let mut selection = LabelsBuilder::new(vec!["key_3"]).unwrap();
+        selection.add(&[1]).unwrap();
+        let result = tensor.blocks_matching(&selection.finish(), false);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "invalid parameter: 'key_3' is not part of the keys for this tensor"
+        );
+    }
+
+    #[test]
+    fn blocks_matching_wildcard() {
+        let mut blocks = Vec::new();
+        for _ in 0..4 {
+            blocks.push(TensorBlock::new(
+                TestArray::new(vec![1, 1]),
+                example_labels(vec!["samples"], vec![[0]]),
+                vec![],
+                example_labels(vec!["properties"], vec![[0]]),
+            ).unwrap());
+        }
+
+        let keys = example_labels(vec!["key_1", "key_2", "key_3"], vec![
+            [0, 1, 2], [0, 2, 2], [1, 1, 2], [0, 1, 3],
+        ]);
+        let tensor = TensorMap::new(keys, blocks).unwrap();
+
+        let mut selection = LabelsBuilder::new(vec!["key_1", "key_2", "key_3"]).unwrap();
+        selection.add(&[LabelValue::new(0), LabelValue::ANY, LabelValue::new(2)]).unwrap();
+        let selection = selection.finish();
+        assert_eq!(tensor.blocks_matching(&selection, true).unwrap(), [0, 1]);
+        assert!(tensor.blocks_matching(&selection, false).unwrap().is_empty());
+
+        let mut selection = LabelsBuilder::new(vec!["key_2", "key_1"]).unwrap();
+        selection.add(&[LabelValue::ANY, LabelValue::ANY]).unwrap();
+        assert_eq!(tensor.blocks_matching(&selection.finish(), true).unwrap(), [0, 1, 2, 3]);
+
+        // keys can contain the same value as `LabelValue::ANY`, and can be
+        // selected exactly without the wildcard
+        let mut blocks = Vec::new();
+        for _ in 0..2 {
+            blocks.push(TensorBlock::new(
+                TestArray::new(vec![1, 1]),
+                example_labels(vec!["samples"], vec![[0]]),
+                vec![],
+                example_labels(vec!["properties"], vec![[0]]),
+            ).unwrap());
+        }
+        let keys = example_labels(vec!["key_1", "key_2"], vec![[0, i32::MIN], [0, 1]]);
+        let tensor = TensorMap::new(keys, blocks).unwrap();
+
+        let mut selection = LabelsBuilder::new(vec!["key_2"]).unwrap();
+        selection.add(&[LabelValue::ANY]).unwrap();
+        let selection = selection.finish();
+        assert_eq!(tensor.blocks_matching(&selection, false).unwrap(), [0]);
+        assert_eq!(tensor.blocks_matching(&selection, true).unwrap(), [0, 1]);
+    }
 }
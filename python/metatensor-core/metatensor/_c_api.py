@@ -26,10 +26,14 @@ MTS_IO_ERROR = 2
 MTS_SERIALIZATION_ERROR = 3
 MTS_BUFFER_SIZE_ERROR = 254
 MTS_INTERNAL_ERROR = 255
+MTS_LABELS_ANY = -2147483648
 
 
 mts_status_t = ctypes.c_int32
 mts_data_origin_t = ctypes.c_uint64
+mts_progress_callback_t = CFUNCTYPE(mts_status_t, ctypes.c_void_p, c_uintptr_t, c_uintptr_t)
+mts_map_keys_callback_t = CFUNCTYPE(mts_status_t, ctypes.c_void_p, POINTER(ctypes.c_int32), POINTER(ctypes.c_int32), c_uintptr_t)
+mts_filter_properties_callback_t = CFUNCTYPE(mts_status_t, ctypes.c_void_p, POINTER(ctypes.c_int32), c_uintptr_t, POINTER(ctypes.c_bool))
 mts_realloc_buffer_t = CFUNCTYPE(ctypes.c_char_p, ctypes.c_void_p, ctypes.c_char_p, c_uintptr_t)
 
 
@@ -76,6 +80,7 @@ mts_array_t._fields_ = [
     ("copy", CFUNCTYPE(mts_status_t, ctypes.c_void_p, POINTER(mts_array_t))),
     ("destroy", CFUNCTYPE(None, ctypes.c_void_p)),
     ("move_samples_from", CFUNCTYPE(mts_status_t, ctypes.c_void_p, ctypes.c_void_p, POINTER(mts_sample_mapping_t), c_uintptr_t, c_uintptr_t, c_uintptr_t)),
+    ("element_size", CFUNCTYPE(mts_status_t, ctypes.c_void_p, POINTER(c_uintptr_t))),
 ]
 
 
@@ -151,6 +156,13 @@ def setup_functions(lib):
     ]
     lib.mts_labels_intersection.restype = _check_status
 
+    lib.mts_labels_cartesian_product.argtypes = [
+        mts_labels_t,
+        mts_labels_t,
+        POINTER(mts_labels_t),
+    ]
+    lib.mts_labels_cartesian_product.restype = _check_status
+
     lib.mts_labels_free.argtypes = [
         POINTER(mts_labels_t),
     ]
@@ -188,6 +200,11 @@ def setup_functions(lib):
     ]
     lib.mts_block_copy.restype = POINTER(mts_block_t)
 
+    lib.mts_block_zeros_like.argtypes = [
+        POINTER(mts_block_t),
+    ]
+    lib.mts_block_zeros_like.restype = POINTER(mts_block_t)
+
     lib.mts_block_labels.argtypes = [
         POINTER(mts_block_t),
         c_uintptr_t,
@@ -195,6 +212,32 @@ def setup_functions(lib):
     ]
     lib.mts_block_labels.restype = _check_status
 
+    lib.mts_block_set_labels.argtypes = [
+        POINTER(mts_block_t),
+        c_uintptr_t,
+        mts_labels_t,
+    ]
+    lib.mts_block_set_labels.restype = _check_status
+
+    lib.mts_block_set_gradient_samples.argtypes = [
+        POINTER(mts_block_t),
+        ctypes.c_char_p,
+        mts_labels_t,
+    ]
+    lib.mts_block_set_gradient_samples.restype = _check_status
+
+    lib.mts_block_set_units.argtypes = [
+        POINTER(mts_block_t),
+        ctypes.c_char_p,
+    ]
+    lib.mts_block_set_units.restype = _check_status
+
+    lib.mts_block_units.argtypes = [
+        POINTER(mts_block_t),
+        POINTER(ctypes.c_char_p),
+    ]
+    lib.mts_block_units.restype = _check_status
+
     lib.mts_block_gradient.argtypes = [
         POINTER(mts_block_t),
         ctypes.c_char_p,
@@ -222,6 +265,36 @@ def setup_functions(lib):
     ]
     lib.mts_block_gradients_list.restype = _check_status
 
+    lib.mts_block_compact_gradients.argtypes = [
+        POINTER(mts_block_t),
+    ]
+    lib.mts_block_compact_gradients.restype = _check_status
+
+    lib.mts_block_nbytes.argtypes = [
+        POINTER(mts_block_t),
+        POINTER(c_uintptr_t),
+    ]
+    lib.mts_block_nbytes.restype = _check_status
+
+    lib.mts_block_append_samples.argtypes = [
+        POINTER(mts_block_t),
+        mts_labels_t,
+        mts_array_t,
+    ]
+    lib.mts_block_append_samples.restype = _check_status
+
+    lib.mts_block_reorder_properties.argtypes = [
+        POINTER(mts_block_t),
+        mts_labels_t,
+    ]
+    lib.mts_block_reorder_properties.restype = _check_status
+
+    lib.mts_block_concatenate_samples.argtypes = [
+        POINTER(POINTER(mts_block_t)),
+        c_uintptr_t,
+    ]
+    lib.mts_block_concatenate_samples.restype = POINTER(mts_block_t)
+
     lib.mts_tensormap.argtypes = [
         mts_labels_t,
         POINTER(POINTER(mts_block_t)),
@@ -239,12 +312,43 @@ def setup_functions(lib):
     ]
     lib.mts_tensormap_copy.restype = POINTER(mts_tensormap_t)
 
+    lib.mts_tensormap_zeros_like.argtypes = [
+        POINTER(mts_tensormap_t),
+    ]
+    lib.mts_tensormap_zeros_like.restype = POINTER(mts_tensormap_t)
+
+    lib.mts_tensormap_check_invariants.argtypes = [
+        POINTER(mts_tensormap_t),
+    ]
+    lib.mts_tensormap_check_invariants.restype = _check_status
+
     lib.mts_tensormap_keys.argtypes = [
         POINTER(mts_tensormap_t),
         POINTER(mts_labels_t),
     ]
     lib.mts_tensormap_keys.restype = _check_status
 
+    lib.mts_tensormap_set_metadata.argtypes = [
+        POINTER(mts_tensormap_t),
+        ctypes.c_char_p,
+        ctypes.c_char_p,
+    ]
+    lib.mts_tensormap_set_metadata.restype = _check_status
+
+    lib.mts_tensormap_get_metadata.argtypes = [
+        POINTER(mts_tensormap_t),
+        ctypes.c_char_p,
+        POINTER(ctypes.c_char_p),
+    ]
+    lib.mts_tensormap_get_metadata.restype = _check_status
+
+    lib.mts_tensormap_metadata_keys.argtypes = [
+        POINTER(mts_tensormap_t),
+        POINTER(POINTER(ctypes.c_char_p)),
+        POINTER(c_uintptr_t),
+    ]
+    lib.mts_tensormap_metadata_keys.restype = _check_status
+
     lib.mts_tensormap_block_by_id.argtypes = [
         POINTER(mts_tensormap_t),
         POINTER(POINTER(mts_block_t)),
@@ -260,6 +364,14 @@ def setup_functions(lib):
     ]
     lib.mts_tensormap_blocks_matching.restype = _check_status
 
+    lib.mts_tensormap_blocks_matching_wildcard.argtypes = [
+        POINTER(mts_tensormap_t),
+        POINTER(c_uintptr_t),
+        POINTER(c_uintptr_t),
+        mts_labels_t,
+    ]
+    lib.mts_tensormap_blocks_matching_wildcard.restype = _check_status
+
     lib.mts_tensormap_keys_to_properties.argtypes = [
         POINTER(mts_tensormap_t),
         mts_labels_t,
@@ -281,6 +393,83 @@ def setup_functions(lib):
     ]
     lib.mts_tensormap_keys_to_samples.restype = POINTER(mts_tensormap_t)
 
+    lib.mts_tensormap_keys_to_samples_with_progress.argtypes = [
+        POINTER(mts_tensormap_t),
+        mts_labels_t,
+        ctypes.c_bool,
+        mts_progress_callback_t,
+        ctypes.c_void_p,
+    ]
+    lib.mts_tensormap_keys_to_samples_with_progress.restype = POINTER(mts_tensormap_t)
+
+    lib.mts_tensormap_keys_to_properties_with_progress.argtypes = [
+        POINTER(mts_tensormap_t),
+        mts_labels_t,
+        ctypes.c_bool,
+        mts_progress_callback_t,
+        ctypes.c_void_p,
+    ]
+    lib.mts_tensormap_keys_to_properties_with_progress.restype = POINTER(mts_tensormap_t)
+
+    lib.mts_tensormap_sort_blocks.argtypes = [
+        POINTER(mts_tensormap_t),
+    ]
+    lib.mts_tensormap_sort_blocks.restype = _check_status
+
+    lib.mts_tensormap_retain_blocks.argtypes = [
+        POINTER(mts_tensormap_t),
+        POINTER(ctypes.c_bool),
+        c_uintptr_t,
+        POINTER(c_uintptr_t),
+    ]
+    lib.mts_tensormap_retain_blocks.restype = _check_status
+
+    lib.mts_tensormap_make_labels_unique.argtypes = [
+        POINTER(mts_tensormap_t),
+    ]
+    lib.mts_tensormap_make_labels_unique.restype = _check_status
+
+    lib.mts_tensormap_properties_to_components.argtypes = [
+        POINTER(mts_tensormap_t),
+        POINTER(ctypes.c_char_p),
+        c_uintptr_t,
+    ]
+    lib.mts_tensormap_properties_to_components.restype = POINTER(mts_tensormap_t)
+
+    lib.mts_tensormap_concatenate_properties.argtypes = [
+        POINTER(POINTER(mts_tensormap_t)),
+        c_uintptr_t,
+        ctypes.c_bool,
+    ]
+    lib.mts_tensormap_concatenate_properties.restype = POINTER(mts_tensormap_t)
+
+    lib.mts_tensormap_concatenate_samples.argtypes = [
+        POINTER(POINTER(mts_tensormap_t)),
+        c_uintptr_t,
+        ctypes.c_bool,
+    ]
+    lib.mts_tensormap_concatenate_samples.restype = POINTER(mts_tensormap_t)
+
+    lib.mts_tensormap_nbytes.argtypes = [
+        POINTER(mts_tensormap_t),
+        POINTER(c_uintptr_t),
+    ]
+    lib.mts_tensormap_nbytes.restype = _check_status
+
+    lib.mts_tensormap_map_keys.argtypes = [
+        POINTER(mts_tensormap_t),
+        mts_map_keys_callback_t,
+        ctypes.c_void_p,
+    ]
+    lib.mts_tensormap_map_keys.restype = POINTER(mts_tensormap_t)
+
+    lib.mts_tensormap_filter_properties.argtypes = [
+        POINTER(mts_tensormap_t),
+        mts_filter_properties_callback_t,
+        ctypes.c_void_p,
+    ]
+    lib.mts_tensormap_filter_properties.restype = POINTER(mts_tensormap_t)
+
     lib.mts_labels_load.argtypes = [
         ctypes.c_char_p,
         POINTER(mts_labels_t),
@@ -198,6 +198,10 @@ class ArrayWrapper:
             _mts_array_move_samples_from
         )
 
+        mts_array.element_size = mts_array.element_size.__class__(
+            _mts_array_element_size
+        )
+
         self._mts_array = mts_array
 
     def into_mts_array(self):
@@ -320,6 +324,16 @@ def _mts_array_destroy(this):
     ctypes.pythonapi.Py_DecRef(ctypes.py_object(wrapper))
 
 
+@catch_exceptions
+def _mts_array_element_size(this, element_size):
+    array = _object_from_ptr(this).array
+
+    if _is_numpy_array(array):
+        element_size[0] = array.itemsize
+    elif _is_torch_array(array):
+        element_size[0] = array.element_size()
+
+
 @catch_exceptions
 def _mts_array_move_samples_from(
     this,
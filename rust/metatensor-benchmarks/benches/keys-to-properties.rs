@@ -0,0 +1,26 @@
+#![allow(clippy::needless_return)]
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+
+use metatensor::Labels;
+
+mod utils;
+use utils::{all_parameters, soap_like_tensor};
+
+fn keys_to_properties(c: &mut Criterion) {
+    let mut group = c.benchmark_group("keys_to_properties");
+    let keys_to_move = Labels::empty(vec!["neighbor_type"]);
+
+    for (name, parameters) in all_parameters() {
+        let tensor = soap_like_tensor(&parameters);
+        let id = BenchmarkId::new(name, tensor.keys().count());
+        group.bench_with_input(id, &tensor, |b, tensor| {
+            b.iter(|| tensor.keys_to_properties(&keys_to_move, true).unwrap());
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, keys_to_properties);
+criterion_main!(benches);
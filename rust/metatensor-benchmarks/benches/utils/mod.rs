@@ -0,0 +1,129 @@
+#![allow(dead_code)]
+#![allow(clippy::needless_return)]
+
+use metatensor::{Labels, LabelsBuilder, TensorBlock, TensorMap};
+
+/// Parameters for [`soap_like_tensor`]
+pub struct SoapLikeParameters {
+    /// maximal angular momentum, there will be `max_angular + 1` values of
+    /// `o3_lambda` in the keys
+    pub max_angular: i32,
+    /// number of atomic species, used for both the `center_type` and the
+    /// `neighbor_type` in the keys
+    pub n_species: i32,
+    /// number of systems in the samples
+    pub n_systems: i32,
+    /// number of atoms of each species in every system
+    pub atoms_per_species: i32,
+    /// number of radial channels in the properties
+    pub n_radial: i32,
+    /// should the blocks contain gradients with respect to positions?
+    pub gradients: bool,
+}
+
+/// Create a `TensorMap` with the same structure as a spherical expansion
+/// (the building block of SOAP), filled with arbitrary data.
+///
+/// The keys contain `o3_lambda`, `center_type` and `neighbor_type`. The
+/// samples of a block are all the atoms with the block's `center_type` in all
+/// systems, there is one `o3_mu` component with `2 * o3_lambda + 1` entries,
+/// and the properties are the radial channels `n`. If requested, the gradients
+/// with respect to positions contain the center atom and one other atom for
+/// each sample.
+pub fn soap_like_tensor(parameters: &SoapLikeParameters) -> TensorMap {
+    let n_atoms = parameters.n_species * parameters.atoms_per_species;
+
+    let mut keys = LabelsBuilder::new(vec!["o3_lambda", "center_type", "neighbor_type"]);
+    let mut blocks = Vec::new();
+    for o3_lambda in 0..=parameters.max_angular {
+        let o3_mu = (-o3_lambda..=o3_lambda).map(|m| [m]).collect::<Vec<_>>();
+        let components = [Labels::new(["o3_mu"], &o3_mu)];
+
+        for center_type in 0..parameters.n_species {
+            let mut samples = LabelsBuilder::new(vec!["system", "atom"]);
+            let mut gradient_samples = LabelsBuilder::new(vec!["sample", "system", "atom"]);
+            let mut sample = 0;
+            for system in 0..parameters.n_systems {
+                for i in 0..parameters.atoms_per_species {
+                    let atom = center_type * parameters.atoms_per_species + i;
+                    samples.add(&[system, atom]);
+                    gradient_samples.add(&[sample, system, atom]);
+                    gradient_samples.add(&[sample, system, (atom + 1) % n_atoms]);
+                    sample += 1;
+                }
+            }
+            let samples = samples.finish();
+            let gradient_samples = gradient_samples.finish();
+
+            for neighbor_type in 0..parameters.n_species {
+                keys.add(&[o3_lambda, center_type, neighbor_type]);
+
+                let properties = (0..parameters.n_radial).map(|n| [n]).collect::<Vec<_>>();
+                let properties = Labels::new(["n"], &properties);
+
+                let shape = vec![samples.count(), components[0].count(), properties.count()];
+                let mut block = TensorBlock::new(
+                    ndarray::ArrayD::from_elem(shape, 1.0),
+                    &samples,
+                    &components,
+                    &properties,
+                ).unwrap();
+
+                if parameters.gradients {
+                    let xyz = Labels::new(["xyz"], &[[0], [1], [2]]);
+                    let shape = vec![gradient_samples.count(), 3, components[0].count(), properties.count()];
+                    let gradient = TensorBlock::new(
+                        ndarray::ArrayD::from_elem(shape, 1.0),
+                        &gradient_samples,
+                        &[xyz, components[0].clone()],
+                        &properties,
+                    ).unwrap();
+                    block.add_gradient("positions", gradient).unwrap();
+                }
+
+                blocks.push(block);
+            }
+        }
+    }
+
+    return TensorMap::new(keys.finish(), blocks).unwrap();
+}
+
+/// Parameters for the benchmarks, going from a few small blocks to many
+/// larger blocks.
+pub fn all_parameters() -> Vec<(&'static str, SoapLikeParameters)> {
+    return vec![
+        ("small", SoapLikeParameters {
+            max_angular: 2,
+            n_species: 2,
+            n_systems: 10,
+            atoms_per_species: 4,
+            n_radial: 4,
+            gradients: true,
+        }),
+        ("many-blocks", SoapLikeParameters {
+            max_angular: 4,
+            n_species: 6,
+            n_systems: 10,
+            atoms_per_species: 4,
+            n_radial: 4,
+            gradients: true,
+        }),
+        ("large-blocks", SoapLikeParameters {
+            max_angular: 2,
+            n_species: 2,
+            n_systems: 100,
+            atoms_per_species: 10,
+            n_radial: 8,
+            gradients: true,
+        }),
+        ("large-no-gradients", SoapLikeParameters {
+            max_angular: 6,
+            n_species: 4,
+            n_systems: 100,
+            atoms_per_species: 10,
+            n_radial: 8,
+            gradients: false,
+        }),
+    ];
+}
@@ -15,6 +15,7 @@ pub const MTS_IO_ERROR: i32 = 2;
 pub const MTS_SERIALIZATION_ERROR: i32 = 3;
 pub const MTS_BUFFER_SIZE_ERROR: i32 = 254;
 pub const MTS_INTERNAL_ERROR: i32 = 255;
+pub const MTS_LABELS_ANY: i32 = -2147483648;
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct mts_block_t {
@@ -204,6 +205,12 @@ pub struct mts_array_t {
             property_end: usize,
         ) -> mts_status_t,
     >,
+    pub element_size: ::std::option::Option<
+        unsafe extern "C" fn(
+            array: *const ::std::os::raw::c_void,
+            element_size: *mut usize,
+        ) -> mts_status_t,
+    >,
 }
 #[test]
 fn bindgen_test_layout_mts_array_t() {
@@ -211,7 +218,7 @@ fn bindgen_test_layout_mts_array_t() {
     let ptr = UNINIT.as_ptr();
     assert_eq!(
         ::std::mem::size_of::<mts_array_t>(),
-        80usize,
+        88usize,
         concat!("Size of: ", stringify!(mts_array_t))
     );
     assert_eq!(
@@ -319,7 +326,40 @@ fn bindgen_test_layout_mts_array_t() {
             stringify!(move_samples_from)
         )
     );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).element_size) as usize - ptr as usize },
+        80usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(mts_array_t),
+            "::",
+            stringify!(element_size)
+        )
+    );
 }
+pub type mts_progress_callback_t = ::std::option::Option<
+    unsafe extern "C" fn(
+        user_data: *mut ::std::os::raw::c_void,
+        done: usize,
+        total: usize,
+    ) -> mts_status_t,
+>;
+pub type mts_map_keys_callback_t = ::std::option::Option<
+    unsafe extern "C" fn(
+        user_data: *mut ::std::os::raw::c_void,
+        key: *const i32,
+        new_key: *mut i32,
+        key_size: usize,
+    ) -> mts_status_t,
+>;
+pub type mts_filter_properties_callback_t = ::std::option::Option<
+    unsafe extern "C" fn(
+        user_data: *mut ::std::os::raw::c_void,
+        property: *const i32,
+        property_size: usize,
+        keep: *mut bool,
+    ) -> mts_status_t,
+>;
 pub type mts_realloc_buffer_t = ::std::option::Option<
     unsafe extern "C" fn(
         user_data: *mut ::std::os::raw::c_void,
@@ -383,6 +423,12 @@ extern "C" {
         second_mapping_count: usize,
     ) -> mts_status_t;
     #[must_use]
+    pub fn mts_labels_cartesian_product(
+        first: mts_labels_t,
+        second: mts_labels_t,
+        result: *mut mts_labels_t,
+    ) -> mts_status_t;
+    #[must_use]
     pub fn mts_labels_free(labels: *mut mts_labels_t) -> mts_status_t;
     #[must_use]
     pub fn mts_register_data_origin(
@@ -405,6 +451,7 @@ extern "C" {
     #[must_use]
     pub fn mts_block_free(block: *mut mts_block_t) -> mts_status_t;
     pub fn mts_block_copy(block: *const mts_block_t) -> *mut mts_block_t;
+    pub fn mts_block_zeros_like(block: *const mts_block_t) -> *mut mts_block_t;
     #[must_use]
     pub fn mts_block_labels(
         block: *const mts_block_t,
@@ -412,6 +459,28 @@ extern "C" {
         labels: *mut mts_labels_t,
     ) -> mts_status_t;
     #[must_use]
+    pub fn mts_block_set_labels(
+        block: *mut mts_block_t,
+        axis: usize,
+        labels: mts_labels_t,
+    ) -> mts_status_t;
+    #[must_use]
+    pub fn mts_block_set_gradient_samples(
+        block: *mut mts_block_t,
+        parameter: *const ::std::os::raw::c_char,
+        labels: mts_labels_t,
+    ) -> mts_status_t;
+    #[must_use]
+    pub fn mts_block_set_units(
+        block: *mut mts_block_t,
+        units: *const ::std::os::raw::c_char,
+    ) -> mts_status_t;
+    #[must_use]
+    pub fn mts_block_units(
+        block: *const mts_block_t,
+        units: *mut *const ::std::os::raw::c_char,
+    ) -> mts_status_t;
+    #[must_use]
     pub fn mts_block_gradient(
         block: *mut mts_block_t,
         parameter: *const ::std::os::raw::c_char,
@@ -431,6 +500,25 @@ extern "C" {
         parameters: *mut *const *const ::std::os::raw::c_char,
         parameters_count: *mut usize,
     ) -> mts_status_t;
+    #[must_use]
+    pub fn mts_block_compact_gradients(block: *mut mts_block_t) -> mts_status_t;
+    #[must_use]
+    pub fn mts_block_nbytes(block: *const mts_block_t, nbytes: *mut usize) -> mts_status_t;
+    #[must_use]
+    pub fn mts_block_append_samples(
+        block: *mut mts_block_t,
+        samples: mts_labels_t,
+        data: mts_array_t,
+    ) -> mts_status_t;
+    #[must_use]
+    pub fn mts_block_reorder_properties(
+        block: *mut mts_block_t,
+        order: mts_labels_t,
+    ) -> mts_status_t;
+    pub fn mts_block_concatenate_samples(
+        blocks: *const *const mts_block_t,
+        blocks_count: usize,
+    ) -> *mut mts_block_t;
     pub fn mts_tensormap(
         keys: mts_labels_t,
         blocks: *mut *mut mts_block_t,
@@ -439,12 +527,33 @@ extern "C" {
     #[must_use]
     pub fn mts_tensormap_free(tensor: *mut mts_tensormap_t) -> mts_status_t;
     pub fn mts_tensormap_copy(tensor: *const mts_tensormap_t) -> *mut mts_tensormap_t;
+    pub fn mts_tensormap_zeros_like(tensor: *const mts_tensormap_t) -> *mut mts_tensormap_t;
+    #[must_use]
+    pub fn mts_tensormap_check_invariants(tensor: *const mts_tensormap_t) -> mts_status_t;
     #[must_use]
     pub fn mts_tensormap_keys(
         tensor: *const mts_tensormap_t,
         keys: *mut mts_labels_t,
     ) -> mts_status_t;
     #[must_use]
+    pub fn mts_tensormap_set_metadata(
+        tensor: *mut mts_tensormap_t,
+        key: *const ::std::os::raw::c_char,
+        value: *const ::std::os::raw::c_char,
+    ) -> mts_status_t;
+    #[must_use]
+    pub fn mts_tensormap_get_metadata(
+        tensor: *const mts_tensormap_t,
+        key: *const ::std::os::raw::c_char,
+        value: *mut *const ::std::os::raw::c_char,
+    ) -> mts_status_t;
+    #[must_use]
+    pub fn mts_tensormap_metadata_keys(
+        tensor: *const mts_tensormap_t,
+        keys: *mut *const *const ::std::os::raw::c_char,
+        keys_count: *mut usize,
+    ) -> mts_status_t;
+    #[must_use]
     pub fn mts_tensormap_block_by_id(
         tensor: *mut mts_tensormap_t,
         block: *mut *mut mts_block_t,
@@ -457,6 +566,13 @@ extern "C" {
         count: *mut usize,
         selection: mts_labels_t,
     ) -> mts_status_t;
+    #[must_use]
+    pub fn mts_tensormap_blocks_matching_wildcard(
+        tensor: *const mts_tensormap_t,
+        block_indexes: *mut usize,
+        count: *mut usize,
+        selection: mts_labels_t,
+    ) -> mts_status_t;
     pub fn mts_tensormap_keys_to_properties(
         tensor: *const mts_tensormap_t,
         keys_to_move: mts_labels_t,
@@ -472,6 +588,59 @@ extern "C" {
         keys_to_move: mts_labels_t,
         sort_samples: bool,
     ) -> *mut mts_tensormap_t;
+    pub fn mts_tensormap_keys_to_samples_with_progress(
+        tensor: *const mts_tensormap_t,
+        keys_to_move: mts_labels_t,
+        sort_samples: bool,
+        callback: mts_progress_callback_t,
+        user_data: *mut ::std::os::raw::c_void,
+    ) -> *mut mts_tensormap_t;
+    pub fn mts_tensormap_keys_to_properties_with_progress(
+        tensor: *const mts_tensormap_t,
+        keys_to_move: mts_labels_t,
+        sort_samples: bool,
+        callback: mts_progress_callback_t,
+        user_data: *mut ::std::os::raw::c_void,
+    ) -> *mut mts_tensormap_t;
+    #[must_use]
+    pub fn mts_tensormap_sort_blocks(tensor: *mut mts_tensormap_t) -> mts_status_t;
+    #[must_use]
+    pub fn mts_tensormap_retain_blocks(
+        tensor: *mut mts_tensormap_t,
+        keep: *const bool,
+        keep_count: usize,
+        removed: *mut usize,
+    ) -> mts_status_t;
+    #[must_use]
+    pub fn mts_tensormap_make_labels_unique(tensor: *mut mts_tensormap_t) -> mts_status_t;
+    pub fn mts_tensormap_properties_to_components(
+        tensor: *const mts_tensormap_t,
+        dimensions: *const *const ::std::os::raw::c_char,
+        dimensions_count: usize,
+    ) -> *mut mts_tensormap_t;
+    pub fn mts_tensormap_concatenate_properties(
+        tensors: *const *const mts_tensormap_t,
+        tensors_count: usize,
+        fill_missing_gradients: bool,
+    ) -> *mut mts_tensormap_t;
+    pub fn mts_tensormap_concatenate_samples(
+        tensors: *const *const mts_tensormap_t,
+        tensors_count: usize,
+        fill_missing_gradients: bool,
+    ) -> *mut mts_tensormap_t;
+    #[must_use]
+    pub fn mts_tensormap_nbytes(tensor: *const mts_tensormap_t, nbytes: *mut usize)
+        -> mts_status_t;
+    pub fn mts_tensormap_map_keys(
+        tensor: *const mts_tensormap_t,
+        callback: mts_map_keys_callback_t,
+        user_data: *mut ::std::os::raw::c_void,
+    ) -> *mut mts_tensormap_t;
+    pub fn mts_tensormap_filter_properties(
+        tensor: *const mts_tensormap_t,
+        callback: mts_filter_properties_callback_t,
+        user_data: *mut ::std::os::raw::c_void,
+    ) -> *mut mts_tensormap_t;
     #[must_use]
     pub fn mts_labels_load(
         path: *const ::std::os::raw::c_char,
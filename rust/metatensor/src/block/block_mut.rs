@@ -2,7 +2,7 @@ use std::ffi::{CString, CStr};
 use std::iter::FusedIterator;
 
 use crate::c_api::{mts_block_t, mts_array_t, MTS_INVALID_PARAMETER_ERROR};
-use crate::{ArrayRef, ArrayRefMut, Labels};
+use crate::{Array, ArrayRef, ArrayRefMut, Labels, Error};
 
 use super::{TensorBlockRef, LazyMetadata};
 use super::block_ref::{get_samples, get_components, get_properties};
@@ -11,6 +11,8 @@ use super::block_ref::{get_samples, get_components, get_properties};
 #[derive(Debug)]
 pub struct TensorBlockRefMut<'a> {
     ptr: *mut mts_block_t,
+    /// Is this a reference to a gradient block, obtained from the parent block?
+    is_gradient: bool,
     marker: std::marker::PhantomData<&'a mut mts_block_t>,
 }
 
@@ -73,10 +75,21 @@ impl<'a> TensorBlockRefMut<'a> {
 
         TensorBlockRefMut {
             ptr: ptr,
+            is_gradient: false,
             marker: std::marker::PhantomData,
         }
     }
 
+    /// Create a new `TensorBlockRefMut` from the given raw `mts_block_t`,
+    /// which is a gradient block stored inside another block.
+    ///
+    /// The same safety requirements as [`TensorBlockRefMut::from_raw`] apply.
+    unsafe fn gradient_from_raw(ptr: *mut mts_block_t) -> TensorBlockRefMut<'a> {
+        let mut block = TensorBlockRefMut::from_raw(ptr);
+        block.is_gradient = true;
+        return block;
+    }
+
     /// Get the underlying raw pointer
     pub(super) fn as_ptr(&self) -> *const mts_block_t {
         self.ptr
@@ -145,14 +158,59 @@ impl<'a> TensorBlockRefMut<'a> {
         return self.as_ref().components();
     }
 
+    /// Get the labels for the component at the given `index` in this block,
+    /// or `None` if the block has fewer components.
+    #[inline]
+    pub fn component(&self, index: usize) -> Option<Labels> {
+        return self.as_ref().component(index);
+    }
+
+    /// Get the index and labels of the component named `name` in this block,
+    /// or `None` if there is no such component.
+    #[inline]
+    pub fn component_by_name(&self, name: &str) -> Option<(usize, Labels)> {
+        return self.as_ref().component_by_name(name);
+    }
+
     /// Get the properties for this block
     #[inline]
     pub fn properties(&self) -> Labels {
         return self.as_ref().properties();
     }
 
+    /// Get the physical units of the values in this block, if any
+    #[inline]
+    pub fn units(&self) -> Option<&str> {
+        return self.as_ref().units();
+    }
+
+    /// Set the physical units of the values in this block to `units`, or
+    /// remove them if `units` is `None`.
+    ///
+    /// The units are only stored as a free-form string (e.g. `"eV"` or
+    /// `"kcal/mol"`), and not interpreted by metatensor. They are kept when
+    /// saving and loading tensor maps.
+    #[inline]
+    pub fn set_units(&mut self, units: Option<&str>) -> Result<(), Error> {
+        let units = units.map(CString::new).transpose().map_err(|_| Error {
+            code: None,
+            message: "units can not contain NULL bytes".into(),
+        })?;
+
+        let units_ptr = units.as_ref().map_or(std::ptr::null(), |units| units.as_ptr());
+        unsafe {
+            crate::errors::check_status(crate::c_api::mts_block_set_units(
+                self.as_mut_ptr(),
+                units_ptr,
+            ))
+        }
+    }
+
     /// Get a mutable reference to the data and metadata for the gradient with
     /// respect to the given parameter in this block, if it exists.
+    ///
+    /// As for [`TensorBlockRef::gradient`], gradients of gradients can be
+    /// accessed by chaining calls to this function.
     #[inline]
     pub fn gradient_mut(&mut self, parameter: &str) -> Option<TensorBlockRefMut<'_>> {
         let parameter = CString::new(parameter).expect("invalid C string");
@@ -161,7 +219,7 @@ impl<'a> TensorBlockRefMut<'a> {
             .map(|gradient_block| {
                 // SAFETY: we are returning an `TensorBlockRefMut` mutably
                 // borrowing from `self`
-                unsafe { TensorBlockRefMut::from_raw(gradient_block) }
+                unsafe { TensorBlockRefMut::gradient_from_raw(gradient_block) }
             })
     }
 
@@ -175,6 +233,240 @@ impl<'a> TensorBlockRefMut<'a> {
             block: block_ptr,
         }
     }
+
+    /// Make sure the values and gradients of this block are stored in
+    /// standard (C-contiguous) layout, copying the data if needed.
+    ///
+    /// Arrays created through the [`Array`](crate::Array) trait can use a
+    /// different memory layout, for example after `mts_array_t.swap_axes`,
+    /// and are copied to a new array with the standard layout. Arrays with a
+    /// different origin must already be contiguous and are not modified.
+    /// Calling this function before using [`TensorBlockRefMut::values_mut`]
+    /// and `mts_array_t.data` ensures the data can be accessed as a single
+    /// slice.
+    #[inline]
+    pub fn to_contiguous(&mut self) -> Result<(), Error> {
+        self.values_mut().make_contiguous()?;
+        for (_, mut gradient) in self.gradients_mut() {
+            gradient.to_contiguous()?;
+        }
+        return Ok(());
+    }
+
+    /// Re-order the properties of this block to follow `order`, permuting
+    /// the values and all gradients accordingly.
+    ///
+    /// `order` must have the same names as the properties of this block, and
+    /// contain each of the existing properties exactly once. This returns an
+    /// error for gradient blocks, since their properties must stay the same as
+    /// the properties of the parent block.
+    #[inline]
+    pub fn reorder_properties(&mut self, order: &Labels) -> Result<(), Error> {
+        unsafe {
+            crate::errors::check_status(crate::c_api::mts_block_reorder_properties(
+                self.as_mut_ptr(),
+                order.as_mts_labels_t(),
+            ))
+        }
+    }
+
+    /// Replace the samples of this block with `samples`, without modifying
+    /// the data. This can be used to change the values of the samples in
+    /// place, for example to renumber systems.
+    ///
+    /// `samples` must have the same names and the same number of entries as
+    /// the current samples, since they describe the same data. Use a
+    /// dedicated function (e.g. to append or remove samples) if you need to
+    /// change the number of samples.
+    ///
+    /// This returns an error for gradient blocks, use
+    /// [`TensorBlockRefMut::set_gradient_samples`] on the parent block to
+    /// set the samples of a gradient.
+    #[inline]
+    pub fn set_samples(&mut self, samples: &Labels) -> Result<(), Error> {
+        unsafe {
+            crate::errors::check_status(crate::c_api::mts_block_set_labels(
+                self.as_mut_ptr(),
+                0,
+                samples.as_mts_labels_t(),
+            ))
+        }
+    }
+
+    /// Replace the samples of the gradient with respect to `parameter` in
+    /// this block with `samples`, without modifying the data.
+    ///
+    /// `samples` must have the same names and the same number of entries as
+    /// the current gradient samples, and the values of the first dimension
+    /// (`sample`) must refer to existing samples of this block.
+    #[inline]
+    pub fn set_gradient_samples(&mut self, parameter: &str, samples: &Labels) -> Result<(), Error> {
+        let parameter = CString::new(parameter).expect("invalid C string");
+        unsafe {
+            crate::errors::check_status(crate::c_api::mts_block_set_gradient_samples(
+                self.as_mut_ptr(),
+                parameter.as_ptr(),
+                samples.as_mts_labels_t(),
+            ))
+        }
+    }
+
+    /// Replace the properties of this block and all of its gradients with
+    /// `properties`, without modifying the data.
+    ///
+    /// `properties` must have the same names and the same number of entries
+    /// as the current properties. Use [`TensorBlockRefMut::reorder_properties`]
+    /// to move the data together with the properties. This returns an error
+    /// for gradient blocks, since their properties must stay the same as the
+    /// properties of the parent block.
+    #[inline]
+    pub fn set_properties(&mut self, properties: &Labels) -> Result<(), Error> {
+        let axis = self.components().len() + 1;
+        unsafe {
+            crate::errors::check_status(crate::c_api::mts_block_set_labels(
+                self.as_mut_ptr(),
+                axis,
+                properties.as_mts_labels_t(),
+            ))
+        }
+    }
+
+    /// Add new `samples` at the end of this block, together with the
+    /// corresponding `data`.
+    ///
+    /// The new `samples` must have the same names as the existing samples, and
+    /// must not contain any of them. `data` must have the same shape as the
+    /// values of this block, except for the first dimension. This returns an
+    /// error if the block contains gradients.
+    #[inline]
+    pub fn append_samples(&mut self, samples: &Labels, data: impl Array) -> Result<(), Error> {
+        let data = (Box::new(data) as Box<dyn Array>).into();
+        unsafe {
+            crate::errors::check_status(crate::c_api::mts_block_append_samples(
+                self.as_mut_ptr(),
+                samples.as_mts_labels_t(),
+                data,
+            ))
+        }
+    }
+
+    /// Restore the consistency between the samples of this block and the
+    /// samples of its gradients.
+    ///
+    /// Gradient samples referring to values samples which no longer exist are
+    /// removed, and the remaining gradient samples are re-ordered to follow
+    /// the order of the values samples. This is applied recursively to
+    /// gradients of gradients.
+    #[inline]
+    pub fn compact_gradients(&mut self) -> Result<(), Error> {
+        unsafe {
+            crate::errors::check_status(crate::c_api::mts_block_compact_gradients(
+                self.as_mut_ptr(),
+            ))
+        }
+    }
+
+    /// Normalize the values of each sample in this block to unit L2 norm,
+    /// where the norm is computed over all the components and properties of
+    /// the sample.
+    ///
+    /// Samples with a norm of zero are left unchanged, or if
+    /// `error_on_zero_norm` is `true`, this function returns an error without
+    /// modifying the block. The gradients are updated to be the gradients of
+    /// the normalized values, using the quotient rule. Gradients of gradients
+    /// are not supported, and this function returns an error when called on a
+    /// gradient block, since normalizing the gradients on their own would not
+    /// be consistent with the values of the parent block.
+    ///
+    /// The data is accessed through `mts_array_t.data`, see the [crate
+    /// documentation](crate#accessing-the-data). Arrays which are not stored in
+    /// standard layout are made contiguous first (see
+    /// [`TensorBlockRefMut::to_contiguous`]).
+    pub fn normalize_samples(&mut self, error_on_zero_norm: bool) -> Result<(), Error> {
+        if self.is_gradient {
+            return Err(Error {
+                code: None,
+                message: "can not normalize the samples of a gradient block, normalize the parent block instead".into(),
+            });
+        }
+
+        for (_, gradient) in self.as_ref().gradients() {
+            if !gradient.gradient_list().is_empty() {
+                return Err(Error {
+                    code: None,
+                    message: "gradients of gradients are not supported in normalize_samples".into(),
+                });
+            }
+        }
+
+        self.to_contiguous()?;
+
+        let samples = self.samples();
+        let mut values = self.values_mut();
+        let data = values.as_raw_mut().data()?;
+        let n_features = if samples.count() == 0 { 0 } else { data.len() / samples.count() };
+
+        let mut norms = Vec::with_capacity(samples.count());
+        for (sample_i, sample) in samples.iter().enumerate() {
+            let values = &data[sample_i * n_features..(sample_i + 1) * n_features];
+            let norm = values.iter().map(|v| v * v).sum::<f64>().sqrt();
+            if norm == 0.0 && error_on_zero_norm {
+                return Err(Error {
+                    code: None,
+                    message: format!(
+                        "can not normalize samples: sample ({}) has a norm of zero",
+                        sample.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+                    ),
+                });
+            }
+            norms.push(norm);
+        }
+
+        for (sample_i, &norm) in norms.iter().enumerate() {
+            if norm != 0.0 {
+                for value in &mut data[sample_i * n_features..(sample_i + 1) * n_features] {
+                    *value /= norm;
+                }
+            }
+        }
+
+        if n_features == 0 || self.as_ref().gradient_list().is_empty() {
+            return Ok(());
+        }
+
+        // d(x / |x|) = dx / |x| - x (x . dx) / |x|^3 = (dx - y (y . dx)) / |x|
+        // with y = x / |x| the normalized values
+        let normalized = self.values_mut().as_raw_mut().data()?.to_vec();
+        for (_, mut gradient) in self.gradients_mut() {
+            let gradient_samples = gradient.samples();
+            let mut gradient_values = gradient.values_mut();
+            let data = gradient_values.as_raw_mut().data()?;
+            if gradient_samples.count() == 0 {
+                continue;
+            }
+
+            let row_size = data.len() / gradient_samples.count();
+            for (row, gradient_sample) in data.chunks_exact_mut(row_size).zip(&gradient_samples) {
+                let sample_i = gradient_sample[0].usize();
+                let norm = norms[sample_i];
+                if norm == 0.0 {
+                    continue;
+                }
+
+                let y = &normalized[sample_i * n_features..(sample_i + 1) * n_features];
+                // the gradient-specific components come first, so each row
+                // contains multiple vectors with the same shape as the values
+                for dx in row.chunks_exact_mut(n_features) {
+                    let dot = dx.iter().zip(y).map(|(a, b)| a * b).sum::<f64>();
+                    for (dx_i, y_i) in dx.iter_mut().zip(y) {
+                        *dx_i = (*dx_i - y_i * dot) / norm;
+                    }
+                }
+            }
+        }
+
+        return Ok(());
+    }
 }
 
 /// Iterator over parameter/[`TensorBlockRefMut`] pairs for all gradients in a
@@ -196,7 +488,7 @@ impl<'a> Iterator for GradientsMutIter<'a> {
             // SAFETY: all blocks are disjoint, and we are only returning a
             // mutable reference to each once. The reference lifetime is
             // constrained by the lifetime of the parent TensorBlockRefMut
-            let block = unsafe { TensorBlockRefMut::from_raw(block) };
+            let block = unsafe { TensorBlockRefMut::gradient_from_raw(block) };
             return (parameter, block);
         })
     }
@@ -217,5 +509,333 @@ impl<'a> FusedIterator for GradientsMutIter<'a> {}
 
 #[cfg(test)]
 mod tests {
-    // TODO
+    use crate::{Labels, TensorBlock};
+
+    #[test]
+    fn reorder_properties() {
+        let mut block = TensorBlock::new(
+            ndarray::arr2(&[[0.0, 1.0, 2.0], [10.0, 11.0, 12.0]]).into_dyn(),
+            &Labels::new(["samples"], &[[0], [1]]),
+            &[],
+            &Labels::new(["properties"], &[[0], [1], [2]]),
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            ndarray::arr3(&[[[0.0, 1.0, 2.0]]]).into_dyn(),
+            &Labels::new(["sample"], &[[1]]),
+            &[Labels::new(["xyz"], &[[0]])],
+            &Labels::new(["properties"], &[[0], [1], [2]]),
+        ).unwrap();
+        block.add_gradient("g", gradient).unwrap();
+
+        let mut block = block.as_ref_mut();
+        block.reorder_properties(&Labels::new(["properties"], &[[1], [2], [0]])).unwrap();
+
+        assert_eq!(block.properties(), Labels::new(["properties"], &[[1], [2], [0]]));
+        assert_eq!(
+            block.values().as_array(),
+            ndarray::arr2(&[[1.0, 2.0, 0.0], [11.0, 12.0, 10.0]]).into_dyn()
+        );
+
+        // the new values use the standard layout, and can be accessed as a
+        // single slice
+        assert!(block.values().as_array().is_standard_layout());
+        assert_eq!(block.values_mut().as_raw_mut().data().unwrap(), [1.0, 2.0, 0.0, 11.0, 12.0, 10.0]);
+
+        let mut gradient = block.gradient_mut("g").unwrap();
+        assert_eq!(gradient.properties(), Labels::new(["properties"], &[[1], [2], [0]]));
+        assert_eq!(
+            gradient.values().as_array(),
+            ndarray::arr3(&[[[1.0, 2.0, 0.0]]]).into_dyn()
+        );
+        assert!(gradient.values().as_array().is_standard_layout());
+
+        let error = gradient.reorder_properties(&Labels::new(["properties"], &[[0], [1], [2]])).unwrap_err();
+        assert_eq!(
+            error.message,
+            "invalid parameter: can not reorder the properties of a gradient \
+            block, reorder the properties of the parent block instead"
+        );
+
+        let error = block.reorder_properties(&Labels::new(["properties"], &[[1], [2]])).unwrap_err();
+        assert_eq!(
+            error.message,
+            "invalid parameter: can not reorder properties: expected 3 entries \
+            in the new order, got 2"
+        );
+    }
+
+    #[test]
+    fn set_labels() {
+        let mut block = TensorBlock::new(
+            ndarray::arr2(&[[0.0, 1.0], [10.0, 11.0]]).into_dyn(),
+            &Labels::new(["system"], &[[0], [1]]),
+            &[],
+            &Labels::new(["properties"], &[[0], [1]]),
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            ndarray::arr3(&[[[0.0, 1.0]]]).into_dyn(),
+            &Labels::new(["sample"], &[[1]]),
+            &[Labels::new(["xyz"], &[[0]])],
+            &Labels::new(["properties"], &[[0], [1]]),
+        ).unwrap();
+        block.add_gradient("g", gradient).unwrap();
+
+        let mut block = block.as_ref_mut();
+        block.set_samples(&Labels::new(["system"], &[[5], [6]])).unwrap();
+        assert_eq!(block.samples(), Labels::new(["system"], &[[5], [6]]));
+
+        block.set_properties(&Labels::new(["properties"], &[[3], [4]])).unwrap();
+        assert_eq!(block.properties(), Labels::new(["properties"], &[[3], [4]]));
+        assert_eq!(
+            block.values().as_array(),
+            ndarray::arr2(&[[0.0, 1.0], [10.0, 11.0]]).into_dyn()
+        );
+
+        let gradient = block.gradient_mut("g").unwrap();
+        assert_eq!(gradient.samples(), Labels::new(["sample"], &[[1]]));
+        assert_eq!(gradient.properties(), Labels::new(["properties"], &[[3], [4]]));
+
+        let error = block.set_samples(&Labels::new(["system"], &[[5]])).unwrap_err();
+        assert_eq!(
+            error.message,
+            "invalid parameter: can not set samples: expected 2 entries, got 1"
+        );
+
+        block.set_gradient_samples("g", &Labels::new(["sample"], &[[0]])).unwrap();
+        assert_eq!(block.gradient_mut("g").unwrap().samples(), Labels::new(["sample"], &[[0]]));
+
+        let error = block.set_gradient_samples("g", &Labels::new(["sample"], &[[99]])).unwrap_err();
+        assert_eq!(
+            error.message,
+            "invalid parameter: invalid value for the 'sample' in gradient \
+            samples: we got 99, but the values contain 2 samples"
+        );
+
+        let mut gradient = block.gradient_mut("g").unwrap();
+        let error = gradient.set_samples(&Labels::new(["sample"], &[[99]])).unwrap_err();
+        assert_eq!(
+            error.message,
+            "invalid parameter: can not set the samples of a gradient block \
+            directly, they must be set from the parent block"
+        );
+
+        let error = gradient.set_properties(&Labels::new(["properties"], &[[0], [1]])).unwrap_err();
+        assert_eq!(
+            error.message,
+            "invalid parameter: can not set the properties of a gradient \
+            block, set the properties of the parent block instead"
+        );
+    }
+
+    #[test]
+    fn append_samples() {
+        let mut block = TensorBlock::new(
+            ndarray::arr2(&[[0.0, 1.0], [10.0, 11.0]]).into_dyn(),
+            &Labels::new(["samples"], &[[0], [1]]),
+            &[],
+            &Labels::new(["properties"], &[[0], [1]]),
+        ).unwrap();
+
+        let mut block = block.as_ref_mut();
+        block.append_samples(
+            &Labels::new(["samples"], &[[4]]),
+            ndarray::arr2(&[[40.0, 41.0]]).into_dyn(),
+        ).unwrap();
+
+        assert_eq!(block.samples(), Labels::new(["samples"], &[[0], [1], [4]]));
+        assert_eq!(
+            block.values().as_array(),
+            ndarray::arr2(&[[0.0, 1.0], [10.0, 11.0], [40.0, 41.0]]).into_dyn()
+        );
+
+        let error = block.append_samples(
+            &Labels::new(["samples"], &[[1]]),
+            ndarray::arr2(&[[10.0, 11.0]]).into_dyn(),
+        ).unwrap_err();
+        assert_eq!(
+            error.message,
+            "invalid parameter: can not have the same label value multiple \
+            time: [1] is already present at position 1"
+        );
+
+        let gradient = TensorBlock::new(
+            ndarray::arr2(&[[0.0, 1.0]]).into_dyn(),
+            &Labels::new(["sample"], &[[0]]),
+            &[],
+            &Labels::new(["properties"], &[[0], [1]]),
+        ).unwrap();
+        let mut block = TensorBlock::new(
+            ndarray::arr2(&[[0.0, 1.0]]).into_dyn(),
+            &Labels::new(["samples"], &[[0]]),
+            &[],
+            &Labels::new(["properties"], &[[0], [1]]),
+        ).unwrap();
+        block.add_gradient("g", gradient).unwrap();
+
+        let error = block.append_samples(
+            &Labels::new(["samples"], &[[1]]),
+            ndarray::arr2(&[[10.0, 11.0]]).into_dyn(),
+        ).unwrap_err();
+        assert_eq!(error.message, "invalid parameter: can not append samples to a block with gradients");
+    }
+
+    #[test]
+    fn compact_gradients() {
+        let mut block = TensorBlock::new(
+            ndarray::arr2(&[[0.0, 1.0], [10.0, 11.0], [20.0, 21.0]]).into_dyn(),
+            &Labels::new(["samples"], &[[0], [1], [2]]),
+            &[],
+            &Labels::new(["properties"], &[[0], [1]]),
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            ndarray::arr2(&[[20.0, 21.0], [0.0, 1.0], [10.0, 11.0]]).into_dyn(),
+            &Labels::new(["sample"], &[[2], [0], [1]]),
+            &[],
+            &Labels::new(["properties"], &[[0], [1]]),
+        ).unwrap();
+        block.add_gradient("g", gradient).unwrap();
+
+        let mut block = block.as_ref_mut();
+        block.compact_gradients().unwrap();
+
+        let gradient = block.gradient_mut("g").unwrap();
+        assert_eq!(gradient.samples(), Labels::new(["sample"], &[[0], [1], [2]]));
+        assert_eq!(
+            gradient.values().as_array(),
+            ndarray::arr2(&[[0.0, 1.0], [10.0, 11.0], [20.0, 21.0]]).into_dyn()
+        );
+
+        // this is a no-op if the gradients are already consistent
+        block.compact_gradients().unwrap();
+        let gradient = block.gradient_mut("g").unwrap();
+        assert_eq!(gradient.samples(), Labels::new(["sample"], &[[0], [1], [2]]));
+    }
+
+    #[test]
+    fn normalize_samples() {
+        let mut block = TensorBlock::new(
+            ndarray::arr2(&[[3.0, 4.0], [0.0, 0.0]]).into_dyn(),
+            &Labels::new(["system"], &[[0], [1]]),
+            &[],
+            &Labels::new(["properties"], &[[0], [1]]),
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            ndarray::arr3(&[[[1.0, 0.0], [0.0, 1.0]], [[1.0, 1.0], [1.0, 1.0]]]).into_dyn(),
+            &Labels::new(["sample"], &[[0], [1]]),
+            &[Labels::new(["xyz"], &[[0], [1]])],
+            &Labels::new(["properties"], &[[0], [1]]),
+        ).unwrap();
+        block.add_gradient("g", gradient).unwrap();
+
+        let error = block.as_ref_mut().normalize_samples(true).unwrap_err();
+        assert_eq!(error.message, "can not normalize samples: sample (1) has a norm of zero");
+
+        let mut block_mut = block.as_ref_mut();
+        let error = block_mut.gradient_mut("g").unwrap().normalize_samples(false).unwrap_err();
+        assert_eq!(error.message, "can not normalize the samples of a gradient block, normalize the parent block instead");
+        for (_, mut gradient) in block_mut.gradients_mut() {
+            assert!(gradient.normalize_samples(false).is_err());
+        }
+        assert_eq!(block.values().as_array(), ndarray::arr2(&[[3.0, 4.0], [0.0, 0.0]]).into_dyn());
+
+        block.as_ref_mut().normalize_samples(false).unwrap();
+        assert_eq!(block.values().as_array(), ndarray::arr2(&[[0.6, 0.8], [0.0, 0.0]]).into_dyn());
+
+        // gradient of x / |x| with x = (3, 4), for dx = (1, 0) and (0, 1)
+        let block = block.as_ref();
+        let gradient = block.gradient("g").unwrap();
+        let expected = ndarray::arr3(&[
+            [[16.0 / 125.0, -12.0 / 125.0], [-12.0 / 125.0, 9.0 / 125.0]],
+            [[1.0, 1.0], [1.0, 1.0]],
+        ]).into_dyn();
+        let values = gradient.values();
+        let difference = values.as_array() - &expected;
+        assert!(difference.iter().all(|d: &f64| d.abs() < 1e-12));
+    }
+
+    #[test]
+    fn normalize_samples_non_contiguous() {
+        let values = ndarray::arr2(&[[3.0, 0.0], [4.0, 1.0]]).reversed_axes().into_dyn();
+        assert!(!values.is_standard_layout());
+        let mut block = TensorBlock::new(
+            values,
+            &Labels::new(["system"], &[[0], [1]]),
+            &[],
+            &Labels::new(["properties"], &[[0], [1]]),
+        ).unwrap();
+
+        block.as_ref_mut().normalize_samples(true).unwrap();
+        assert!(block.values().as_array().is_standard_layout());
+        assert_eq!(block.values().as_array(), ndarray::arr2(&[[0.6, 0.8], [0.0, 1.0]]).into_dyn());
+    }
+
+    #[test]
+    fn to_contiguous() {
+        let values = ndarray::arr2(&[[1.0, 2.0], [3.0, 4.0], [5.0, 6.0]]).reversed_axes().into_dyn();
+        assert!(!values.is_standard_layout());
+        let mut block = TensorBlock::new(
+            values.clone(),
+            &Labels::new(["system"], &[[0], [1]]),
+            &[],
+            &Labels::new(["properties"], &[[0], [1], [2]]),
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            values.clone(),
+            &Labels::new(["sample"], &[[0], [1]]),
+            &[],
+            &Labels::new(["properties"], &[[0], [1], [2]]),
+        ).unwrap();
+        block.add_gradient("g", gradient).unwrap();
+
+        block.to_contiguous().unwrap();
+        assert!(block.values().as_array().is_standard_layout());
+        assert_eq!(block.values().as_array(), values);
+
+        let mut block = block.as_ref_mut();
+        assert_eq!(block.values_mut().as_raw_mut().data().unwrap(), [1.0, 3.0, 5.0, 2.0, 4.0, 6.0]);
+
+        let gradient = block.gradient_mut("g").unwrap();
+        assert!(gradient.values().as_array().is_standard_layout());
+        assert_eq!(gradient.values().as_array(), values);
+
+        let values = ndarray::arr2(&[[1, 2], [3, 4], [5, 6]]).reversed_axes().into_dyn();
+        let mut block = TensorBlock::new(
+            values.clone(),
+            &Labels::new(["system"], &[[0], [1]]),
+            &[],
+            &Labels::new(["properties"], &[[0], [1], [2]]),
+        ).unwrap();
+
+        block.to_contiguous().unwrap();
+        let array = block.values();
+        let array = array.as_any().downcast_ref::<ndarray::ArrayD<i32>>().unwrap();
+        assert!(array.is_standard_layout());
+        assert_eq!(array, values);
+    }
+
+    #[test]
+    fn units() {
+        let mut block = TensorBlock::new(
+            ndarray::arr2(&[[1.0]]).into_dyn(),
+            &Labels::new(["system"], &[[0]]),
+            &[],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap();
+        assert_eq!(block.as_ref().units(), None);
+
+        let mut block = block.as_ref_mut();
+        block.set_units(Some("eV")).unwrap();
+        assert_eq!(block.units(), Some("eV"));
+
+        block.set_units(None).unwrap();
+        assert_eq!(block.units(), None);
+
+        let error = block.set_units(Some("e\0V")).unwrap_err();
+        assert_eq!(error.message, "units can not contain NULL bytes");
+    }
 }
@@ -166,6 +166,29 @@ impl<'a> TensorBlockRef<'a> {
         return result;
     }
 
+    /// Get the labels for the component at the given `index` in this block,
+    /// or `None` if the block has fewer components.
+    #[inline]
+    pub fn component(&self, index: usize) -> Option<Labels> {
+        let values = self.values();
+        let shape = values.as_raw().shape().expect("failed to get the data shape");
+
+        if index + 2 < shape.len() {
+            return Some(self.labels(index + 1));
+        } else {
+            return None;
+        }
+    }
+
+    /// Get the index and labels of the component named `name` in this block,
+    /// or `None` if there is no such component.
+    #[inline]
+    pub fn component_by_name(&self, name: &str) -> Option<(usize, Labels)> {
+        return self.components().into_iter()
+            .enumerate()
+            .find(|(_, component)| component.names() == [name]);
+    }
+
     /// Get the properties for this block
     #[inline]
     pub fn properties(&self) -> Labels {
@@ -175,6 +198,30 @@ impl<'a> TensorBlockRef<'a> {
         return self.labels(shape.len() - 1);
     }
 
+    /// Get the physical units of the values in this block, if they have been
+    /// set with [`TensorBlockRefMut::set_units`](crate::TensorBlockRefMut::set_units).
+
+    // SAFETY: we can return a string with the `'a` lifetime for the same
+    // reasons as in `gradient_list`.
+    #[inline]
+    pub fn units(&self) -> Option<&'a str> {
+        let mut units = std::ptr::null();
+        unsafe {
+            check_status(crate::c_api::mts_block_units(
+                self.as_ptr(),
+                &mut units,
+            )).expect("failed to get the block units");
+        }
+
+        if units.is_null() {
+            return None;
+        } else {
+            unsafe {
+                return Some(CStr::from_ptr(units).to_str().expect("invalid UTF8"));
+            }
+        }
+    }
+
     /// Get the full list of gradients in this block
 
     // SAFETY: we can return strings with the `'a` lifetime (instead of
@@ -207,6 +254,10 @@ impl<'a> TensorBlockRef<'a> {
 
     /// Get the data and metadata for the gradient with respect to the given
     /// parameter in this block, if it exists.
+    ///
+    /// Gradients can themselves contain gradients, for example to store
+    /// second derivatives. These can be accessed by chaining calls, e.g.
+    /// `block.gradient("positions")?.gradient("positions")`.
 
     // SAFETY: we can return a TensorBlockRef with lifetime `'a` (instead of
     // `'self`) for the same reasons as in the `values` function.
@@ -223,6 +274,80 @@ impl<'a> TensorBlockRef<'a> {
         })
     }
 
+    /// Get the components which are only present in the gradient with
+    /// respect to `parameter`, and not in the values of this block.
+    ///
+    /// The components of a gradient are made of some gradient-specific
+    /// components (for example the Cartesian direction for gradients with
+    /// respect to positions), followed by the same components as the values.
+    /// This function returns the former, and an error if there is no gradient
+    /// with respect to `parameter` in this block.
+    pub fn gradient_specific_components(&self, parameter: &str) -> Result<Vec<Labels>, Error> {
+        let gradient = self.gradient(parameter).ok_or_else(|| Error {
+            code: None,
+            message: format!("this block does not have gradients with respect to '{}'", parameter),
+        })?;
+
+        let mut components = gradient.components();
+        let n_specific = components.len() - self.components().len();
+        components.truncate(n_specific);
+
+        return Ok(components);
+    }
+
+    /// Check if this block and `other` have the same metadata, without
+    /// comparing the data.
+    ///
+    /// Two blocks are structurally equal if they have the same samples,
+    /// components and properties (same names and same entries in the same
+    /// order), and gradients with respect to the same parameters, which are
+    /// themselves structurally equal. This can be used to check that two
+    /// blocks can be added together.
+    pub fn structurally_equal(&self, other: TensorBlockRef<'_>) -> bool {
+        let shape = self.values().as_raw().shape().expect("failed to get the data shape").len();
+        let other_shape = other.values().as_raw().shape().expect("failed to get the data shape").len();
+        if shape != other_shape {
+            return false;
+        }
+
+        for dimension in 0..shape {
+            if self.labels(dimension) != other.labels(dimension) {
+                return false;
+            }
+        }
+
+        let parameters = self.gradient_list();
+        if parameters.len() != other.gradient_list().len() {
+            return false;
+        }
+
+        for parameter in parameters {
+            let gradient = self.gradient(parameter).expect("missing gradient");
+            match other.gradient(parameter) {
+                Some(other_gradient) => {
+                    if !gradient.structurally_equal(other_gradient) {
+                        return false;
+                    }
+                }
+                None => return false,
+            }
+        }
+
+        return true;
+    }
+
+    /// Get the estimated size in bytes of the data (values and gradients) in
+    /// this block, computed from the shape of the arrays and the size of a
+    /// single element.
+    #[inline]
+    pub fn nbytes(&self) -> Result<usize, Error> {
+        let mut nbytes = 0;
+        unsafe {
+            check_status(crate::c_api::mts_block_nbytes(self.as_ptr(), &mut nbytes))?;
+        }
+        return Ok(nbytes);
+    }
+
     /// Clone this block, cloning all the data and metadata contained inside.
     ///
     /// This can fail if the external data held inside an `mts_array_t` can not
@@ -237,6 +362,21 @@ impl<'a> TensorBlockRef<'a> {
         return Ok(unsafe { TensorBlock::from_raw(ptr) });
     }
 
+    /// Create a new block with the same metadata as this one (including
+    /// gradients), and all the data filled with zeros.
+    ///
+    /// The new arrays are created with `mts_array_t.create` from the arrays in
+    /// this block, so they use the same array type.
+    #[inline]
+    pub fn zeros_like(&self) -> Result<TensorBlock, Error> {
+        let ptr = unsafe {
+            crate::c_api::mts_block_zeros_like(self.as_ptr())
+        };
+        crate::errors::check_ptr(ptr)?;
+
+        return Ok(unsafe { TensorBlock::from_raw(ptr) });
+    }
+
     /// Get an iterator over parameter/[`TensorBlockRef`] pairs for all gradients in
     /// this block
     #[inline]
@@ -288,5 +428,182 @@ impl<'a> FusedIterator for GradientsIter<'a> {}
 
 #[cfg(test)]
 mod tests {
-    // TODO: check gradient/gradient iter code
+    use crate::{Labels, TensorBlock};
+
+    #[test]
+    fn components() {
+        let block = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![1, 3, 2, 1], 1.0),
+            &Labels::new(["system"], &[[0]]),
+            &[Labels::new(["m"], &[[-1], [0], [1]]), Labels::new(["xyz"], &[[0], [1]])],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap();
+        let block = block.as_ref();
+
+        assert_eq!(block.component(0).unwrap(), Labels::new(["m"], &[[-1], [0], [1]]));
+        assert_eq!(block.component(1).unwrap(), Labels::new(["xyz"], &[[0], [1]]));
+        assert!(block.component(2).is_none());
+
+        let (index, component) = block.component_by_name("xyz").unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(component, Labels::new(["xyz"], &[[0], [1]]));
+        assert!(block.component_by_name("properties").is_none());
+
+        let block = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![1, 1], 1.0),
+            &Labels::new(["system"], &[[0]]),
+            &[],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap();
+        assert!(block.component(0).is_none());
+        assert!(block.component_by_name("m").is_none());
+    }
+
+    #[test]
+    fn gradient_specific_components() {
+        let mut block = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![1, 3, 1], 1.0),
+            &Labels::new(["system"], &[[0]]),
+            &[Labels::new(["m"], &[[-1], [0], [1]])],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![1, 3, 3, 1], 1.0),
+            &Labels::new(["sample", "atom"], &[[0, 0]]),
+            &[Labels::new(["xyz"], &[[0], [1], [2]]), Labels::new(["m"], &[[-1], [0], [1]])],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap();
+        block.add_gradient("positions", gradient).unwrap();
+
+        let gradient = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![1, 3, 1], 1.0),
+            &Labels::new(["sample"], &[[0]]),
+            &[Labels::new(["m"], &[[-1], [0], [1]])],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap();
+        block.add_gradient("temperature", gradient).unwrap();
+
+        assert_eq!(
+            block.gradient_specific_components("positions").unwrap(),
+            [Labels::new(["xyz"], &[[0], [1], [2]])],
+        );
+        assert!(block.gradient_specific_components("temperature").unwrap().is_empty());
+
+        let error = block.gradient_specific_components("cell").unwrap_err();
+        assert_eq!(error.message, "this block does not have gradients with respect to 'cell'");
+    }
+
+    #[test]
+    fn structurally_equal() {
+        let create_block = |values: f64, properties: &[[i32; 1]], gradient: Option<&str>| {
+            let mut block = TensorBlock::new(
+                ndarray::ArrayD::from_elem(vec![2, 3, properties.len()], values),
+                &Labels::new(["system"], &[[0], [1]]),
+                &[Labels::new(["m"], &[[-1], [0], [1]])],
+                &Labels::new(["n"], properties),
+            ).unwrap();
+
+            if let Some(parameter) = gradient {
+                let gradient = TensorBlock::new(
+                    ndarray::ArrayD::from_elem(vec![1, 3, properties.len()], values),
+                    &Labels::new(["sample"], &[[1]]),
+                    &[Labels::new(["m"], &[[-1], [0], [1]])],
+                    &Labels::new(["n"], properties),
+                ).unwrap();
+                block.add_gradient(parameter, gradient).unwrap();
+            }
+
+            block
+        };
+
+        let block = create_block(1.0, &[[0], [1]], Some("g"));
+
+        // the data is not compared
+        assert!(block.structurally_equal(&create_block(2.0, &[[0], [1]], Some("g"))));
+
+        assert!(!block.structurally_equal(&create_block(1.0, &[[1], [0]], Some("g"))));
+        assert!(!block.structurally_equal(&create_block(1.0, &[[0]], Some("g"))));
+        assert!(!block.structurally_equal(&create_block(1.0, &[[0], [1]], None)));
+        assert!(!block.structurally_equal(&create_block(1.0, &[[0], [1]], Some("other"))));
+
+        let other = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![2, 2], 1.0),
+            &Labels::new(["system"], &[[0], [1]]),
+            &[],
+            &Labels::new(["n"], &[[0], [1]]),
+        ).unwrap();
+        assert!(!block.structurally_equal(&other));
+    }
+
+    #[test]
+    fn zeros_like() {
+        let mut block = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![2, 3, 1], 1.0),
+            &Labels::new(["system"], &[[0], [1]]),
+            &[Labels::new(["m"], &[[-1], [0], [1]])],
+            &Labels::new(["n"], &[[0]]),
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![1, 3, 1], 2.0),
+            &Labels::new(["sample"], &[[1]]),
+            &[Labels::new(["m"], &[[-1], [0], [1]])],
+            &Labels::new(["n"], &[[0]]),
+        ).unwrap();
+        block.add_gradient("g", gradient).unwrap();
+
+        let zeros = block.zeros_like().unwrap();
+        assert!(zeros.structurally_equal(&block));
+        assert_eq!(zeros.values().as_array(), ndarray::ArrayD::from_elem(vec![2, 3, 1], 0.0));
+
+        let gradient = zeros.as_ref().gradient("g").unwrap();
+        assert_eq!(gradient.values().as_array(), ndarray::ArrayD::from_elem(vec![1, 3, 1], 0.0));
+
+        // the original block is not modified
+        assert_eq!(block.values().as_array(), ndarray::ArrayD::from_elem(vec![2, 3, 1], 1.0));
+    }
+
+    #[test]
+    fn gradients_of_gradients() {
+        let mut block = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![2, 1], 1.0),
+            &Labels::new(["system"], &[[0], [1]]),
+            &[],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap();
+
+        let mut gradient = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![2, 3, 1], 2.0),
+            &Labels::new(["sample", "atom"], &[[0, 0], [1, 0]]),
+            &[Labels::new(["xyz_1"], &[[0], [1], [2]])],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap();
+
+        let hessian = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![1, 3, 3, 1], 3.0),
+            &Labels::new(["sample", "atom"], &[[1, 0]]),
+            &[Labels::new(["xyz_2"], &[[0], [1], [2]]), Labels::new(["xyz_1"], &[[0], [1], [2]])],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap();
+        gradient.add_gradient("positions", hessian).unwrap();
+        block.add_gradient("positions", gradient).unwrap();
+
+        let block = block.as_ref();
+        assert_eq!(block.gradient_list(), ["positions"]);
+
+        let gradient = block.gradient("positions").unwrap();
+        assert_eq!(gradient.values().as_array().shape(), [2, 3, 1]);
+        assert_eq!(gradient.gradient_list(), ["positions"]);
+        assert!(gradient.gradient("cell").is_none());
+
+        let hessian = gradient.gradient("positions").unwrap();
+        assert_eq!(hessian.samples(), Labels::new(["sample", "atom"], &[[1, 0]]));
+        assert_eq!(hessian.values().as_array().shape(), [1, 3, 3, 1]);
+        assert!(hessian.gradient_list().is_empty());
+
+        let (parameter, gradient) = block.gradients().next().unwrap();
+        assert_eq!(parameter, "positions");
+        assert_eq!(gradient.gradients().count(), 1);
+    }
 }
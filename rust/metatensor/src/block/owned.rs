@@ -1,4 +1,4 @@
-use crate::c_api::mts_block_t;
+use crate::c_api::{mts_array_t, mts_block_t};
 use crate::errors::check_status;
 use crate::{Array, ArrayRef, Labels, Error};
 
@@ -88,12 +88,98 @@ impl TensorBlock {
         return self.as_ref().components();
     }
 
+    /// Get the labels for the component at the given `index` in this block,
+    /// or `None` if the block has fewer components.
+    #[inline]
+    pub fn component(&self, index: usize) -> Option<Labels> {
+        return self.as_ref().component(index);
+    }
+
+    /// Get the index and labels of the component named `name` in this block,
+    /// or `None` if there is no such component.
+    #[inline]
+    pub fn component_by_name(&self, name: &str) -> Option<(usize, Labels)> {
+        return self.as_ref().component_by_name(name);
+    }
+
     /// Get the properties for this block
     #[inline]
     pub fn properties(&self) -> Labels {
         return self.as_ref().properties();
     }
 
+    /// Replace the properties of this block and all of its gradients with
+    /// `properties`, without modifying the data.
+    ///
+    /// See [`TensorBlockRefMut::set_properties`] for more information.
+    #[inline]
+    pub fn set_properties(&mut self, properties: &Labels) -> Result<(), Error> {
+        return self.as_ref_mut().set_properties(properties);
+    }
+
+    /// Get the components which are only present in the gradient with
+    /// respect to `parameter`, and not in the values of this block.
+    ///
+    /// See [`TensorBlockRef::gradient_specific_components`] for more
+    /// information.
+    #[inline]
+    pub fn gradient_specific_components(&self, parameter: &str) -> Result<Vec<Labels>, Error> {
+        return self.as_ref().gradient_specific_components(parameter);
+    }
+
+    /// Check if this block and `other` have the same metadata, without
+    /// comparing the data.
+    ///
+    /// See [`TensorBlockRef::structurally_equal`] for more information.
+    #[inline]
+    pub fn structurally_equal(&self, other: &TensorBlock) -> bool {
+        return self.as_ref().structurally_equal(other.as_ref());
+    }
+
+    /// Get the estimated size in bytes of the data in this block.
+    ///
+    /// See [`TensorBlockRef::nbytes`] for more information.
+    #[inline]
+    pub fn nbytes(&self) -> Result<usize, Error> {
+        return self.as_ref().nbytes();
+    }
+
+    /// Add new `samples` at the end of this block, together with the
+    /// corresponding `data`.
+    ///
+    /// See [`TensorBlockRefMut::append_samples`] for more information.
+    #[inline]
+    pub fn append_samples(&mut self, samples: &Labels, data: impl Array) -> Result<(), Error> {
+        return self.as_ref_mut().append_samples(samples, data);
+    }
+
+    /// Create a new block with the same metadata as this one, and all the data
+    /// filled with zeros.
+    ///
+    /// See [`TensorBlockRef::zeros_like`] for more information.
+    #[inline]
+    pub fn zeros_like(&self) -> Result<TensorBlock, Error> {
+        return self.as_ref().zeros_like();
+    }
+
+    /// Normalize the values of each sample in this block to unit L2 norm,
+    /// updating the gradients accordingly.
+    ///
+    /// See [`TensorBlockRefMut::normalize_samples`] for more information.
+    #[inline]
+    pub fn normalize_samples(&mut self, error_on_zero_norm: bool) -> Result<(), Error> {
+        return self.as_ref_mut().normalize_samples(error_on_zero_norm);
+    }
+
+    /// Make sure the values and gradients of this block are stored in
+    /// standard (C-contiguous) layout, copying the data if needed.
+    ///
+    /// See [`TensorBlockRefMut::to_contiguous`] for more information.
+    #[inline]
+    pub fn to_contiguous(&mut self) -> Result<(), Error> {
+        return self.as_ref_mut().to_contiguous();
+    }
+
     /// Create a new [`TensorBlock`] containing the given data, described by the
     /// `samples`, `components`, and `properties` labels. The block is
     /// initialized without any gradients.
@@ -103,6 +189,19 @@ impl TensorBlock {
         samples: &Labels,
         components: &[Labels],
         properties: &Labels
+    ) -> Result<TensorBlock, Error> {
+        let values = (Box::new(data) as Box<dyn Array>).into();
+        return TensorBlock::from_raw_values(values, samples, components, properties);
+    }
+
+    /// Create a new [`TensorBlock`] from a raw `values` array and the
+    /// corresponding labels. The block takes ownership of `values`, even if
+    /// this function returns an error.
+    pub(crate) fn from_raw_values(
+        values: mts_array_t,
+        samples: &Labels,
+        components: &[Labels],
+        properties: &Labels,
     ) -> Result<TensorBlock, Error> {
         let mut c_components = Vec::new();
         for component in components {
@@ -111,7 +210,7 @@ impl TensorBlock {
 
         let ptr = unsafe {
             crate::c_api::mts_block(
-                (Box::new(data) as Box<dyn Array>).into(),
+                values,
                 samples.as_mts_labels_t(),
                 c_components.as_ptr(),
                 c_components.len(),
@@ -124,11 +223,28 @@ impl TensorBlock {
         return Ok(unsafe { TensorBlock::from_raw(ptr) });
     }
 
+    /// Create a new [`TensorBlock`] without any components, containing the
+    /// given 2-dimensional data, described by the `samples` and `properties`
+    /// labels. This is equivalent to calling [`TensorBlock::new`] with an
+    /// empty list of components.
+    #[inline]
+    pub fn new_scalar(
+        data: impl Array,
+        samples: &Labels,
+        properties: &Labels
+    ) -> Result<TensorBlock, Error> {
+        return TensorBlock::new(data, samples, &[], properties);
+    }
+
     /// Add a gradient with respect to `parameter` to this block.
     ///
     /// The property of the gradient should match the ones of this block. The
     /// components of the gradients must contain at least the same entries as
     /// the value components, and can prepend other components.
+    ///
+    /// The `gradient` can itself contain gradients (added with this same
+    /// function before calling `add_gradient`), to store higher order
+    /// derivatives.
     #[allow(clippy::needless_pass_by_value)]
     #[inline]
     pub fn add_gradient(
@@ -155,6 +271,31 @@ impl TensorBlock {
 
         return Ok(());
     }
+
+    /// Concatenate multiple blocks along the samples axis, creating a new
+    /// block containing a copy of all the data.
+    ///
+    /// All the `blocks` must have the same components and properties, and
+    /// gradients with respect to the same parameters. The samples of the
+    /// blocks are concatenated in the order of `blocks`, and must not contain the
+    /// same entry more than once. The `"sample"` dimension of the gradient
+    /// samples is offset to refer to the position of the corresponding sample
+    /// in the new block.
+    #[inline]
+    pub fn concatenate_samples(blocks: &[&TensorBlock]) -> Result<TensorBlock, Error> {
+        let blocks_ptr = blocks.iter().map(|block| block.as_ptr()).collect::<Vec<_>>();
+
+        let ptr = unsafe {
+            crate::c_api::mts_block_concatenate_samples(
+                blocks_ptr.as_ptr(),
+                blocks_ptr.len(),
+            )
+        };
+
+        crate::errors::check_ptr(ptr)?;
+
+        return Ok(unsafe { TensorBlock::from_raw(ptr) });
+    }
 }
 
 
@@ -170,4 +311,122 @@ mod tests {
         assert_eq!(std::mem::size_of::<TensorBlock>(), std::mem::size_of::<*mut mts_block_t>());
         assert_eq!(std::mem::align_of::<TensorBlock>(), std::mem::align_of::<*mut mts_block_t>());
     }
+
+    #[test]
+    fn new_scalar() {
+        let block = TensorBlock::new_scalar(
+            ndarray::ArrayD::from_elem(vec![2, 3], 1.0),
+            &Labels::new(["samples"], &[[0], [1]]),
+            &Labels::new(["properties"], &[[-2], [0], [1]]),
+        ).unwrap();
+
+        assert!(block.components().is_empty());
+        assert_eq!(block.values().to_array().shape(), [2, 3]);
+
+        let error = TensorBlock::new_scalar(
+            ndarray::ArrayD::from_elem(vec![2, 1, 3], 1.0),
+            &Labels::new(["samples"], &[[0], [1]]),
+            &Labels::new(["properties"], &[[-2], [0], [1]]),
+        ).unwrap_err();
+        assert_eq!(
+            error.message,
+            "invalid parameter: data and labels don't match: the array has \
+            3 dimensions, but we have 2 separate labels"
+        );
+    }
+
+    #[test]
+    fn set_properties() {
+        let mut block = TensorBlock::new(
+            ndarray::arr2(&[[1.0, 2.0, 3.0]]).into_dyn(),
+            &Labels::new(["system"], &[[0]]),
+            &[],
+            &Labels::new(["n"], &[[0], [1], [2]]),
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            ndarray::arr2(&[[4.0, 5.0, 6.0]]).into_dyn(),
+            &Labels::new(["sample"], &[[0]]),
+            &[],
+            &Labels::new(["n"], &[[0], [1], [2]]),
+        ).unwrap();
+        block.add_gradient("g", gradient).unwrap();
+
+        block.set_properties(&Labels::new(["n"], &[[2], [4], [6]])).unwrap();
+        assert_eq!(block.properties(), Labels::new(["n"], &[[2], [4], [6]]));
+        assert_eq!(block.values().as_array(), ndarray::arr2(&[[1.0, 2.0, 3.0]]).into_dyn());
+
+        let gradient = block.as_ref().gradient("g").unwrap();
+        assert_eq!(gradient.properties(), Labels::new(["n"], &[[2], [4], [6]]));
+
+        let error = block.set_properties(&Labels::new(["n"], &[[0], [1]])).unwrap_err();
+        assert_eq!(
+            error.message,
+            "invalid parameter: can not set properties: expected 3 entries, got 2"
+        );
+    }
+
+    #[test]
+    fn concatenate_samples() {
+        let create_block = |samples: &[[i32; 1]], gradient_samples: &[[i32; 2]], value: f64| {
+            let mut block = TensorBlock::new(
+                ndarray::ArrayD::from_elem(vec![samples.len(), 2], value),
+                &Labels::new(["system"], samples),
+                &[],
+                &Labels::new(["properties"], &[[0], [1]]),
+            ).unwrap();
+
+            let gradient = TensorBlock::new(
+                ndarray::ArrayD::from_elem(vec![gradient_samples.len(), 2], -value),
+                &Labels::new(["sample", "atom"], gradient_samples),
+                &[],
+                &Labels::new(["properties"], &[[0], [1]]),
+            ).unwrap();
+            block.add_gradient("positions", gradient).unwrap();
+
+            return block;
+        };
+
+        let first = create_block(&[[0], [1]], &[[0, 0], [1, 0]], 1.0);
+        let second = create_block(&[[2], [3], [4]], &[[2, 1]], 2.0);
+
+        let block = TensorBlock::concatenate_samples(&[&first, &second]).unwrap();
+        assert_eq!(block.samples(), Labels::new(["system"], &[[0], [1], [2], [3], [4]]));
+        assert_eq!(
+            block.values().as_array(),
+            ndarray::arr2(&[[1.0, 1.0], [1.0, 1.0], [2.0, 2.0], [2.0, 2.0], [2.0, 2.0]]).into_dyn()
+        );
+
+        let gradient = block.as_ref().gradient("positions").unwrap();
+        assert_eq!(gradient.samples(), Labels::new(["sample", "atom"], &[[0, 0], [1, 0], [4, 1]]));
+        assert_eq!(
+            gradient.values().as_array(),
+            ndarray::arr2(&[[-1.0, -1.0], [-1.0, -1.0], [-2.0, -2.0]]).into_dyn()
+        );
+
+        let error = TensorBlock::concatenate_samples(&[&first, &first]).unwrap_err();
+        assert_eq!(
+            error.message,
+            "invalid parameter: can not concatenate samples: the entry [0] \
+            is present in more than one block"
+        );
+
+        let other = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![1, 1], 1.0),
+            &Labels::new(["system"], &[[5]]),
+            &[],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap();
+        let error = TensorBlock::concatenate_samples(&[&first, &other]).unwrap_err();
+        assert_eq!(
+            error.message,
+            "invalid parameter: can not concatenate samples of blocks with different properties"
+        );
+
+        let error = TensorBlock::concatenate_samples(&[]).unwrap_err();
+        assert_eq!(
+            error.message,
+            "invalid parameter: can not concatenate the samples of an empty list of blocks"
+        );
+    }
 }
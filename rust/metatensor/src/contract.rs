@@ -0,0 +1,208 @@
+use crate::reduce::array_to_ndarray;
+use crate::{Error, Labels, TensorBlock, TensorBlockRef};
+
+impl TensorBlock {
+    /// Contract the values of this block with the values of `other`, summing
+    /// over `self_axis` in this block and `other_axis` in `other`.
+    ///
+    /// See [`TensorBlockRef::contract`] for more information.
+    #[inline]
+    pub fn contract(&self, other: &TensorBlock, self_axis: usize, other_axis: usize) -> Result<TensorBlock, Error> {
+        return self.as_ref().contract(&other.as_ref(), self_axis, other_axis);
+    }
+}
+
+impl TensorBlockRef<'_> {
+    /// Contract the values of this block with the values of `other`, summing
+    /// over `self_axis` in this block and `other_axis` in `other`.
+    ///
+    /// Axes are numbered as in the values array: `0` for the samples, `1` to
+    /// `n_components` for the components and `n_components + 1` for the
+    /// properties. The labels of the two contracted axes must be the same.
+    ///
+    /// The axes of the new block are all the axes of this block except
+    /// `self_axis`, in order, followed by all the axes of `other` except
+    /// `other_axis`, in order; and each axis keeps its labels. This means
+    /// that the samples of the new block are the samples of this block
+    /// (unless `self_axis` is `0`), and the properties of the new block are
+    /// the properties of `other` (unless `other_axis` is the last axis). This
+    /// returns an error if the resulting axes do not form a valid block, for
+    /// example if labels with multiple dimensions end up as components.
+    ///
+    /// For example, contracting the properties of a block with shape
+    /// `(samples, components, properties)` with the samples of a block with
+    /// shape `(samples_2, properties_2)` gives a block with shape `(samples,
+    /// components, properties_2)`.
+    ///
+    /// The data is read through `mts_array_t.data`, see the [crate
+    /// documentation](crate#accessing-the-data). The new block always stores
+    /// its data in `ndarray::ArrayD<f64>`. Gradients are not supported, and
+    /// this function returns an error if any of the blocks contains gradients.
+    pub fn contract(&self, other: &TensorBlockRef<'_>, self_axis: usize, other_axis: usize) -> Result<TensorBlock, Error> {
+        if !self.gradient_list().is_empty() || !other.gradient_list().is_empty() {
+            return Err(Error {
+                code: None,
+                message: "gradients are not supported in contract".into(),
+            });
+        }
+
+        let mut self_labels = all_axes_labels(*self);
+        let mut other_labels = all_axes_labels(*other);
+        check_axis(self_axis, self_labels.len(), "first")?;
+        check_axis(other_axis, other_labels.len(), "second")?;
+
+        let contracted = self_labels.remove(self_axis);
+        if contracted != other_labels.remove(other_axis) {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "can not contract axis {} of the first block with axis {} \
+                    of the second block: the labels of these axes are different",
+                    self_axis, other_axis
+                ),
+            });
+        }
+
+        // move the contracted axis to the end of the first array and to the
+        // start of the second array, and flatten all the other axes to be
+        // able to use a matrix-matrix product
+        let self_values = array_to_ndarray(&self.values())?;
+        let mut order = (0..self_values.ndim()).filter(|&i| i != self_axis).collect::<Vec<_>>();
+        order.push(self_axis);
+        let self_values = self_values.permuted_axes(order);
+
+        let other_values = array_to_ndarray(&other.values())?;
+        let mut order = vec![other_axis];
+        order.extend((0..other_values.ndim()).filter(|&i| i != other_axis));
+        let other_values = other_values.permuted_axes(order);
+
+        let n_contracted = contracted.count();
+        let self_shape = &self_values.shape()[..self_values.ndim() - 1];
+        let other_shape = &other_values.shape()[1..];
+        let self_size = self_shape.iter().product::<usize>();
+        let other_size = other_shape.iter().product::<usize>();
+
+        let self_matrix = ndarray::Array2::from_shape_vec(
+            (self_size, n_contracted),
+            self_values.iter().copied().collect(),
+        ).expect("invalid shape for the data");
+        let other_matrix = ndarray::Array2::from_shape_vec(
+            (n_contracted, other_size),
+            other_values.iter().copied().collect(),
+        ).expect("invalid shape for the data");
+
+        let mut shape = self_shape.to_vec();
+        shape.extend_from_slice(other_shape);
+        let result = self_matrix.dot(&other_matrix)
+            .into_shape(shape)
+            .expect("invalid shape for the result");
+
+        self_labels.append(&mut other_labels);
+        let n_axes = self_labels.len();
+        return TensorBlock::new(
+            result,
+            &self_labels[0],
+            &self_labels[1..n_axes - 1],
+            &self_labels[n_axes - 1],
+        );
+    }
+}
+
+/// Get the labels for all the axes of `block`, in the same order as the axes
+/// of the values array.
+fn all_axes_labels(block: TensorBlockRef<'_>) -> Vec<Labels> {
+    let mut labels = vec![block.samples()];
+    labels.extend(block.components());
+    labels.push(block.properties());
+    return labels;
+}
+
+fn check_axis(axis: usize, n_axes: usize, block: &str) -> Result<(), Error> {
+    if axis >= n_axes {
+        return Err(Error {
+            code: None,
+            message: format!(
+                "axis {} is out of bounds for the {} block, which has {} axes",
+                axis, block, n_axes
+            ),
+        });
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Labels, TensorBlock};
+
+    #[test]
+    fn contract() {
+        let block = TensorBlock::new(
+            ndarray::arr3(&[[[1.0, 2.0]], [[3.0, 4.0]], [[5.0, 6.0]]]).into_dyn(),
+            &Labels::new(["system"], &[[0], [1], [2]]),
+            &[Labels::new(["o3_mu"], &[[0]])],
+            &Labels::new(["n"], &[[0], [1]]),
+        ).unwrap();
+
+        let weights = TensorBlock::new(
+            ndarray::arr2(&[[1.0, 0.0, 1.0], [0.0, 1.0, 2.0]]).into_dyn(),
+            &Labels::new(["n"], &[[0], [1]]),
+            &[],
+            &Labels::new(["out"], &[[0], [1], [2]]),
+        ).unwrap();
+
+        let result = block.contract(&weights, 2, 0).unwrap();
+        assert_eq!(result.samples(), Labels::new(["system"], &[[0], [1], [2]]));
+        assert_eq!(result.components(), [Labels::new(["o3_mu"], &[[0]])]);
+        assert_eq!(result.properties(), Labels::new(["out"], &[[0], [1], [2]]));
+        assert_eq!(
+            result.values().as_array(),
+            ndarray::arr3(&[
+                [[1.0, 2.0, 5.0]],
+                [[3.0, 4.0, 11.0]],
+                [[5.0, 6.0, 17.0]],
+            ]).into_dyn()
+        );
+
+        let result = weights.contract(&weights, 1, 1).unwrap();
+        assert_eq!(result.samples(), Labels::new(["n"], &[[0], [1]]));
+        assert_eq!(result.properties(), Labels::new(["n"], &[[0], [1]]));
+        assert_eq!(result.values().as_array(), ndarray::arr2(&[[2.0, 2.0], [2.0, 5.0]]).into_dyn());
+
+        // contract over the samples
+        let other = TensorBlock::new(
+            ndarray::arr2(&[[1.0], [1.0], [1.0]]).into_dyn(),
+            &Labels::new(["system"], &[[0], [1], [2]]),
+            &[],
+            &Labels::new(["p"], &[[0]]),
+        ).unwrap();
+
+        let result = block.contract(&other, 0, 0).unwrap();
+        assert_eq!(result.samples(), Labels::new(["o3_mu"], &[[0]]));
+        assert_eq!(result.components(), [Labels::new(["n"], &[[0], [1]])]);
+        assert_eq!(result.properties(), Labels::new(["p"], &[[0]]));
+        assert_eq!(
+            result.values().as_array(),
+            ndarray::arr3(&[[[9.0], [12.0]]]).into_dyn()
+        );
+    }
+
+    #[test]
+    fn contract_errors() {
+        let block = TensorBlock::new(
+            ndarray::arr2(&[[1.0, 2.0]]).into_dyn(),
+            &Labels::new(["system"], &[[0]]),
+            &[],
+            &Labels::new(["n"], &[[0], [1]]),
+        ).unwrap();
+
+        let error = block.contract(&block, 2, 0).unwrap_err();
+        assert_eq!(error.message, "axis 2 is out of bounds for the first block, which has 2 axes");
+
+        let error = block.contract(&block, 1, 0).unwrap_err();
+        assert_eq!(
+            error.message,
+            "can not contract axis 1 of the first block with axis 0 of the \
+            second block: the labels of these axes are different"
+        );
+    }
+}
@@ -4,6 +4,17 @@ use std::os::raw::c_void;
 use once_cell::sync::Lazy;
 
 use crate::c_api::{mts_array_t, mts_data_origin_t, mts_sample_mapping_t, mts_status_t};
+use crate::Error;
+
+/// Type of the elements stored in an [`Array`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ArrayDType {
+    /// 64-bit floating point values
+    Float64,
+    /// 32-bit signed integers
+    Int32,
+}
 
 /// The Array trait is used by metatensor to manage different kind of data array
 /// with a single API. Metatensor only knows about `Box<dyn Array>`, and
@@ -29,12 +40,19 @@ pub trait Array: std::any::Any + Send + Sync {
     /// (data type, data location, etc.)
     fn copy(&self) -> Box<dyn Array>;
 
+    /// Get the type of the elements stored in this array.
+    ///
+    /// The default implementation returns [`ArrayDType::Float64`].
+    fn dtype(&self) -> ArrayDType {
+        return ArrayDType::Float64;
+    }
+
     /// Get the underlying data storage as a contiguous slice
     ///
-    /// This function is allowed to panic if the data is not accessible in RAM,
-    /// not stored as 64-bit floating point values, or not stored as a
-    /// C-contiguous array.
-    fn data(&mut self) -> &mut [f64];
+    /// This function is only called for arrays with [`ArrayDType::Float64`]
+    /// dtype, and should return an error if the data is not accessible in RAM
+    /// or not stored as a C-contiguous array.
+    fn data(&mut self) -> Result<&mut [f64], Error>;
 
     /// Get the shape of the array
     fn shape(&self) -> &[usize];
@@ -56,7 +74,8 @@ pub trait Array: std::any::Any + Send + Sync {
     ///
     /// This function should copy data from `input[sample.input, ..., :]` to
     /// `array[sample.output, ..., properties]` for each sample in `samples`.
-    /// All indexes are 0-based.
+    /// All indexes are 0-based. The `input` is guaranteed to have the same
+    /// [`Array::dtype`] as `self`.
     fn move_samples_from(
         &mut self,
         input: &dyn Array,
@@ -83,6 +102,7 @@ impl From<Box<dyn Array>> for mts_array_t {
             copy: Some(rust_array_copy),
             destroy: Some(rust_array_destroy),
             move_samples_from: Some(rust_array_move_samples_from),
+            element_size: Some(rust_array_element_size),
         }
     }
 }
@@ -189,10 +209,22 @@ unsafe extern fn rust_array_data(
     array: *mut c_void,
     data: *mut *mut f64,
 ) -> mts_status_t {
-    crate::errors::catch_unwind(|| {
+    crate::errors::catch_unwind_result(|| {
         check_pointers!(array, data);
-        let array = array.cast::<Box<dyn Array>>();
-        *data = (*array).data().as_mut_ptr();
+        let array = &mut *array.cast::<Box<dyn Array>>();
+        let dtype = array.dtype();
+        if dtype != ArrayDType::Float64 {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "can not access the data of this array as 64-bit floating \
+                    point values: the array contains {:?} data", dtype
+                ),
+            });
+        }
+
+        *data = array.data()?.as_mut_ptr();
+        return Ok(());
     })
 }
 
@@ -230,10 +262,21 @@ unsafe extern fn rust_array_move_samples_from(
     property_start: usize,
     property_end: usize,
 ) -> mts_status_t {
-    crate::errors::catch_unwind(|| {
+    crate::errors::catch_unwind_result(|| {
         check_pointers!(output, input);
-        let output = output.cast::<Box<dyn Array>>();
-        let input = input.cast::<Box<dyn Array>>();
+        let output = &mut *output.cast::<Box<dyn Array>>();
+        let input = &*input.cast::<Box<dyn Array>>();
+
+        if output.dtype() != input.dtype() {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "can not move samples between arrays with different data \
+                    types: got {:?} for the input and {:?} for the output",
+                    input.dtype(), output.dtype()
+                ),
+            });
+        }
 
         let samples = if samples_count == 0 {
             &[]
@@ -242,7 +285,23 @@ unsafe extern fn rust_array_move_samples_from(
             std::slice::from_raw_parts(samples, samples_count)
         };
 
-        (*output).move_samples_from(&**input, samples, property_start..property_end);
+        output.move_samples_from(&**input, samples, property_start..property_end);
+        return Ok(());
+    })
+}
+
+/// Implementation of `mts_array_t.element_size` using `Box<dyn Array>`
+unsafe extern fn rust_array_element_size(
+    array: *const c_void,
+    element_size: *mut usize,
+) -> mts_status_t {
+    crate::errors::catch_unwind(|| {
+        check_pointers!(array, element_size);
+        let array = array.cast::<Box<dyn Array>>();
+        *element_size = match (*array).dtype() {
+            ArrayDType::Float64 => std::mem::size_of::<f64>(),
+            ArrayDType::Int32 => std::mem::size_of::<i32>(),
+        };
     })
 }
 
@@ -265,8 +324,11 @@ impl Array for ndarray::ArrayD<f64> {
         return Box::new(self.clone());
     }
 
-    fn data(&mut self) -> &mut [f64] {
-        return self.as_slice_mut().expect("array is not contiguous")
+    fn data(&mut self) -> Result<&mut [f64], Error> {
+        return self.as_slice_mut().ok_or_else(|| Error {
+            code: None,
+            message: "can not access the data of this array: it is not stored in standard layout".into(),
+        });
     }
 
     fn shape(&self) -> &[usize] {
@@ -289,22 +351,88 @@ impl Array for ndarray::ArrayD<f64> {
         samples: &[mts_sample_mapping_t],
         property: Range<usize>,
     ) {
-        use ndarray::{Axis, Slice};
+        ndarray_move_samples_from(self, input, samples, property);
+    }
+}
 
-        // -2 since we also remove one axis with `index_axis_mut` below
-        let property_axis = self.shape().len() - 2;
+/// Integer arrays can be used to store data such as counts or masks inside
+/// blocks. They can not be accessed through `mts_array_t.data`, which only
+/// supports 64-bit floating point values, and samples can only be moved
+/// between integer arrays.
+impl Array for ndarray::ArrayD<i32> {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
 
-        let input = input.as_any().downcast_ref::<ndarray::ArrayD<f64>>().expect("input must be a ndarray");
-        for sample in samples {
-            let value = input.index_axis(Axis(0), sample.input);
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
 
-            let mut output_location = self.index_axis_mut(Axis(0), sample.output);
-            let mut output_location = output_location.slice_axis_mut(
-                Axis(property_axis), Slice::from(property.clone())
-            );
+    fn create(&self, shape: &[usize]) -> Box<dyn Array> {
+        return Box::new(ndarray::Array::from_elem(shape, 0));
+    }
 
-            output_location.assign(&value);
-        }
+    fn copy(&self) -> Box<dyn Array> {
+        return Box::new(self.clone());
+    }
+
+    fn dtype(&self) -> ArrayDType {
+        return ArrayDType::Int32;
+    }
+
+    fn data(&mut self) -> Result<&mut [f64], Error> {
+        return Err(Error {
+            code: None,
+            message: "can not access the data of ndarray::ArrayD<i32> as 64-bit floating point values".into(),
+        });
+    }
+
+    fn shape(&self) -> &[usize] {
+        return self.shape();
+    }
+
+    fn reshape(&mut self, shape: &[usize]) {
+        let mut array = std::mem::take(self);
+        array = array.to_shape(shape).expect("invalid shape").to_owned();
+        std::mem::swap(self, &mut array);
+    }
+
+    fn swap_axes(&mut self, axis_1: usize, axis_2: usize) {
+        self.swap_axes(axis_1, axis_2);
+    }
+
+    fn move_samples_from(
+        &mut self,
+        input: &dyn Array,
+        samples: &[mts_sample_mapping_t],
+        property: Range<usize>,
+    ) {
+        ndarray_move_samples_from(self, input, samples, property);
+    }
+}
+
+/// Implementation of `Array::move_samples_from` for `ndarray::ArrayD<T>`
+fn ndarray_move_samples_from<T: Clone + 'static>(
+    output: &mut ndarray::ArrayD<T>,
+    input: &dyn Array,
+    samples: &[mts_sample_mapping_t],
+    property: Range<usize>,
+) {
+    use ndarray::{Axis, Slice};
+
+    // -2 since we also remove one axis with `index_axis_mut` below
+    let property_axis = output.shape().len() - 2;
+
+    let input = input.as_any().downcast_ref::<ndarray::ArrayD<T>>().expect("input must be a ndarray with the same data type");
+    for sample in samples {
+        let value = input.index_axis(Axis(0), sample.input);
+
+        let mut output_location = output.index_axis_mut(Axis(0), sample.output);
+        let mut output_location = output_location.slice_axis_mut(
+            Axis(property_axis), Slice::from(property.clone())
+        );
+
+        output_location.assign(&value);
     }
 }
 
@@ -334,8 +462,11 @@ impl Array for EmptyArray {
         self
     }
 
-    fn data(&mut self) -> &mut [f64] {
-        panic!("can not call Array::data() for EmptyArray");
+    fn data(&mut self) -> Result<&mut [f64], Error> {
+        return Err(Error {
+            code: None,
+            message: "can not access the data of an EmptyArray".into(),
+        });
     }
 
     fn create(&self, shape: &[usize]) -> Box<dyn Array> {
@@ -1,7 +1,9 @@
-use crate::c_api::mts_array_t;
+use crate::c_api::{mts_array_t, mts_sample_mapping_t};
 use crate::data::origin::get_data_origin;
+use crate::errors::check_status;
+use crate::Error;
 
-use super::Array;
+use super::{Array, EmptyArray};
 
 /// Reference to a data array in metatensor-core
 ///
@@ -84,6 +86,110 @@ impl<'a> ArrayRef<'a> {
         self.to_any().downcast_ref().expect("this is not a ndarray::ArrayD")
     }
 
+    /// Get a copy of the data in this `ArrayRef` as a `ndarray::ArrayD<f64>`,
+    /// converting integer data to floating point values.
+    ///
+    /// This function will panic if the data in this `mts_array_t` is not a
+    /// `ndarray::ArrayD<f64>` or a `ndarray::ArrayD<i32>`.
+    pub fn to_f64(&self) -> ndarray::ArrayD<f64> {
+        let array = self.as_any();
+        if let Some(array) = array.downcast_ref::<ndarray::ArrayD<f64>>() {
+            return array.clone();
+        } else if let Some(array) = array.downcast_ref::<ndarray::ArrayD<i32>>() {
+            return array.mapv(f64::from);
+        } else {
+            panic!("this is not a ndarray::ArrayD of f64 or i32");
+        }
+    }
+
+    /// Get a read-only view of the data in this array, as a C-contiguous
+    /// slice of 64-bit floating point values.
+    ///
+    /// `ndarray::ArrayD<f64>` created through the [`Array`] trait are read
+    /// directly, and must be stored in standard layout. Other arrays are
+    /// accessed through `mts_array_t.data`, and any error from this function
+    /// (for example for arrays stored on other devices) is returned here.
+    pub fn data(&self) -> Result<&'a [f64], Error> {
+        if self.array.origin()? == *super::array::RUST_DATA_ORIGIN {
+            if let Some(array) = self.to_any().downcast_ref::<ndarray::ArrayD<f64>>() {
+                return array.as_slice().ok_or_else(|| Error {
+                    code: None,
+                    message: "can not access the data of this array: it is not stored in standard layout".into(),
+                });
+            }
+        }
+
+        let len = self.array.shape()?.iter().product::<usize>();
+        let function = self.array.data.expect("mts_array_t.data function is NULL");
+
+        let mut data = std::ptr::null_mut();
+        unsafe {
+            check_status(function(self.array.ptr, &mut data))?;
+        }
+
+        if len == 0 {
+            return Ok(&[]);
+        }
+
+        assert!(!data.is_null());
+        return Ok(unsafe { std::slice::from_raw_parts(data.cast_const(), len) });
+    }
+
+    /// Get a single element of this array. The `indices` are checked against
+    /// the shape of the array, and an error is returned if they are out of
+    /// bounds.
+    ///
+    /// This function is mainly intended for debugging and tests, use
+    /// [`ArrayRef::data`] to access multiple elements.
+    pub fn get(&self, indices: &[usize]) -> Result<f64, Error> {
+        let shape = self.array.shape()?;
+        if indices.len() != shape.len() {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "expected {} indices for an array with shape {:?}, got {}",
+                    shape.len(), shape, indices.len()
+                ),
+            });
+        }
+
+        let mut linear_index = 0;
+        for (&index, &size) in indices.iter().zip(shape) {
+            if index >= size {
+                return Err(Error {
+                    code: None,
+                    message: format!(
+                        "index {:?} is out of bounds for an array with shape {:?}",
+                        indices, shape
+                    ),
+                });
+            }
+            linear_index = linear_index * size + index;
+        }
+
+        return Ok(self.data()?[linear_index]);
+    }
+
+    /// Sum the elements of this array along `axis`, creating a new array
+    /// with `mts_array_t.create` where the size of `axis` is 1.
+    ///
+    /// The data is read with [`ArrayRef::data`], and the new array uses the
+    /// same backend as this one.
+    pub fn sum_axis(&self, axis: usize) -> Result<mts_array_t, Error> {
+        let shape = self.array.shape()?;
+        if axis >= shape.len() {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "axis {} is out of bounds for an array with shape {:?}",
+                    axis, shape
+                ),
+            });
+        }
+
+        return super::sum_along_axis(self, axis, &vec![0; shape[axis]], 1);
+    }
+
     /// Get the raw underlying `mts_array_t`
     pub fn as_raw(&self) -> &mts_array_t {
         &self.array
@@ -240,4 +346,73 @@ impl<'a> ArrayRefMut<'a> {
     pub fn as_raw_mut(&mut self) -> &mut mts_array_t {
         &mut self.array
     }
+
+    /// Set all the elements of this array to `value`, using
+    /// `mts_array_t.data` to access the data.
+    ///
+    /// This can be used to initialize an array created with
+    /// `mts_array_t.create`, without going through an intermediate buffer.
+    pub fn fill(&mut self, value: f64) -> Result<(), Error> {
+        self.array.data()?.fill(value);
+        return Ok(());
+    }
+
+    /// Make sure the data in this array is stored in standard (C-contiguous)
+    /// layout, copying it if needed.
+    ///
+    /// Rust arrays are copied to a new array created with [`Array::create`],
+    /// using `mts_array_t.move_samples_from` to move all the samples. The copy
+    /// is skipped for `ndarray::ArrayD` already in standard layout and for
+    /// [`EmptyArray`], which does not contain any data. Arrays with a
+    /// different origin are required to give access to C-contiguous data in
+    /// `mts_array_t.data`, and are left unchanged.
+    pub(crate) fn make_contiguous(&mut self) -> Result<(), crate::Error> {
+        if self.array.origin()? != *super::array::RUST_DATA_ORIGIN {
+            return Ok(());
+        }
+
+        let array = self.as_any();
+        if let Some(array) = array.downcast_ref::<ndarray::ArrayD<f64>>() {
+            if array.is_standard_layout() {
+                return Ok(());
+            }
+        } else if let Some(array) = array.downcast_ref::<ndarray::ArrayD<i32>>() {
+            if array.is_standard_layout() {
+                return Ok(());
+            }
+        } else if array.is::<EmptyArray>() {
+            return Ok(());
+        }
+
+        let shape = self.array.shape()?.to_vec();
+        let samples = (0..shape[0])
+            .map(|i| mts_sample_mapping_t { input: i, output: i })
+            .collect::<Vec<_>>();
+        let properties = 0..shape[shape.len() - 1];
+
+        let input = &self.array;
+        let new_array = super::create_array(input, &shape, |output| {
+            output.move_samples_from(input, &samples, properties)
+        })?;
+
+        if new_array.origin()? != *super::array::RUST_DATA_ORIGIN {
+            super::destroy_array(new_array);
+            return Err(crate::Error {
+                code: None,
+                message: "can not make this array contiguous: Array::create \
+                    returned an array which was not created as a rust Array".into(),
+            });
+        }
+
+        // SAFETY: both arrays have the rust origin, so `ptr` is a
+        // `Box<dyn Array>` allocated with `Box::into_raw`. We take ownership
+        // of the new array, and replace the old one in place, since the
+        // `mts_array_t` itself is owned by the block.
+        unsafe {
+            let new_array = Box::from_raw(new_array.ptr.cast::<Box<dyn Array>>());
+            *self.array.ptr.cast::<Box<dyn Array>>() = *new_array;
+        }
+
+        return Ok(());
+    }
 }
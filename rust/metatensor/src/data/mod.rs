@@ -4,13 +4,77 @@ mod array_ref;
 pub use self::array_ref::{ArrayRef, ArrayRefMut};
 
 mod array;
-pub use self::array::Array;
+pub use self::array::{Array, ArrayDType};
 pub use self::array::EmptyArray;
 
+use crate::c_api::mts_array_t;
+use crate::Error;
+
+/// Create a new array with the same backend as `array` using
+/// `mts_array_t.create`, and initialize it with `init`. The new array is
+/// destroyed if `init` returns an error.
+pub(crate) fn create_array<F>(array: &mts_array_t, shape: &[usize], init: F) -> Result<mts_array_t, Error>
+    where F: FnOnce(&mut mts_array_t) -> Result<(), Error>
+{
+    let mut new_array = array.create(shape)?;
+    if let Err(error) = init(&mut new_array) {
+        destroy_array(new_array);
+        return Err(error);
+    }
+
+    return Ok(new_array);
+}
+
+/// Free the memory used by an `mts_array_t` which is not owned by a block
+pub(crate) fn destroy_array(array: mts_array_t) {
+    if let Some(destroy) = array.destroy {
+        unsafe { destroy(array.ptr) };
+    }
+}
+
+/// Sum the entries of `array` along `axis` into a new array created with
+/// `mts_array_t.create`, with `count` entries along this axis. The entry `i`
+/// of `array` along `axis` is added to the entry `mapping[i]` of the new
+/// array.
+pub(crate) fn sum_along_axis(
+    array: &ArrayRef<'_>,
+    axis: usize,
+    mapping: &[usize],
+    count: usize,
+) -> Result<mts_array_t, Error> {
+    let input = array.data()?;
+
+    let shape = array.as_raw().shape()?;
+    assert_eq!(mapping.len(), shape[axis]);
+    let outer = shape[..axis].iter().product::<usize>();
+    let inner = shape[axis + 1..].iter().product::<usize>();
+
+    let mut new_shape = shape.to_vec();
+    new_shape[axis] = count;
+
+    return create_array(array.as_raw(), &new_shape, |output| {
+        let output = output.data()?;
+        output.fill(0.0);
+        for i_outer in 0..outer {
+            for (i, &position) in mapping.iter().enumerate() {
+                let input_start = (i_outer * mapping.len() + i) * inner;
+                let output_start = (i_outer * count + position) * inner;
+
+                let input = &input[input_start..input_start + inner];
+                let output = &mut output[output_start..output_start + inner];
+                for (output, input) in output.iter_mut().zip(input) {
+                    *output += input;
+                }
+            }
+        }
+        Ok(())
+    });
+}
+
 
 #[cfg(test)]
 mod tests {
-    use ndarray::ArrayD;
+    use ndarray::{ArrayD, Axis};
 
     use crate::c_api::mts_sample_mapping_t;
 
@@ -31,6 +95,59 @@ mod tests {
         assert_eq!(array.as_raw().shape().unwrap(), [2, 12]);
     }
 
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn get() {
+        let array = ArrayD::from_shape_vec(vec![2, 3], vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+        let array = Box::new(array) as Box<dyn Array>;
+        let array = unsafe { ArrayRef::from_raw(array.into()) };
+
+        assert_eq!(array.get(&[0, 0]).unwrap(), 1.0);
+        assert_eq!(array.get(&[0, 2]).unwrap(), 3.0);
+        assert_eq!(array.get(&[1, 1]).unwrap(), 5.0);
+
+        let error = array.get(&[2, 0]).unwrap_err();
+        assert_eq!(error.message, "index [2, 0] is out of bounds for an array with shape [2, 3]");
+
+        let error = array.get(&[1]).unwrap_err();
+        assert_eq!(error.message, "expected 2 indices for an array with shape [2, 3], got 1");
+    }
+
+    #[test]
+    fn fill() {
+        let array = Box::new(ArrayD::from_elem(vec![2, 3], 1.0)) as Box<dyn Array>;
+        let mut array = unsafe { ArrayRefMut::new(array.into()) };
+
+        array.fill(-3.5).unwrap();
+        assert_eq!(array.as_array(), ArrayD::from_elem(vec![2, 3], -3.5));
+
+        let empty = Box::new(ArrayD::from_elem(vec![0, 3], 1.0)) as Box<dyn Array>;
+        let mut empty = unsafe { ArrayRefMut::new(empty.into()) };
+        empty.fill(-3.5).unwrap();
+    }
+
+    #[test]
+    fn sum_axis() {
+        let array = ArrayD::from_shape_vec(vec![2, 3, 2], (0..12).map(f64::from).collect()).unwrap();
+        let expected = array.clone();
+        let array = Box::new(array) as Box<dyn Array>;
+        let array = unsafe { ArrayRef::from_raw(array.into()) };
+
+        for axis in 0..3 {
+            let summed = unsafe { ArrayRef::from_raw(array.sum_axis(axis).unwrap()) };
+            assert_eq!(get_data_origin(summed.as_raw().origin().unwrap()).unwrap(), "rust.Box<dyn Array>");
+            assert_eq!(summed.as_array(), expected.sum_axis(Axis(axis)).insert_axis(Axis(axis)));
+        }
+
+        let empty = Box::new(ArrayD::from_elem(vec![0, 3], 1.0)) as Box<dyn Array>;
+        let empty = unsafe { ArrayRef::from_raw(empty.into()) };
+        let summed = unsafe { ArrayRef::from_raw(empty.sum_axis(0).unwrap()) };
+        assert_eq!(summed.as_array(), ArrayD::from_elem(vec![1, 3], 0.0));
+
+        let error = array.sum_axis(3).unwrap_err();
+        assert_eq!(error.message, "axis 3 is out of bounds for an array with shape [2, 3, 2]");
+    }
+
     #[test]
     fn create() {
         let array = Box::new(ArrayD::from_elem(vec![4, 2], 1.0)) as Box<dyn Array>;
@@ -66,4 +183,81 @@ mod tests {
         ]).unwrap();
         assert_eq!(other.as_array(), expected);
     }
+
+    #[test]
+    fn move_samples_from_remapping() {
+        let input = ArrayD::from_shape_vec(vec![2, 2, 2], vec![
+            1.0, 2.0, 3.0, 4.0,
+            5.0, 6.0, 7.0, 8.0,
+        ]).unwrap();
+        let input = Box::new(input) as Box<dyn Array>;
+        let input = unsafe { ArrayRef::from_raw(input.into()) };
+
+        let mut output = unsafe { ArrayRefMut::new(input.as_raw().create(&[3, 2, 3]).unwrap()) };
+
+        let samples = [
+            mts_sample_mapping_t { input: 0, output: 2 },
+            mts_sample_mapping_t { input: 1, output: 0 },
+        ];
+        output.as_raw_mut().move_samples_from(input.as_raw(), &samples, 1..3).unwrap();
+
+        let expected = ArrayD::from_shape_vec(vec![3, 2, 3], vec![
+            0.0, 5.0, 6.0,
+            0.0, 7.0, 8.0,
+
+            0.0, 0.0, 0.0,
+            0.0, 0.0, 0.0,
+
+            0.0, 1.0, 2.0,
+            0.0, 3.0, 4.0,
+        ]).unwrap();
+        assert_eq!(output.as_array(), expected);
+
+        // rows can also be moved between arrays stored in a non-standard layout
+        let mut input = unsafe { ArrayRefMut::new(*input.as_raw()) };
+        input.as_raw_mut().swap_axes(1, 2).unwrap();
+
+        let mut output = unsafe { ArrayRefMut::new(input.as_raw().create(&[3, 2, 2]).unwrap()) };
+        output.as_raw_mut().move_samples_from(input.as_raw(), &samples, 0..2).unwrap();
+
+        let expected = ArrayD::from_shape_vec(vec![3, 2, 2], vec![
+            5.0, 7.0, 6.0, 8.0,
+            0.0, 0.0, 0.0, 0.0,
+            1.0, 3.0, 2.0, 4.0,
+        ]).unwrap();
+        assert_eq!(output.as_array(), expected);
+    }
+
+    #[test]
+    fn integer_data() {
+        let array = Box::new(ArrayD::from_elem(vec![3, 2], 3_i32)) as Box<dyn Array>;
+        let array = unsafe { ArrayRef::from_raw(array.into()) };
+        assert_eq!(array.as_raw().shape().unwrap(), [3, 2]);
+
+        let mut other = unsafe { ArrayRefMut::new(array.as_raw().create(&[2, 4]).unwrap()) };
+        let mapping = mts_sample_mapping_t {
+            output: 1,
+            input: 2,
+        };
+        other.as_raw_mut().move_samples_from(array.as_raw(), &[mapping], 1..3).unwrap();
+
+        let expected = ArrayD::from_shape_vec(vec![2, 4], vec![
+            0, 0, 0, 0,
+            0, 3, 3, 0,
+        ]).unwrap();
+        assert_eq!(other.as_any().downcast_ref::<ArrayD<i32>>().unwrap(), expected);
+
+        let other = unsafe { ArrayRef::from_raw(*other.as_raw()) };
+        assert_eq!(other.to_f64(), expected.mapv(f64::from));
+
+        let error = other.data().unwrap_err();
+        assert_eq!(error.message,
+            "can not access the data of this array as 64-bit floating point \
+            values: the array contains Int32 data"
+        );
+
+        let float = Box::new(ArrayD::from_elem(vec![2, 4], 1.0)) as Box<dyn Array>;
+        let mut float = unsafe { ArrayRefMut::new(float.into()) };
+        assert!(float.as_raw_mut().move_samples_from(array.as_raw(), &[mapping], 1..3).is_err());
+    }
 }
@@ -0,0 +1,298 @@
+use ndarray::Dimension;
+
+use crate::reduce::array_to_ndarray;
+use crate::labels::format_key;
+use crate::{Labels, TensorBlockRef, TensorMap};
+
+impl TensorMap {
+    /// Get a human-readable description of all the differences between this
+    /// tensor map and `other`.
+    ///
+    /// This reports keys which are only present in one of the tensor maps,
+    /// and for the blocks present in both differences in the samples,
+    /// components, properties and gradients. When all the metadata of a block
+    /// (or gradient) is the same, the values are compared, and the number of
+    /// values differing by more than `tolerance` is reported together with
+    /// the largest difference, instead of listing every value. Labels with the
+    /// same entries in a different order are reported as different, and the
+    /// corresponding values are not compared.
+    ///
+    /// The returned list is empty if the two tensor maps are equal (up to
+    /// `tolerance`). This is intended for debugging, for example to explain
+    /// why two tensor maps are not equal in a failing test.
+    ///
+    /// The values are read through `mts_array_t.data`, see the [crate
+    /// documentation](crate#accessing-the-data). Errors when reading the values
+    /// are part of the returned differences.
+    pub fn diff(&self, other: &TensorMap, tolerance: f64) -> Vec<String> {
+        let mut differences = Vec::new();
+
+        let keys = self.keys();
+        let other_keys = other.keys();
+        if keys.names() != other_keys.names() {
+            differences.push(format!(
+                "keys have different names: [{}] and [{}]",
+                keys.names().join(", "), other_keys.names().join(", ")
+            ));
+            return differences;
+        }
+
+        let names = keys.names();
+        let mut same_entries = keys.count() == other_keys.count();
+        let mut same_order = true;
+        for (block_i, key) in keys.iter().enumerate() {
+            if let Some(other_i) = other_keys.position(key) {
+                same_order &= block_i == other_i;
+                let context = format!("block for key ({})", format_key(&names, key));
+                diff_blocks(&context, self.block_by_id(block_i), other.block_by_id(other_i), tolerance, &mut differences);
+            } else {
+                same_entries = false;
+                differences.push(format!(
+                    "key ({}) is only present in the first tensor map", format_key(&names, key)
+                ));
+            }
+        }
+
+        for key in other_keys {
+            if keys.position(key).is_none() {
+                same_entries = false;
+                differences.push(format!(
+                    "key ({}) is only present in the second tensor map", format_key(&names, key)
+                ));
+            }
+        }
+
+        if same_entries && !same_order {
+            differences.push("keys contain the same entries in a different order".into());
+        }
+
+        return differences;
+    }
+}
+
+/// Compare two sets of labels, adding a description of the differences to
+/// `differences`. This returns `true` if the labels are the same.
+fn diff_labels(context: &str, axis: &str, first: &Labels, second: &Labels, differences: &mut Vec<String>) -> bool {
+    if first.names() != second.names() {
+        differences.push(format!(
+            "{}: {} have different names: [{}] and [{}]",
+            context, axis, first.names().join(", "), second.names().join(", ")
+        ));
+        return false;
+    }
+
+    if first == second {
+        return true;
+    }
+
+    let only_first = first.iter().filter(|&entry| second.position(entry).is_none()).count();
+    let only_second = second.iter().filter(|&entry| first.position(entry).is_none()).count();
+    if only_first == 0 && only_second == 0 {
+        differences.push(format!(
+            "{}: {} contain the same entries in a different order", context, axis
+        ));
+    } else {
+        differences.push(format!(
+            "{}: {} differ, {} entries are only in the first tensor map and {} \
+            entries are only in the second",
+            context, axis, only_first, only_second
+        ));
+    }
+
+    return false;
+}
+
+/// Compare two blocks and all of their gradients, adding a description of the
+/// differences to `differences`.
+fn diff_blocks(
+    context: &str,
+    first: TensorBlockRef<'_>,
+    second: TensorBlockRef<'_>,
+    tolerance: f64,
+    differences: &mut Vec<String>,
+) {
+    let mut same_metadata = diff_labels(context, "samples", &first.samples(), &second.samples(), differences);
+
+    let components = first.components();
+    let other_components = second.components();
+    if components.len() == other_components.len() {
+        for (component, other_component) in components.iter().zip(&other_components) {
+            same_metadata &= diff_labels(context, "components", component, other_component, differences);
+        }
+    } else {
+        differences.push(format!(
+            "{}: different number of components: {} and {}",
+            context, components.len(), other_components.len()
+        ));
+        same_metadata = false;
+    }
+
+    same_metadata &= diff_labels(context, "properties", &first.properties(), &second.properties(), differences);
+
+    let mut parameters = first.gradient_list();
+    parameters.sort_unstable();
+    let mut other_parameters = second.gradient_list();
+    other_parameters.sort_unstable();
+    if parameters != other_parameters {
+        differences.push(format!(
+            "{}: gradients with respect to different parameters: [{}] and [{}]",
+            context, parameters.join(", "), other_parameters.join(", ")
+        ));
+    }
+
+    for parameter in parameters {
+        if let Some(other_gradient) = second.gradient(parameter) {
+            let gradient = first.gradient(parameter).expect("missing gradient");
+            let gradient_context = format!("{}, gradient with respect to '{}'", context, parameter);
+            diff_blocks(&gradient_context, gradient, other_gradient, tolerance, differences);
+        }
+    }
+
+    if same_metadata {
+        diff_values(context, first, second, tolerance, differences);
+    }
+}
+
+/// Compare the values of two blocks with the same metadata, adding a summary
+/// of the differences to `differences`.
+fn diff_values(
+    context: &str,
+    first: TensorBlockRef<'_>,
+    second: TensorBlockRef<'_>,
+    tolerance: f64,
+    differences: &mut Vec<String>,
+) {
+    let values = array_to_ndarray(&first.values());
+    let other_values = array_to_ndarray(&second.values());
+    let (values, other_values) = match (values, other_values) {
+        (Ok(values), Ok(other_values)) => (values, other_values),
+        (Err(error), _) | (_, Err(error)) => {
+            differences.push(format!("{}: could not compare the values: {}", context, error.message));
+            return;
+        }
+    };
+
+    let mut n_different = 0;
+    let mut largest = 0.0;
+    let mut largest_index = Vec::new();
+    for ((index, &value), &other_value) in values.indexed_iter().zip(&other_values) {
+        let delta = if value.is_nan() || other_value.is_nan() {
+            if value.is_nan() && other_value.is_nan() {
+                continue;
+            }
+            f64::INFINITY
+        } else {
+            (value - other_value).abs()
+        };
+
+        if delta > tolerance {
+            n_different += 1;
+            if delta > largest || largest_index.is_empty() {
+                largest = delta;
+                largest_index = index.slice().to_vec();
+            }
+        }
+    }
+
+    if n_different != 0 {
+        differences.push(format!(
+            "{}: {} out of {} values differ by more than {}, the largest \
+            difference is {} at index [{}]",
+            context, n_different, values.len(), tolerance, largest,
+            largest_index.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Labels, TensorBlock, TensorMap};
+
+    fn example_tensor(keys: &[[i32; 1]], values: f64, gradient: bool) -> TensorMap {
+        let mut blocks = Vec::new();
+        for _ in keys {
+            let mut block = TensorBlock::new(
+                ndarray::ArrayD::from_elem(vec![2, 3], values),
+                &Labels::new(["system"], &[[0], [1]]),
+                &[],
+                &Labels::new(["n"], &[[0], [1], [2]]),
+            ).unwrap();
+
+            if gradient {
+                let gradient = TensorBlock::new(
+                    ndarray::ArrayD::from_elem(vec![1, 3], values),
+                    &Labels::new(["sample"], &[[0]]),
+                    &[],
+                    &Labels::new(["n"], &[[0], [1], [2]]),
+                ).unwrap();
+                block.add_gradient("g", gradient).unwrap();
+            }
+
+            blocks.push(block);
+        }
+
+        return TensorMap::new(Labels::new(["key"], keys), blocks).unwrap();
+    }
+
+    #[test]
+    fn diff_metadata() {
+        let tensor = example_tensor(&[[0], [1]], 1.0, true);
+        assert!(tensor.diff(&tensor, 0.0).is_empty());
+
+        let block = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![1, 1], 1.0),
+            &Labels::new(["system"], &[[0]]),
+            &[],
+            &Labels::new(["n"], &[[0]]),
+        ).unwrap();
+        let other = TensorMap::new(Labels::new(["other"], &[[0]]), vec![block]).unwrap();
+        assert_eq!(tensor.diff(&other, 0.0), ["keys have different names: [key] and [other]"]);
+
+        let other = example_tensor(&[[1], [0]], 1.0, true);
+        assert_eq!(tensor.diff(&other, 0.0), ["keys contain the same entries in a different order"]);
+
+        let other = example_tensor(&[[1], [0]], 1.0, false);
+        assert_eq!(tensor.diff(&other, 0.0), [
+            "block for key (key = 0): gradients with respect to different parameters: [g] and []",
+            "block for key (key = 1): gradients with respect to different parameters: [g] and []",
+            "keys contain the same entries in a different order",
+        ]);
+
+        let other = example_tensor(&[[1], [2]], 1.0, false);
+        assert_eq!(tensor.diff(&other, 0.0), [
+            "key (key = 0) is only present in the first tensor map",
+            "block for key (key = 1): gradients with respect to different parameters: [g] and []",
+            "key (key = 2) is only present in the second tensor map",
+        ]);
+
+        let mut other = example_tensor(&[[0], [1]], 1.0, true);
+        other.block_mut_by_id(0).set_samples(&Labels::new(["system"], &[[1], [0]])).unwrap();
+        other.block_mut_by_id(1).set_samples(&Labels::new(["system"], &[[0], [3]])).unwrap();
+        assert_eq!(tensor.diff(&other, 0.0), [
+            "block for key (key = 0): samples contain the same entries in a different order",
+            "block for key (key = 1): samples differ, 1 entries are only in the first \
+            tensor map and 1 entries are only in the second",
+        ]);
+    }
+
+    #[test]
+    fn diff_values() {
+        let tensor = example_tensor(&[[0]], 1.0, true);
+
+        let other = example_tensor(&[[0]], 1.5, true);
+        assert!(tensor.diff(&other, 0.5).is_empty());
+        assert_eq!(tensor.diff(&other, 0.1), [
+            "block for key (key = 0), gradient with respect to 'g': 3 out of 3 values \
+            differ by more than 0.1, the largest difference is 0.5 at index [0, 0]",
+            "block for key (key = 0): 6 out of 6 values differ by more than 0.1, the \
+            largest difference is 0.5 at index [0, 0]",
+        ]);
+
+        let mut other = example_tensor(&[[0]], 1.0, true);
+        other.block_mut_by_id(0).values_mut().as_array_mut()[[1, 2]] = 4.0;
+        assert_eq!(tensor.diff(&other, 1e-12), [
+            "block for key (key = 0): 1 out of 6 values differ by more than 0.000000000001, \
+            the largest difference is 3 at index [1, 2]",
+        ]);
+    }
+}
@@ -52,15 +52,27 @@ pub fn check_ptr<T>(ptr: *mut T) -> Result<NonNull<T>, Error> {
 pub(crate) fn catch_unwind<F>(function: F) -> mts_status_t where F: FnOnce() + std::panic::UnwindSafe {
     match std::panic::catch_unwind(function) {
         Ok(()) => MTS_SUCCESS,
-        Err(e) => {
-            // Store the error in LAST_RUST_ERROR, we will extract it later
-            // in `check_status`
-            LAST_RUST_ERROR.with(|last_error| {
-                let mut last_error = last_error.borrow_mut();
-                *last_error = e.into();
-            });
+        Err(e) => store_error(e.into()),
+    }
+}
 
-            RUST_FUNCTION_FAILED_ERROR_CODE
-        }
+/// Same as [`catch_unwind`], for functions returning a `Result`. Both errors
+/// and panics are transformed into `mts_status_t`.
+pub(crate) fn catch_unwind_result<F>(function: F) -> mts_status_t where F: FnOnce() -> Result<(), Error> + std::panic::UnwindSafe {
+    match std::panic::catch_unwind(function) {
+        Ok(Ok(())) => MTS_SUCCESS,
+        Ok(Err(error)) => store_error(error),
+        Err(e) => store_error(e.into()),
     }
 }
+
+/// Store the error in `LAST_RUST_ERROR`, and return the corresponding status
+/// code. The error will be extracted later in `check_status`.
+fn store_error(error: Error) -> mts_status_t {
+    LAST_RUST_ERROR.with(|last_error| {
+        let mut last_error = last_error.borrow_mut();
+        *last_error = error;
+    });
+
+    return RUST_FUNCTION_FAILED_ERROR_CODE;
+}
@@ -1,6 +1,6 @@
 use std:: ffi::CStr;
 use std::ffi::CString;
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashSet};
 use std::iter::FusedIterator;
 
 use smallvec::SmallVec;
@@ -42,6 +42,17 @@ impl std::fmt::Display for LabelValue {
     }
 }
 
+/// Parse a `LabelValue` from its integer representation, as produced by the
+/// `Display` implementation.
+impl std::str::FromStr for LabelValue {
+    type Err = std::num::ParseIntError;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<LabelValue, Self::Err> {
+        s.parse().map(LabelValue)
+    }
+}
+
 #[allow(clippy::cast_possible_wrap, clippy::cast_possible_truncation)]
 impl From<u32> for LabelValue {
     #[inline]
@@ -102,6 +113,14 @@ impl LabelValue {
     pub fn i32(self) -> i32 {
         self.0
     }
+
+    /// Special value used in block selections to match any value of the
+    /// corresponding key dimension, see [`TensorMap::blocks_matching_wildcard`](crate::TensorMap::blocks_matching_wildcard).
+    ///
+    /// This is stored as `i32::MIN`, and is only used as a wildcard by
+    /// [`TensorMap::blocks_matching_wildcard`](crate::TensorMap::blocks_matching_wildcard):
+    /// other functions compare it exactly with the keys.
+    pub const ANY: LabelValue = LabelValue(crate::c_api::MTS_LABELS_ANY);
 }
 
 /// A set of labels used to carry metadata associated with a tensor map.
@@ -132,6 +151,14 @@ impl std::fmt::Debug for Labels {
     }
 }
 
+/// Format a single `key` as a comma-separated list of `name = value` pairs
+pub(crate) fn format_key<V: Copy + Into<LabelValue>>(names: &[impl AsRef<str>], key: &[V]) -> String {
+    return names.iter().zip(key)
+        .map(|(name, &value)| format!("{} = {}", name.as_ref(), value.into()))
+        .collect::<Vec<_>>()
+        .join(", ");
+}
+
 /// Helper function to print labels in a Debug mode
 pub(crate) fn pretty_print_labels(
     labels: &Labels,
@@ -212,6 +239,77 @@ impl Labels {
         return builder.finish();
     }
 
+    /// Create a new set of `Labels` with the given `names`, containing a
+    /// single entry with the given `values`.
+    ///
+    /// This is useful to create selections for [`crate::TensorMap::block`] or
+    /// [`crate::TensorMap::blocks_matching`]. This returns an error if there
+    /// is not exactly one value for each name, or if the names are invalid.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn new_single_entry(names: Vec<&str>, values: Vec<LabelValue>) -> Result<Labels, Error> {
+        if names.len() != values.len() {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "expected one value for each of the {} names, got {} values",
+                    names.len(), values.len()
+                ),
+            });
+        }
+
+        let builder = LabelsBuilder {
+            names: names.into_iter().map(String::from).collect(),
+            values: values,
+        };
+
+        return builder.try_finish();
+    }
+
+    /// Create a new set of `Labels` from data stored column-wise, with one
+    /// column of values for each of the `names`.
+    ///
+    /// All columns must have the same length, which will be the number of
+    /// entries in the labels. This returns an error if the columns have
+    /// different lengths, if there is not exactly one column for each name,
+    /// or if the resulting entries are not unique.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn from_columns(names: Vec<&str>, columns: Vec<Vec<LabelValue>>) -> Result<Labels, Error> {
+        if names.len() != columns.len() {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "expected one column for each of the {} names, got {} columns",
+                    names.len(), columns.len()
+                ),
+            });
+        }
+
+        let count = columns.first().map_or(0, Vec::len);
+        for (name, column) in names.iter().zip(&columns) {
+            if column.len() != count {
+                return Err(Error {
+                    code: None,
+                    message: format!(
+                        "all columns must have the same length, but the column for '{}' \
+                        has {} values instead of {}", name, column.len(), count
+                    ),
+                });
+            }
+        }
+
+        let mut values = Vec::with_capacity(count * names.len());
+        for i in 0..count {
+            values.extend(columns.iter().map(|column| column[i]));
+        }
+
+        let builder = LabelsBuilder {
+            names: names.into_iter().map(String::from).collect(),
+            values: values,
+        };
+
+        return builder.try_finish();
+    }
+
     /// Load `Labels` from the file at `path`
     ///
     /// This is a convenience function calling [`crate::io::load_labels`]
@@ -261,6 +359,29 @@ impl Labels {
         }
     }
 
+    /// Check if this set of labels and `other` have the same names, in the
+    /// same order
+    #[inline]
+    pub fn same_names(&self, other: &Labels) -> bool {
+        return self.names() == other.names();
+    }
+
+    /// Check that this set of labels and `other` have the same names, in the
+    /// same order, returning an error otherwise
+    pub fn assert_same_names(&self, other: &Labels) -> Result<(), Error> {
+        if !self.same_names(other) {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "expected Labels with the same names, got [{}] and [{}]",
+                    self.names().join(", "),
+                    other.names().join(", "),
+                ),
+            });
+        }
+        return Ok(());
+    }
+
     /// Get the total number of entries in this set of labels
     #[inline]
     pub fn count(&self) -> usize {
@@ -273,12 +394,27 @@ impl Labels {
         self.count() == 0
     }
 
-    /// Check whether the given `label` is part of this set of labels
+    /// Check whether the given `label` is part of this set of labels.
+    ///
+    /// This is equivalent to `labels.position(label).is_some()`, and panics
+    /// if `label.len()` does not match the size of these labels.
     #[inline]
     pub fn contains(&self, label: &[LabelValue]) -> bool {
         return self.position(label).is_some();
     }
 
+    /// Get the entry (i.e. row) at the given `index` in these labels, or
+    /// `None` if `index` is out of bounds. This is the inverse of
+    /// [`Labels::position`].
+    #[inline]
+    pub fn row(&self, index: usize) -> Option<&[LabelValue]> {
+        if index < self.count() {
+            return Some(&self[index]);
+        } else {
+            return None;
+        }
+    }
+
     /// Get the position (i.e. row index) of the given label in the full labels
     /// array, or None.
     #[inline]
@@ -298,6 +434,77 @@ impl Labels {
         return result.try_into().ok();
     }
 
+    /// Search for the given `entry` in these labels using a binary search,
+    /// assuming that the entries are sorted in lexicographic order.
+    ///
+    /// This returns `Ok(index)` if the entry is found, and `Err(index)` with
+    /// the position where the entry could be inserted while keeping the
+    /// labels sorted otherwise, with the same convention as
+    /// [`slice::binary_search`].
+    ///
+    /// **The labels must be sorted**, otherwise the result is unspecified.
+    /// Unlike [`Labels::position`], this does not need to call into
+    /// metatensor-core, which makes it faster for repeated lookups in sorted
+    /// labels.
+    #[inline]
+    pub fn binary_search(&self, entry: &[LabelValue]) -> Result<usize, usize> {
+        assert!(entry.len() == self.size(), "invalid size of index in Labels::binary_search");
+
+        let mut low = 0;
+        let mut high = self.count();
+        while low < high {
+            let middle = low + (high - low) / 2;
+            match self[middle].cmp(entry) {
+                std::cmp::Ordering::Less => low = middle + 1,
+                std::cmp::Ordering::Greater => high = middle,
+                std::cmp::Ordering::Equal => return Ok(middle),
+            }
+        }
+
+        return Err(low);
+    }
+
+    /// Iterate over the entries of these labels whose first dimensions are
+    /// equal to `prefix`, yielding the index of each matching entry together
+    /// with the full entry.
+    ///
+    /// This is useful with hierarchical labels, for example to get all the
+    /// blocks sharing the same `center_type` in a `TensorMap` with keys
+    /// `(center_type, neighbor_type)`. The entries are checked one by one in
+    /// Rust, without calling into metatensor-core. An empty `prefix` matches
+    /// all entries.
+    ///
+    /// # Panics
+    ///
+    /// If `prefix` has more values than these labels have dimensions.
+    #[inline]
+    pub fn iter_matching_prefix<'a, 'b>(&'a self, prefix: &'b [LabelValue]) -> impl Iterator<Item=(usize, &'a [LabelValue])> + 'b
+        where 'a: 'b
+    {
+        assert!(prefix.len() <= self.size(), "invalid size of prefix in Labels::iter_matching_prefix");
+
+        return self.iter()
+            .enumerate()
+            .filter(move |(_, entry)| entry.starts_with(prefix));
+    }
+
+    /// Check if `self` and `other` contain the same entries, regardless of
+    /// the order of these entries.
+    ///
+    /// The names of the labels must be the same and in the same order. This
+    /// uses [`Labels::contains`] to look up the entries of `self` in `other`,
+    /// and is linear in the number of entries.
+    #[inline]
+    pub fn equal_unordered(&self, other: &Labels) -> bool {
+        if self.names() != other.names() || self.count() != other.count() {
+            return false;
+        }
+
+        // entries in labels are unique, so checking that all entries of
+        // `self` are in `other` is enough if both have the same size
+        return self.iter().all(|entry| other.contains(entry));
+    }
+
     /// Take the union of `self` with `other`.
     ///
     /// If requested, this function can also give the positions in the union
@@ -388,6 +595,172 @@ impl Labels {
         }
     }
 
+    /// Take the cartesian product of `self` with `other`.
+    ///
+    /// The names of the resulting `Labels` are the names of `self` followed by
+    /// the names of `other`, and the entries contain every combination of one
+    /// entry of `self` followed by one entry of `other`. This returns an error
+    /// if `self` and `other` have a dimension name in common.
+    #[inline]
+    pub fn cartesian_product(&self, other: &Labels) -> Result<Labels, Error> {
+        let mut output = mts_labels_t::null();
+        unsafe {
+            check_status(crate::c_api::mts_labels_cartesian_product(
+                self.raw,
+                other.raw,
+                &mut output,
+            ))?;
+
+            return Ok(Labels::from_raw(output));
+        }
+    }
+
+    /// Get the positions of all the entries in this set of labels matching
+    /// the given `selection`.
+    ///
+    /// The `selection` contains pairs of dimension name and value, and an
+    /// entry matches if it has the corresponding value for all the dimensions
+    /// in the selection. This returns an error if one of the dimensions in the
+    /// selection is not part of these labels.
+    pub fn matches(&self, selection: &[(&str, LabelValue)]) -> Result<Vec<usize>, Error> {
+        let names = self.names();
+        let mut criteria = Vec::new();
+        for &(name, value) in selection {
+            if let Some(position) = names.iter().position(|&n| n == name) {
+                criteria.push((position, value));
+            } else {
+                return Err(Error {
+                    code: None,
+                    message: format!(
+                        "'{}' is not part of these Labels, the dimensions are [{}]",
+                        name, names.join(", ")
+                    ),
+                });
+            }
+        }
+
+        let matching = self.iter()
+            .enumerate()
+            .filter(|(_, entry)| criteria.iter().all(|&(i, value)| entry[i] == value))
+            .map(|(position, _)| position)
+            .collect();
+
+        return Ok(matching);
+    }
+
+    /// Get the positions of all the entries in this set of labels where the
+    /// `name` dimension takes a value between `min` and `max` (both
+    /// included).
+    ///
+    /// This can be used for range-based selection, for example to only keep
+    /// the properties with a radial index `n` between 0 and 4. This returns an
+    /// error if `name` is not part of these labels, or if `min` is larger
+    /// than `max`.
+    pub fn select_range(&self, name: &str, min: LabelValue, max: LabelValue) -> Result<Vec<usize>, Error> {
+        let names = self.names();
+        let dimension = names.iter().position(|&n| n == name).ok_or_else(|| Error {
+            code: None,
+            message: format!(
+                "'{}' is not part of these Labels, the dimensions are [{}]",
+                name, names.join(", ")
+            ),
+        })?;
+
+        if min > max {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "invalid range for '{}': min ({}) is larger than max ({})",
+                    name, min, max
+                ),
+            });
+        }
+
+        let matching = self.iter()
+            .enumerate()
+            .filter(|(_, entry)| min <= entry[dimension] && entry[dimension] <= max)
+            .map(|(position, _)| position)
+            .collect();
+
+        return Ok(matching);
+    }
+
+    /// Create new `Labels` containing only the dimensions in `names`, in the
+    /// requested order.
+    ///
+    /// Entries which become identical after removing the other dimensions are
+    /// only included once, at the position of their first occurrence. This
+    /// returns an error if one of the `names` is not part of these labels, or
+    /// if the same name is given multiple times.
+    pub fn project(&self, names: &[&str]) -> Result<Labels, Error> {
+        let all_names = self.names();
+        let mut positions = Vec::new();
+        for &name in names {
+            if let Some(position) = all_names.iter().position(|&n| n == name) {
+                positions.push(position);
+            } else {
+                return Err(Error {
+                    code: None,
+                    message: format!(
+                        "'{}' is not part of these Labels, the dimensions are [{}]",
+                        name, all_names.join(", ")
+                    ),
+                });
+            }
+        }
+
+        let mut builder = LabelsBuilder::new_unchecked(names.to_vec());
+        let mut seen = HashSet::new();
+        for entry in self {
+            let projected = positions.iter().map(|&i| entry[i]).collect::<Vec<_>>();
+            if seen.insert(projected.clone()) {
+                builder.add(&projected);
+            }
+        }
+
+        return builder.try_finish();
+    }
+
+    /// Create new `Labels` where every entry of these labels is repeated
+    /// `times` consecutively, with a new last dimension called `name`
+    /// containing the index of the repetition (from `0` to `times - 1`).
+    ///
+    /// Since all the entries in `Labels` must be unique, it is not possible to
+    /// only repeat the entries; the new `name` dimension is what makes the
+    /// repeated entries different from one another. For example, repeating
+    /// `system` labels `[[0], [1]]` twice with `name = "atom"` gives
+    /// `system, atom` labels `[[0, 0], [0, 1], [1, 0], [1, 1]]`, pairing each
+    /// original entry with a set of new sub-indices.
+    ///
+    /// This returns an error if `name` is already part of these labels.
+    pub fn repeat(&self, name: &str, times: usize) -> Result<Labels, Error> {
+        let mut names = self.names();
+        if names.contains(&name) {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "can not repeat Labels: '{}' is already part of these Labels",
+                    name
+                ),
+            });
+        }
+        names.push(name);
+
+        let mut builder = LabelsBuilder::new(names);
+        builder.reserve(self.count() * times);
+        let mut repeated = Vec::with_capacity(self.size() + 1);
+        for entry in self {
+            for i in 0..times {
+                repeated.clear();
+                repeated.extend_from_slice(entry);
+                repeated.push(LabelValue::from(i));
+                builder.add(&repeated);
+            }
+        }
+
+        return Ok(builder.finish());
+    }
+
     /// Iterate over the entries in this set of labels
     #[inline]
     pub fn iter(&self) -> LabelsIter<'_> {
@@ -601,6 +974,16 @@ impl LabelsBuilder {
         }
     }
 
+    /// Create a new empty `LabelsBuilder` with the given `names`, without
+    /// checking them. The names are validated in [`LabelsBuilder::try_finish`]
+    /// instead, which returns an error for duplicated or invalid names.
+    pub(crate) fn new_unchecked(names: Vec<&str>) -> LabelsBuilder {
+        LabelsBuilder {
+            names: names.into_iter().map(|s| s.into()).collect(),
+            values: Vec::new(),
+        }
+    }
+
     /// Reserve space for `additional` other entries in the labels.
     #[inline]
     pub fn reserve(&mut self, additional: usize) {
@@ -634,6 +1017,12 @@ impl LabelsBuilder {
     /// Finish building the `Labels`
     #[inline]
     pub fn finish(self) -> Labels {
+        return self.try_finish().expect("invalid labels?");
+    }
+
+    /// Finish building the `Labels`, returning an error if the names or
+    /// values are invalid.
+    pub(crate) fn try_finish(self) -> Result<Labels, Error> {
         let mut raw_names = Vec::new();
         let mut raw_names_ptr = Vec::new();
 
@@ -659,10 +1048,10 @@ impl LabelsBuilder {
         unsafe {
             check_status(
                 crate::c_api::mts_labels_create(&mut raw_labels)
-            ).expect("invalid labels?");
+            )?;
         }
 
-        return unsafe { Labels::from_raw(raw_labels) };
+        return Ok(unsafe { Labels::from_raw(raw_labels) });
     }
 }
 
@@ -693,6 +1082,102 @@ mod tests {
         assert_eq!(labels.count(), 0);
     }
 
+    #[test]
+    fn label_value_from_str() {
+        for value in [0, 1, -1, 42, -2413, i32::MAX, i32::MIN] {
+            let value = LabelValue::new(value);
+            assert_eq!(value.to_string().parse::<LabelValue>(), Ok(value));
+        }
+
+        assert_eq!("-7".parse::<LabelValue>(), Ok(LabelValue::new(-7)));
+        assert!("".parse::<LabelValue>().is_err());
+        assert!("1.5".parse::<LabelValue>().is_err());
+        assert!("foo".parse::<LabelValue>().is_err());
+        assert!("2147483648".parse::<LabelValue>().is_err());
+    }
+
+    #[test]
+    fn binary_search() {
+        let labels = Labels::new(["foo", "bar"], &[[0, 1], [0, 3], [1, 0], [2, 2]]);
+
+        let search = |entry: [i32; 2]| labels.binary_search(&[entry[0].into(), entry[1].into()]);
+        assert_eq!(search([0, 1]), Ok(0));
+        assert_eq!(search([1, 0]), Ok(2));
+        assert_eq!(search([2, 2]), Ok(3));
+        assert_eq!(search([-1, 0]), Err(0));
+        assert_eq!(search([0, 2]), Err(1));
+        assert_eq!(search([1, 5]), Err(3));
+        assert_eq!(search([3, 0]), Err(4));
+
+        let empty = Labels::empty(vec!["foo", "bar"]);
+        assert_eq!(empty.binary_search(&[0.into(), 0.into()]), Err(0));
+    }
+
+    #[test]
+    fn iter_matching_prefix() {
+        let labels = Labels::new(["foo", "bar", "baz"], &[[0, 1, 0], [1, 1, 0], [0, 3, 1], [0, 1, 2]]);
+
+        let matching = labels.iter_matching_prefix(&[LabelValue::new(0)]).collect::<Vec<_>>();
+        assert_eq!(matching.len(), 3);
+        assert_eq!(matching[0], (0, &[0, 1, 0].map(LabelValue::new)[..]));
+        assert_eq!(matching[1], (2, &[0, 3, 1].map(LabelValue::new)[..]));
+        assert_eq!(matching[2], (3, &[0, 1, 2].map(LabelValue::new)[..]));
+
+        let prefix = [LabelValue::new(0), LabelValue::new(1)];
+        let positions = labels.iter_matching_prefix(&prefix).map(|(i, _)| i).collect::<Vec<_>>();
+        assert_eq!(positions, [0, 3]);
+
+        assert_eq!(labels.iter_matching_prefix(&[LabelValue::new(2)]).count(), 0);
+        assert_eq!(labels.iter_matching_prefix(&[]).count(), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid size of prefix in Labels::iter_matching_prefix")]
+    fn iter_matching_prefix_wrong_size() {
+        let labels = Labels::new(["foo"], &[[2]]);
+        let _ = labels.iter_matching_prefix(&[LabelValue::new(2), LabelValue::new(3)]);
+    }
+
+    #[test]
+    fn same_names() {
+        let labels = Labels::new(["foo", "bar"], &[[0, 1], [0, 3]]);
+        let other = Labels::new(["foo", "bar"], &[[2, 2]]);
+        assert!(labels.same_names(&other));
+        labels.assert_same_names(&other).unwrap();
+
+        let renamed = Labels::new(["bar", "foo"], &[[0, 1], [0, 3]]);
+        assert!(!labels.same_names(&renamed));
+
+        let error = labels.assert_same_names(&renamed).unwrap_err();
+        assert_eq!(error.message, "expected Labels with the same names, got [foo, bar] and [bar, foo]");
+
+        assert!(Labels::single().same_names(&Labels::single()));
+        assert!(!Labels::single().same_names(&labels));
+    }
+
+    #[test]
+    fn equal_unordered() {
+        let labels = Labels::new(["foo", "bar"], &[[0, 1], [0, 3], [1, 0]]);
+
+        let shuffled = Labels::new(["foo", "bar"], &[[1, 0], [0, 1], [0, 3]]);
+        assert_ne!(labels, shuffled);
+        assert!(labels.equal_unordered(&shuffled));
+        assert!(shuffled.equal_unordered(&labels));
+
+        let other = Labels::new(["foo", "bar"], &[[1, 0], [0, 1], [0, 2]]);
+        assert!(!labels.equal_unordered(&other));
+
+        let subset = Labels::new(["foo", "bar"], &[[1, 0], [0, 1]]);
+        assert!(!labels.equal_unordered(&subset));
+        assert!(!subset.equal_unordered(&labels));
+
+        let renamed = Labels::new(["bar", "foo"], &[[0, 1], [0, 3], [1, 0]]);
+        assert!(!labels.equal_unordered(&renamed));
+
+        let empty = Labels::empty(vec!["foo", "bar"]);
+        assert!(empty.equal_unordered(&Labels::empty(vec!["foo", "bar"])));
+    }
+
     #[test]
     fn direct_construct() {
         let labels = Labels::new(
@@ -713,6 +1198,39 @@ mod tests {
         assert_eq!(labels[2], [-4, -2413]);
     }
 
+    #[test]
+    fn row() {
+        let labels = Labels::new(["foo", "bar"], &[[2, 3], [1, 243], [-4, -2413]]);
+
+        assert_eq!(labels.row(0).unwrap(), [2, 3]);
+        assert_eq!(labels.row(2).unwrap(), [-4, -2413]);
+        assert_eq!(labels.row(3), None);
+
+        for (i, entry) in labels.iter().enumerate() {
+            assert_eq!(labels.position(entry), Some(i));
+            assert_eq!(labels.row(i), Some(entry));
+        }
+
+        assert_eq!(Labels::empty(vec!["foo"]).row(0), None);
+    }
+
+    #[test]
+    fn contains() {
+        let labels = Labels::new(["foo", "bar"], &[[2, 3], [1, 243], [-4, -2413]]);
+
+        assert!(labels.contains(&[LabelValue::new(2), LabelValue::new(3)]));
+        assert!(labels.contains(&[LabelValue::new(-4), LabelValue::new(-2413)]));
+        assert!(!labels.contains(&[LabelValue::new(3), LabelValue::new(2)]));
+        assert!(!Labels::empty(vec!["foo", "bar"]).contains(&[LabelValue::new(2), LabelValue::new(3)]));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid size of index in Labels::position")]
+    fn contains_wrong_size() {
+        let labels = Labels::new(["foo", "bar"], &[[2, 3]]);
+        labels.contains(&[LabelValue::new(2)]);
+    }
+
     #[test]
     fn labels_iter() {
         let mut builder = LabelsBuilder::new(vec!["foo", "bar"]);
@@ -842,4 +1360,137 @@ mod tests {
         assert_eq!(first_mapping, [-1, 0]);
         assert_eq!(second_mapping, [-1, 0, -1]);
     }
+
+    #[test]
+    fn cartesian_product() {
+        let first = Labels::new(["aa", "bb"], &[[0, 1], [1, 2]]);
+        let second = Labels::new(["cc"], &[[2], [3], [4]]);
+
+        let product = first.cartesian_product(&second).unwrap();
+        assert_eq!(product.names(), ["aa", "bb", "cc"]);
+        assert_eq!(product.values(), [
+            0, 1, 2, 0, 1, 3, 0, 1, 4,
+            1, 2, 2, 1, 2, 3, 1, 2, 4,
+        ]);
+
+        let error = first.cartesian_product(&first).unwrap_err();
+        assert_eq!(
+            error.message,
+            "invalid parameter: can not take the cartesian product of these \
+            Labels, they both contain the 'aa' dimension"
+        );
+    }
+
+    #[test]
+    fn matches() {
+        let labels = Labels::new(["aa", "bb", "cc"], &[
+            [0, 1, 2], [0, 2, 2], [1, 1, 2], [0, 1, 3],
+        ]);
+
+        let matching = labels.matches(&[("aa", LabelValue::new(0))]).unwrap();
+        assert_eq!(matching, [0, 1, 3]);
+
+        let selection = [("aa", LabelValue::new(0)), ("bb", LabelValue::new(1))];
+        assert_eq!(labels.matches(&selection).unwrap(), [0, 3]);
+
+        let selection = [("cc", LabelValue::new(2)), ("bb", LabelValue::new(1))];
+        assert_eq!(labels.matches(&selection).unwrap(), [0, 2]);
+
+        let selection = [("aa", LabelValue::new(5))];
+        assert!(labels.matches(&selection).unwrap().is_empty());
+
+        // an empty selection matches everything
+        assert_eq!(labels.matches(&[]).unwrap(), [0, 1, 2, 3]);
+
+        let error = labels.matches(&[("dd", LabelValue::new(0))]).unwrap_err();
+        assert_eq!(error.message, "'dd' is not part of these Labels, the dimensions are [aa, bb, cc]");
+    }
+
+    #[test]
+    fn select_range() {
+        let labels = Labels::new(["l", "n"], &[[0, 0], [0, 3], [1, 5], [1, 4], [2, -1]]);
+
+        let selected = labels.select_range("n", LabelValue::new(0), LabelValue::new(4)).unwrap();
+        assert_eq!(selected, [0, 1, 3]);
+
+        let selected = labels.select_range("l", LabelValue::new(1), LabelValue::new(1)).unwrap();
+        assert_eq!(selected, [2, 3]);
+
+        let selected = labels.select_range("n", LabelValue::new(6), LabelValue::new(10)).unwrap();
+        assert!(selected.is_empty());
+
+        let error = labels.select_range("m", LabelValue::new(0), LabelValue::new(1)).unwrap_err();
+        assert_eq!(error.message, "'m' is not part of these Labels, the dimensions are [l, n]");
+
+        let error = labels.select_range("n", LabelValue::new(4), LabelValue::new(0)).unwrap_err();
+        assert_eq!(error.message, "invalid range for 'n': min (4) is larger than max (0)");
+    }
+
+    #[test]
+    fn project() {
+        let labels = Labels::new(["aa", "bb", "cc"], &[
+            [0, 1, 2], [0, 2, 2], [1, 1, 2], [0, 1, 3],
+        ]);
+
+        let projected = labels.project(&["cc", "aa"]).unwrap();
+        assert_eq!(projected, Labels::new(["cc", "aa"], &[[2, 0], [2, 1], [3, 0]]));
+
+        let projected = labels.project(&["bb"]).unwrap();
+        assert_eq!(projected, Labels::new(["bb"], &[[1], [2]]));
+
+        let error = labels.project(&["aa", "dd"]).unwrap_err();
+        assert_eq!(error.message, "'dd' is not part of these Labels, the dimensions are [aa, bb, cc]");
+
+        let error = labels.project(&["aa", "bb", "aa"]).unwrap_err();
+        assert_eq!(error.message, "invalid parameter: labels names must be unique, got 'aa' multiple times");
+    }
+
+    #[test]
+    fn repeat() {
+        let labels = Labels::new(["system", "atom"], &[[0, 1], [1, 0]]);
+
+        let repeated = labels.repeat("neighbor", 3).unwrap();
+        assert_eq!(repeated, Labels::new(["system", "atom", "neighbor"], &[
+            [0, 1, 0], [0, 1, 1], [0, 1, 2], [1, 0, 0], [1, 0, 1], [1, 0, 2],
+        ]));
+
+        let repeated = labels.repeat("neighbor", 0).unwrap();
+        assert_eq!(repeated, Labels::empty(vec!["system", "atom", "neighbor"]));
+
+        let error = labels.repeat("atom", 2).unwrap_err();
+        assert_eq!(error.message, "can not repeat Labels: 'atom' is already part of these Labels");
+    }
+
+    #[test]
+    fn from_columns() {
+        let center = vec![LabelValue::new(0), LabelValue::new(0), LabelValue::new(1)];
+        let species = vec![LabelValue::new(1), LabelValue::new(8), LabelValue::new(1)];
+
+        let labels = Labels::from_columns(vec!["center", "species"], vec![center.clone(), species.clone()]).unwrap();
+        assert_eq!(labels, Labels::new(["center", "species"], &[[0, 1], [0, 8], [1, 1]]));
+
+        let labels = Labels::from_columns(vec!["center", "species"], vec![vec![], vec![]]).unwrap();
+        assert_eq!(labels, Labels::empty(vec!["center", "species"]));
+
+        let error = Labels::from_columns(vec!["center"], vec![center.clone(), species.clone()]).unwrap_err();
+        assert_eq!(error.message, "expected one column for each of the 1 names, got 2 columns");
+
+        let error = Labels::from_columns(vec!["center", "species"], vec![center.clone(), vec![LabelValue::new(1)]]).unwrap_err();
+        assert_eq!(error.message, "all columns must have the same length, but the column for 'species' has 1 values instead of 3");
+
+        let error = Labels::from_columns(vec!["center", "species"], vec![center, vec![LabelValue::new(1); 3]]).unwrap_err();
+        assert_eq!(error.message, "invalid parameter: can not have the same label value multiple time: [0, 1] is already present at position 0");
+    }
+
+    #[test]
+    fn new_single_entry() {
+        let labels = Labels::new_single_entry(vec!["center", "species"], vec![LabelValue::new(3), LabelValue::new(8)]).unwrap();
+        assert_eq!(labels, Labels::new(["center", "species"], &[[3, 8]]));
+
+        let error = Labels::new_single_entry(vec!["center", "species"], vec![LabelValue::new(3)]).unwrap_err();
+        assert_eq!(error.message, "expected one value for each of the 2 names, got 1 values");
+
+        let error = Labels::new_single_entry(vec!["center", "center"], vec![LabelValue::new(3), LabelValue::new(8)]).unwrap_err();
+        assert_eq!(error.message, "invalid parameter: labels names must be unique, got 'center' multiple times");
+    }
 }
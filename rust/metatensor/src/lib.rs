@@ -19,6 +19,20 @@
 //! [dependencies]
 //! metatensor = {version = "...", features = ["static"]}
 //! ```
+//!
+//! ## Accessing the data
+//!
+//! Most functions in this crate only manipulate the metadata, and move the
+//! data around with the functions in `mts_array_t` (`create`,
+//! `move_samples_from`, …), which works with any kind of array. Some
+//! functions also need to read or modify the values themselves (for example
+//! [`TensorMap::sum_over_all_samples`] or [`TensorMap::apply`]), and do so
+//! through `mts_array_t.data`. Read-only accesses go through
+//! [`ArrayRef::data`], which reads `ndarray::ArrayD<f64>` directly without
+//! requiring mutable access to the array. This requires the data to be
+//! stored as 64-bit floating point values on CPU, and any error from
+//! `mts_array_t.data` (for example for arrays stored on other devices) is
+//! propagated by these functions.
 
 #![warn(clippy::all, clippy::pedantic)]
 
@@ -37,7 +51,7 @@ pub use self::errors::Error;
 
 mod data;
 pub use self::data::{ArrayRef, ArrayRefMut};
-pub use self::data::{Array, EmptyArray};
+pub use self::data::{Array, ArrayDType, EmptyArray};
 
 mod labels;
 pub use self::labels::{Labels, LabelsBuilder, LabelValue};
@@ -53,9 +67,21 @@ pub use self::block::{GradientsIter, GradientsMutIter};
 pub use self::block::LazyMetadata;
 
 mod tensor;
-pub use self::tensor::TensorMap;
+pub use self::tensor::{TensorMap, MetadataAxis, SampleOrigin, ApplyGradients};
 pub use self::tensor::{TensorMapIter, TensorMapIterMut};
 #[cfg(feature = "rayon")]
 pub use self::tensor::{TensorMapParIter, TensorMapParIterMut};
 
+mod schema;
+pub use self::schema::{TensorMapSchema, TensorBlockSchema, LabelsSchema};
+
+mod reduce;
+mod contract;
+mod pad;
+mod diff;
+mod split;
+
+mod stats;
+pub use self::stats::BlockStats;
+
 pub mod io;
@@ -0,0 +1,159 @@
+use ndarray::Axis;
+
+use crate::reduce::array_to_ndarray;
+use crate::{Error, Labels, LabelsBuilder, LabelValue, TensorBlock, TensorBlockRef};
+
+impl TensorBlock {
+    /// Create a new block with `target` as samples, filling the samples
+    /// missing from this block with `fill`.
+    ///
+    /// See [`TensorBlockRef::pad_samples`] for more information.
+    #[inline]
+    pub fn pad_samples(&self, target: &Labels, fill: f64) -> Result<TensorBlock, Error> {
+        return self.as_ref().pad_samples(target, fill);
+    }
+}
+
+impl TensorBlockRef<'_> {
+    /// Create a new block with `target` as samples, filling the samples
+    /// missing from this block with `fill`.
+    ///
+    /// `target` must have the same names as the samples of this block, and
+    /// contain all of these samples (in any order). The values for each
+    /// existing sample are copied to the position of this sample in
+    /// `target`, and the values for all other samples are set to `fill`.
+    /// This can be used to pad multiple blocks to the same shape, for
+    /// example when batching structures of different sizes.
+    ///
+    /// The gradients are copied with their `"sample"` dimension updated to
+    /// refer to the new positions, and no gradient samples are added for the
+    /// padded samples.
+    ///
+    /// The data is read through `mts_array_t.data`, see the [crate
+    /// documentation](crate#accessing-the-data). The new block always stores
+    /// its data in `ndarray::ArrayD<f64>`.
+    pub fn pad_samples(&self, target: &Labels, fill: f64) -> Result<TensorBlock, Error> {
+        let samples = self.samples();
+        if samples.names() != target.names() {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "can not pad samples: expected target samples with names [{}], got [{}]",
+                    samples.names().join(", "), target.names().join(", ")
+                ),
+            });
+        }
+
+        let mut mapping = Vec::with_capacity(samples.count());
+        for sample in &samples {
+            let position = target.position(sample).ok_or_else(|| Error {
+                code: None,
+                message: format!(
+                    "can not pad samples: sample ({}) is not part of the target samples",
+                    sample.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+                ),
+            })?;
+            mapping.push(position);
+        }
+
+        let values = array_to_ndarray(&self.values())?;
+        let mut shape = values.shape().to_vec();
+        shape[0] = target.count();
+        let mut padded = ndarray::ArrayD::from_elem(shape, fill);
+        for (sample_i, &position) in mapping.iter().enumerate() {
+            padded.index_axis_mut(Axis(0), position).assign(&values.index_axis(Axis(0), sample_i));
+        }
+
+        let properties = self.properties();
+        let mut new_block = TensorBlock::new(padded, target, &self.components(), &properties)?;
+
+        for (parameter, gradient) in self.gradients() {
+            if !gradient.gradient_list().is_empty() {
+                return Err(Error {
+                    code: None,
+                    message: "gradients of gradients are not supported in pad_samples".into(),
+                });
+            }
+
+            let gradient_samples = gradient.samples();
+            let mut builder = LabelsBuilder::new(gradient_samples.names());
+            builder.reserve(gradient_samples.count());
+            for sample in &gradient_samples {
+                let mut new_sample = sample.to_vec();
+                new_sample[0] = LabelValue::from(mapping[sample[0].usize()]);
+                builder.add(&new_sample);
+            }
+
+            let new_gradient = TensorBlock::new(
+                array_to_ndarray(&gradient.values())?,
+                &builder.finish(),
+                &gradient.components(),
+                &properties,
+            )?;
+            new_block.add_gradient(parameter, new_gradient)?;
+        }
+
+        return Ok(new_block);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Labels, TensorBlock};
+
+    #[test]
+    fn pad_samples() {
+        let mut block = TensorBlock::new(
+            ndarray::arr2(&[[1.0, 2.0], [3.0, 4.0]]).into_dyn(),
+            &Labels::new(["system", "atom"], &[[0, 1], [0, 0]]),
+            &[],
+            &Labels::new(["properties"], &[[0], [1]]),
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            ndarray::arr3(&[[[1.0, 1.0]], [[2.0, 2.0]]]).into_dyn(),
+            &Labels::new(["sample", "atom"], &[[0, 0], [1, 1]]),
+            &[Labels::new(["xyz"], &[[0]])],
+            &Labels::new(["properties"], &[[0], [1]]),
+        ).unwrap();
+        block.add_gradient("positions", gradient).unwrap();
+
+        let target = Labels::new(["system", "atom"], &[[0, 0], [0, 1], [0, 2]]);
+        let padded = block.pad_samples(&target, -1.0).unwrap();
+
+        assert_eq!(padded.samples(), target);
+        assert_eq!(
+            padded.values().as_array(),
+            ndarray::arr2(&[[3.0, 4.0], [1.0, 2.0], [-1.0, -1.0]]).into_dyn()
+        );
+
+        let gradient = padded.as_ref().gradient("positions").unwrap();
+        assert_eq!(gradient.samples(), Labels::new(["sample", "atom"], &[[1, 0], [0, 1]]));
+        assert_eq!(
+            gradient.values().as_array(),
+            ndarray::arr3(&[[[1.0, 1.0]], [[2.0, 2.0]]]).into_dyn()
+        );
+    }
+
+    #[test]
+    fn pad_samples_errors() {
+        let block = TensorBlock::new(
+            ndarray::arr2(&[[1.0], [3.0]]).into_dyn(),
+            &Labels::new(["system"], &[[0], [1]]),
+            &[],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap();
+
+        let error = block.pad_samples(&Labels::new(["atom"], &[[0], [1]]), 0.0).unwrap_err();
+        assert_eq!(
+            error.message,
+            "can not pad samples: expected target samples with names [system], got [atom]"
+        );
+
+        let error = block.pad_samples(&Labels::new(["system"], &[[0], [2]]), 0.0).unwrap_err();
+        assert_eq!(
+            error.message,
+            "can not pad samples: sample (1) is not part of the target samples"
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use crate::data::sum_along_axis;
+use crate::{ArrayRef, Error, Labels, LabelsBuilder, LabelValue};
+use crate::{TensorBlock, TensorBlockRef, TensorMap};
+
+impl TensorMap {
+    /// Sum the values of each block over all of its samples, creating a new
+    /// `TensorMap` where each block contains a single sample.
+    ///
+    /// The samples of the new blocks are given by [`Labels::single`], while
+    /// the components and properties are unchanged. The gradients are summed
+    /// in the same way, merging all the gradient samples which only differ by
+    /// the `"sample"` dimension.
+    ///
+    /// The data is read through `mts_array_t.data`, see the [crate
+    /// documentation](crate#accessing-the-data). The new arrays are created
+    /// with `mts_array_t.create`, and use the same backend as the current
+    /// arrays.
+    pub fn sum_over_all_samples(&self) -> Result<TensorMap, Error> {
+        let blocks = self.blocks().into_iter()
+            .map(sum_block_over_all_samples)
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        return TensorMap::new(self.keys().clone(), blocks);
+    }
+}
+
+/// Get a copy of the data in `array` as a `ndarray::ArrayD<f64>`, reading it
+/// with [`ArrayRef::data`].
+pub(crate) fn array_to_ndarray(array: &ArrayRef<'_>) -> Result<ndarray::ArrayD<f64>, Error> {
+    let shape = array.as_raw().shape()?.to_vec();
+    let data = array.data()?.to_vec();
+
+    return Ok(ndarray::ArrayD::from_shape_vec(shape, data).expect("invalid shape for the data"));
+}
+
+fn sum_block_over_all_samples(block: TensorBlockRef<'_>) -> Result<TensorBlock, Error> {
+    let summed = block.values().sum_axis(0)?;
+
+    let properties = block.properties();
+    let mut new_block = TensorBlock::from_raw_values(
+        summed,
+        &Labels::single(),
+        &block.components(),
+        &properties,
+    )?;
+
+    for (parameter, gradient) in block.gradients() {
+        if !gradient.gradient_list().is_empty() {
+            return Err(Error {
+                code: None,
+                message: "gradients of gradients are not supported in sum_over_all_samples".into(),
+            });
+        }
+
+        // all the gradient samples now refer to the single sample of the
+        // values, and gradient samples which only differed by the "sample"
+        // dimension are merged together
+        let samples = gradient.samples();
+        let mut new_samples = Vec::new();
+        let mut positions = HashMap::new();
+        let mut mapping = Vec::with_capacity(samples.count());
+        for sample in &samples {
+            let mut new_sample = sample.to_vec();
+            new_sample[0] = LabelValue::new(0);
+
+            let position = *positions.entry(new_sample.clone()).or_insert_with(|| {
+                new_samples.push(new_sample);
+                new_samples.len() - 1
+            });
+            mapping.push(position);
+        }
+
+        let mut builder = LabelsBuilder::new(samples.names());
+        builder.reserve(new_samples.len());
+        for sample in &new_samples {
+            builder.add(sample);
+        }
+
+        let summed = sum_along_axis(&gradient.values(), 0, &mapping, new_samples.len())?;
+        let new_gradient = TensorBlock::from_raw_values(
+            summed,
+            &builder.finish(),
+            &gradient.components(),
+            &properties,
+        )?;
+        new_block.add_gradient(parameter, new_gradient)?;
+    }
+
+    return Ok(new_block);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Labels, TensorBlock, TensorMap};
+
+    #[test]
+    fn sum_over_all_samples() {
+        let mut block = TensorBlock::new(
+            ndarray::arr2(&[[1.0, 2.0], [3.0, 4.0], [5.0, 6.0]]).into_dyn(),
+            &Labels::new(["system", "atom"], &[[0, 0], [0, 1], [1, 0]]),
+            &[],
+            &Labels::new(["properties"], &[[0], [1]]),
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            ndarray::arr3(&[[[1.0, 1.0]], [[2.0, 2.0]], [[4.0, 4.0]]]).into_dyn(),
+            &Labels::new(["sample", "atom"], &[[0, 0], [1, 0], [2, 1]]),
+            &[Labels::new(["xyz"], &[[0]])],
+            &Labels::new(["properties"], &[[0], [1]]),
+        ).unwrap();
+        block.add_gradient("positions", gradient).unwrap();
+
+        let tensor = TensorMap::new(Labels::new(["key"], &[[0]]), vec![block]).unwrap();
+        let summed = tensor.sum_over_all_samples().unwrap();
+
+        assert_eq!(summed.keys(), tensor.keys());
+
+        let block = summed.block_by_id(0);
+        assert_eq!(block.samples(), Labels::single());
+        assert_eq!(block.properties(), Labels::new(["properties"], &[[0], [1]]));
+        assert_eq!(block.values().as_array(), ndarray::arr2(&[[9.0, 12.0]]).into_dyn());
+
+        let gradient = block.gradient("positions").unwrap();
+        assert_eq!(gradient.samples(), Labels::new(["sample", "atom"], &[[0, 0], [0, 1]]));
+        assert_eq!(gradient.components(), [Labels::new(["xyz"], &[[0]])]);
+        assert_eq!(
+            gradient.values().as_array(),
+            ndarray::arr3(&[[[3.0, 3.0]], [[4.0, 4.0]]]).into_dyn()
+        );
+    }
+
+    #[test]
+    fn sum_over_all_samples_empty() {
+        let block = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![0, 2], 1.0),
+            &Labels::empty(vec!["system"]),
+            &[],
+            &Labels::new(["properties"], &[[0], [1]]),
+        ).unwrap();
+
+        let tensor = TensorMap::new(Labels::new(["key"], &[[0]]), vec![block]).unwrap();
+        let summed = tensor.sum_over_all_samples().unwrap();
+
+        let block = summed.block_by_id(0);
+        assert_eq!(block.samples(), Labels::single());
+        assert_eq!(block.values().as_array(), ndarray::arr2(&[[0.0, 0.0]]).into_dyn());
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::BTreeMap;
+
+use crate::labels::format_key;
+use crate::{Error, Labels, TensorBlockRef, TensorMap};
+
+/// Description of the structure of a [`TensorMap`], without any of the data.
+///
+/// This contains the keys of the tensor map, and the names and sizes of the
+/// metadata for each block. When the `serde` feature is enabled, this can be
+/// serialized (for example to JSON) to check that some tensor map has the
+/// expected structure without loading all the data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TensorMapSchema {
+    /// names of the dimensions of the keys
+    pub key_names: Vec<String>,
+    /// values of the keys, one entry for each block
+    pub keys: Vec<Vec<i32>>,
+    /// description of each block, in the same order as the keys
+    pub blocks: Vec<TensorBlockSchema>,
+}
+
+/// Description of the structure of a single block, see [`TensorMapSchema`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TensorBlockSchema {
+    /// description of the sample labels
+    pub samples: LabelsSchema,
+    /// description of the component labels
+    pub components: Vec<LabelsSchema>,
+    /// description of the property labels
+    pub properties: LabelsSchema,
+    /// description of the gradients of this block, indexed by the gradient
+    /// parameter
+    pub gradients: BTreeMap<String, TensorBlockSchema>,
+}
+
+/// Names and number of entries of a set of [`Labels`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LabelsSchema {
+    /// names of the dimensions of the labels
+    pub names: Vec<String>,
+    /// number of entries in the labels
+    pub count: usize,
+}
+
+impl LabelsSchema {
+    fn new(labels: &Labels) -> LabelsSchema {
+        LabelsSchema {
+            names: labels.names().into_iter().map(String::from).collect(),
+            count: labels.count(),
+        }
+    }
+}
+
+impl TensorBlockSchema {
+    fn new(block: TensorBlockRef<'_>) -> TensorBlockSchema {
+        let mut gradients = BTreeMap::new();
+        for (parameter, gradient) in block.gradients() {
+            gradients.insert(parameter.into(), TensorBlockSchema::new(gradient));
+        }
+
+        TensorBlockSchema {
+            samples: LabelsSchema::new(&block.samples()),
+            components: block.components().iter().map(LabelsSchema::new).collect(),
+            properties: LabelsSchema::new(&block.properties()),
+            gradients: gradients,
+        }
+    }
+}
+
+impl TensorMap {
+    /// Get a description of the structure of this `TensorMap`, containing the
+    /// keys and the names and sizes of the metadata of all blocks, but none
+    /// of the data.
+    pub fn schema(&self) -> TensorMapSchema {
+        let keys = self.keys();
+        TensorMapSchema {
+            key_names: keys.names().into_iter().map(String::from).collect(),
+            keys: keys.iter().map(|key| key.iter().map(|v| v.i32()).collect()).collect(),
+            blocks: self.blocks().into_iter().map(TensorBlockSchema::new).collect(),
+        }
+    }
+
+    /// Check that this `TensorMap` matches the structure described by
+    /// `schema`, returning an error describing the first difference if it
+    /// does not.
+    ///
+    /// This compares the names and values of the keys, and for each block
+    /// (and recursively each gradient) the names and number of entries of
+    /// the samples, components and properties. The values of the labels
+    /// inside the blocks and the data are not checked.
+    pub fn validate_against(&self, schema: &TensorMapSchema) -> Result<(), Error> {
+        let actual = self.schema();
+        let error = |message| Error { code: None, message };
+
+        if actual.key_names != schema.key_names {
+            return Err(error(format!(
+                "expected keys with names [{}], got [{}]",
+                schema.key_names.join(", "), actual.key_names.join(", ")
+            )));
+        }
+
+        if actual.keys.len() != schema.keys.len() {
+            return Err(error(format!(
+                "expected {} blocks, got {}", schema.keys.len(), actual.keys.len()
+            )));
+        }
+
+        let iter = actual.keys.iter().zip(&schema.keys).zip(actual.blocks.iter().zip(&schema.blocks));
+        for (block_i, ((actual_key, key), (actual_block, block))) in iter.enumerate() {
+            if actual_key != key {
+                return Err(error(format!(
+                    "expected key ({}) for block {}, got ({})",
+                    format_key(&schema.key_names, key), block_i,
+                    format_key(&schema.key_names, actual_key)
+                )));
+            }
+
+            let context = format!("block for key ({})", format_key(&schema.key_names, key));
+            compare_blocks(&context, actual_block, block).map_err(error)?;
+        }
+
+        return Ok(());
+    }
+}
+
+/// Compare the `actual` and `expected` schema for a single set of labels,
+/// returning an error message if they differ.
+fn compare_labels(context: &str, axis: &str, actual: &LabelsSchema, expected: &LabelsSchema) -> Result<(), String> {
+    if actual.names != expected.names {
+        return Err(format!(
+            "{}: expected {} with names [{}], got [{}]",
+            context, axis, expected.names.join(", "), actual.names.join(", ")
+        ));
+    }
+
+    if actual.count != expected.count {
+        return Err(format!(
+            "{}: expected {} {} entries, got {}",
+            context, expected.count, axis, actual.count
+        ));
+    }
+
+    return Ok(());
+}
+
+/// Compare the `actual` and `expected` schema for a block and all of its
+/// gradients, returning an error message if they differ.
+fn compare_blocks(context: &str, actual: &TensorBlockSchema, expected: &TensorBlockSchema) -> Result<(), String> {
+    compare_labels(context, "samples", &actual.samples, &expected.samples)?;
+
+    if actual.components.len() != expected.components.len() {
+        return Err(format!(
+            "{}: expected {} components, got {}",
+            context, expected.components.len(), actual.components.len()
+        ));
+    }
+
+    for (actual_component, component) in actual.components.iter().zip(&expected.components) {
+        compare_labels(context, "components", actual_component, component)?;
+    }
+
+    compare_labels(context, "properties", &actual.properties, &expected.properties)?;
+
+    let actual_parameters = actual.gradients.keys().cloned().collect::<Vec<_>>();
+    let parameters = expected.gradients.keys().cloned().collect::<Vec<_>>();
+    if actual_parameters != parameters {
+        return Err(format!(
+            "{}: expected gradients with respect to [{}], got [{}]",
+            context, parameters.join(", "), actual_parameters.join(", ")
+        ));
+    }
+
+    for (parameter, gradient) in &expected.gradients {
+        let gradient_context = format!("{}, gradient with respect to '{}'", context, parameter);
+        compare_blocks(&gradient_context, &actual.gradients[parameter], gradient)?;
+    }
+
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Labels, TensorBlock, TensorMap};
+    use super::*;
+
+    #[test]
+    fn schema() {
+        let mut block = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![2, 3, 1], 1.0),
+            &Labels::new(["system"], &[[0], [1]]),
+            &[Labels::new(["xyz"], &[[0], [1], [2]])],
+            &Labels::new(["n"], &[[4]]),
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![1, 3, 3, 1], 1.0),
+            &Labels::new(["sample", "atom"], &[[0, 2]]),
+            &[Labels::new(["direction"], &[[0], [1], [2]]), Labels::new(["xyz"], &[[0], [1], [2]])],
+            &Labels::new(["n"], &[[4]]),
+        ).unwrap();
+        block.add_gradient("positions", gradient).unwrap();
+
+        let tensor = TensorMap::new(Labels::new(["center"], &[[6]]), vec![block]).unwrap();
+        let schema = tensor.schema();
+
+        assert_eq!(schema.key_names, ["center"]);
+        assert_eq!(schema.keys, [[6]]);
+        assert_eq!(schema.blocks.len(), 1);
+
+        let block = &schema.blocks[0];
+        assert_eq!(block.samples, LabelsSchema { names: vec!["system".into()], count: 2 });
+        assert_eq!(block.components, [LabelsSchema { names: vec!["xyz".into()], count: 3 }]);
+        assert_eq!(block.properties, LabelsSchema { names: vec!["n".into()], count: 1 });
+
+        let gradient = &block.gradients["positions"];
+        assert_eq!(gradient.samples, LabelsSchema { names: vec!["sample".into(), "atom".into()], count: 1 });
+        assert_eq!(gradient.components.len(), 2);
+        assert!(gradient.gradients.is_empty());
+    }
+
+    #[test]
+    fn validate_against() {
+        let mut block = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![2, 1], 1.0),
+            &Labels::new(["system"], &[[0], [1]]),
+            &[],
+            &Labels::new(["n"], &[[4]]),
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![1, 3, 1], 1.0),
+            &Labels::new(["sample", "atom"], &[[0, 2]]),
+            &[Labels::new(["xyz"], &[[0], [1], [2]])],
+            &Labels::new(["n"], &[[4]]),
+        ).unwrap();
+        block.add_gradient("positions", gradient).unwrap();
+
+        let tensor = TensorMap::new(Labels::new(["center"], &[[6]]), vec![block]).unwrap();
+        let schema = tensor.schema();
+        tensor.validate_against(&schema).unwrap();
+
+        let mut wrong = schema.clone();
+        wrong.key_names = vec!["species".into()];
+        let error = tensor.validate_against(&wrong).unwrap_err();
+        assert_eq!(error.message, "expected keys with names [species], got [center]");
+
+        let mut wrong = schema.clone();
+        wrong.keys[0][0] = 8;
+        let error = tensor.validate_against(&wrong).unwrap_err();
+        assert_eq!(error.message, "expected key (center = 8) for block 0, got (center = 6)");
+
+        let mut wrong = schema.clone();
+        wrong.blocks[0].samples.count = 3;
+        let error = tensor.validate_against(&wrong).unwrap_err();
+        assert_eq!(error.message, "block for key (center = 6): expected 3 samples entries, got 2");
+
+        let mut wrong = schema.clone();
+        wrong.blocks[0].gradients.get_mut("positions").unwrap().components[0].names = vec!["abc".into()];
+        let error = tensor.validate_against(&wrong).unwrap_err();
+        assert_eq!(
+            error.message,
+            "block for key (center = 6), gradient with respect to 'positions': \
+            expected components with names [abc], got [xyz]"
+        );
+
+        let mut wrong = schema;
+        wrong.blocks[0].gradients.clear();
+        let error = tensor.validate_against(&wrong).unwrap_err();
+        assert_eq!(error.message, "block for key (center = 6): expected gradients with respect to [], got [positions]");
+    }
+}
@@ -0,0 +1,199 @@
+use crate::c_api::{mts_array_t, mts_sample_mapping_t};
+use crate::data::create_array;
+use crate::{ArrayRef, Error, LabelsBuilder, LabelValue, TensorBlock, TensorBlockRef, TensorMap};
+
+impl TensorMap {
+    /// Split the blocks of this `TensorMap` along the samples, so that no
+    /// block contains more than `max_samples` samples.
+    ///
+    /// Each block is split in chunks of consecutive samples, and the keys of
+    /// the new `TensorMap` contain an additional `__chunk` dimension with the
+    /// index of the chunk, to keep the keys unique. Blocks with
+    /// `max_samples` samples or fewer (including empty blocks) are kept as a
+    /// single chunk, with `__chunk=0`. The gradients are split together with
+    /// the values, keeping only the gradient samples referring to the
+    /// samples of each chunk. This can be used to process very large blocks
+    /// a few samples at the time, for example to limit memory usage.
+    ///
+    /// The data is copied to new arrays created with `mts_array_t.create`,
+    /// using `mts_array_t.move_samples_from`, and can use any array backend.
+    pub fn split_block_samples(&self, max_samples: usize) -> Result<TensorMap, Error> {
+        if max_samples == 0 {
+            return Err(Error {
+                code: None,
+                message: "can not split blocks: max_samples must be at least 1".into(),
+            });
+        }
+
+        let keys = self.keys();
+        let mut names = keys.names();
+        if names.contains(&"__chunk") {
+            return Err(Error {
+                code: None,
+                message: "can not split blocks: '__chunk' is already part of the keys".into(),
+            });
+        }
+        names.push("__chunk");
+
+        let mut new_keys = LabelsBuilder::new(names);
+        let mut new_blocks = Vec::new();
+        for (key, block) in self {
+            let n_samples = block.samples().count();
+            let n_chunks = usize::max(1, (n_samples + max_samples - 1) / max_samples);
+            for chunk in 0..n_chunks {
+                let start = chunk * max_samples;
+                let stop = usize::min(start + max_samples, n_samples);
+
+                let mut new_key = key.to_vec();
+                new_key.push(LabelValue::from(chunk));
+                new_keys.add(&new_key);
+
+                new_blocks.push(block_samples_range(block, start, stop)?);
+            }
+        }
+
+        return TensorMap::new(new_keys.finish(), new_blocks);
+    }
+}
+
+/// Create a new block containing the samples between `start` and `stop`
+/// (excluded) of `block`, and the corresponding gradient samples.
+fn block_samples_range(block: TensorBlockRef<'_>, start: usize, stop: usize) -> Result<TensorBlock, Error> {
+    let samples = block.samples();
+    let mut new_samples = LabelsBuilder::new(samples.names());
+    new_samples.reserve(stop - start);
+    let mut mapping = Vec::with_capacity(stop - start);
+    for (output, input) in (start..stop).enumerate() {
+        new_samples.add(&samples[input]);
+        mapping.push(mts_sample_mapping_t { input, output });
+    }
+
+    let properties = block.properties();
+    let mut new_block = TensorBlock::from_raw_values(
+        move_samples(&block.values(), &mapping)?,
+        &new_samples.finish(),
+        &block.components(),
+        &properties,
+    )?;
+
+    for (parameter, gradient) in block.gradients() {
+        if !gradient.gradient_list().is_empty() {
+            return Err(Error {
+                code: None,
+                message: "gradients of gradients are not supported in split_block_samples".into(),
+            });
+        }
+
+        let gradient_samples = gradient.samples();
+        let mut new_gradient_samples = LabelsBuilder::new(gradient_samples.names());
+        let mut mapping = Vec::new();
+        for (input, sample) in gradient_samples.iter().enumerate() {
+            let sample_i = sample[0].usize();
+            if sample_i >= start && sample_i < stop {
+                let mut new_sample = sample.to_vec();
+                new_sample[0] = LabelValue::from(sample_i - start);
+                new_gradient_samples.add(&new_sample);
+                mapping.push(mts_sample_mapping_t { input, output: mapping.len() });
+            }
+        }
+
+        let new_gradient = TensorBlock::from_raw_values(
+            move_samples(&gradient.values(), &mapping)?,
+            &new_gradient_samples.finish(),
+            &gradient.components(),
+            &properties,
+        )?;
+        new_block.add_gradient(parameter, new_gradient)?;
+    }
+
+    return Ok(new_block);
+}
+
+/// Create a new array with the same backend as `values`, containing the
+/// samples of `values` given in `mapping`.
+pub(crate) fn move_samples(values: &ArrayRef<'_>, mapping: &[mts_sample_mapping_t]) -> Result<mts_array_t, Error> {
+    let input = values.as_raw();
+    let mut shape = input.shape()?.to_vec();
+    shape[0] = mapping.len();
+    let n_properties = shape[shape.len() - 1];
+
+    return create_array(input, &shape, |output| {
+        output.move_samples_from(input, mapping, 0..n_properties)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Labels, TensorBlock, TensorMap};
+
+    #[test]
+    fn split_block_samples() {
+        let mut block = TensorBlock::new(
+            ndarray::arr2(&[[1.0], [2.0], [3.0], [4.0], [5.0]]).into_dyn(),
+            &Labels::new(["system"], &[[0], [1], [2], [3], [4]]),
+            &[],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            ndarray::arr2(&[[10.0], [30.0], [31.0], [50.0]]).into_dyn(),
+            &Labels::new(["sample", "atom"], &[[0, 0], [2, 0], [2, 1], [4, 0]]),
+            &[],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap();
+        block.add_gradient("positions", gradient).unwrap();
+
+        let mut small = TensorBlock::new(
+            ndarray::arr2(&[[6.0]]).into_dyn(),
+            &Labels::new(["system"], &[[0]]),
+            &[],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            ndarray::arr2(&[[60.0]]).into_dyn(),
+            &Labels::new(["sample", "atom"], &[[0, 3]]),
+            &[],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap();
+        small.add_gradient("positions", gradient).unwrap();
+
+        let tensor = TensorMap::new(Labels::new(["key"], &[[0], [1]]), vec![block, small]).unwrap();
+        let split = tensor.split_block_samples(2).unwrap();
+
+        assert_eq!(split.keys(), &Labels::new(["key", "__chunk"], &[[0, 0], [0, 1], [0, 2], [1, 0]]));
+
+        let block = split.block_by_id(0);
+        assert_eq!(block.samples(), Labels::new(["system"], &[[0], [1]]));
+        assert_eq!(block.values().as_array(), ndarray::arr2(&[[1.0], [2.0]]).into_dyn());
+        let gradient = block.gradient("positions").unwrap();
+        assert_eq!(gradient.samples(), Labels::new(["sample", "atom"], &[[0, 0]]));
+        assert_eq!(gradient.values().as_array(), ndarray::arr2(&[[10.0]]).into_dyn());
+
+        let block = split.block_by_id(1);
+        assert_eq!(block.samples(), Labels::new(["system"], &[[2], [3]]));
+        assert_eq!(block.values().as_array(), ndarray::arr2(&[[3.0], [4.0]]).into_dyn());
+        let gradient = block.gradient("positions").unwrap();
+        assert_eq!(gradient.samples(), Labels::new(["sample", "atom"], &[[0, 0], [0, 1]]));
+        assert_eq!(gradient.values().as_array(), ndarray::arr2(&[[30.0], [31.0]]).into_dyn());
+
+        let block = split.block_by_id(2);
+        assert_eq!(block.samples(), Labels::new(["system"], &[[4]]));
+        assert_eq!(block.values().as_array(), ndarray::arr2(&[[5.0]]).into_dyn());
+        let gradient = block.gradient("positions").unwrap();
+        assert_eq!(gradient.samples(), Labels::new(["sample", "atom"], &[[0, 0]]));
+        assert_eq!(gradient.values().as_array(), ndarray::arr2(&[[50.0]]).into_dyn());
+
+        let block = split.block_by_id(3);
+        assert_eq!(block.samples(), Labels::new(["system"], &[[0]]));
+        assert_eq!(block.values().as_array(), ndarray::arr2(&[[6.0]]).into_dyn());
+        let gradient = block.gradient("positions").unwrap();
+        assert_eq!(gradient.samples(), Labels::new(["sample", "atom"], &[[0, 3]]));
+
+        let error = tensor.split_block_samples(0).unwrap_err();
+        assert_eq!(error.message, "can not split blocks: max_samples must be at least 1");
+
+        let error = split.split_block_samples(1).unwrap_err();
+        assert_eq!(error.message, "can not split blocks: '__chunk' is already part of the keys");
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{Error, TensorBlockRef, TensorMap};
+
+/// Summary statistics of the data in a single block, as returned by
+/// [`TensorMap::stats`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockStats {
+    /// Total number of values in the block
+    pub count: usize,
+    /// Number of values which are NaN or infinite
+    pub non_finite: usize,
+    /// Smallest finite value, or NaN if there are no finite values
+    pub min: f64,
+    /// Largest finite value, or NaN if there are no finite values
+    pub max: f64,
+    /// Mean of the finite values, or NaN if there are no finite values
+    pub mean: f64,
+    /// Statistics for the gradients of this block, in the same order as
+    /// [`TensorBlockRef::gradients`]. This is empty if the statistics for
+    /// gradients were not requested.
+    pub gradients: Vec<(String, BlockStats)>,
+}
+
+impl TensorMap {
+    /// Get summary statistics (min, max, mean and number of non-finite
+    /// values) for the values of each block in this `TensorMap`, in the same
+    /// order as the keys.
+    ///
+    /// This is intended for quick sanity checks, for example to find NaN or
+    /// infinite values before using the data. The min, max and mean only
+    /// consider finite values. If `gradients` is `true`, the statistics for
+    /// all the gradients (including gradients of gradients) are computed as
+    /// well.
+    ///
+    /// The values are read in place with
+    /// [`ArrayRef::data`](crate::ArrayRef::data) without copying them, see the
+    /// [crate documentation](crate#accessing-the-data).
+    pub fn stats(&self, gradients: bool) -> Result<Vec<BlockStats>, Error> {
+        return self.blocks().into_iter()
+            .map(|block| block_stats(block, gradients))
+            .collect();
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn block_stats(block: TensorBlockRef<'_>, gradients: bool) -> Result<BlockStats, Error> {
+    let values = block.values();
+    let data = values.data()?;
+
+    let mut non_finite = 0;
+    let mut min = f64::INFINITY;
+    let mut max = f64::NEG_INFINITY;
+    let mut sum = 0.0;
+    for &value in data {
+        if value.is_finite() {
+            min = f64::min(min, value);
+            max = f64::max(max, value);
+            sum += value;
+        } else {
+            non_finite += 1;
+        }
+    }
+
+    let n_finite = data.len() - non_finite;
+    let mut stats = if n_finite == 0 {
+        BlockStats {
+            count: data.len(),
+            non_finite,
+            min: f64::NAN,
+            max: f64::NAN,
+            mean: f64::NAN,
+            gradients: Vec::new(),
+        }
+    } else {
+        BlockStats {
+            count: data.len(),
+            non_finite,
+            min,
+            max,
+            mean: sum / n_finite as f64,
+            gradients: Vec::new(),
+        }
+    };
+
+    if gradients {
+        for (parameter, gradient) in block.gradients() {
+            stats.gradients.push((parameter.into(), block_stats(gradient, true)?));
+        }
+    }
+
+    return Ok(stats);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Labels, TensorBlock, TensorMap};
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn stats() {
+        let mut block = TensorBlock::new(
+            ndarray::arr2(&[[1.0, f64::NAN], [-2.0, 4.0]]).into_dyn(),
+            &Labels::new(["system"], &[[0], [1]]),
+            &[],
+            &Labels::new(["n"], &[[0], [1]]),
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            ndarray::arr2(&[[f64::INFINITY, f64::NAN]]).into_dyn(),
+            &Labels::new(["sample"], &[[0]]),
+            &[],
+            &Labels::new(["n"], &[[0], [1]]),
+        ).unwrap();
+        block.add_gradient("g", gradient).unwrap();
+
+        let mut other = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![3, 1], 2.5),
+            &Labels::new(["system"], &[[0], [1], [2]]),
+            &[],
+            &Labels::new(["n"], &[[0]]),
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![0, 1], 0.0),
+            &Labels::empty(vec!["sample"]),
+            &[],
+            &Labels::new(["n"], &[[0]]),
+        ).unwrap();
+        other.add_gradient("g", gradient).unwrap();
+
+        let tensor = TensorMap::new(Labels::new(["key"], &[[0], [1]]), vec![block, other]).unwrap();
+
+        let stats = tensor.stats(false).unwrap();
+        assert_eq!(stats.len(), 2);
+
+        assert_eq!(stats[0].count, 4);
+        assert_eq!(stats[0].non_finite, 1);
+        assert_eq!(stats[0].min, -2.0);
+        assert_eq!(stats[0].max, 4.0);
+        assert_eq!(stats[0].mean, 1.0);
+        assert!(stats[0].gradients.is_empty());
+
+        assert_eq!(stats[1].count, 3);
+        assert_eq!(stats[1].non_finite, 0);
+        assert_eq!(stats[1].min, 2.5);
+        assert_eq!(stats[1].max, 2.5);
+        assert_eq!(stats[1].mean, 2.5);
+
+        let stats = tensor.stats(true).unwrap();
+        assert_eq!(stats[0].gradients.len(), 1);
+        let (parameter, gradient) = &stats[0].gradients[0];
+        assert_eq!(parameter, "g");
+        assert_eq!(gradient.count, 2);
+        assert_eq!(gradient.non_finite, 2);
+        assert!(gradient.min.is_nan());
+        assert!(gradient.max.is_nan());
+        assert!(gradient.mean.is_nan());
+
+        let (_, gradient) = &stats[1].gradients[0];
+        assert_eq!(gradient.count, 0);
+        assert_eq!(gradient.non_finite, 0);
+        assert!(gradient.mean.is_nan());
+    }
+}
@@ -1,11 +1,14 @@
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::iter::FusedIterator;
 
 use crate::block::TensorBlockRefMut;
-use crate::c_api::{mts_tensormap_t, mts_labels_t};
+use crate::c_api::{mts_tensormap_t, mts_labels_t, mts_status_t, mts_array_t, mts_sample_mapping_t};
 
+use crate::data::destroy_array;
 use crate::errors::{check_status, check_ptr};
-use crate::{Error, TensorBlock, TensorBlockRef, Labels, LabelValue};
+use crate::labels::format_key;
+use crate::{Error, TensorBlock, TensorBlockRef, Labels, LabelsBuilder, LabelValue};
 
 /// [`TensorMap`] is the main user-facing struct of this library, and can
 /// store any kind of data used in atomistic machine learning.
@@ -27,6 +30,49 @@ unsafe impl Send for TensorMap {}
 // SAFETY: Sync is fine since there is no internal mutability in TensorMap
 unsafe impl Sync for TensorMap {}
 
+/// The different kinds of metadata defined on the blocks of a [`TensorMap`],
+/// used to select labels in [`TensorMap::unique_metadata`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataAxis {
+    /// The samples labels of the blocks
+    Samples,
+    /// All the components labels of the blocks
+    Components,
+    /// The properties labels of the blocks
+    Properties,
+}
+
+impl std::fmt::Display for MetadataAxis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MetadataAxis::Samples => write!(f, "samples"),
+            MetadataAxis::Components => write!(f, "components"),
+            MetadataAxis::Properties => write!(f, "properties"),
+        }
+    }
+}
+
+/// What to do with the gradients of the blocks in [`TensorMap::apply`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApplyGradients {
+    /// Keep the gradients unchanged
+    Keep,
+    /// Apply the same function to every element of the gradients
+    Apply,
+    /// Remove all the gradients from the blocks
+    Remove,
+}
+
+/// Position of a sample in the input of [`TensorMap::keys_to_samples_traced`],
+/// corresponding to a given sample in the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SampleOrigin {
+    /// Index of the block in the input `TensorMap`
+    pub block: usize,
+    /// Index of the sample in the input block
+    pub sample: usize,
+}
+
 impl std::fmt::Debug for TensorMap {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use crate::labels::pretty_print_labels;
@@ -78,6 +124,35 @@ impl TensorMap {
         return Ok(unsafe { TensorMap::from_raw(ptr) });
     }
 
+    /// Create a new `TensorMap` from an iterator over pairs of keys and blocks.
+    ///
+    /// The keys are accumulated in [`Labels`] with the given `key_names`, and
+    /// the blocks are checked in the same way as in [`TensorMap::new`]. This
+    /// function returns an error if a key does not have one value for each of
+    /// the `key_names`, or if the same key is given more than once.
+    pub fn from_iter_checked<I>(key_names: Vec<&str>, iter: I) -> Result<TensorMap, Error>
+        where I: IntoIterator<Item=(Vec<LabelValue>, TensorBlock)>
+    {
+        let mut keys = LabelsBuilder::new_unchecked(key_names);
+        let mut blocks = Vec::new();
+        for (key, block) in iter {
+            if key.len() != keys.size() {
+                return Err(Error {
+                    code: None,
+                    message: format!(
+                        "the key {:?} has {} values, but the keys have {} dimensions",
+                        key, key.len(), keys.size()
+                    ),
+                });
+            }
+
+            keys.add(&key);
+            blocks.push(block);
+        }
+
+        return TensorMap::new(keys.try_finish()?, blocks);
+    }
+
     /// Create a new `TensorMap` from a raw pointer.
     ///
     /// This function takes ownership of the pointer, and will call
@@ -129,6 +204,20 @@ impl TensorMap {
         return Ok(unsafe { TensorMap::from_raw(ptr) });
     }
 
+    /// Create a new `TensorMap` with the same keys and blocks metadata as this
+    /// one, and all the data filled with zeros.
+    ///
+    /// See [`TensorBlockRef::zeros_like`] for more information.
+    #[inline]
+    pub fn zeros_like(&self) -> Result<TensorMap, Error> {
+        let ptr = unsafe {
+            crate::c_api::mts_tensormap_zeros_like(self.ptr)
+        };
+        crate::errors::check_ptr(ptr)?;
+
+        return Ok(unsafe { TensorMap::from_raw(ptr) });
+    }
+
     /// Load a `TensorMap` from the file at `path`
     ///
     /// This is a convenience function calling [`crate::io::load`]
@@ -163,6 +252,82 @@ impl TensorMap {
         &self.keys
     }
 
+    /// Set the metadata entry `key` of this `TensorMap` to `value`,
+    /// overwriting any existing value for this `key`.
+    ///
+    /// Metadata is a set of arbitrary key/value pairs of strings, which can be
+    /// used to store provenance information (model version, units, etc.)
+    /// together with the data. It is kept by [`TensorMap::try_clone`] and
+    /// when saving/loading tensor maps, but other operations creating new
+    /// tensor maps do not propagate it.
+    #[inline]
+    pub fn set_metadata(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        let key = CString::new(key).map_err(|_| Error {
+            code: None,
+            message: "metadata key can not contain NULL bytes".into(),
+        })?;
+        let value = CString::new(value).map_err(|_| Error {
+            code: None,
+            message: "metadata value can not contain NULL bytes".into(),
+        })?;
+
+        unsafe {
+            check_status(crate::c_api::mts_tensormap_set_metadata(
+                self.ptr,
+                key.as_ptr(),
+                value.as_ptr(),
+            ))
+        }
+    }
+
+    /// Get the value of the metadata entry `key` in this `TensorMap`, or
+    /// `None` if there is no such entry.
+    #[inline]
+    pub fn metadata(&self, key: &str) -> Option<&str> {
+        let key = CString::new(key).ok()?;
+
+        let mut value = std::ptr::null();
+        unsafe {
+            check_status(crate::c_api::mts_tensormap_get_metadata(
+                self.ptr,
+                key.as_ptr(),
+                &mut value,
+            )).expect("failed to get metadata");
+
+            if value.is_null() {
+                return None;
+            }
+            return Some(CStr::from_ptr(value).to_str().expect("invalid UTF8"));
+        }
+    }
+
+    /// Iterate over all the key/value pairs in the metadata of this
+    /// `TensorMap`, in the order in which the keys were first set.
+    pub fn metadata_iter(&self) -> impl Iterator<Item=(&str, &str)> + '_ {
+        let mut keys_ptr = std::ptr::null();
+        let mut keys_count = 0;
+        unsafe {
+            check_status(crate::c_api::mts_tensormap_metadata_keys(
+                self.ptr,
+                &mut keys_ptr,
+                &mut keys_count,
+            )).expect("failed to get metadata keys");
+        }
+
+        let keys = if keys_count == 0 {
+            &[]
+        } else {
+            assert!(!keys_ptr.is_null());
+            unsafe { std::slice::from_raw_parts(keys_ptr, keys_count) }
+        };
+
+        return keys.iter().map(move |&key| {
+            let key = unsafe { CStr::from_ptr(key).to_str().expect("invalid UTF8") };
+            let value = self.metadata(key).expect("missing metadata value");
+            (key, value)
+        });
+    }
+
     /// Get a reference to the block at the given `index` in this `TensorMap`
     ///
     /// # Panics
@@ -221,7 +386,12 @@ impl TensorMap {
     ///
     /// The selection must contains a single entry, defining the requested key
     /// or keys. If the selection contains only a subset of the dimensions of the
-    /// keys, there can be multiple matching blocks.
+    /// keys, there can be multiple matching blocks: all the values of the
+    /// dimensions which are not part of the selection are matched.
+    ///
+    /// The values in the selection are compared exactly with the keys, see
+    /// [`TensorMap::blocks_matching_wildcard`] to match any value of some
+    /// dimensions.
     #[inline]
     pub fn blocks_matching(&self, selection: &Labels) -> Result<Vec<usize>, Error> {
         let mut indexes = vec![0; self.keys().count()];
@@ -239,6 +409,43 @@ impl TensorMap {
         return Ok(indexes);
     }
 
+    /// Get the index of blocks matching the given selection, using
+    /// [`LabelValue::ANY`] as a wildcard.
+    ///
+    /// This function is similar to [`TensorMap::blocks_matching`], but the
+    /// selection can also use [`LabelValue::ANY`] as the value for some
+    /// dimensions, to match all the values of these dimensions while still
+    /// selecting specific values for the other dimensions.
+    #[inline]
+    pub fn blocks_matching_wildcard(&self, selection: &Labels) -> Result<Vec<usize>, Error> {
+        let mut indexes = vec![0; self.keys().count()];
+        let mut matching = indexes.len();
+        unsafe {
+            check_status(crate::c_api::mts_tensormap_blocks_matching_wildcard(
+                self.ptr,
+                indexes.as_mut_ptr(),
+                &mut matching,
+                selection.as_mts_labels_t(),
+            ))?;
+        }
+        indexes.resize(matching, 0);
+
+        return Ok(indexes);
+    }
+
+    /// Get the keys and blocks matching the given selection.
+    ///
+    /// This function is similar to [`TensorMap::blocks_matching`], but returns
+    /// each matching block together with the corresponding key, which is
+    /// useful to know which block is which when the selection matches
+    /// multiple blocks.
+    #[inline]
+    pub fn blocks_matching_with_keys(&self, selection: &Labels) -> Result<Vec<(&[LabelValue], TensorBlockRef<'_>)>, Error> {
+        let keys = self.keys();
+        let matching = self.blocks_matching(selection)?;
+        return Ok(matching.into_iter().map(|i| (&keys[i], self.block_by_id(i))).collect());
+    }
+
     /// Get the index of the single block matching the given selection.
     ///
     /// This function is similar to [`TensorMap::blocks_matching`], but also
@@ -247,14 +454,39 @@ impl TensorMap {
     pub fn block_matching(&self, selection: &Labels) -> Result<usize, Error> {
         let matching = self.blocks_matching(selection)?;
         if matching.len() != 1 {
-            let selection_str = selection.names()
-                .iter().zip(&selection[0])
-                .map(|(name, value)| format!("{} = {}", name, value))
-                .collect::<Vec<_>>()
-                .join(", ");
-
+            let selection_str = format_key(&selection.names(), &selection[0]);
 
             if matching.is_empty() {
+                // check if one of the selected values is missing entirely
+                // from the keys, to give a more helpful error message
+                let keys = self.keys();
+                let keys_names = keys.names();
+                for (name, value) in selection.names().iter().zip(&selection[0]) {
+                    let dimension = keys_names.iter()
+                        .position(|n| n == name)
+                        .expect("blocks_matching should have checked the names");
+
+                    let available = keys.iter()
+                        .map(|key| key[dimension])
+                        .collect::<BTreeSet<_>>();
+
+                    if !available.contains(value) {
+                        return Err(Error {
+                            code: None,
+                            message: format!(
+                                "no blocks matched the selection ({}): {} = {} \
+                                is not part of the keys, available values for \
+                                '{}' are [{}]",
+                                selection_str,
+                                name,
+                                value,
+                                name,
+                                available.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "),
+                            ),
+                        });
+                    }
+                }
+
                 return Err(Error {
                     code: None,
                     message: format!(
@@ -263,12 +495,19 @@ impl TensorMap {
                     ),
                 });
             } else {
+                let matching_keys = matching.iter()
+                    .map(|&i| format!("({})", format_key(&self.keys().names(), &self.keys()[i])))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
                 return Err(Error {
                     code: None,
                     message: format!(
-                        "{} blocks matched the selection ({}), expected only one",
+                        "{} blocks matched the selection ({}), expected only one. \
+                        The matching keys are {}",
                         matching.len(),
-                        selection_str
+                        selection_str,
+                        matching_keys,
                     ),
                 });
             }
@@ -287,6 +526,57 @@ impl TensorMap {
         return Ok(self.block_by_id(id));
     }
 
+    /// Get a reference to the single block matching the given `selection`,
+    /// specified as pairs of key dimension name and value.
+    ///
+    /// This is a shorthand for [`TensorMap::block`], building the selection
+    /// `Labels` from the pairs, e.g. `tensor.block_where(&[("center",
+    /// 6.into()), ("neighbor", 1.into())])`.
+    pub fn block_where(&self, selection: &[(&str, LabelValue)]) -> Result<TensorBlockRef<'_>, Error> {
+        let names = selection.iter().map(|&(name, _)| name).collect::<Vec<_>>();
+        if names.iter().collect::<BTreeSet<_>>().len() != names.len() {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "the same key dimension is used multiple times in the selection [{}]",
+                    names.join(", ")
+                ),
+            });
+        }
+
+        let values = selection.iter().map(|&(_, value)| value).collect::<Vec<_>>();
+        return self.block(&Labels::new_single_entry(names, values)?);
+    }
+
+    /// Get a reference to the block associated with the given `key`.
+    ///
+    /// The `key` must contain one value for each dimension of the keys. This
+    /// uses the position of `key` in the keys, without having to build
+    /// `Labels` for a selection as in [`TensorMap::block`].
+    #[inline]
+    pub fn block_for_key(&self, key: &[LabelValue]) -> Result<TensorBlockRef<'_>, Error> {
+        if key.len() != self.keys().size() {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "expected a key with {} values, got {}",
+                    self.keys().size(), key.len()
+                ),
+            });
+        }
+
+        if let Some(id) = self.keys().position(key) {
+            return Ok(self.block_by_id(id));
+        }
+
+        let key_str = format_key(&self.keys().names(), key);
+
+        return Err(Error {
+            code: None,
+            message: format!("there is no block with key ({}) in this TensorMap", key_str),
+        });
+    }
+
     /// Get a reference to every blocks in this `TensorMap`
     #[inline]
     pub fn blocks(&self) -> Vec<TensorBlockRef<'_>> {
@@ -307,6 +597,186 @@ impl TensorMap {
         return blocks;
     }
 
+    /// Get the indexes of all the blocks in this `TensorMap`, sorted by
+    /// decreasing number of samples.
+    ///
+    /// Blocks with the same number of samples are kept in the same order as
+    /// in the keys. This can be used to schedule work over the blocks, e.g.
+    /// processing the largest blocks first when using a thread pool.
+    pub fn blocks_by_size(&self) -> Vec<usize> {
+        let blocks = self.blocks();
+        let mut indexes = (0..blocks.len()).collect::<Vec<_>>();
+        indexes.sort_by_key(|&i| std::cmp::Reverse(blocks[i].samples().count()));
+        return indexes;
+    }
+
+    /// Get the single value stored in this `TensorMap`, for example the
+    /// total energy predicted by a model.
+    ///
+    /// This only succeeds if the `TensorMap` contains a single block, with a
+    /// single sample, no components and a single property; and returns an
+    /// error describing the shape of the data otherwise. Gradients are
+    /// ignored. The value is read through `mts_array_t.data`, see the
+    /// [crate documentation](crate#accessing-the-data).
+    pub fn into_scalar(&self) -> Result<f64, Error> {
+        let error = |message: String| Error {
+            code: None,
+            message: format!("can not convert this TensorMap to a scalar: {}", message),
+        };
+
+        let n_blocks = self.keys().count();
+        if n_blocks != 1 {
+            return Err(error(format!("expected a single block, got {}", n_blocks)));
+        }
+
+        let block = self.block_by_id(0);
+        let n_samples = block.samples().count();
+        if n_samples != 1 {
+            return Err(error(format!("expected a single sample, got {}", n_samples)));
+        }
+
+        let n_components = block.components().len();
+        if n_components != 0 {
+            return Err(error(format!("expected no components, got {}", n_components)));
+        }
+
+        let n_properties = block.properties().count();
+        if n_properties != 1 {
+            return Err(error(format!("expected a single property, got {}", n_properties)));
+        }
+
+        return block.values().get(&[0, 0]);
+    }
+
+    /// Get the union of the samples of all the blocks in this `TensorMap`.
+    ///
+    /// All the blocks in a `TensorMap` have the same sample names, which are
+    /// used for the returned `Labels`. The entries are deduplicated, and
+    /// appear in the order in which they are first found in the blocks. This
+    /// can be used to build a global index of all the samples, for example
+    /// all the atoms in a dataset. If this `TensorMap` does not contain any
+    /// block, the sample names are not known and this returns empty `Labels`
+    /// without any dimension.
+    pub fn samples_union(&self) -> Result<Labels, Error> {
+        let all_samples = self.blocks().iter()
+            .map(|block| block.samples())
+            .collect::<Vec<_>>();
+
+        let names = all_samples.first().map_or_else(Vec::new, Labels::names);
+        let mut builder = LabelsBuilder::new(names);
+
+        let mut seen = HashSet::new();
+        for samples in &all_samples {
+            for entry in samples {
+                if seen.insert(entry) {
+                    builder.add(entry);
+                }
+            }
+        }
+
+        return Ok(builder.finish());
+    }
+
+    /// Get all the unique values taken by the `name` dimension in the labels
+    /// for the given `axis`, across all the blocks in this `TensorMap`.
+    ///
+    /// The values are returned sorted, as `Labels` with a single dimension
+    /// called `name`. This returns an error if no block contains a dimension
+    /// called `name` on this `axis`.
+    pub fn unique_metadata(&self, axis: MetadataAxis, name: &str) -> Result<Labels, Error> {
+        let mut found = false;
+        let mut values = BTreeSet::new();
+        for block in self.blocks() {
+            let all_labels = match axis {
+                MetadataAxis::Samples => vec![block.samples()],
+                MetadataAxis::Components => block.components(),
+                MetadataAxis::Properties => vec![block.properties()],
+            };
+
+            for labels in all_labels {
+                if let Some(position) = labels.names().iter().position(|&n| n == name) {
+                    found = true;
+                    for entry in &labels {
+                        values.insert(entry[position]);
+                    }
+                }
+            }
+        }
+
+        if !found {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "there is no '{}' dimension in the {} of any block", name, axis
+                ),
+            });
+        }
+
+        let mut builder = LabelsBuilder::new(vec![name]);
+        builder.reserve(values.len());
+        for value in values {
+            builder.add(&[value]);
+        }
+
+        return Ok(builder.finish());
+    }
+
+    /// Get a human-readable table describing all the blocks in this
+    /// `TensorMap`.
+    ///
+    /// The table contains one row per block, with the values of the key, the
+    /// number of samples and properties, the shape of the components, and
+    /// the list of gradients of the block. Columns are aligned to make the
+    /// table easier to read.
+    pub fn describe_blocks(&self) -> String {
+        use std::fmt::Write;
+
+        let mut rows = Vec::with_capacity(self.keys().count() + 1);
+
+        let mut header = self.keys().names().iter().map(|&name| name.to_owned()).collect::<Vec<_>>();
+        header.extend(["samples", "properties", "components", "gradients"].map(String::from));
+        rows.push(header);
+
+        for (key, block) in self {
+            let mut row = key.iter().map(ToString::to_string).collect::<Vec<_>>();
+            row.push(block.samples().count().to_string());
+            row.push(block.properties().count().to_string());
+
+            let components = block.components().iter()
+                .map(|component| component.count().to_string())
+                .collect::<Vec<_>>();
+            row.push(format!("[{}]", components.join(", ")));
+
+            let gradients = block.gradient_list();
+            if gradients.is_empty() {
+                row.push("-".into());
+            } else {
+                row.push(gradients.join(", "));
+            }
+
+            rows.push(row);
+        }
+
+        let mut widths = vec![0; rows[0].len()];
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = usize::max(*width, cell.len());
+            }
+        }
+
+        let mut output = String::new();
+        for row in &rows {
+            let mut line = String::new();
+            for (cell, &width) in row.iter().zip(&widths) {
+                write!(line, "{:<width$}  ", cell, width = width).expect("failed to write to string");
+            }
+            output += line.trim_end();
+            output.push('\n');
+        }
+
+        return output;
+    }
+
     /// Merge blocks with the same value for selected keys dimensions along the
     /// samples axis.
     ///
@@ -314,10 +784,22 @@ impl TensorMap {
     /// the sample labels, and blocks with the same remaining keys dimensions
     /// will be merged together along the sample axis.
     ///
+    /// Blocks are grouped by the values of the remaining keys dimensions:
+    /// blocks which only differ in the moved dimensions are merged together,
+    /// while blocks with different values for any of the remaining dimensions
+    /// are kept separate. The new keys contain the unique values of the
+    /// remaining dimensions, in the order in which they first appear in the
+    /// current keys.
+    ///
     /// `keys_to_move` must be empty (`keys_to_move.count() == 0`), and the new
     /// sample labels will contain entries corresponding to the merged blocks'
     /// keys.
     ///
+    /// The moved dimensions are always added after the existing dimensions of
+    /// the samples, in the same order as in `keys_to_move`. For example, moving
+    /// `c, a` from keys `a, b, c` to blocks with samples `system, atom` gives
+    /// samples `system, atom, c, a`.
+    ///
     /// The new sample labels will contains all of the merged blocks sample
     /// labels. The order of the samples is controlled by `sort_samples`. If
     /// `sort_samples` is true, samples are re-ordered to keep them
@@ -326,6 +808,9 @@ impl TensorMap {
     ///
     /// This function is only implemented if all merged block have the same
     /// property labels.
+    ///
+    /// If `keys_to_move` does not contain any dimension, this function returns
+    /// a copy of the current `TensorMap`, without re-ordering the samples.
     #[inline]
     pub fn keys_to_samples(&self, keys_to_move: &Labels, sort_samples: bool) -> Result<TensorMap, Error> {
         let ptr = unsafe {
@@ -340,6 +825,98 @@ impl TensorMap {
         return Ok(unsafe { TensorMap::from_raw(ptr) });
     }
 
+    /// Same as [`TensorMap::keys_to_samples`], calling `progress` each time a
+    /// new block is created with the number of blocks created so far and the
+    /// total number of blocks in the output. This can be used to report
+    /// progress when merging very large tensor maps.
+    ///
+    /// If `keys_to_move` does not contain any dimension, `progress` is not
+    /// called.
+    pub fn keys_to_samples_with_progress<F>(
+        &self,
+        keys_to_move: &Labels,
+        sort_samples: bool,
+        mut progress: F,
+    ) -> Result<TensorMap, Error> where F: FnMut(usize, usize) {
+        let ptr = unsafe {
+            crate::c_api::mts_tensormap_keys_to_samples_with_progress(
+                self.ptr,
+                keys_to_move.as_mts_labels_t(),
+                sort_samples,
+                Some(progress_callback::<F>),
+                (&mut progress as *mut F).cast(),
+            )
+        };
+
+        check_ptr(ptr)?;
+        return Ok(unsafe { TensorMap::from_raw(ptr) });
+    }
+
+    /// Same as [`TensorMap::keys_to_samples`], also returning where each sample
+    /// of the new blocks comes from in the current `TensorMap`.
+    ///
+    /// The second element of the returned tuple contains one entry for each
+    /// block in the new `TensorMap`, which itself contains one
+    /// [`SampleOrigin`] for each sample of this block, giving the index of the
+    /// block and of the sample in the current `TensorMap` the data was taken
+    /// from. This can be used to trace the provenance of the merged data, or
+    /// to scatter results computed on the merged blocks back to the original
+    /// blocks.
+    #[allow(clippy::type_complexity)]
+    pub fn keys_to_samples_traced(
+        &self,
+        keys_to_move: &Labels,
+        sort_samples: bool,
+    ) -> Result<(TensorMap, Vec<Vec<SampleOrigin>>), Error> {
+        let merged = self.keys_to_samples(keys_to_move, sort_samples)?;
+
+        let keys = self.keys();
+        let merged_keys = merged.keys();
+
+        let moved_positions = keys_to_move.names().iter()
+            .map(|name| keys.names().iter().position(|n| n == name).expect("missing key dimension"))
+            .collect::<Vec<_>>();
+        // if all the key dimensions are moved, the merged keys contain a
+        // single `_` dimension, and all the blocks are merged together
+        let remaining_positions = (0..keys.size())
+            .filter(|i| !moved_positions.contains(i))
+            .collect::<Vec<_>>();
+
+        let merged_samples = merged.blocks().iter()
+            .map(|block| block.samples())
+            .collect::<Vec<_>>();
+        let mut origins = merged_samples.iter()
+            .map(|samples| vec![None; samples.count()])
+            .collect::<Vec<_>>();
+
+        for (block_i, key) in keys.iter().enumerate() {
+            let merged_i = if remaining_positions.is_empty() {
+                0
+            } else {
+                let remaining_key = remaining_positions.iter().map(|&i| key[i]).collect::<Vec<_>>();
+                merged_keys.position(&remaining_key).expect("missing merged key")
+            };
+
+            let samples = self.block_by_id(block_i).samples();
+            for (sample_i, sample) in samples.iter().enumerate() {
+                let mut merged_sample = sample.to_vec();
+                merged_sample.extend(moved_positions.iter().map(|&i| key[i]));
+
+                let merged_sample_i = merged_samples[merged_i].position(&merged_sample).expect("missing merged sample");
+                origins[merged_i][merged_sample_i] = Some(SampleOrigin {
+                    block: block_i,
+                    sample: sample_i,
+                });
+            }
+        }
+
+        let origins = origins.into_iter()
+            .map(|block| block.into_iter().map(|origin| origin.expect("missing sample origin")).collect())
+            .collect();
+
+        return Ok((merged, origins));
+    }
+
     /// Merge blocks with the same value for selected keys dimensions along the
     /// property axis.
     ///
@@ -361,11 +938,36 @@ impl TensorMap {
     /// blocks with properties `p=1, 2` will result in `a, p = (2, 1), (2, 2),
     /// (3, 1), (3, 2)`.
     ///
+    /// The moved dimensions are always added before the existing dimensions of
+    /// the properties, in the same order as in `keys_to_move`. For example,
+    /// moving `c, a` from keys `a, b, c` to blocks with properties `n, l` gives
+    /// properties `c, a, n, l`.
+    ///
+    /// The new keys contain the dimensions of the current keys which are not
+    /// moved, in the same order as in the current keys. If all the dimensions
+    /// are moved, the new keys contain a single `_` dimension with a single
+    /// entry.
+    ///
     /// The new sample labels will contains all of the merged blocks sample
     /// labels. The order of the samples is controlled by `sort_samples`. If
     /// `sort_samples` is true, samples are re-ordered to keep them
     /// lexicographically sorted. Otherwise they are kept in the order in which
     /// they appear in the blocks.
+    ///
+    /// The samples of the merged blocks do not need to be the same. If a
+    /// sample is not present in one of the merged blocks, the values (and
+    /// gradients) for this sample in the properties coming from this block
+    /// are set to zero.
+    ///
+    /// The same applies to the gradients: the gradient samples of the merged
+    /// blocks do not need to be the same, and the gradient samples of the new
+    /// block contain all of them (after updating the `sample` dimension to
+    /// refer to the new samples). Gradients which are not present in one of
+    /// the merged blocks are set to zero in the properties coming from this
+    /// block.
+    ///
+    /// If `keys_to_move` does not contain any dimension, this function returns
+    /// a copy of the current `TensorMap`, without re-ordering the samples.
     #[inline]
     pub fn keys_to_properties(&self, keys_to_move: &Labels, sort_samples: bool) -> Result<TensorMap, Error> {
         let ptr = unsafe {
@@ -380,6 +982,73 @@ impl TensorMap {
         return Ok(unsafe { TensorMap::from_raw(ptr) });
     }
 
+    /// Same as [`TensorMap::keys_to_properties`], calling `progress` each time
+    /// a new block is created with the number of blocks created so far and the
+    /// total number of blocks in the output.
+    ///
+    /// If `keys_to_move` does not contain any dimension, `progress` is not
+    /// called.
+    pub fn keys_to_properties_with_progress<F>(
+        &self,
+        keys_to_move: &Labels,
+        sort_samples: bool,
+        mut progress: F,
+    ) -> Result<TensorMap, Error> where F: FnMut(usize, usize) {
+        let ptr = unsafe {
+            crate::c_api::mts_tensormap_keys_to_properties_with_progress(
+                self.ptr,
+                keys_to_move.as_mts_labels_t(),
+                sort_samples,
+                Some(progress_callback::<F>),
+                (&mut progress as *mut F).cast(),
+            )
+        };
+
+        check_ptr(ptr)?;
+        return Ok(unsafe { TensorMap::from_raw(ptr) });
+    }
+
+    /// Get the names of the keys after moving the `moved` dimensions out of
+    /// the keys, with [`TensorMap::keys_to_properties`] or
+    /// [`TensorMap::keys_to_samples`].
+    ///
+    /// These are the names of the current keys which are not in `moved`, in
+    /// the same order as in the current keys; or `["_"]` if all the
+    /// dimensions are moved. This can be used to prepare selections for the
+    /// new `TensorMap` in advance. This returns an error if one of the
+    /// `moved` dimensions is not part of the keys, or is given multiple
+    /// times.
+    pub fn remaining_key_names(&self, moved: &[&str]) -> Result<Vec<String>, Error> {
+        let keys = self.keys();
+        let names = keys.names();
+        for (i, name) in moved.iter().enumerate() {
+            if !names.contains(name) {
+                return Err(Error {
+                    code: None,
+                    message: format!("'{}' is not part of the keys of this TensorMap", name),
+                });
+            }
+
+            if moved[..i].contains(name) {
+                return Err(Error {
+                    code: None,
+                    message: format!("'{}' is given multiple times in the dimensions to move", name),
+                });
+            }
+        }
+
+        let remaining = names.iter()
+            .filter(|name| !moved.contains(name))
+            .map(|&name| name.to_owned())
+            .collect::<Vec<_>>();
+
+        if remaining.is_empty() {
+            return Ok(vec!["_".into()]);
+        }
+
+        return Ok(remaining);
+    }
+
     /// Move the given dimensions from the component labels to the property
     /// labels for each block in this `TensorMap`.
     #[inline]
@@ -405,32 +1074,529 @@ impl TensorMap {
         return Ok(unsafe { TensorMap::from_raw(ptr) });
     }
 
-    /// Get an iterator over the keys and associated blocks
+    /// Move the given dimensions from the property labels to the component
+    /// labels for each block in this `TensorMap`.
+    ///
+    /// This is the inverse of [`TensorMap::components_to_properties`]. The
+    /// `dimensions` must be the first dimensions of the properties, and the
+    /// properties of all blocks must form a regular grid over these
+    /// dimensions. Each dimension becomes a new component, after the existing
+    /// components.
     #[inline]
-    pub fn iter(&self) -> TensorMapIter<'_> {
-        return TensorMapIter {
-            inner: self.keys().iter().zip(self.blocks())
+    pub fn properties_to_components(&self, dimensions: &[&str]) -> Result<TensorMap, Error> {
+        let dimensions_c = dimensions.iter()
+            .map(|&v| CString::new(v).expect("unexpected NULL byte"))
+            .collect::<Vec<_>>();
+
+        let dimensions_ptr = dimensions_c.iter()
+            .map(|v| v.as_ptr())
+            .collect::<Vec<_>>();
+
+        let ptr = unsafe {
+            crate::c_api::mts_tensormap_properties_to_components(
+                self.ptr,
+                dimensions_ptr.as_ptr(),
+                dimensions.len(),
+            )
         };
+
+        check_ptr(ptr)?;
+        return Ok(unsafe { TensorMap::from_raw(ptr) });
     }
 
-    /// Get an iterator over the keys and associated blocks, with read-write
-    /// access to the blocks
+    /// Create a new `TensorMap` with the same blocks as this one, and keys
+    /// transformed by `function`.
+    ///
+    /// `function` is called once for each key, and should return the new
+    /// values for this key. The names of the keys are not modified, so
+    /// `function` must return as many values as there are dimensions in the
+    /// keys, and the new keys must be unique.
     #[inline]
-    pub fn iter_mut(&mut self) -> TensorMapIterMut<'_> {
-        // we can not use `self.blocks_mut()` here, since it would
-        // double-borrow self
-        let mut blocks = Vec::new();
-        for i in 0..self.keys().count() {
-            blocks.push(unsafe { TensorMap::raw_block_mut_by_id(self.ptr, i) });
-        }
-
-        return TensorMapIterMut {
-            inner: self.keys().into_iter().zip(blocks)
+    pub fn map_keys<F>(&self, mut function: F) -> Result<TensorMap, Error>
+        where F: FnMut(&[LabelValue]) -> Vec<LabelValue>
+    {
+        let ptr = unsafe {
+            crate::c_api::mts_tensormap_map_keys(
+                self.ptr,
+                Some(map_keys_callback::<F>),
+                (&mut function as *mut F).cast(),
+            )
         };
+
+        check_ptr(ptr)?;
+        return Ok(unsafe { TensorMap::from_raw(ptr) });
     }
 
-    /// Get a parallel iterator over the keys and associated blocks
-    #[cfg(feature = "rayon")]
+    /// Create a new `TensorMap` where each block only contains the properties
+    /// for which `keep` returns `true`, together with the corresponding values
+    /// and gradients.
+    ///
+    /// This is useful to apply a rule such as "keep properties with radial
+    /// index below 5" to all blocks at once. The samples and components of the
+    /// blocks are not modified. If `keep` rejects all the properties of a
+    /// block, the corresponding block in the output will have zero properties.
+    #[inline]
+    pub fn filter_properties<F>(&self, keep: F) -> Result<TensorMap, Error>
+        where F: Fn(&[LabelValue]) -> bool
+    {
+        let ptr = unsafe {
+            crate::c_api::mts_tensormap_filter_properties(
+                self.ptr,
+                Some(filter_properties_callback::<F>),
+                (&keep as *const F).cast_mut().cast(),
+            )
+        };
+
+        check_ptr(ptr)?;
+        return Ok(unsafe { TensorMap::from_raw(ptr) });
+    }
+
+    /// Sort the keys of this `TensorMap` in lexicographic order, re-ordering
+    /// the blocks to keep them associated with the same keys.
+    ///
+    /// This is useful to get a canonical representation of a `TensorMap`
+    /// before comparing or serializing it.
+    pub fn sort_blocks_by_key(&mut self) -> Result<(), Error> {
+        let mut keys = mts_labels_t::null();
+        unsafe {
+            check_status(crate::c_api::mts_tensormap_sort_blocks(self.ptr))?;
+
+            check_status(crate::c_api::mts_tensormap_keys(self.ptr, &mut keys))?;
+            self.keys = Labels::from_raw(keys);
+        }
+
+        return Ok(());
+    }
+
+    /// Only keep the blocks (and corresponding keys) for which `predicate`
+    /// returns `true`, removing all the others. This works like
+    /// `Vec::retain`, and the order of the remaining blocks is preserved.
+    ///
+    /// `predicate` is called once for each block, with the values of the
+    /// corresponding key and the block itself. This function returns the
+    /// number of blocks that were removed.
+    pub fn retain_blocks<F>(&mut self, mut predicate: F) -> Result<usize, Error>
+        where F: FnMut(&[LabelValue], &TensorBlockRef<'_>) -> bool
+    {
+        let keep = self.iter()
+            .map(|(key, block)| predicate(key, &block))
+            .collect::<Vec<_>>();
+
+        let mut removed = 0;
+        let mut keys = mts_labels_t::null();
+        unsafe {
+            check_status(crate::c_api::mts_tensormap_retain_blocks(
+                self.ptr,
+                keep.as_ptr(),
+                keep.len(),
+                &mut removed,
+            ))?;
+
+            check_status(crate::c_api::mts_tensormap_keys(self.ptr, &mut keys))?;
+            self.keys = Labels::from_raw(keys);
+        }
+
+        return Ok(removed);
+    }
+
+    /// Group the blocks of this `TensorMap` by the values of the `names`
+    /// dimensions of the keys.
+    ///
+    /// Each group contains the values taken by the `names` dimensions (in the
+    /// same order as `names`), and the indexes of all the blocks with these
+    /// values in their key. The groups are returned in the order in which
+    /// their values first appear in the keys, and the indexes inside each
+    /// group are sorted.
+    ///
+    /// This returns an error if any of the `names` is not one of the keys
+    /// dimensions, or if the same name is given more than once.
+    #[allow(clippy::type_complexity)]
+    pub fn group_blocks_by(&self, names: &[&str]) -> Result<Vec<(Vec<LabelValue>, Vec<usize>)>, Error> {
+        let keys = self.keys();
+        let keys_names = keys.names();
+
+        let mut positions = Vec::with_capacity(names.len());
+        for (i, &name) in names.iter().enumerate() {
+            if names[..i].contains(&name) {
+                return Err(Error {
+                    code: None,
+                    message: format!("'{}' is present more than once in the names to group by", name),
+                });
+            }
+
+            let position = keys_names.iter().position(|&n| n == name).ok_or_else(|| Error {
+                code: None,
+                message: format!(
+                    "'{}' is not part of the keys of this TensorMap, the dimensions are [{}]",
+                    name, keys_names.join(", ")
+                ),
+            })?;
+            positions.push(position);
+        }
+
+        let mut groups = Vec::<(Vec<LabelValue>, Vec<usize>)>::new();
+        let mut group_for_values = HashMap::new();
+        for (block_i, key) in keys.iter().enumerate() {
+            let values = positions.iter().map(|&p| key[p]).collect::<Vec<_>>();
+            let group_i = *group_for_values.entry(values.clone()).or_insert_with(|| {
+                groups.push((values, Vec::new()));
+                groups.len() - 1
+            });
+            groups[group_i].1.push(block_i);
+        }
+
+        return Ok(groups);
+    }
+
+    /// Split this `TensorMap` into multiple `TensorMap`, one for each distinct
+    /// value of the `name` dimension of the keys.
+    ///
+    /// Each new `TensorMap` contains copies of the blocks for which the key
+    /// has the corresponding value, in the same order as in this `TensorMap`.
+    /// The pairs of value and `TensorMap` are returned sorted by value. If
+    /// `remove_dimension` is `true`, the `name` dimension is removed from the
+    /// keys of the new `TensorMap`; and if it was the only dimension of the
+    /// keys, the new keys are [`Labels::single`].
+    ///
+    /// This returns an error if `name` is not one of the keys dimensions.
+    pub fn split_by_key_column(&self, name: &str, remove_dimension: bool) -> Result<Vec<(LabelValue, TensorMap)>, Error> {
+        let mut groups = self.group_blocks_by(&[name])?;
+        groups.sort_unstable_by_key(|(values, _)| values[0]);
+
+        let keys = self.keys();
+        let names = keys.names();
+        let position = names.iter().position(|&n| n == name).expect("missing dimension");
+
+        let mut new_names = names.clone();
+        if remove_dimension {
+            new_names.remove(position);
+        }
+
+        let mut result = Vec::with_capacity(groups.len());
+        for (values, blocks_ids) in groups {
+            let new_keys = if new_names.is_empty() {
+                Labels::single()
+            } else {
+                let mut builder = LabelsBuilder::new(new_names.clone());
+                builder.reserve(blocks_ids.len());
+                for &block_i in &blocks_ids {
+                    let mut key = keys[block_i].to_vec();
+                    if remove_dimension {
+                        key.remove(position);
+                    }
+                    builder.add(&key);
+                }
+                builder.finish()
+            };
+
+            let blocks = blocks_ids.iter()
+                .map(|&block_i| self.block_by_id(block_i).try_clone())
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            result.push((values[0], TensorMap::new(new_keys, blocks)?));
+        }
+
+        return Ok(result);
+    }
+
+    /// Make sure that the labels of each block are not shared with any other
+    /// block, copying the shared labels.
+    ///
+    /// Labels are usually shared between blocks (and between a block and its
+    /// gradients), for example after [`TensorMap::keys_to_samples`]. Copying
+    /// them uses more memory, but ensures each block owns its labels.
+    #[inline]
+    pub fn make_labels_unique(&mut self) -> Result<(), Error> {
+        unsafe {
+            check_status(crate::c_api::mts_tensormap_make_labels_unique(self.ptr))?;
+        }
+        return Ok(());
+    }
+
+    /// Check that this `TensorMap` is still valid, re-running all the checks
+    /// performed when creating it and its blocks.
+    ///
+    /// Modifying the data of the blocks (for example by replacing the arrays
+    /// through [`TensorBlockRefMut::values_mut`]) could break these invariants,
+    /// and this function can be used to check that the tensor map is still
+    /// consistent. The returned error describes which invariant is violated.
+    #[inline]
+    pub fn check_invariants(&self) -> Result<(), Error> {
+        unsafe {
+            check_status(crate::c_api::mts_tensormap_check_invariants(self.ptr))?;
+        }
+        return Ok(());
+    }
+
+    /// Get the estimated size in bytes of the data in all the blocks of this
+    /// tensor map, see [`TensorBlockRef::nbytes`].
+    #[inline]
+    pub fn nbytes(&self) -> Result<usize, Error> {
+        let mut nbytes = 0;
+        unsafe {
+            check_status(crate::c_api::mts_tensormap_nbytes(self.ptr, &mut nbytes))?;
+        }
+        return Ok(nbytes);
+    }
+
+    /// Get the union of the gradient parameters defined in all the `tensors`,
+    /// in lexicographic order.
+    pub fn gradient_parameters_union(tensors: &[&TensorMap]) -> Vec<String> {
+        let mut parameters = BTreeSet::new();
+        for tensor in tensors {
+            // all blocks in a tensor map have the same gradients
+            if tensor.keys().count() != 0 {
+                let block = tensor.block_by_id(0);
+                parameters.extend(block.gradient_list().into_iter().map(String::from));
+            }
+        }
+        return parameters.into_iter().collect();
+    }
+
+    /// Check that the blocks in this `TensorMap` have gradients with respect
+    /// to `parameter`, returning an error otherwise.
+    ///
+    /// This can be used to check the preconditions at the start of a function
+    /// expecting some gradients. A `TensorMap` without any blocks does not
+    /// have any gradients.
+    pub fn assert_has_gradient(&self, parameter: &str) -> Result<(), Error> {
+        let parameters = TensorMap::gradient_parameters_union(&[self]);
+        if !parameters.iter().any(|p| p == parameter) {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "expected gradients with respect to '{}' in this TensorMap, \
+                    the available gradients are [{}]",
+                    parameter, parameters.join(", ")
+                ),
+            });
+        }
+        return Ok(());
+    }
+
+    /// Check that the blocks in this `TensorMap` do not have any gradients,
+    /// returning an error otherwise.
+    ///
+    /// This can be used to check the preconditions at the start of a function
+    /// which does not support gradients.
+    pub fn assert_no_gradients(&self) -> Result<(), Error> {
+        let parameters = TensorMap::gradient_parameters_union(&[self]);
+        if !parameters.is_empty() {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "expected no gradients in this TensorMap, got gradients \
+                    with respect to [{}]",
+                    parameters.join(", ")
+                ),
+            });
+        }
+        return Ok(());
+    }
+
+    /// Concatenate multiple `TensorMap` along the property axis, creating a
+    /// new `TensorMap` containing a copy of all the data.
+    ///
+    /// All the `tensors` must have the same keys, and for each key the blocks
+    /// must have the same samples and components. If `fill_missing_gradients`
+    /// is `false`, all the `tensors` must also have gradients with respect to
+    /// the same parameters (see [`TensorMap::gradient_parameters_union`]). If
+    /// it is `true`, gradients only defined in some of the `tensors` are
+    /// considered to be zero in the others.
+    #[inline]
+    pub fn concatenate_properties(tensors: &[&TensorMap], fill_missing_gradients: bool) -> Result<TensorMap, Error> {
+        let tensors_ptr = tensors.iter().map(|tensor| tensor.ptr.cast_const()).collect::<Vec<_>>();
+
+        let ptr = unsafe {
+            crate::c_api::mts_tensormap_concatenate_properties(
+                tensors_ptr.as_ptr(),
+                tensors_ptr.len(),
+                fill_missing_gradients,
+            )
+        };
+
+        check_ptr(ptr)?;
+
+        return Ok(unsafe { TensorMap::from_raw(ptr) });
+    }
+
+    /// Concatenate multiple `TensorMap` along the sample axis, creating a new
+    /// `TensorMap` containing a copy of all the data.
+    ///
+    /// All the `tensors` must have the same keys, and for each key the blocks
+    /// must have the same components and properties. If
+    /// `fill_missing_gradients` is `false`, all the `tensors` must also have
+    /// gradients with respect to the same parameters (see
+    /// [`TensorMap::gradient_parameters_union`]). If it is `true`, gradients
+    /// only defined in some of the `tensors` are considered to be zero in the
+    /// others.
+    #[inline]
+    pub fn concatenate_samples(tensors: &[&TensorMap], fill_missing_gradients: bool) -> Result<TensorMap, Error> {
+        let tensors_ptr = tensors.iter().map(|tensor| tensor.ptr.cast_const()).collect::<Vec<_>>();
+
+        let ptr = unsafe {
+            crate::c_api::mts_tensormap_concatenate_samples(
+                tensors_ptr.as_ptr(),
+                tensors_ptr.len(),
+                fill_missing_gradients,
+            )
+        };
+
+        check_ptr(ptr)?;
+
+        return Ok(unsafe { TensorMap::from_raw(ptr) });
+    }
+
+    /// Apply `function` to every element of the values of all the blocks in
+    /// this `TensorMap`, modifying the values in-place. The gradients are
+    /// kept unchanged, modified with the same `function`, or removed
+    /// depending on `gradients`.
+    ///
+    /// The data is modified through `mts_array_t.data`, see the
+    /// [crate documentation](crate#accessing-the-data).
+    ///
+    /// Applying a non-linear function to the values makes the existing
+    /// gradients incorrect, and applying the same function to the gradients
+    /// does not fix this. In this case, use [`ApplyGradients::Remove`] to
+    /// discard the gradients. The values are then copied to new blocks without
+    /// gradients, using `mts_array_t.create` and
+    /// `mts_array_t.move_samples_from`. When the `rayon` feature is enabled,
+    /// the blocks are processed in parallel.
+    pub fn apply<F>(&mut self, function: F, gradients: ApplyGradients) -> Result<(), Error>
+        where F: Fn(f64) -> f64 + Sync
+    {
+        if gradients == ApplyGradients::Remove {
+            *self = self.without_gradients()?;
+        }
+        let gradients = gradients == ApplyGradients::Apply;
+
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            return self.par_iter_mut().try_for_each(|(_, mut block)| {
+                apply_to_block(&mut block, &function, gradients)
+            });
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            for mut block in self.blocks_mut() {
+                apply_to_block(&mut block, &function, gradients)?;
+            }
+            return Ok(());
+        }
+    }
+
+    /// Create a copy of this `TensorMap` where the blocks only contain the
+    /// values, without any gradients. The metadata of the tensor map and the
+    /// units of the blocks are kept.
+    fn without_gradients(&self) -> Result<TensorMap, Error> {
+        let mut blocks = Vec::new();
+        for block in self.blocks() {
+            let values = block.values();
+            let n_samples = values.as_raw().shape()?[0];
+            let mapping = (0..n_samples)
+                .map(|i| mts_sample_mapping_t { input: i, output: i })
+                .collect::<Vec<_>>();
+
+            let mut new_block = TensorBlock::from_raw_values(
+                crate::split::move_samples(&values, &mapping)?,
+                &block.samples(),
+                &block.components(),
+                &block.properties(),
+            )?;
+            new_block.as_ref_mut().set_units(block.units())?;
+            blocks.push(new_block);
+        }
+
+        let mut tensor = TensorMap::new(self.keys().clone(), blocks)?;
+        for (key, value) in self.metadata_iter() {
+            tensor.set_metadata(key, value)?;
+        }
+
+        return Ok(tensor);
+    }
+
+    /// Get an iterator over the keys and associated blocks
+    #[inline]
+    pub fn iter(&self) -> TensorMapIter<'_> {
+        return TensorMapIter {
+            inner: self.keys().iter().zip(self.blocks())
+        };
+    }
+
+    /// Get an iterator over owned copies of the keys and the associated
+    /// blocks.
+    ///
+    /// Unlike [`TensorMap::iter`], the keys are not borrowed from this
+    /// `TensorMap`, which makes it easier to store them in other collections,
+    /// for example to build a `HashMap<Vec<LabelValue>, _>` index.
+    #[inline]
+    pub fn iter_keys_owned(&self) -> impl Iterator<Item=(Vec<LabelValue>, TensorBlockRef<'_>)> {
+        self.iter().map(|(key, block)| (key.to_vec(), block))
+    }
+
+    /// Get an iterator over the keys and the gradients with respect to
+    /// `parameter` of the associated blocks.
+    ///
+    /// This returns an error if the blocks do not contain gradients with
+    /// respect to `parameter`.
+    #[inline]
+    pub fn iter_gradients(&self, parameter: &str) -> Result<TensorMapIter<'_>, Error> {
+        let mut gradients = Vec::new();
+        for block in self.blocks() {
+            if let Some(gradient) = block.gradient(parameter) {
+                gradients.push(gradient);
+            } else {
+                return Err(Error {
+                    code: None,
+                    message: format!(
+                        "the blocks in this TensorMap do not contain gradients with respect to '{}'",
+                        parameter
+                    ),
+                });
+            }
+        }
+
+        return Ok(TensorMapIter {
+            inner: self.keys().iter().zip(gradients)
+        });
+    }
+
+    /// Create a new `TensorMap` with the same keys as this one, where the
+    /// blocks are copies of the gradients with respect to `parameter`.
+    ///
+    /// The samples, components and properties of the gradients become the
+    /// labels of the new blocks, and gradients of these gradients (if any)
+    /// become the gradients of the new blocks. This can be used to handle
+    /// e.g. forces as a standalone `TensorMap`. This returns an error if the
+    /// blocks do not contain gradients with respect to `parameter`.
+    pub fn gradient_to_new_map(&self, parameter: &str) -> Result<TensorMap, Error> {
+        let blocks = self.iter_gradients(parameter)?
+            .map(|(_, gradient)| gradient.try_clone())
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        return TensorMap::new(self.keys().clone(), blocks);
+    }
+
+    /// Get an iterator over the keys and associated blocks, with read-write
+    /// access to the blocks
+    #[inline]
+    pub fn iter_mut(&mut self) -> TensorMapIterMut<'_> {
+        // we can not use `self.blocks_mut()` here, since it would
+        // double-borrow self
+        let mut blocks = Vec::new();
+        for i in 0..self.keys().count() {
+            blocks.push(unsafe { TensorMap::raw_block_mut_by_id(self.ptr, i) });
+        }
+
+        return TensorMapIterMut {
+            inner: self.keys().into_iter().zip(blocks)
+        };
+    }
+
+    /// Get a parallel iterator over the keys and associated blocks
+    #[cfg(feature = "rayon")]
     #[inline]
     pub fn par_iter(&self) -> TensorMapParIter {
         use rayon::prelude::*;
@@ -457,6 +1623,191 @@ impl TensorMap {
             inner: self.keys().par_iter().zip_eq(blocks)
         }
     }
+
+    /// Create a new `TensorMap` with the same metadata as this one, where all
+    /// the arrays (values and gradients) are converted with `convert`.
+    ///
+    /// This can be used to move all the data to a different array backend
+    /// (for example from CPU to GPU), without having to rebuild the blocks
+    /// manually. `convert` must return a new, owned `mts_array_t` with the
+    /// same shape as the input. The labels are shared between this
+    /// `TensorMap` and the new one, and are not copied.
+    pub fn cast_backend<F>(&self, convert: F) -> Result<TensorMap, Error>
+        where F: Fn(&mts_array_t) -> Result<mts_array_t, Error>
+    {
+        return self.map_arrays(convert);
+    }
+
+    /// Create a new `TensorMap` with the same metadata as this one, where all
+    /// the arrays (values and gradients) are replaced by the result of
+    /// `function`.
+    ///
+    /// This is a more general version of [`TensorMap::cast_backend`], which
+    /// can be used to implement custom array semantics, for example wrapping
+    /// all the arrays in a type tracking the operations applied to them.
+    /// `function` is called once for the values of each block, followed by
+    /// all of its gradients, and can keep state between calls.
+    ///
+    /// `function` must return a new, owned `mts_array_t` with the same shape
+    /// as the input, and this function returns an error otherwise. The labels
+    /// are shared between this `TensorMap` and the new one, and are not
+    /// copied.
+    pub fn map_arrays<F>(&self, mut function: F) -> Result<TensorMap, Error>
+        where F: FnMut(&mts_array_t) -> Result<mts_array_t, Error>
+    {
+        let blocks = self.blocks().into_iter()
+            .map(|block| map_block_arrays(block, &mut function))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        return TensorMap::new(self.keys().clone(), blocks);
+    }
+
+    /// Apply `function` to all the blocks in this `TensorMap` in parallel, and
+    /// create a new `TensorMap` with the same keys and the resulting blocks.
+    ///
+    /// `function` is called with the key and an owned copy of each block, and
+    /// the blocks of the new `TensorMap` are kept in the same order as the
+    /// keys. Since blocks can not be moved out of a `TensorMap`, this function
+    /// will copy the data of every block before calling `function`, using the
+    /// `mts_array_t.copy` function of the arrays. The arrays must then support
+    /// being copied and released from any thread.
+    #[cfg(feature = "rayon")]
+    pub fn par_map_blocks<F>(self, function: F) -> Result<TensorMap, Error>
+        where F: Fn(&[LabelValue], TensorBlock) -> Result<TensorBlock, Error> + Sync
+    {
+        use rayon::prelude::*;
+
+        let blocks = self.par_iter()
+            .map(|(key, block)| function(key, block.try_clone()?))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        return TensorMap::new(self.keys().clone(), blocks);
+    }
+}
+
+/// Apply `function` to all the values in `block`, and recursively to all the
+/// gradients if `gradients` is `true`. This is the implementation of
+/// [`TensorMap::apply`].
+fn apply_to_block<F>(block: &mut TensorBlockRefMut<'_>, function: &F, gradients: bool) -> Result<(), Error>
+    where F: Fn(f64) -> f64
+{
+    for value in block.values_mut().as_raw_mut().data()? {
+        *value = function(*value);
+    }
+
+    if gradients {
+        for (_, mut gradient) in block.gradients_mut() {
+            apply_to_block(&mut gradient, function, gradients)?;
+        }
+    }
+
+    return Ok(());
+}
+
+/// Create a new block with the same metadata as `block`, replacing the values
+/// and all the gradients with the output of `function`. This is the
+/// implementation of [`TensorMap::map_arrays`].
+fn map_block_arrays<F>(block: TensorBlockRef<'_>, function: &mut F) -> Result<TensorBlock, Error>
+    where F: FnMut(&mts_array_t) -> Result<mts_array_t, Error>
+{
+    let input = block.values();
+    let values = function(input.as_raw())?;
+
+    let shape = input.as_raw().shape()?;
+    let new_shape = match values.shape() {
+        Ok(new_shape) => new_shape.to_vec(),
+        Err(error) => {
+            destroy_array(values);
+            return Err(error);
+        }
+    };
+
+    if new_shape != shape {
+        destroy_array(values);
+        return Err(Error {
+            code: None,
+            message: format!(
+                "the new array must have the same shape as the input array: \
+                expected {:?}, got {:?}", shape, new_shape
+            ),
+        });
+    }
+
+    let mut new_block = TensorBlock::from_raw_values(
+        values,
+        &block.samples(),
+        &block.components(),
+        &block.properties(),
+    )?;
+
+    for (parameter, gradient) in block.gradients() {
+        new_block.add_gradient(parameter, map_block_arrays(gradient, function)?)?;
+    }
+
+    return Ok(new_block);
+}
+
+/// Implementation of `mts_map_keys_callback_t` calling a Rust function of
+/// type `F`, passed in `user_data`.
+unsafe extern fn map_keys_callback<F>(
+    user_data: *mut std::os::raw::c_void,
+    key: *const i32,
+    new_key: *mut i32,
+    key_size: usize,
+) -> mts_status_t where F: FnMut(&[LabelValue]) -> Vec<LabelValue> {
+    let unwind_wrapper = std::panic::AssertUnwindSafe((user_data, key, new_key));
+    crate::errors::catch_unwind_result(move || {
+        let (user_data, key, new_key) = *unwind_wrapper;
+        let function = &mut *user_data.cast::<F>();
+        let key = std::slice::from_raw_parts(key.cast::<LabelValue>(), key_size);
+
+        let values = function(key);
+        if values.len() != key_size {
+            return Err(Error {
+                code: None,
+                message: format!(
+                    "the new key for {:?} has {} values, but the keys have {} dimensions",
+                    key, values.len(), key_size
+                ),
+            });
+        }
+
+        let new_key = std::slice::from_raw_parts_mut(new_key.cast::<LabelValue>(), key_size);
+        new_key.copy_from_slice(&values);
+        return Ok(());
+    })
+}
+
+/// Implementation of `mts_filter_properties_callback_t` calling a Rust
+/// function of type `F`, passed in `user_data`.
+unsafe extern fn filter_properties_callback<F>(
+    user_data: *mut std::os::raw::c_void,
+    property: *const i32,
+    property_size: usize,
+    keep: *mut bool,
+) -> mts_status_t where F: Fn(&[LabelValue]) -> bool {
+    let unwind_wrapper = std::panic::AssertUnwindSafe((user_data, property, keep));
+    crate::errors::catch_unwind(move || {
+        let (user_data, property, keep) = *unwind_wrapper;
+        let function = &*user_data.cast::<F>();
+        let property = std::slice::from_raw_parts(property.cast::<LabelValue>(), property_size);
+        *keep = function(property);
+    })
+}
+
+/// Implementation of `mts_progress_callback_t` calling a Rust function of
+/// type `F`, passed in `user_data`.
+unsafe extern fn progress_callback<F>(
+    user_data: *mut std::os::raw::c_void,
+    done: usize,
+    total: usize,
+) -> mts_status_t where F: FnMut(usize, usize) {
+    let unwind_wrapper = std::panic::AssertUnwindSafe(user_data);
+    crate::errors::catch_unwind(move || {
+        let user_data = *unwind_wrapper;
+        let function = &mut *user_data.cast::<F>();
+        function(done, total);
+    })
 }
 
 /******************************************************************************/
@@ -620,7 +1971,8 @@ impl<'a> rayon::iter::IndexedParallelIterator for TensorMapParIterMut<'a> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{Labels, TensorBlock, TensorMap};
+    use crate::{Labels, LabelValue, TensorBlock, TensorMap};
+    use super::{ApplyGradients, MetadataAxis};
 
     #[test]
     #[allow(clippy::cast_lossless, clippy::float_cmp)]
@@ -663,4 +2015,1146 @@ mod tests {
             assert_eq!(array[[0, 0]], 2.0 * (key[0].i32() as f64));
         }
     }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn iter_gradients() {
+        let mut blocks = Vec::new();
+        for i in 0..2 {
+            let mut block = TensorBlock::new(
+                ndarray::ArrayD::from_elem(vec![1, 1], 1.0),
+                &Labels::new(["samples"], &[[0]]),
+                &[],
+                &Labels::new(["properties"], &[[0]]),
+            ).unwrap();
+
+            let gradient = TensorBlock::new(
+                ndarray::ArrayD::from_elem(vec![1, 1], f64::from(i)),
+                &Labels::new(["sample"], &[[0]]),
+                &[],
+                &Labels::new(["properties"], &[[0]]),
+            ).unwrap();
+            block.add_gradient("positions", gradient).unwrap();
+
+            blocks.push(block);
+        }
+
+        let tensor = TensorMap::new(Labels::new(["key"], &[[0], [1]]), blocks).unwrap();
+
+        let gradients = tensor.iter_gradients("positions").unwrap();
+        assert_eq!(gradients.len(), 2);
+        for (key, gradient) in gradients {
+            assert_eq!(gradient.samples().names(), ["sample"]);
+            assert_eq!(gradient.values().to_array()[[0, 0]], f64::from(key[0].i32()));
+        }
+
+        let Err(error) = tensor.iter_gradients("cell") else {
+            panic!("expected an error");
+        };
+        assert_eq!(
+            error.message,
+            "the blocks in this TensorMap do not contain gradients with respect to 'cell'"
+        );
+    }
+
+    #[test]
+    fn gradient_to_new_map() {
+        let mut block = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![2, 1], 1.0),
+            &Labels::new(["system"], &[[0], [1]]),
+            &[],
+            &Labels::new(["energy"], &[[0]]),
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![3, 3, 1], 2.0),
+            &Labels::new(["sample", "atom"], &[[0, 0], [0, 1], [1, 0]]),
+            &[Labels::new(["xyz"], &[[0], [1], [2]])],
+            &Labels::new(["energy"], &[[0]]),
+        ).unwrap();
+        block.add_gradient("positions", gradient).unwrap();
+
+        let tensor = TensorMap::new(Labels::new(["key"], &[[0]]), vec![block]).unwrap();
+        let forces = tensor.gradient_to_new_map("positions").unwrap();
+
+        assert_eq!(forces.keys(), tensor.keys());
+        let block = forces.block_by_id(0);
+        assert_eq!(block.samples(), Labels::new(["sample", "atom"], &[[0, 0], [0, 1], [1, 0]]));
+        assert_eq!(block.components(), [Labels::new(["xyz"], &[[0], [1], [2]])]);
+        assert_eq!(block.properties(), Labels::new(["energy"], &[[0]]));
+        assert_eq!(block.values().as_array(), ndarray::ArrayD::from_elem(vec![3, 3, 1], 2.0));
+        assert!(block.gradient_list().is_empty());
+
+        let error = tensor.gradient_to_new_map("cell").unwrap_err();
+        assert_eq!(
+            error.message,
+            "the blocks in this TensorMap do not contain gradients with respect to 'cell'"
+        );
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn block_for_key() {
+        let mut blocks = Vec::new();
+        for value in [1.0, 2.0] {
+            blocks.push(TensorBlock::new(
+                ndarray::ArrayD::from_elem(vec![1, 1], value),
+                &Labels::new(["samples"], &[[0]]),
+                &[],
+                &Labels::new(["properties"], &[[0]]),
+            ).unwrap());
+        }
+
+        let tensor = TensorMap::new(
+            Labels::new(["key_1", "key_2"], &[[0, 1], [3, 4]]),
+            blocks,
+        ).unwrap();
+
+        let block = tensor.block_for_key(&[LabelValue::new(3), LabelValue::new(4)]).unwrap();
+        assert_eq!(block.values().to_array()[[0, 0]], 2.0);
+
+        let error = tensor.block_for_key(&[LabelValue::new(3), LabelValue::new(1)]).unwrap_err();
+        assert_eq!(error.message, "there is no block with key (key_1 = 3, key_2 = 1) in this TensorMap");
+
+        let error = tensor.block_for_key(&[LabelValue::new(3)]).unwrap_err();
+        assert_eq!(error.message, "expected a key with 2 values, got 1");
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn block_where() {
+        let mut blocks = Vec::new();
+        for value in [1.0, 2.0, 3.0] {
+            blocks.push(TensorBlock::new(
+                ndarray::ArrayD::from_elem(vec![1, 1], value),
+                &Labels::new(["samples"], &[[0]]),
+                &[],
+                &Labels::new(["properties"], &[[0]]),
+            ).unwrap());
+        }
+
+        let tensor = TensorMap::new(
+            Labels::new(["key_1", "key_2"], &[[0, 1], [0, 2], [1, 1]]),
+            blocks,
+        ).unwrap();
+
+        let block = tensor.block_where(&[("key_2", 2.into()), ("key_1", 0.into())]).unwrap();
+        assert_eq!(block.values().to_array()[[0, 0]], 2.0);
+
+        let block = tensor.block_where(&[("key_1", 1.into())]).unwrap();
+        assert_eq!(block.values().to_array()[[0, 0]], 3.0);
+
+        let error = tensor.block_where(&[("key_1", 0.into())]).unwrap_err();
+        assert_eq!(
+            error.message,
+            "2 blocks matched the selection (key_1 = 0), expected only one. \
+            The matching keys are (key_1 = 0, key_2 = 1), (key_1 = 0, key_2 = 2)"
+        );
+
+        let error = tensor.block_where(&[("key_1", 0.into()), ("key_1", 1.into())]).unwrap_err();
+        assert_eq!(error.message, "the same key dimension is used multiple times in the selection [key_1, key_1]");
+
+        let error = tensor.block_where(&[("key_3", 0.into())]).unwrap_err();
+        assert_eq!(error.message, "invalid parameter: 'key_3' is not part of the keys for this tensor");
+    }
+
+    #[test]
+    fn iter_keys_owned() {
+        let mut blocks = Vec::new();
+        for n_samples in [1, 2, 3] {
+            let samples = (0..n_samples).map(|i| [i]).collect::<Vec<_>>();
+            blocks.push(TensorBlock::new(
+                ndarray::ArrayD::from_elem(vec![samples.len(), 1], 1.0),
+                &Labels::new(["samples"], &samples),
+                &[],
+                &Labels::new(["properties"], &[[0]]),
+            ).unwrap());
+        }
+
+        let tensor = TensorMap::new(
+            Labels::new(["key_1", "key_2"], &[[0, 1], [0, 2], [1, 1]]),
+            blocks,
+        ).unwrap();
+
+        let index = tensor.iter_keys_owned().collect::<std::collections::HashMap<_, _>>();
+        assert_eq!(index.len(), 3);
+
+        let key = vec![LabelValue::new(0), LabelValue::new(2)];
+        assert_eq!(index[&key].samples().count(), 2);
+        let key = vec![LabelValue::new(1), LabelValue::new(1)];
+        assert_eq!(index[&key].samples().count(), 3);
+    }
+
+    #[test]
+    fn from_iter_checked() {
+        let block = |value: f64| TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![1, 1], value),
+            &Labels::new(["samples"], &[[0]]),
+            &[],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap();
+
+        let tensor = TensorMap::from_iter_checked(vec!["key"], vec![
+            (vec![LabelValue::new(3)], block(1.0)),
+            (vec![LabelValue::new(1)], block(2.0)),
+        ]).unwrap();
+        assert_eq!(tensor.keys(), &Labels::new(["key"], &[[3], [1]]));
+        assert_eq!(tensor.block_by_id(1).values().as_array(), ndarray::ArrayD::from_elem(vec![1, 1], 2.0));
+
+        let error = TensorMap::from_iter_checked(vec!["key"], vec![
+            (vec![LabelValue::new(3)], block(1.0)),
+            (vec![LabelValue::new(3)], block(2.0)),
+        ]).unwrap_err();
+        assert_eq!(error.message, "invalid parameter: can not have the same label value multiple time: [3] is already present at position 0");
+
+        let error = TensorMap::from_iter_checked(vec!["key"], vec![
+            (vec![LabelValue::new(3), LabelValue::new(1)], block(1.0)),
+        ]).unwrap_err();
+        assert_eq!(error.message, "the key [3, 1] has 2 values, but the keys have 1 dimensions");
+
+        let error = TensorMap::from_iter_checked(vec!["key", "key"], Vec::new()).unwrap_err();
+        assert_eq!(error.message, "invalid parameter: labels names must be unique, got 'key' multiple times");
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn sort_blocks_by_key() {
+        let mut blocks = Vec::new();
+        for i in 0..3 {
+            blocks.push(TensorBlock::new(
+                ndarray::arr2(&[[f64::from(i)]]).into_dyn(),
+                &Labels::new(["samples"], &[[0]]),
+                &[],
+                &Labels::new(["properties"], &[[0]]),
+            ).unwrap());
+        }
+        let mut tensor = TensorMap::new(Labels::new(["a", "b"], &[[1, 0], [0, 3], [0, 1]]), blocks).unwrap();
+
+        tensor.sort_blocks_by_key().unwrap();
+        assert_eq!(tensor.keys(), &Labels::new(["a", "b"], &[[0, 1], [0, 3], [1, 0]]));
+
+        let values = tensor.blocks().iter()
+            .map(|block| block.values().as_array()[[0, 0]])
+            .collect::<Vec<_>>();
+        assert_eq!(values, [2.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn retain_blocks() {
+        /// Create a tensor map with the given `keys`, where the block for each
+        /// key contains the corresponding number of samples in `n_samples`.
+        fn tensor_with_keys(keys: Labels, n_samples: &[i32]) -> TensorMap {
+            let blocks = n_samples.iter().map(|&n_samples| {
+                let samples = (0..n_samples).map(|i| [i]).collect::<Vec<_>>();
+                TensorBlock::new(
+                    ndarray::ArrayD::from_elem(vec![samples.len(), 1], 1.0),
+                    &Labels::new(["samples"], &samples),
+                    &[],
+                    &Labels::new(["properties"], &[[0]]),
+                ).unwrap()
+            }).collect();
+
+            return TensorMap::new(keys, blocks).unwrap();
+        }
+
+        let mut tensor = tensor_with_keys(
+            Labels::new(["key_1", "key_2"], &[[0, 1], [0, 2], [1, 1], [1, 2]]),
+            &[1, 2, 0, 4],
+        );
+
+        let removed = tensor.retain_blocks(|_, block| block.samples().count() != 0).unwrap();
+        assert_eq!(removed, 1);
+        assert_eq!(tensor.keys(), &Labels::new(["key_1", "key_2"], &[[0, 1], [0, 2], [1, 2]]));
+
+        let removed = tensor.retain_blocks(|key, _| key[1] == 2).unwrap();
+        assert_eq!(removed, 1);
+        assert_eq!(tensor.keys(), &Labels::new(["key_1", "key_2"], &[[0, 2], [1, 2]]));
+
+        let n_samples = tensor.blocks().iter().map(|b| b.samples().count()).collect::<Vec<_>>();
+        assert_eq!(n_samples, [2, 4]);
+
+        assert_eq!(tensor.retain_blocks(|_, _| true).unwrap(), 0);
+        assert_eq!(tensor.keys().count(), 2);
+
+        // all the blocks are removed from the tensor
+        let mut tensor = tensor_with_keys(Labels::new(["key"], &[[0], [1]]), &[0, 0]);
+        assert_eq!(tensor.retain_blocks(|_, block| block.samples().count() != 0).unwrap(), 2);
+        assert_eq!(tensor.keys().count(), 0);
+        assert_eq!(tensor.keys().names(), ["key"]);
+        assert!(tensor.blocks().is_empty());
+    }
+
+    #[test]
+    fn remaining_key_names() {
+        let mut blocks = Vec::new();
+        for _ in 0..2 {
+            blocks.push(TensorBlock::new(
+                ndarray::ArrayD::from_elem(vec![1, 1], 1.0),
+                &Labels::new(["samples"], &[[0]]),
+                &[],
+                &Labels::new(["properties"], &[[0]]),
+            ).unwrap());
+        }
+        let keys = Labels::new(["a", "b", "c"], &[[0, 1, 2], [1, 1, 3]]);
+        let tensor = TensorMap::new(keys, blocks).unwrap();
+
+        for moved in [&["c", "a"] as &[&str], &["b"], &["a", "b", "c"]] {
+            let names = tensor.remaining_key_names(moved).unwrap();
+            let merged = tensor.keys_to_properties(&Labels::empty(moved.to_vec()), true).unwrap();
+            assert_eq!(names, merged.keys().names());
+        }
+
+        assert_eq!(tensor.remaining_key_names(&["c", "a"]).unwrap(), ["b"]);
+        assert_eq!(tensor.remaining_key_names(&["b"]).unwrap(), ["a", "c"]);
+        assert_eq!(tensor.remaining_key_names(&["a", "b", "c"]).unwrap(), ["_"]);
+        assert_eq!(tensor.remaining_key_names(&[]).unwrap(), ["a", "b", "c"]);
+
+        let error = tensor.remaining_key_names(&["d"]).unwrap_err();
+        assert_eq!(error.message, "'d' is not part of the keys of this TensorMap");
+
+        let error = tensor.remaining_key_names(&["a", "a"]).unwrap_err();
+        assert_eq!(error.message, "'a' is given multiple times in the dimensions to move");
+    }
+
+    #[test]
+    fn samples_union() {
+        let mut blocks = Vec::new();
+        for samples in [&[[0, 1], [0, 0]] as &[_], &[[1, 0], [0, 0]], &[]] {
+            blocks.push(TensorBlock::new(
+                ndarray::ArrayD::from_elem(vec![samples.len(), 1], 1.0),
+                &Labels::new(["system", "atom"], samples),
+                &[],
+                &Labels::new(["properties"], &[[0]]),
+            ).unwrap());
+        }
+
+        let tensor = TensorMap::new(Labels::new(["key"], &[[0], [1], [2]]), blocks).unwrap();
+        assert_eq!(
+            tensor.samples_union().unwrap(),
+            Labels::new(["system", "atom"], &[[0, 1], [0, 0], [1, 0]]),
+        );
+
+        let tensor = TensorMap::new(Labels::empty(vec!["key"]), vec![]).unwrap();
+        assert_eq!(tensor.samples_union().unwrap(), Labels::empty(vec![]));
+    }
+
+    #[test]
+    fn blocks_by_size() {
+        let mut blocks = Vec::new();
+        for n_samples in [1, 3, 0, 3, 5] {
+            let samples = (0..n_samples).map(|i| [i]).collect::<Vec<_>>();
+            blocks.push(TensorBlock::new(
+                ndarray::ArrayD::from_elem(vec![samples.len(), 1], 1.0),
+                &Labels::new(["samples"], &samples),
+                &[],
+                &Labels::new(["properties"], &[[0]]),
+            ).unwrap());
+        }
+
+        let tensor = TensorMap::new(Labels::new(["key"], &[[0], [1], [2], [3], [4]]), blocks).unwrap();
+        assert_eq!(tensor.blocks_by_size(), [4, 1, 3, 0, 2]);
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn into_scalar() {
+        let create_block = |n_samples: i32, components: &[Labels], n_properties: i32| {
+            let samples = (0..n_samples).map(|i| [i]).collect::<Vec<_>>();
+            let properties = (0..n_properties).map(|i| [i]).collect::<Vec<_>>();
+            let mut shape = vec![samples.len()];
+            shape.extend(components.iter().map(Labels::count));
+            shape.push(properties.len());
+            TensorBlock::new(
+                ndarray::ArrayD::from_elem(shape, -3.5),
+                &Labels::new(["system"], &samples),
+                components,
+                &Labels::new(["energy"], &properties),
+            ).unwrap()
+        };
+
+        let tensor = TensorMap::new(Labels::single(), vec![create_block(1, &[], 1)]).unwrap();
+        assert_eq!(tensor.into_scalar().unwrap(), -3.5);
+
+        let tensor = TensorMap::new(
+            Labels::new(["key"], &[[0], [1]]),
+            vec![create_block(1, &[], 1), create_block(1, &[], 1)],
+        ).unwrap();
+        let error = tensor.into_scalar().unwrap_err();
+        assert_eq!(error.message, "can not convert this TensorMap to a scalar: expected a single block, got 2");
+
+        let tensor = TensorMap::new(Labels::single(), vec![create_block(3, &[], 1)]).unwrap();
+        let error = tensor.into_scalar().unwrap_err();
+        assert_eq!(error.message, "can not convert this TensorMap to a scalar: expected a single sample, got 3");
+
+        let xyz = Labels::new(["xyz"], &[[0]]);
+        let tensor = TensorMap::new(Labels::single(), vec![create_block(1, &[xyz], 1)]).unwrap();
+        let error = tensor.into_scalar().unwrap_err();
+        assert_eq!(error.message, "can not convert this TensorMap to a scalar: expected no components, got 1");
+
+        let tensor = TensorMap::new(Labels::single(), vec![create_block(1, &[], 2)]).unwrap();
+        let error = tensor.into_scalar().unwrap_err();
+        assert_eq!(error.message, "can not convert this TensorMap to a scalar: expected a single property, got 2");
+    }
+
+    #[test]
+    fn split_by_key_column() {
+        let mut blocks = Vec::new();
+        for n_samples in [1, 2, 3, 4] {
+            let samples = (0..n_samples).map(|i| [i]).collect::<Vec<_>>();
+            blocks.push(TensorBlock::new(
+                ndarray::ArrayD::from_elem(vec![samples.len(), 1], 1.0),
+                &Labels::new(["samples"], &samples),
+                &[],
+                &Labels::new(["properties"], &[[0]]),
+            ).unwrap());
+        }
+
+        let tensor = TensorMap::new(
+            Labels::new(["key_1", "key_2"], &[[1, 1], [0, 2], [1, 2], [0, 1]]),
+            blocks,
+        ).unwrap();
+
+        let split = tensor.split_by_key_column("key_1", true).unwrap();
+        assert_eq!(split.len(), 2);
+
+        assert_eq!(split[0].0, 0);
+        assert_eq!(split[0].1.keys(), &Labels::new(["key_2"], &[[2], [1]]));
+        let n_samples = split[0].1.blocks().iter().map(|b| b.samples().count()).collect::<Vec<_>>();
+        assert_eq!(n_samples, [2, 4]);
+
+        assert_eq!(split[1].0, 1);
+        assert_eq!(split[1].1.keys(), &Labels::new(["key_2"], &[[1], [2]]));
+        let n_samples = split[1].1.blocks().iter().map(|b| b.samples().count()).collect::<Vec<_>>();
+        assert_eq!(n_samples, [1, 3]);
+
+        let split = tensor.split_by_key_column("key_2", false).unwrap();
+        assert_eq!(split[0].0, 1);
+        assert_eq!(split[0].1.keys(), &Labels::new(["key_1", "key_2"], &[[1, 1], [0, 1]]));
+        assert_eq!(split[1].0, 2);
+        assert_eq!(split[1].1.keys(), &Labels::new(["key_1", "key_2"], &[[0, 2], [1, 2]]));
+
+        // removing the only dimension of the keys
+        let (_, tensor) = split.into_iter().next().unwrap();
+        let tensor = tensor.keys_to_properties(&Labels::empty(vec!["key_1"]), true).unwrap();
+        let split = tensor.split_by_key_column("key_2", true).unwrap();
+        assert_eq!(split.len(), 1);
+        assert_eq!(split[0].1.keys(), &Labels::single());
+
+        let error = tensor.split_by_key_column("missing", true).unwrap_err();
+        assert_eq!(
+            error.message,
+            "'missing' is not part of the keys of this TensorMap, the dimensions are [key_2]"
+        );
+    }
+
+    #[test]
+    fn group_blocks_by() {
+        let mut blocks = Vec::new();
+        for _ in 0..5 {
+            blocks.push(TensorBlock::new(
+                ndarray::ArrayD::from_elem(vec![1, 1], 1.0),
+                &Labels::new(["samples"], &[[0]]),
+                &[],
+                &Labels::new(["properties"], &[[0]]),
+            ).unwrap());
+        }
+
+        let tensor = TensorMap::new(
+            Labels::new(["a", "b", "c"], &[[1, 0, 0], [0, 0, 1], [1, 1, 0], [1, 0, 1], [0, 0, 0]]),
+            blocks,
+        ).unwrap();
+
+        let groups = tensor.group_blocks_by(&["a"]).unwrap();
+        assert_eq!(groups, [
+            (vec![LabelValue::new(1)], vec![0, 2, 3]),
+            (vec![LabelValue::new(0)], vec![1, 4]),
+        ]);
+
+        let groups = tensor.group_blocks_by(&["c", "a"]).unwrap();
+        assert_eq!(groups, [
+            (vec![LabelValue::new(0), LabelValue::new(1)], vec![0, 2]),
+            (vec![LabelValue::new(1), LabelValue::new(0)], vec![1]),
+            (vec![LabelValue::new(1), LabelValue::new(1)], vec![3]),
+            (vec![LabelValue::new(0), LabelValue::new(0)], vec![4]),
+        ]);
+
+        let groups = tensor.group_blocks_by(&[]).unwrap();
+        assert_eq!(groups, [(vec![], vec![0, 1, 2, 3, 4])]);
+
+        let error = tensor.group_blocks_by(&["a", "d"]).unwrap_err();
+        assert_eq!(
+            error.message,
+            "'d' is not part of the keys of this TensorMap, the dimensions are [a, b, c]"
+        );
+
+        let error = tensor.group_blocks_by(&["b", "b"]).unwrap_err();
+        assert_eq!(error.message, "'b' is present more than once in the names to group by");
+    }
+
+    #[test]
+    fn map_keys() {
+        let mut blocks = Vec::new();
+        for i in 0..3 {
+            blocks.push(TensorBlock::new(
+                ndarray::arr2(&[[f64::from(i)]]).into_dyn(),
+                &Labels::new(["samples"], &[[0]]),
+                &[],
+                &Labels::new(["properties"], &[[0]]),
+            ).unwrap());
+        }
+        let tensor = TensorMap::new(Labels::new(["a", "b"], &[[0, 1], [1, 1], [2, 3]]), blocks).unwrap();
+
+        let mapped = tensor.map_keys(|key| vec![key[1], LabelValue::new(key[0].i32() * 10)]).unwrap();
+        assert_eq!(mapped.keys(), &Labels::new(["a", "b"], &[[1, 0], [1, 10], [3, 20]]));
+        assert_eq!(mapped.block_by_id(2).values().as_array(), ndarray::arr2(&[[2.0]]).into_dyn());
+
+        // the original tensor is not modified
+        assert_eq!(tensor.keys(), &Labels::new(["a", "b"], &[[0, 1], [1, 1], [2, 3]]));
+
+        let error = tensor.map_keys(|key| vec![key[1], key[1]]).unwrap_err();
+        assert_eq!(
+            error.message,
+            "invalid parameter: the new keys are not unique: both [0, 1] and \
+            [1, 1] are mapped to [1, 1]"
+        );
+
+        let error = tensor.map_keys(|key| vec![key[0]]).unwrap_err();
+        assert_eq!(
+            error.message,
+            "external error: calling mts_map_keys_callback_t failed (status -4242)"
+        );
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn filter_properties() {
+        let mut blocks = Vec::new();
+        for properties in [vec![[0, 0], [3, 1], [5, 0], [7, 2]], vec![[6, 0], [8, 1]]] {
+            let n_properties = properties.len();
+            blocks.push(TensorBlock::new(
+                ndarray::Array::from_shape_fn(vec![2, n_properties], |i| (10 * i[0] + i[1]) as f64),
+                &Labels::new(["samples"], &[[0], [1]]),
+                &[],
+                &Labels::new(["n", "l"], &properties),
+            ).unwrap());
+        }
+        let tensor = TensorMap::new(Labels::new(["key"], &[[0], [1]]), blocks).unwrap();
+
+        let filtered = tensor.filter_properties(|property| property[0].i32() < 5).unwrap();
+        assert_eq!(filtered.keys(), tensor.keys());
+
+        let block = filtered.block_by_id(0);
+        assert_eq!(block.properties(), Labels::new(["n", "l"], &[[0, 0], [3, 1]]));
+        assert_eq!(block.samples(), Labels::new(["samples"], &[[0], [1]]));
+        assert_eq!(block.values().as_array(), ndarray::arr2(&[[0.0, 1.0], [10.0, 11.0]]).into_dyn());
+
+        let block = filtered.block_by_id(1);
+        assert_eq!(block.properties().count(), 0);
+        assert_eq!(block.values().as_array().shape(), [2, 0]);
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn apply() {
+        let mut block = TensorBlock::new(
+            ndarray::arr2(&[[-1.0, 2.0], [3.0, -4.0]]).into_dyn(),
+            &Labels::new(["samples"], &[[0], [1]]),
+            &[],
+            &Labels::new(["properties"], &[[0], [1]]),
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            ndarray::arr3(&[[[-2.0, 5.0]]]).into_dyn(),
+            &Labels::new(["sample"], &[[1]]),
+            &[Labels::new(["xyz"], &[[0]])],
+            &Labels::new(["properties"], &[[0], [1]]),
+        ).unwrap();
+        block.add_gradient("g", gradient).unwrap();
+
+        let mut tensor = TensorMap::new(Labels::new(["key"], &[[0]]), vec![block]).unwrap();
+
+        tensor.apply(|x| x.max(0.0), ApplyGradients::Keep).unwrap();
+        let block = tensor.block_by_id(0);
+        assert_eq!(block.values().as_array(), ndarray::arr2(&[[0.0, 2.0], [3.0, 0.0]]).into_dyn());
+        let gradient = block.gradient("g").unwrap();
+        assert_eq!(gradient.values().as_array(), ndarray::arr3(&[[[-2.0, 5.0]]]).into_dyn());
+
+        tensor.apply(|x| 2.0 * x, ApplyGradients::Apply).unwrap();
+        let block = tensor.block_by_id(0);
+        assert_eq!(block.values().as_array(), ndarray::arr2(&[[0.0, 4.0], [6.0, 0.0]]).into_dyn());
+        let gradient = block.gradient("g").unwrap();
+        assert_eq!(gradient.values().as_array(), ndarray::arr3(&[[[-4.0, 10.0]]]).into_dyn());
+
+        tensor.set_metadata("model", "v1").unwrap();
+        tensor.block_mut_by_id(0).set_units(Some("eV")).unwrap();
+
+        tensor.apply(|x| x * x, ApplyGradients::Remove).unwrap();
+        let block = tensor.block_by_id(0);
+        assert_eq!(block.values().as_array(), ndarray::arr2(&[[0.0, 16.0], [36.0, 0.0]]).into_dyn());
+        assert_eq!(block.samples(), Labels::new(["samples"], &[[0], [1]]));
+        assert_eq!(block.properties(), Labels::new(["properties"], &[[0], [1]]));
+        assert!(block.gradient_list().is_empty());
+        assert_eq!(block.units(), Some("eV"));
+        assert_eq!(tensor.metadata("model"), Some("v1"));
+    }
+
+    #[test]
+    fn metadata() {
+        let block = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![1, 1], 1.0),
+            &Labels::new(["samples"], &[[0]]),
+            &[],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap();
+        let mut tensor = TensorMap::new(Labels::new(["key"], &[[0]]), vec![block]).unwrap();
+
+        assert_eq!(tensor.metadata("units"), None);
+        assert_eq!(tensor.metadata_iter().count(), 0);
+
+        tensor.set_metadata("units", "eV").unwrap();
+        tensor.set_metadata("model", "v1.2").unwrap();
+        assert_eq!(tensor.metadata("units"), Some("eV"));
+        tensor.set_metadata("units", "meV").unwrap();
+
+        let expected = [("units", "meV"), ("model", "v1.2")];
+        assert_eq!(tensor.metadata_iter().collect::<Vec<_>>(), expected);
+
+        let error = tensor.set_metadata("units", "e\0V").unwrap_err();
+        assert_eq!(error.message, "metadata value can not contain NULL bytes");
+
+        let clone = tensor.try_clone().unwrap();
+        assert_eq!(clone.metadata_iter().collect::<Vec<_>>(), expected);
+
+        let mut buffer = Vec::new();
+        tensor.save_buffer(&mut buffer).unwrap();
+        let loaded = TensorMap::load_buffer(&buffer).unwrap();
+        assert_eq!(loaded.metadata_iter().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn describe_blocks() {
+        let mut block_1 = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![3, 2], 1.0),
+            &Labels::new(["samples"], &[[0], [1], [2]]),
+            &[],
+            &Labels::new(["properties"], &[[0], [1]]),
+        ).unwrap();
+        let gradient = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![1, 3, 2], 1.0),
+            &Labels::new(["sample"], &[[0]]),
+            &[Labels::new(["xyz"], &[[0], [1], [2]])],
+            &Labels::new(["properties"], &[[0], [1]]),
+        ).unwrap();
+        block_1.add_gradient("positions", gradient).unwrap();
+
+        let mut block_2 = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![12, 2], 1.0),
+            &Labels::new(["samples"], &(0..12).map(|i| [i]).collect::<Vec<_>>()),
+            &[],
+            &Labels::new(["properties"], &[[0], [1]]),
+        ).unwrap();
+        let gradient = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![0, 3, 2], 1.0),
+            &Labels::empty(vec!["sample"]),
+            &[Labels::new(["xyz"], &[[0], [1], [2]])],
+            &Labels::new(["properties"], &[[0], [1]]),
+        ).unwrap();
+        block_2.add_gradient("positions", gradient).unwrap();
+
+        let keys = Labels::new(["key", "long_key_name"], &[[0, 1], [10, -2]]);
+        let tensor = TensorMap::new(keys, vec![block_1, block_2]).unwrap();
+
+        assert_eq!(tensor.describe_blocks(), "\
+key  long_key_name  samples  properties  components  gradients
+0    1              3        2           []          positions
+10   -2             12       2           []          positions
+");
+    }
+
+    #[test]
+    #[allow(clippy::cast_possible_truncation)]
+    fn cast_backend() {
+        let mut block = TensorBlock::new(
+            ndarray::arr2(&[[1.0, 2.0], [3.0, 4.0]]).into_dyn(),
+            &Labels::new(["samples"], &[[0], [1]]),
+            &[],
+            &Labels::new(["properties"], &[[0], [1]]),
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            ndarray::arr3(&[[[5.0, 6.0]]]).into_dyn(),
+            &Labels::new(["sample"], &[[1]]),
+            &[Labels::new(["xyz"], &[[0]])],
+            &Labels::new(["properties"], &[[0], [1]]),
+        ).unwrap();
+        block.add_gradient("g", gradient).unwrap();
+
+        let tensor = TensorMap::new(Labels::new(["key"], &[[0]]), vec![block]).unwrap();
+
+        // convert all the data to integers
+        let converted = tensor.cast_backend(|array| {
+            let array = unsafe { crate::ArrayRef::from_raw(*array) };
+            let converted = array.as_array().mapv(|v| v as i32);
+            Ok((Box::new(converted) as Box<dyn crate::Array>).into())
+        }).unwrap();
+
+        assert_eq!(converted.keys(), tensor.keys());
+
+        let block = converted.block_by_id(0);
+        let values = block.values().as_any().downcast_ref::<ndarray::ArrayD<i32>>().unwrap().clone();
+        assert_eq!(values, ndarray::arr2(&[[1, 2], [3, 4]]).into_dyn());
+
+        let gradient = block.gradient("g").unwrap();
+        let values = gradient.values().as_any().downcast_ref::<ndarray::ArrayD<i32>>().unwrap().clone();
+        assert_eq!(values, ndarray::arr3(&[[[5, 6]]]).into_dyn());
+        assert_eq!(gradient.samples(), Labels::new(["sample"], &[[1]]));
+        assert_eq!(gradient.components(), [Labels::new(["xyz"], &[[0]])]);
+
+        // the labels are shared with the original tensor
+        let original = tensor.block_by_id(0);
+        assert_eq!(
+            block.samples().as_mts_labels_t().internal_ptr_,
+            original.samples().as_mts_labels_t().internal_ptr_,
+        );
+        assert_eq!(
+            block.properties().as_mts_labels_t().internal_ptr_,
+            original.properties().as_mts_labels_t().internal_ptr_,
+        );
+
+        // errors in the conversion are propagated
+        let error = tensor.cast_backend(|_| Err(crate::Error {
+            code: None,
+            message: "conversion failed".into(),
+        })).unwrap_err();
+        assert_eq!(error.message, "conversion failed");
+    }
+
+    #[test]
+    fn map_arrays() {
+        let mut block = TensorBlock::new(
+            ndarray::arr2(&[[1.0, 2.0], [3.0, 4.0]]).into_dyn(),
+            &Labels::new(["samples"], &[[0], [1]]),
+            &[],
+            &Labels::new(["properties"], &[[0], [1]]),
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            ndarray::arr3(&[[[5.0, 6.0]]]).into_dyn(),
+            &Labels::new(["sample"], &[[1]]),
+            &[Labels::new(["xyz"], &[[0]])],
+            &Labels::new(["properties"], &[[0], [1]]),
+        ).unwrap();
+        block.add_gradient("g", gradient).unwrap();
+
+        let tensor = TensorMap::new(Labels::new(["key"], &[[0]]), vec![block]).unwrap();
+
+        // the function can keep state between calls
+        let mut n_calls = 0;
+        let mapped = tensor.map_arrays(|array| {
+            n_calls += 1;
+            let array = unsafe { crate::ArrayRef::from_raw(*array) };
+            let mapped = array.as_array() * f64::from(n_calls);
+            Ok((Box::new(mapped) as Box<dyn crate::Array>).into())
+        }).unwrap();
+        assert_eq!(n_calls, 2);
+
+        assert_eq!(mapped.keys(), tensor.keys());
+        let block = mapped.block_by_id(0);
+        assert_eq!(block.values().as_array(), ndarray::arr2(&[[1.0, 2.0], [3.0, 4.0]]).into_dyn());
+        assert_eq!(block.samples(), Labels::new(["samples"], &[[0], [1]]));
+
+        let gradient = block.gradient("g").unwrap();
+        assert_eq!(gradient.values().as_array(), ndarray::arr3(&[[[10.0, 12.0]]]).into_dyn());
+        assert_eq!(gradient.components(), [Labels::new(["xyz"], &[[0]])]);
+
+        // the shape of the new arrays is checked
+        let error = tensor.map_arrays(|_| {
+            let mapped = ndarray::ArrayD::from_elem(vec![1, 2], 0.0);
+            Ok((Box::new(mapped) as Box<dyn crate::Array>).into())
+        }).unwrap_err();
+        assert_eq!(
+            error.message,
+            "the new array must have the same shape as the input array: \
+            expected [2, 2], got [1, 2]"
+        );
+    }
+
+    #[test]
+    fn zeros_like() {
+        let mut blocks = Vec::new();
+        for i in 0..2 {
+            blocks.push(TensorBlock::new(
+                ndarray::ArrayD::from_elem(vec![2, 1], f64::from(i + 1)),
+                &Labels::new(["samples"], &[[0], [1]]),
+                &[],
+                &Labels::new(["properties"], &[[i]]),
+            ).unwrap());
+        }
+        let tensor = TensorMap::new(Labels::new(["key"], &[[0], [1]]), blocks).unwrap();
+
+        let zeros = tensor.zeros_like().unwrap();
+        assert_eq!(zeros.keys(), tensor.keys());
+        for (block, zeros_block) in tensor.blocks().iter().zip(zeros.blocks()) {
+            assert!(zeros_block.structurally_equal(*block));
+            assert_eq!(zeros_block.values().as_array(), ndarray::ArrayD::from_elem(vec![2, 1], 0.0));
+        }
+    }
+
+    #[test]
+    fn check_invariants() {
+        let block = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![2, 1], 1.0),
+            &Labels::new(["samples"], &[[0], [1]]),
+            &[],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap();
+        let mut tensor = TensorMap::new(Labels::new(["key"], &[[0]]), vec![block]).unwrap();
+        tensor.check_invariants().unwrap();
+
+        // replace the values with an array of the wrong shape
+        let mut block = tensor.block_mut_by_id(0);
+        *block.values_mut().as_array_mut() = ndarray::ArrayD::from_elem(vec![3, 1], 1.0);
+
+        let error = tensor.check_invariants().unwrap_err();
+        assert_eq!(
+            error.message,
+            "invalid parameter: data and labels don't match: the array shape \
+            along axis 0 is 3 but we have 2 sample labels"
+        );
+    }
+
+    #[test]
+    fn nbytes() {
+        let mut block = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![2, 3], 1.0),
+            &Labels::new(["samples"], &[[0], [1]]),
+            &[],
+            &Labels::new(["properties"], &[[0], [1], [2]]),
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![1, 3], 1.0),
+            &Labels::new(["sample"], &[[0]]),
+            &[],
+            &Labels::new(["properties"], &[[0], [1], [2]]),
+        ).unwrap();
+        block.add_gradient("g", gradient).unwrap();
+        assert_eq!(block.nbytes().unwrap(), (2 * 3 + 3) * 8);
+
+        let integers = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![4, 1], 1_i32),
+            &Labels::new(["samples"], &[[0], [1], [2], [3]]),
+            &[],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap();
+        assert_eq!(integers.nbytes().unwrap(), 4 * 4);
+
+        let other = block.as_ref().try_clone().unwrap();
+        let tensor = TensorMap::new(Labels::new(["key"], &[[0], [1]]), vec![block, other]).unwrap();
+        assert_eq!(tensor.nbytes().unwrap(), 2 * (2 * 3 + 3) * 8);
+    }
+
+    #[test]
+    fn make_labels_unique() {
+        let properties = Labels::new(["properties"], &[[0], [1]]);
+        let mut blocks = Vec::new();
+        for _ in 0..2 {
+            let mut block = TensorBlock::new(
+                ndarray::ArrayD::from_elem(vec![1, 2], 1.0),
+                &Labels::new(["samples"], &[[0]]),
+                &[],
+                &properties,
+            ).unwrap();
+
+            let gradient = TensorBlock::new(
+                ndarray::ArrayD::from_elem(vec![1, 2], 1.0),
+                &Labels::new(["sample"], &[[0]]),
+                &[],
+                &properties,
+            ).unwrap();
+            block.add_gradient("g", gradient).unwrap();
+            blocks.push(block);
+        }
+        std::mem::drop(properties);
+
+        let mut tensor = TensorMap::new(Labels::new(["key"], &[[0], [1]]), blocks).unwrap();
+
+        let properties_ptr = |tensor: &TensorMap, block: usize, gradient: bool| {
+            let block = tensor.block_by_id(block);
+            let properties = if gradient {
+                block.gradient("g").unwrap().properties()
+            } else {
+                block.properties()
+            };
+            properties.as_mts_labels_t().internal_ptr_
+        };
+
+        assert_eq!(properties_ptr(&tensor, 0, false), properties_ptr(&tensor, 1, false));
+        assert_eq!(properties_ptr(&tensor, 0, false), properties_ptr(&tensor, 0, true));
+
+        tensor.make_labels_unique().unwrap();
+
+        assert_ne!(properties_ptr(&tensor, 0, false), properties_ptr(&tensor, 1, false));
+        assert_ne!(properties_ptr(&tensor, 0, false), properties_ptr(&tensor, 0, true));
+        assert_ne!(properties_ptr(&tensor, 1, false), properties_ptr(&tensor, 1, true));
+
+        assert_eq!(tensor.block_by_id(0).properties(), tensor.block_by_id(1).properties());
+    }
+
+    #[test]
+    fn concatenate_fill_missing_gradients() {
+        let create_tensor = |system: i32, parameter: Option<&str>| {
+            let mut block = TensorBlock::new(
+                ndarray::ArrayD::from_elem(vec![1, 3, 1], 1.0),
+                &Labels::new(["system"], &[[system]]),
+                &[Labels::new(["xyz"], &[[0], [1], [2]])],
+                &Labels::new(["properties"], &[[0]]),
+            ).unwrap();
+
+            if let Some(parameter) = parameter {
+                let gradient = TensorBlock::new(
+                    ndarray::ArrayD::from_elem(vec![1, 3, 1], 1.0),
+                    &Labels::new(["sample", "atom"], &[[0, 2]]),
+                    &[Labels::new(["xyz"], &[[0], [1], [2]])],
+                    &Labels::new(["properties"], &[[0]]),
+                ).unwrap();
+                block.add_gradient(parameter, gradient).unwrap();
+            }
+
+            TensorMap::new(Labels::new(["key"], &[[0]]), vec![block]).unwrap()
+        };
+
+        let first = create_tensor(0, Some("positions"));
+        let second = create_tensor(1, Some("strain"));
+        let third = create_tensor(2, None);
+
+        assert_eq!(
+            TensorMap::gradient_parameters_union(&[&first, &second, &third]),
+            ["positions", "strain"]
+        );
+        assert!(TensorMap::gradient_parameters_union(&[&third]).is_empty());
+
+        let error = TensorMap::concatenate_samples(&[&first, &third], false).unwrap_err();
+        assert_eq!(error.message, "invalid parameter: can not concatenate samples of blocks with different gradients");
+
+        let tensor = TensorMap::concatenate_samples(&[&first, &second, &third], true).unwrap();
+        let block = tensor.block_by_id(0);
+        assert_eq!(block.samples(), Labels::new(["system"], &[[0], [1], [2]]));
+
+        let mut gradients = block.gradient_list();
+        gradients.sort_unstable();
+        assert_eq!(gradients, ["positions", "strain"]);
+
+        let gradient = block.gradient("strain").unwrap();
+        assert_eq!(gradient.samples(), Labels::new(["sample", "atom"], &[[1, 2]]));
+        assert_eq!(gradient.values().as_array().shape(), [1, 3, 1]);
+    }
+
+    #[test]
+    fn assert_gradients() {
+        let mut block = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![1, 1], 1.0),
+            &Labels::new(["system"], &[[0]]),
+            &[],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap();
+        let without_gradients = TensorMap::new(Labels::new(["key"], &[[0]]), vec![block.as_ref().try_clone().unwrap()]).unwrap();
+
+        let gradient = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![1, 1], 1.0),
+            &Labels::new(["sample"], &[[0]]),
+            &[],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap();
+        block.add_gradient("positions", gradient).unwrap();
+        let with_gradients = TensorMap::new(Labels::new(["key"], &[[0]]), vec![block]).unwrap();
+
+        with_gradients.assert_has_gradient("positions").unwrap();
+        without_gradients.assert_no_gradients().unwrap();
+
+        let error = with_gradients.assert_has_gradient("strain").unwrap_err();
+        assert_eq!(
+            error.message,
+            "expected gradients with respect to 'strain' in this TensorMap, \
+            the available gradients are [positions]"
+        );
+
+        let error = without_gradients.assert_has_gradient("positions").unwrap_err();
+        assert_eq!(
+            error.message,
+            "expected gradients with respect to 'positions' in this TensorMap, \
+            the available gradients are []"
+        );
+
+        let error = with_gradients.assert_no_gradients().unwrap_err();
+        assert_eq!(
+            error.message,
+            "expected no gradients in this TensorMap, got gradients with respect to [positions]"
+        );
+    }
+
+    #[test]
+    fn unique_metadata() {
+        let block_1 = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![2, 3, 1], 1.0),
+            &Labels::new(["structure", "atom"], &[[0, 1], [2, 1]]),
+            &[Labels::new(["xyz"], &[[0], [1], [2]])],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap();
+
+        let block_2 = TensorBlock::new(
+            ndarray::ArrayD::from_elem(vec![3, 3, 1], 1.0),
+            &Labels::new(["structure", "atom"], &[[4, 0], [2, 0], [2, 3]]),
+            &[Labels::new(["xyz"], &[[0], [1], [2]])],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap();
+
+        let tensor = TensorMap::new(
+            Labels::new(["key"], &[[0], [1]]),
+            vec![block_1, block_2],
+        ).unwrap();
+
+        let structures = tensor.unique_metadata(MetadataAxis::Samples, "structure").unwrap();
+        assert_eq!(structures.names(), ["structure"]);
+        assert_eq!(structures.iter().collect::<Vec<_>>(), [[0], [2], [4]]);
+
+        let atoms = tensor.unique_metadata(MetadataAxis::Samples, "atom").unwrap();
+        assert_eq!(atoms.iter().collect::<Vec<_>>(), [[0], [1], [3]]);
+
+        let xyz = tensor.unique_metadata(MetadataAxis::Components, "xyz").unwrap();
+        assert_eq!(xyz.iter().collect::<Vec<_>>(), [[0], [1], [2]]);
+
+        let error = tensor.unique_metadata(MetadataAxis::Properties, "structure").unwrap_err();
+        assert_eq!(error.message, "there is no 'structure' dimension in the properties of any block");
+    }
+
+    #[test]
+    fn block_matching() {
+        let mut blocks = Vec::new();
+        for _ in 0..3 {
+            blocks.push(TensorBlock::new(
+                ndarray::ArrayD::from_elem(vec![1, 1], 1.0),
+                &Labels::new(["samples"], &[[0]]),
+                &[],
+                &Labels::new(["properties"], &[[0]]),
+            ).unwrap());
+        }
+
+        let tensor = TensorMap::new(
+            Labels::new(["key_1", "key_2"], &[[0, 1], [0, 2], [1, 1]]),
+            blocks,
+        ).unwrap();
+
+        let selection = Labels::new(["key_1", "key_2"], &[[0, 2]]);
+        assert_eq!(tensor.block_matching(&selection).unwrap(), 1);
+
+        let selection = Labels::new(["key_1"], &[[3]]);
+        let error = tensor.block_matching(&selection).unwrap_err();
+        assert_eq!(
+            error.message,
+            "no blocks matched the selection (key_1 = 3): key_1 = 3 is not \
+            part of the keys, available values for 'key_1' are [0, 1]"
+        );
+
+        // all values exist in the keys, but not this combination
+        let selection = Labels::new(["key_1", "key_2"], &[[1, 2]]);
+        let error = tensor.block_matching(&selection).unwrap_err();
+        assert_eq!(error.message, "no blocks matched the selection (key_1 = 1, key_2 = 2)");
+
+        let selection = Labels::new(["key_3"], &[[1]]);
+        assert!(tensor.block_matching(&selection).is_err());
+
+        let selection = Labels::new(["key_1"], &[[0]]);
+        let error = tensor.block_matching(&selection).unwrap_err();
+        assert_eq!(
+            error.message,
+            "2 blocks matched the selection (key_1 = 0), expected only one. \
+            The matching keys are (key_1 = 0, key_2 = 1), (key_1 = 0, key_2 = 2)"
+        );
+
+        // wildcard selection
+        let selection = Labels::new(["key_1", "key_2"], &[[LabelValue::ANY, LabelValue::new(2)]]);
+        assert_eq!(tensor.blocks_matching_wildcard(&selection).unwrap(), [1]);
+
+        let selection = Labels::new(["key_1", "key_2"], &[[LabelValue::ANY, LabelValue::new(1)]]);
+        assert_eq!(tensor.blocks_matching_wildcard(&selection).unwrap(), [0, 2]);
+
+        let selection = Labels::new(["key_1", "key_2"], &[[LabelValue::ANY, LabelValue::new(3)]]);
+        assert!(tensor.blocks_matching_wildcard(&selection).unwrap().is_empty());
+
+        // without the wildcard, ANY is compared exactly with the keys
+        let selection = Labels::new(["key_1", "key_2"], &[[LabelValue::ANY, LabelValue::new(1)]]);
+        assert!(tensor.blocks_matching(&selection).unwrap().is_empty());
+    }
+
+    #[test]
+    fn blocks_matching_with_keys() {
+        let mut blocks = Vec::new();
+        for i in 0..3 {
+            blocks.push(TensorBlock::new(
+                ndarray::ArrayD::from_elem(vec![1, 1], 1.0),
+                &Labels::new(["samples"], &[[i]]),
+                &[],
+                &Labels::new(["properties"], &[[0]]),
+            ).unwrap());
+        }
+
+        let tensor = TensorMap::new(
+            Labels::new(["key_1", "key_2"], &[[0, 1], [0, 2], [1, 1]]),
+            blocks,
+        ).unwrap();
+
+        let selection = Labels::new(["key_1"], &[[0]]);
+        let matching = tensor.blocks_matching_with_keys(&selection).unwrap();
+        assert_eq!(matching.len(), 2);
+        assert_eq!(matching[0].0, [0, 1]);
+        assert_eq!(matching[0].1.samples(), Labels::new(["samples"], &[[0]]));
+        assert_eq!(matching[1].0, [0, 2]);
+        assert_eq!(matching[1].1.samples(), Labels::new(["samples"], &[[1]]));
+
+        let selection = Labels::new(["key_2"], &[[3]]);
+        assert!(tensor.blocks_matching_with_keys(&selection).unwrap().is_empty());
+
+        let selection = Labels::new(["key_3"], &[[1]]);
+        assert!(tensor.blocks_matching_with_keys(&selection).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    #[allow(clippy::cast_lossless, clippy::float_cmp)]
+    fn par_map_blocks() {
+        let mut blocks = Vec::new();
+        for value in [1.0, 3.0, -4.0] {
+            blocks.push(TensorBlock::new(
+                ndarray::ArrayD::from_elem(vec![1, 2], value),
+                &Labels::new(["samples"], &[[0]]),
+                &[],
+                &Labels::new(["properties"], &[[0], [1]]),
+            ).unwrap());
+        }
+
+        let tensor = TensorMap::new(
+            Labels::new(["key"], &[[1], [3], [-4]]),
+            blocks,
+        ).unwrap();
+
+        let tensor = tensor.par_map_blocks(|key, mut block| {
+            let mut block_mut = block.as_ref_mut();
+            let array = block_mut.values_mut().to_array_mut();
+            *array *= key[0].i32() as f64;
+            Ok(block)
+        }).unwrap();
+
+        assert_eq!(tensor.keys().iter().collect::<Vec<_>>(), [[1], [3], [-4]]);
+        for (key, block) in &tensor {
+            let expected = (key[0].i32() * key[0].i32()) as f64;
+            assert_eq!(block.values().to_array()[[0, 1]], expected);
+        }
+
+        let error = tensor.par_map_blocks(|_, _| Err(crate::Error {
+            code: None,
+            message: "failed".into(),
+        })).unwrap_err();
+        assert_eq!(error.message, "failed");
+    }
 }
@@ -121,3 +121,44 @@ fn multiple_components() {
 
     assert_eq!(gradient.values().as_array(), ArrayD::from_elem(vec![3, 3, 4], 11.0));
 }
+
+#[test]
+fn properties_to_components() {
+    let mut block = TensorBlock::new(
+        ArrayD::from_shape_fn(vec![3, 2, 3], |i| (100 * i[0] + 10 * i[1] + i[2]) as f64),
+        &example_labels(vec!["samples"], vec![[0], [1], [2]]),
+        &[example_labels(vec!["components"], vec![[0], [1]])],
+        &example_labels(vec!["properties"], vec![[0], [1], [2]]),
+    ).unwrap();
+
+    let gradient = TensorBlock::new(
+        ArrayD::from_shape_fn(vec![2, 2, 3], |i| (100 * i[0] + 10 * i[1] + i[2]) as f64),
+        &example_labels(vec!["sample", "parameter"], vec![[0, 2], [1, 2]]),
+        &[example_labels(vec!["components"], vec![[0], [1]])],
+        &example_labels(vec!["properties"], vec![[0], [1], [2]]),
+    ).unwrap();
+    block.add_gradient("parameter", gradient).unwrap();
+
+    let tensor = TensorMap::new(Labels::single(), vec![block]).unwrap();
+    let moved = tensor.components_to_properties(&["components"]).unwrap();
+    let moved = moved.properties_to_components(&["components"]).unwrap();
+
+    let block = moved.block_by_id(0);
+    let expected = tensor.block_by_id(0);
+    assert_eq!(block.components(), expected.components());
+    assert_eq!(block.properties(), expected.properties());
+    assert_eq!(block.values().as_array(), expected.values().as_array());
+
+    let gradient = block.gradient("parameter").unwrap();
+    let expected = expected.gradient("parameter").unwrap();
+    assert_eq!(gradient.samples(), expected.samples());
+    assert_eq!(gradient.components(), expected.components());
+    assert_eq!(gradient.values().as_array(), expected.values().as_array());
+
+    let error = tensor.properties_to_components(&["samples"]).unwrap_err();
+    assert_eq!(
+        error.message,
+        "invalid parameter: [samples] must be the first dimensions of the \
+        properties, got properties [properties]"
+    );
+}
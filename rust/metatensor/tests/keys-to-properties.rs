@@ -1,6 +1,6 @@
 #![allow(clippy::needless_return)]
 
-use metatensor::{Labels, TensorMap};
+use metatensor::{Labels, TensorBlock, TensorMap};
 
 mod utils;
 use utils::{example_tensor, example_block, example_labels};
@@ -87,6 +87,24 @@ fn unsorted_samples() {
     );
 }
 
+#[test]
+fn progress() {
+    let keys_to_move = Labels::empty(vec!["key_1"]);
+
+    let mut calls = Vec::new();
+    let tensor = example_tensor().keys_to_properties_with_progress(&keys_to_move, true, |done, total| {
+        calls.push((done, total));
+    }).unwrap();
+
+    assert_eq!(calls, [(1, 3), (2, 3), (3, 3)]);
+    assert_eq!(tensor.keys(), example_tensor().keys_to_properties(&keys_to_move, true).unwrap().keys());
+
+    let keys_to_move = Labels::empty(Vec::<&str>::new());
+    let mut called = false;
+    example_tensor().keys_to_properties_with_progress(&keys_to_move, true, |_, _| called = true).unwrap();
+    assert!(!called);
+}
+
 #[test]
 fn user_provided_entries_different_properties() {
     let keys_to_move = Labels::new(["key_1"], &[[0]]);
@@ -225,6 +243,40 @@ fn keys_to_move_in_different_order() {
     );
 }
 
+#[test]
+fn merged_properties_layout() {
+    // the moved dimensions are added before the existing properties
+    // dimensions, in the order of `keys_to_move` and not in the order of the
+    // keys
+    let mut blocks = Vec::new();
+    for i in 0..2 {
+        blocks.push(TensorBlock::new(
+            ArrayD::from_elem(vec![1, 2], f64::from(i)),
+            &Labels::new(["system"], &[[0]]),
+            &[],
+            &Labels::new(["n", "l"], &[[0, 1], [1, 0]]),
+        ).unwrap());
+    }
+
+    let keys = Labels::new(["a", "b", "c"], &[[1, 0, 3], [2, 0, 4]]);
+    let tensor = TensorMap::new(keys, blocks).unwrap();
+
+    let keys_to_move = Labels::empty(vec!["c", "a"]);
+    let tensor = tensor.keys_to_properties(&keys_to_move, true).unwrap();
+
+    assert_eq!(tensor.keys(), &Labels::new(["b"], &[[0]]));
+
+    let block = tensor.block_by_id(0);
+    assert_eq!(block.properties().names(), ["c", "a", "n", "l"]);
+    assert_eq!(block.properties(), Labels::new(["c", "a", "n", "l"], &[
+        [3, 1, 0, 1],
+        [3, 1, 1, 0],
+        [4, 2, 0, 1],
+        [4, 2, 1, 0],
+    ]));
+    assert_eq!(block.values().as_array(), ArrayD::from_shape_vec(vec![1, 4], vec![0.0, 0.0, 1.0, 1.0]).unwrap());
+}
+
 #[test]
 #[allow(clippy::too_many_lines)]
 fn user_provided_entries() {
@@ -380,3 +432,280 @@ fn user_provided_entries() {
         ])
     );
 }
+
+#[test]
+fn no_dimensions_to_move() {
+    let tensor = example_tensor();
+    let moved = tensor.keys_to_properties(&Labels::empty(vec![]), true).unwrap();
+
+    assert_eq!(moved.keys(), tensor.keys());
+    for (block, moved_block) in tensor.blocks().iter().zip(moved.blocks()) {
+        assert_eq!(moved_block.samples(), block.samples());
+        assert_eq!(moved_block.components(), block.components());
+        assert_eq!(moved_block.properties(), block.properties());
+        assert_eq!(moved_block.values().as_array(), block.values().as_array());
+
+        let gradient = block.gradient("parameter").unwrap();
+        let moved_gradient = moved_block.gradient("parameter").unwrap();
+        assert_eq!(moved_gradient.samples(), gradient.samples());
+        assert_eq!(moved_gradient.values().as_array(), gradient.values().as_array());
+    }
+}
+
+#[test]
+#[allow(clippy::cast_precision_loss)]
+fn gradients_follow_values_properties() {
+    // gradient data is equal to the values data + 100, with one gradient
+    // sample for each sample, so the gradients should have the exact same
+    // layout as the values after moving the keys to properties
+    let mut blocks = Vec::new();
+    for (key, properties) in [(0, vec![[0], [1]]), (1, vec![[1], [2], [3]])] {
+        let samples = example_labels(vec!["samples"], vec![[0], [1]]);
+        let components = [example_labels(vec!["components"], vec![[0], [1]])];
+        let properties = example_labels(vec!["properties"], properties);
+
+        let shape = vec![samples.count(), components[0].count(), properties.count()];
+        let values = ArrayD::from_shape_fn(shape, |index| {
+            (100 * key + 10 * index[0] + index[2]) as f64
+        });
+
+        let mut block = TensorBlock::new(
+            values.clone(),
+            &samples,
+            &components,
+            &properties,
+        ).unwrap();
+
+        let gradient = TensorBlock::new(
+            values + 100.0,
+            &example_labels(vec!["sample", "parameter"], vec![[0, 0], [1, 0]]),
+            &components,
+            &properties,
+        ).unwrap();
+        block.add_gradient("parameter", gradient).unwrap();
+
+        blocks.push(block);
+    }
+
+    let tensor = TensorMap::new(Labels::new(["key_1"], &[[0], [1]]), blocks).unwrap();
+    let tensor = tensor.keys_to_properties(&Labels::empty(vec!["key_1"]), true).unwrap();
+
+    let block = tensor.block_by_id(0);
+    assert_eq!(
+        block.properties(),
+        Labels::new(["key_1", "properties"], &[[0, 0], [0, 1], [1, 1], [1, 2], [1, 3]])
+    );
+
+    let gradient = block.gradient("parameter").unwrap();
+    assert_eq!(gradient.properties(), block.properties());
+    assert_eq!(gradient.components(), block.components());
+    assert_eq!(
+        gradient.samples(),
+        Labels::new(["sample", "parameter"], &[[0, 0], [1, 0]])
+    );
+
+    assert_eq!(gradient.values().as_array(), &(block.values().as_array() + 100.0));
+
+    let expected = ArrayD::from_shape_vec(vec![2, 2, 5], vec![
+        0.0, 1.0, 100.0, 101.0, 102.0,
+        0.0, 1.0, 100.0, 101.0, 102.0,
+        10.0, 11.0, 110.0, 111.0, 112.0,
+        10.0, 11.0, 110.0, 111.0, 112.0,
+    ]).unwrap();
+    assert_eq!(block.values().as_array(), expected);
+}
+
+#[test]
+fn blocks_without_components() {
+    // blocks without components, e.g. for energy-like features
+    let block_1 = TensorBlock::new(
+        ndarray::arr2(&[[1.0, 2.0], [3.0, 4.0]]).into_dyn(),
+        &Labels::new(["system"], &[[0], [1]]),
+        &[],
+        &Labels::new(["n"], &[[0], [1]]),
+    ).unwrap();
+
+    let block_2 = TensorBlock::new(
+        ndarray::arr2(&[[5.0], [6.0]]).into_dyn(),
+        &Labels::new(["system"], &[[1], [2]]),
+        &[],
+        &Labels::new(["n"], &[[3]]),
+    ).unwrap();
+
+    let tensor = TensorMap::new(Labels::new(["key"], &[[0], [1]]), vec![block_1, block_2]).unwrap();
+
+    let keys_to_move = Labels::empty(vec!["key"]);
+    let merged = tensor.keys_to_properties(&keys_to_move, true).unwrap();
+    assert_eq!(merged.keys(), &Labels::single());
+
+    let block = merged.block_by_id(0);
+    assert_eq!(block.samples(), Labels::new(["system"], &[[0], [1], [2]]));
+    assert!(block.components().is_empty());
+    assert_eq!(block.properties(), Labels::new(["key", "n"], &[[0, 0], [0, 1], [1, 3]]));
+    assert_eq!(block.values().as_array(), ndarray::arr2(&[
+        [1.0, 2.0, 0.0],
+        [3.0, 4.0, 5.0],
+        [0.0, 0.0, 6.0],
+    ]).into_dyn());
+}
+
+#[test]
+fn partially_overlapping_samples() {
+    // blocks computed over different subsets of atoms, the missing samples
+    // are filled with zeros in the values and gradients
+    let mut block_1 = TensorBlock::new(
+        ndarray::arr2(&[[1.0], [2.0]]).into_dyn(),
+        &Labels::new(["atom"], &[[0], [1]]),
+        &[],
+        &Labels::new(["n"], &[[0]]),
+    ).unwrap();
+    block_1.add_gradient("g", TensorBlock::new(
+        ndarray::arr2(&[[10.0], [20.0]]).into_dyn(),
+        &Labels::new(["sample"], &[[0], [1]]),
+        &[],
+        &Labels::new(["n"], &[[0]]),
+    ).unwrap()).unwrap();
+
+    let mut block_2 = TensorBlock::new(
+        ndarray::arr2(&[[3.0], [4.0]]).into_dyn(),
+        &Labels::new(["atom"], &[[2], [1]]),
+        &[],
+        &Labels::new(["n"], &[[0]]),
+    ).unwrap();
+    block_2.add_gradient("g", TensorBlock::new(
+        ndarray::arr2(&[[30.0]]).into_dyn(),
+        &Labels::new(["sample"], &[[0]]),
+        &[],
+        &Labels::new(["n"], &[[0]]),
+    ).unwrap()).unwrap();
+
+    let tensor = TensorMap::new(Labels::new(["key"], &[[0], [1]]), vec![block_1, block_2]).unwrap();
+
+    let merged = tensor.keys_to_properties(&Labels::empty(vec!["key"]), false).unwrap();
+    let block = merged.block_by_id(0);
+    assert_eq!(block.samples(), Labels::new(["atom"], &[[0], [1], [2]]));
+    assert_eq!(block.properties(), Labels::new(["key", "n"], &[[0, 0], [1, 0]]));
+    assert_eq!(block.values().as_array(), ndarray::arr2(&[
+        [1.0, 0.0],
+        [2.0, 4.0],
+        [0.0, 3.0],
+    ]).into_dyn());
+
+    let gradient = block.gradient("g").unwrap();
+    assert_eq!(gradient.samples(), Labels::new(["sample"], &[[0], [1], [2]]));
+    assert_eq!(gradient.values().as_array(), ndarray::arr2(&[
+        [10.0, 0.0],
+        [20.0, 0.0],
+        [0.0, 30.0],
+    ]).into_dyn());
+}
+
+#[test]
+fn different_gradient_samples() {
+    // the gradient samples of the merged blocks can differ even when the
+    // samples are the same, the gradients of each block must end up in the
+    // right gradient samples, with zeros for the missing gradients
+    let mut block_1 = TensorBlock::new(
+        ndarray::arr2(&[[1.0], [2.0]]).into_dyn(),
+        &Labels::new(["atom"], &[[0], [1]]),
+        &[],
+        &Labels::new(["n"], &[[0]]),
+    ).unwrap();
+    block_1.add_gradient("positions", TensorBlock::new(
+        ndarray::arr2(&[[10.0], [11.0], [20.0]]).into_dyn(),
+        &Labels::new(["sample", "atom"], &[[0, 0], [0, 1], [1, 1]]),
+        &[],
+        &Labels::new(["n"], &[[0]]),
+    ).unwrap()).unwrap();
+
+    let mut block_2 = TensorBlock::new(
+        ndarray::arr2(&[[3.0], [4.0]]).into_dyn(),
+        &Labels::new(["atom"], &[[0], [1]]),
+        &[],
+        &Labels::new(["n"], &[[0]]),
+    ).unwrap();
+    block_2.add_gradient("positions", TensorBlock::new(
+        ndarray::arr2(&[[40.0], [30.0], [32.0]]).into_dyn(),
+        &Labels::new(["sample", "atom"], &[[1, 0], [0, 0], [0, 2]]),
+        &[],
+        &Labels::new(["n"], &[[0]]),
+    ).unwrap()).unwrap();
+
+    let tensor = TensorMap::new(Labels::new(["key"], &[[0], [1]]), vec![block_1, block_2]).unwrap();
+
+    let merged = tensor.keys_to_properties(&Labels::empty(vec!["key"]), true).unwrap();
+    let block = merged.block_by_id(0);
+    assert_eq!(block.samples(), Labels::new(["atom"], &[[0], [1]]));
+    assert_eq!(block.values().as_array(), ndarray::arr2(&[
+        [1.0, 3.0],
+        [2.0, 4.0],
+    ]).into_dyn());
+
+    let gradient = block.gradient("positions").unwrap();
+    assert_eq!(gradient.samples(), Labels::new(
+        ["sample", "atom"],
+        &[[0, 0], [0, 1], [0, 2], [1, 0], [1, 1]],
+    ));
+    assert_eq!(gradient.values().as_array(), ndarray::arr2(&[
+        [10.0, 30.0],
+        [11.0, 0.0],
+        [0.0, 32.0],
+        [0.0, 40.0],
+        [20.0, 0.0],
+    ]).into_dyn());
+}
+
+#[test]
+fn different_components_values() {
+    // the components must be identical (names and values) to merge blocks
+    // along the properties
+    let block_1 = TensorBlock::new(
+        ArrayD::from_elem(vec![1, 2, 1], 1.0),
+        &Labels::new(["system"], &[[0]]),
+        &[Labels::new(["o3_mu"], &[[-1], [0]])],
+        &Labels::new(["n"], &[[0]]),
+    ).unwrap();
+
+    let block_2 = TensorBlock::new(
+        ArrayD::from_elem(vec![1, 2, 1], 2.0),
+        &Labels::new(["system"], &[[0]]),
+        &[Labels::new(["o3_mu"], &[[0], [1]])],
+        &Labels::new(["n"], &[[0]]),
+    ).unwrap();
+
+    let tensor = TensorMap::new(Labels::new(["key"], &[[0], [1]]), vec![block_1, block_2]).unwrap();
+
+    let error = tensor.keys_to_properties(&Labels::empty(vec!["key"]), true).unwrap_err();
+    assert_eq!(
+        error.message,
+        "invalid parameter: all blocks must have the same values for the \
+        [o3_mu] component to move keys to properties, call \
+        components_to_properties first"
+    );
+}
+
+#[test]
+fn different_dtypes() {
+    // integer and floating point data can not be merged in the same block
+    let block_1 = TensorBlock::new(
+        ArrayD::from_elem(vec![1, 1], 1.0),
+        &Labels::new(["system"], &[[0]]),
+        &[],
+        &Labels::new(["n"], &[[0]]),
+    ).unwrap();
+
+    let block_2 = TensorBlock::new(
+        ArrayD::from_elem(vec![1, 1], 2_i32),
+        &Labels::new(["system"], &[[0]]),
+        &[],
+        &Labels::new(["n"], &[[0]]),
+    ).unwrap();
+
+    let tensor = TensorMap::new(Labels::new(["key"], &[[0], [1]]), vec![block_1, block_2]).unwrap();
+
+    let error = tensor.keys_to_properties(&Labels::empty(vec!["key"]), true).unwrap_err();
+    assert_eq!(
+        error.message,
+        "external error: calling mts_array_t.move_samples_from failed (status -4242)"
+    );
+}
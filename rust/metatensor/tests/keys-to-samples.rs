@@ -1,4 +1,4 @@
-use metatensor::{Labels, TensorMap};
+use metatensor::{Labels, SampleOrigin, TensorBlock, TensorMap};
 
 mod utils;
 use utils::{example_tensor, example_block};
@@ -100,6 +100,24 @@ fn unsorted_samples() {
     assert_eq!(block_3.samples()[7], [5, 3]);
 }
 
+#[test]
+fn progress() {
+    let keys_to_move = Labels::empty(vec!["key_2"]);
+
+    let mut calls = Vec::new();
+    let tensor = example_tensor().keys_to_samples_with_progress(&keys_to_move, true, |done, total| {
+        calls.push((done, total));
+    }).unwrap();
+
+    assert_eq!(calls, [(1, 3), (2, 3), (3, 3)]);
+    assert_eq!(tensor.keys(), example_tensor().keys_to_samples(&keys_to_move, true).unwrap().keys());
+
+    let keys_to_move = Labels::empty(Vec::<&str>::new());
+    let mut called = false;
+    example_tensor().keys_to_samples_with_progress(&keys_to_move, true, |_, _| called = true).unwrap();
+    assert!(!called);
+}
+
 #[test]
 fn user_provided_entries() {
     let keys_to_move = Labels::new(["key_2"], &[[3]]);
@@ -153,3 +171,157 @@ fn empty_samples() {
         ])
     );
 }
+
+#[test]
+fn no_dimensions_to_move() {
+    let tensor = example_tensor();
+    let moved = tensor.keys_to_samples(&Labels::empty(vec![]), true).unwrap();
+
+    assert_eq!(moved.keys(), tensor.keys());
+    for (block, moved_block) in tensor.blocks().iter().zip(moved.blocks()) {
+        assert_eq!(moved_block.samples(), block.samples());
+        assert_eq!(moved_block.components(), block.components());
+        assert_eq!(moved_block.properties(), block.properties());
+        assert_eq!(moved_block.values().as_array(), block.values().as_array());
+
+        let gradient = block.gradient("parameter").unwrap();
+        let moved_gradient = moved_block.gradient("parameter").unwrap();
+        assert_eq!(moved_gradient.samples(), gradient.samples());
+        assert_eq!(moved_gradient.values().as_array(), gradient.values().as_array());
+    }
+}
+
+#[test]
+fn merged_samples_layout() {
+    // the moved dimensions are added after the existing samples dimensions,
+    // in the order of `keys_to_move` and not in the order of the keys
+    let mut blocks = Vec::new();
+    for i in 0..2 {
+        blocks.push(TensorBlock::new(
+            ArrayD::from_elem(vec![1, 1], f64::from(i)),
+            &Labels::new(["system", "atom"], &[[0, i]]),
+            &[],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap());
+    }
+
+    let keys = Labels::new(["a", "b", "c"], &[[1, 0, 3], [2, 0, 4]]);
+    let tensor = TensorMap::new(keys, blocks).unwrap();
+
+    let keys_to_move = Labels::empty(vec!["c", "a"]);
+    let tensor = tensor.keys_to_samples(&keys_to_move, false).unwrap();
+
+    assert_eq!(tensor.keys(), &Labels::new(["b"], &[[0]]));
+
+    let block = tensor.block_by_id(0);
+    assert_eq!(block.samples().names(), ["system", "atom", "c", "a"]);
+    assert_eq!(block.samples(), Labels::new(["system", "atom", "c", "a"], &[[0, 0, 3, 1], [0, 1, 4, 2]]));
+}
+
+#[test]
+fn grouping_by_remaining_keys() {
+    // blocks which only differ in the moved key are merged, blocks which
+    // differ in any other key stay separate
+    let mut blocks = Vec::new();
+    for i in 0..4 {
+        blocks.push(TensorBlock::new(
+            ArrayD::from_elem(vec![1, 1], f64::from(i)),
+            &Labels::new(["system"], &[[i]]),
+            &[],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap());
+    }
+
+    let keys = Labels::new(["center", "neighbor"], &[[1, 0], [0, 0], [1, 1], [0, 2]]);
+    let tensor = TensorMap::new(keys, blocks).unwrap();
+
+    let keys_to_move = Labels::empty(vec!["neighbor"]);
+    let tensor = tensor.keys_to_samples(&keys_to_move, false).unwrap();
+
+    // the new keys are in the order of first appearance in the old keys
+    assert_eq!(tensor.keys(), &Labels::new(["center"], &[[1], [0]]));
+
+    let block = tensor.block_by_id(0);
+    assert_eq!(block.samples(), Labels::new(["system", "neighbor"], &[[0, 0], [2, 1]]));
+    assert_eq!(block.values().as_array(), ArrayD::from_shape_vec(vec![2, 1], vec![0.0, 2.0]).unwrap());
+
+    let block = tensor.block_by_id(1);
+    assert_eq!(block.samples(), Labels::new(["system", "neighbor"], &[[1, 0], [3, 2]]));
+    assert_eq!(block.values().as_array(), ArrayD::from_shape_vec(vec![2, 1], vec![1.0, 3.0]).unwrap());
+}
+
+#[test]
+fn traced_samples() {
+    let tensor = example_tensor();
+    let keys_to_move = Labels::empty(vec!["key_2"]);
+    let (merged, origins) = tensor.keys_to_samples_traced(&keys_to_move, true).unwrap();
+
+    assert_eq!(merged.keys(), tensor.keys_to_samples(&keys_to_move, true).unwrap().keys());
+    assert_eq!(origins.len(), 3);
+
+    // the first two blocks are not modified
+    let origin = |block, sample| SampleOrigin { block, sample };
+    assert_eq!(origins[0], [origin(0, 0), origin(0, 1), origin(0, 2)]);
+    assert_eq!(origins[1], [origin(1, 0), origin(1, 1), origin(1, 2)]);
+
+    // the third block contains the samples of the old third and fourth
+    // blocks, sorted
+    assert_eq!(origins[2], [
+        origin(2, 0), origin(3, 0), origin(3, 1), origin(3, 2),
+        origin(2, 1), origin(3, 3), origin(2, 2), origin(2, 3),
+    ]);
+
+    // the data of each sample comes from the corresponding origin
+    for (block_i, block_origins) in origins.iter().enumerate() {
+        let block = merged.block_by_id(block_i);
+        let values = block.values();
+        let values = values.as_array();
+        for (sample_i, origin) in block_origins.iter().enumerate() {
+            let input = tensor.block_by_id(origin.block);
+            let input_values = input.values();
+            let input_values = input_values.as_array();
+            assert_eq!(
+                values.index_axis(ndarray::Axis(0), sample_i),
+                input_values.index_axis(ndarray::Axis(0), origin.sample),
+            );
+            assert_eq!(
+                block.samples()[sample_i][0],
+                input.samples()[origin.sample][0],
+            );
+        }
+    }
+
+    // without dimensions to move, each sample comes from the same block
+    let (_, origins) = tensor.keys_to_samples_traced(&Labels::empty(vec![]), false).unwrap();
+    for (block_i, block_origins) in origins.iter().enumerate() {
+        for (sample_i, origin) in block_origins.iter().enumerate() {
+            assert_eq!(*origin, SampleOrigin { block: block_i, sample: sample_i });
+        }
+    }
+}
+
+#[test]
+fn traced_samples_all_keys() {
+    // moving all the key dimensions merges all the blocks together
+    let mut blocks = Vec::new();
+    for i in 0..3 {
+        blocks.push(TensorBlock::new(
+            ArrayD::from_elem(vec![1, 1], f64::from(i)),
+            &Labels::new(["system"], &[[2 - i]]),
+            &[],
+            &Labels::new(["properties"], &[[0]]),
+        ).unwrap());
+    }
+
+    let keys = Labels::new(["key"], &[[0], [1], [2]]);
+    let tensor = TensorMap::new(keys, blocks).unwrap();
+
+    let keys_to_move = Labels::empty(vec!["key"]);
+    let (merged, origins) = tensor.keys_to_samples_traced(&keys_to_move, true).unwrap();
+
+    assert_eq!(merged.keys(), &Labels::new(["_"], &[[0]]));
+    assert_eq!(origins.len(), 1);
+
+    let origin = |block, sample| SampleOrigin { block, sample };
+    assert_eq!(origins[0], [origin(2, 0), origin(1, 0), origin(0, 0)]);
+}
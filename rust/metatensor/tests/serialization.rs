@@ -42,6 +42,33 @@ mod tensor {
         assert_eq!(buffer, saved);
     }
 
+    #[test]
+    fn save_after_filter_properties() {
+        let tensor = TensorMap::load("../../metatensor-core/tests/data.npz").unwrap();
+        let filtered = tensor.filter_properties(|property| property[0].i32() != 1).unwrap();
+
+        let mut buffer = Vec::new();
+        filtered.save_buffer(&mut buffer).unwrap();
+        let loaded = TensorMap::load_buffer(&buffer).unwrap();
+
+        assert_eq!(loaded.keys(), filtered.keys());
+        for (block, expected) in loaded.blocks().iter().zip(filtered.blocks()) {
+            assert_eq!(block.properties(), expected.properties());
+            assert_eq!(block.values().as_array(), expected.values().as_array());
+
+            let gradient = block.gradient("positions").unwrap();
+            let expected = expected.gradient("positions").unwrap();
+            assert_eq!(gradient.values().as_array(), expected.values().as_array());
+        }
+
+        let block = loaded.block_by_id(13);
+        assert_eq!(block.properties().count(), 2);
+        assert_eq!(
+            block.values().as_array(),
+            tensor.block_by_id(13).values().as_array().select(ndarray::Axis(2), &[0, 2]),
+        );
+    }
+
     fn check_tensor(tensor: &TensorMap) {
         assert_eq!(tensor.keys().names(), ["o3_lambda", "o3_sigma", "center_type", "neighbor_type"]);
         assert_eq!(tensor.keys().count(), 27);